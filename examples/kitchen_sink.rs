@@ -0,0 +1,122 @@
+//! A tour of the native-executor API.
+//!
+//! Each scenario is a self-contained async function; `main` drives the main
+//! thread until the last one has finished.
+//!
+//! ```bash
+//! cargo run --example kitchen_sink
+//! ```
+
+use native_executor::{
+    Mailbox, NativeExecutor, Priority, callback, spawn, spawn_main, spawn_supervised,
+    spawn_with_priority,
+    supervisor::{RestartPolicy, supervise},
+    timer::{Interval, Timer, timeout},
+};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+fn main() {
+    NativeExecutor::run_until(async {
+        priorities().await;
+        mailbox().await;
+        ticks().await;
+        timeout_race().await;
+        supervision().await;
+        callbacks().await;
+    });
+    println!("all scenarios finished");
+}
+
+/// Runs the same computation at every standard priority.
+async fn priorities() {
+    let priorities = [
+        Priority::Background,
+        Priority::Utility,
+        Priority::Default,
+        Priority::UserInitiated,
+        Priority::UserInteractive,
+    ];
+    for priority in priorities {
+        let sum = spawn_with_priority(async { (1..=10).sum::<u32>() }, priority).await;
+        println!("{priority:?} task computed {sum}");
+    }
+}
+
+/// Sends updates from pool tasks to a value owned by the main thread.
+async fn mailbox() {
+    let mailbox = Arc::new(spawn_main(async { Mailbox::main(0u32) }).await);
+
+    let senders: Vec<_> = (0..3)
+        .map(|_| {
+            let mailbox = mailbox.clone();
+            spawn(async move { mailbox.handle(|count| *count += 1) })
+        })
+        .collect();
+    for sender in senders {
+        sender.await;
+    }
+
+    let count = mailbox.call(|count| *count).await;
+    println!("mailbox received {count} updates");
+}
+
+/// Prints a few ticks of a periodic timer.
+async fn ticks() {
+    let mut interval = Interval::every(Duration::from_millis(20));
+    // The first tick completes right away
+    interval.tick().await;
+    for tick in 1..=3 {
+        interval.tick().await;
+        println!("tick {tick}");
+    }
+}
+
+/// Races work against a deadline.
+async fn timeout_race() {
+    let fast = timeout(Duration::from_millis(100), async { "fast work" }).await;
+    println!("fast work: {fast:?}");
+
+    let slow = timeout(
+        Duration::from_millis(20),
+        Timer::after(Duration::from_secs(10)),
+    )
+    .await;
+    println!("slow work: {slow:?}");
+}
+
+/// Restarts a flaky task until it succeeds, and aborts a supervised task.
+async fn supervision() {
+    let attempts = AtomicU32::new(0);
+    let handle = supervise(
+        move || {
+            let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+            async move { if attempt < 2 { Err("flaky") } else { Ok(()) } }
+        },
+        RestartPolicy::new().backoff(Duration::from_millis(5), Duration::from_millis(20)),
+    );
+    let mut states = handle.watch();
+    while states.next().await.is_some() {}
+    println!(
+        "supervised task succeeded after {} restarts",
+        handle.restarts()
+    );
+
+    let task = spawn_supervised(std::future::pending::<()>());
+    task.abort();
+    let error = task.await.unwrap_err();
+    println!("supervised spawn reported: {error}");
+}
+
+/// Bridges a callback-based API into a future.
+async fn callbacks() {
+    let (completer, result) = callback::future();
+    thread::spawn(move || completer.complete("callback value"));
+    println!("callback delivered {:?}", result.await);
+}
@@ -1,4 +1,4 @@
-use native_executor::{spawn, timer::Timer};
+use native_executor::{NativeExecutor, spawn, timer::Timer};
 use std::time::Duration;
 
 fn main() {
@@ -15,8 +15,8 @@ fn main() {
     })
     .detach();
 
-    // Keep the main thread alive
-    std::thread::sleep(Duration::from_secs(2));
+    // Drive the main thread until the task is done
+    NativeExecutor::keep_alive_until_idle(Duration::from_millis(100));
 
     println!("Example completed");
 }
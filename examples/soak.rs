@@ -0,0 +1,139 @@
+//! A soak test: runs a mixed workload in phases for a while, and checks after
+//! each phase that the live objects of the crate are back to where they
+//! started.
+//!
+//! ```bash
+//! cargo run --release --features leak-check --example soak -- 600
+//! ```
+//!
+//! The argument is the duration in seconds, 10 by default. `cargo test
+//! --features leak-check --example soak` runs a short soak.
+
+use native_executor::{
+    Mailbox, NativeExecutor, Priority, diagnostics, execute_after, spawn, spawn_main,
+    spawn_with_priority,
+    timer::{Interval, Timer},
+};
+use std::{
+    future::Future,
+    pin::Pin,
+    time::{Duration, Instant},
+};
+
+fn main() {
+    let secs = std::env::args()
+        .nth(1)
+        .map_or(10, |secs| secs.parse().expect("the duration is in seconds"));
+    soak(Duration::from_secs(secs));
+}
+
+#[test]
+fn soak_briefly() {
+    soak(Duration::from_secs(2));
+}
+
+/// Runs phases until `duration` has elapsed, panicking if a phase leaves
+/// objects behind.
+fn soak(duration: Duration) {
+    NativeExecutor::run_until(async move {
+        let start = Instant::now();
+        let baseline = diagnostics::live_objects();
+        let mut phases = 0u32;
+        while start.elapsed() < duration {
+            timers().await;
+            mailboxes().await;
+            fan_out(4, 4).await;
+            settle(baseline).await;
+            phases += 1;
+        }
+        let report = diagnostics::live_objects();
+        println!(
+            "{phases} phases in {:?}, back to {report:#?}",
+            start.elapsed()
+        );
+    });
+}
+
+/// Churns timers: sleeping tasks, intervals, and closures scheduled with
+/// `execute_after`, half of them cancelled.
+async fn timers() {
+    let sleepers: Vec<_> = (0..64u64)
+        .map(|n| {
+            spawn_with_priority(
+                async move { Timer::after(Duration::from_micros(n * 50)).await },
+                Priority::Utility,
+            )
+        })
+        .collect();
+    let mut interval = Interval::every(Duration::from_millis(1));
+    for _ in 0..5 {
+        interval.tick().await;
+    }
+    for n in 0..64u64 {
+        let scheduled = execute_after(Duration::from_micros(n * 20), || {});
+        if n % 2 == 0 {
+            scheduled.cancel();
+        }
+    }
+    for sleeper in sleepers {
+        sleeper.await;
+    }
+    // Dropped before it fires
+    drop(Timer::after(Duration::from_hours(1)));
+}
+
+/// Creates mailboxes, feeds them from the pool, and drops them.
+async fn mailboxes() {
+    for _ in 0..16 {
+        let mailbox = spawn_main(async { Mailbox::main(Vec::<u64>::new()) }).await;
+        let mailbox = std::sync::Arc::new(mailbox);
+        let producers: Vec<_> = (0..4)
+            .map(|n| {
+                let mailbox = mailbox.clone();
+                spawn(async move { mailbox.handle(move |values| values.push(n)) })
+            })
+            .collect();
+        for producer in producers {
+            producer.await;
+        }
+        assert_eq!(mailbox.call(|values| values.len()).await, 4);
+    }
+}
+
+/// Spawns a tree of tasks, `width` children per task, `depth` levels deep.
+fn fan_out(width: u32, depth: u32) -> Pin<Box<dyn Future<Output = u32> + Send>> {
+    Box::pin(async move {
+        if depth == 0 {
+            return 1;
+        }
+        let children: Vec<_> = (0..width)
+            .map(|_| spawn(fan_out(width, depth - 1)))
+            .collect();
+        let mut count = 1;
+        for child in children {
+            count += child.await;
+        }
+        count
+    })
+}
+
+/// Waits until the live counts are back to `baseline`, panicking if they
+/// are not within a few seconds.
+async fn settle(baseline: diagnostics::LiveReport) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let report = diagnostics::live_objects();
+        let settled = report.tasks.live == baseline.tasks.live
+            && report.timers.live == baseline.timers.live
+            && report.mailboxes.live == baseline.mailboxes.live
+            && report.scheduled.live == baseline.scheduled.live;
+        if settled {
+            return;
+        }
+        assert!(
+            Instant::now() < deadline,
+            "live objects did not return to the baseline\nbaseline: {baseline:#?}\nnow: {report:#?}"
+        );
+        Timer::after(Duration::from_millis(5)).await;
+    }
+}
@@ -1,5 +1,5 @@
 use native_executor::{
-    spawn,
+    NativeExecutor, spawn,
     timer::{Timer, sleep},
 };
 use std::time::Duration;
@@ -20,13 +20,13 @@ fn main() {
 
         // Use the sleep convenience function
         println!("Sleeping for 2 seconds...");
-        sleep(2).await;
+        sleep(Duration::from_secs(2)).await;
         println!("2 seconds elapsed");
 
         println!("Timers example completed");
     })
     .detach();
 
-    // Keep the main thread alive
-    std::thread::sleep(Duration::from_secs(4));
+    // Drive the main thread until the timers are done
+    NativeExecutor::keep_alive_until_idle(Duration::from_millis(100));
 }
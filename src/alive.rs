@@ -0,0 +1,260 @@
+//! The count of tasks and closures that keep the process busy.
+//!
+//! Every task and closure submitted through this crate counts from the moment
+//! it is spawned until it completes or is dropped, unless it was spawned as a
+//! daemon with [`SpawnOptions::daemon`](crate::SpawnOptions::daemon).
+//!
+//! The same count enforces [`Config::max_alive_tasks`](crate::Config::max_alive_tasks):
+//! a task takes a slot when it is spawned, or once one is free with
+//! [`SpawnOverflow::Wait`], and frees it when it ends.
+
+use alloc::collections::VecDeque;
+use core::{
+    fmt,
+    future::poll_fn,
+    sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    task::{Poll, Waker},
+    time::Duration,
+};
+use std::sync::{Mutex, PoisonError};
+
+use crate::{config, config::SpawnOverflow, hooks::TaskInfo, timer::Timer};
+
+static ALIVE: AtomicUsize = AtomicUsize::new(0);
+/// The number of tracked tasks ever spawned, which tells a quiet grace
+/// period from one in which short tasks came and went.
+static SPAWNED: AtomicU64 = AtomicU64::new(0);
+/// Woken when the count drops to zero.
+static IDLE: Mutex<Option<Waker>> = Mutex::new(None);
+/// The tasks waiting for a slot under the cap, by [`Waiter`] id, in arrival
+/// order.
+static WAITING: Mutex<VecDeque<(u64, Waker)>> = Mutex::new(VecDeque::new());
+/// Whether [`WAITING`] may hold wakers.
+static ANY_WAITING: AtomicBool = AtomicBool::new(false);
+static NEXT_WAITER: AtomicU64 = AtomicU64::new(0);
+
+/// Counts a task as alive until dropped.
+#[derive(Debug)]
+pub struct Alive(());
+
+impl Drop for Alive {
+    fn drop(&mut self) {
+        if ALIVE.fetch_sub(1, Ordering::AcqRel) == 1 {
+            let waker = IDLE.lock().unwrap_or_else(PoisonError::into_inner).take();
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+        if ANY_WAITING.load(Ordering::Acquire) {
+            wake_one();
+        }
+    }
+}
+
+/// Wakes the task that has waited longest for a slot.
+fn wake_one() {
+    let mut waiting = WAITING.lock().unwrap_or_else(PoisonError::into_inner);
+    let waiter = waiting.pop_front();
+    if waiting.is_empty() {
+        ANY_WAITING.store(false, Ordering::Release);
+    }
+    drop(waiting);
+    if let Some((_, waker)) = waiter {
+        waker.wake();
+    }
+}
+
+/// Returns whether the cap on alive tasks leaves a slot free.
+fn slot_free() -> bool {
+    config::current()
+        .alive_task_limit()
+        .is_some_and(|max| ALIVE.load(Ordering::Acquire) < max)
+}
+
+/// The place of a task in [`WAITING`].
+///
+/// A waiter that is woken leaves the queue; one that is dropped after that
+/// without using the freed slot wakes the next waiter in its place, and one
+/// that is dropped while queued leaves the queue.
+struct Waiter {
+    id: u64,
+    queued: bool,
+}
+
+impl Waiter {
+    fn new() -> Self {
+        Self {
+            id: NEXT_WAITER.fetch_add(1, Ordering::Relaxed),
+            queued: false,
+        }
+    }
+
+    /// Queues the task to be woken with `waker` once a slot is freed.
+    fn queue(&mut self, waker: &Waker) {
+        let mut waiting = WAITING.lock().unwrap_or_else(PoisonError::into_inner);
+        match waiting.iter_mut().find(|(id, _)| *id == self.id) {
+            Some((_, queued)) => queued.clone_from(waker),
+            None => waiting.push_back((self.id, waker.clone())),
+        }
+        drop(waiting);
+        ANY_WAITING.store(true, Ordering::Release);
+        self.queued = true;
+    }
+}
+
+impl Drop for Waiter {
+    fn drop(&mut self) {
+        if !self.queued {
+            return;
+        }
+        let mut waiting = WAITING.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(index) = waiting.iter().position(|(id, _)| *id == self.id) {
+            waiting.remove(index);
+            if waiting.is_empty() {
+                ANY_WAITING.store(false, Ordering::Release);
+            }
+        } else {
+            drop(waiting);
+            // Woken for a slot that this task may not have used
+            if slot_free() {
+                wake_one();
+            }
+        }
+    }
+}
+
+/// The error returned by the `try_spawn` functions, such as
+/// [`try_spawn`](crate::try_spawn), when a task cannot be spawned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SpawnError {
+    /// As many tasks as [`Config::max_alive_tasks`](crate::Config::max_alive_tasks)
+    /// allows are alive.
+    AtCapacity,
+}
+
+impl fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AtCapacity => f.write_str(
+                "as many tasks as `Config::max_alive_tasks` allows are alive; the task was not \
+                 spawned",
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SpawnError {}
+
+/// The slot of a task under the cap on alive tasks.
+pub enum Slot {
+    /// Taken when the task was spawned; `None` for daemons, which are not
+    /// counted.
+    Taken(Option<Alive>),
+    /// To be taken by the task once one is free.
+    Pending,
+}
+
+impl Slot {
+    /// Takes a slot for the task described by `info`, failing if the cap is
+    /// reached.
+    pub fn try_take(info: &TaskInfo) -> Result<Self, SpawnError> {
+        try_track(info).map(Self::Taken)
+    }
+
+    /// Takes a slot for the task described by `info`, or leaves it to the
+    /// task with [`SpawnOverflow::Wait`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cap is reached with [`SpawnOverflow::Panic`].
+    #[track_caller]
+    pub fn take(info: &TaskInfo) -> Self {
+        match try_track(info) {
+            Ok(alive) => Self::Taken(alive),
+            Err(error) => match config::current().spawn_overflow() {
+                SpawnOverflow::Wait => Self::Pending,
+                SpawnOverflow::Panic => panic!("{error}"),
+            },
+        }
+    }
+
+    /// Waits until the task described by `info` holds its slot.
+    pub async fn acquire(self, info: &TaskInfo) -> Option<Alive> {
+        match self {
+            Self::Taken(alive) => alive,
+            Self::Pending => {
+                let mut waiter = Waiter::new();
+                poll_fn(|cx| {
+                    if let Ok(alive) = try_track(info) {
+                        return Poll::Ready(alive);
+                    }
+                    waiter.queue(cx.waker());
+                    // A slot may have been freed before the waker was stored.
+                    try_track(info).map_or(Poll::Pending, Poll::Ready)
+                })
+                .await
+            }
+        }
+    }
+}
+
+/// Counts the task described by `info` as alive, unless it is a daemon or
+/// the cap is reached.
+///
+/// The cap is enforced with a compare-and-swap, so concurrent spawns never
+/// exceed it.
+fn try_track(info: &TaskInfo) -> Result<Option<Alive>, SpawnError> {
+    let Some(max) = config::current().alive_task_limit() else {
+        return Ok(track(info));
+    };
+    if info.daemon {
+        return Ok(None);
+    }
+    ALIVE
+        .fetch_update(Ordering::AcqRel, Ordering::Acquire, |alive| {
+            (alive < max).then_some(alive + 1)
+        })
+        .map_err(|_| SpawnError::AtCapacity)?;
+    SPAWNED.fetch_add(1, Ordering::Relaxed);
+    Ok(Some(Alive(())))
+}
+
+/// Counts the task described by `info` as alive, unless it is a daemon.
+pub fn track(info: &TaskInfo) -> Option<Alive> {
+    if info.daemon {
+        return None;
+    }
+    SPAWNED.fetch_add(1, Ordering::Relaxed);
+    ALIVE.fetch_add(1, Ordering::AcqRel);
+    Some(Alive(()))
+}
+
+/// Completes once no task has been alive for `grace`.
+pub async fn idle(grace: Duration) {
+    loop {
+        poll_fn(|cx| {
+            if ALIVE.load(Ordering::Acquire) == 0 {
+                return Poll::Ready(());
+            }
+            let mut idle = IDLE.lock().unwrap_or_else(PoisonError::into_inner);
+            match &mut *idle {
+                Some(waker) => waker.clone_from(cx.waker()),
+                None => *idle = Some(cx.waker().clone()),
+            }
+            drop(idle);
+            // The last task may have ended before the waker was stored.
+            if ALIVE.load(Ordering::Acquire) == 0 {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+        let spawned = SPAWNED.load(Ordering::Relaxed);
+        Timer::after(grace).await;
+        if ALIVE.load(Ordering::Acquire) == 0 && SPAWNED.load(Ordering::Relaxed) == spawned {
+            return;
+        }
+    }
+}
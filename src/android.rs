@@ -3,15 +3,18 @@
 //! This module provides a minimal native executor for Android targets.
 //! It leverages long-lived worker threads to execute queued jobs and
 //! supports delayed scheduling for timer integration.
-use core::time::Duration;
+use core::{cell::Cell, future::Future, time::Duration};
 use std::{
-    sync::{OnceLock, mpsc},
+    sync::{Arc, Mutex, OnceLock, PoisonError, mpsc},
     thread,
 };
 
-use crate::{PlatformExecutor, Priority};
-
-type Job = Box<dyn FnOnce() + Send + 'static>;
+use crate::{
+    PlatformExecutor, Priority,
+    main_queue::{Job, MainQueue},
+    nice::with_nice,
+    priority::SchedClass,
+};
 
 #[derive(Debug)]
 struct ExecutorQueue {
@@ -19,14 +22,27 @@ struct ExecutorQueue {
 }
 
 impl ExecutorQueue {
-    fn new() -> Self {
+    fn new(name: &str, workers: usize) -> Self {
         let (sender, receiver) = mpsc::channel::<Job>();
-        // The worker processes jobs sequentially on a dedicated OS thread.
-        let _ = thread::spawn(move || {
-            while let Ok(job) = receiver.recv() {
-                job();
-            }
-        });
+        // Each worker processes jobs sequentially on a dedicated OS thread.
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..workers {
+            let receiver = receiver.clone();
+            let _ = thread::Builder::new()
+                .name(format!("{}.{name}", crate::label_prefix()))
+                .spawn(move || {
+                    crate::config::run_worker(|| {
+                        loop {
+                            let job = receiver
+                                .lock()
+                                .unwrap_or_else(PoisonError::into_inner)
+                                .recv();
+                            let Ok(job) = job else { break };
+                            job();
+                        }
+                    });
+                });
+        }
         Self { sender }
     }
 
@@ -53,8 +69,13 @@ impl ExecutorQueue {
     }
 }
 
+std::thread_local! {
+    /// Whether this thread runs the main queue.
+    static ON_MAIN: Cell<bool> = const { Cell::new(false) };
+}
+
 struct AndroidRuntime {
-    main: ExecutorQueue,
+    main: MainQueue,
     default: ExecutorQueue,
     background: ExecutorQueue,
 }
@@ -63,19 +84,33 @@ impl AndroidRuntime {
     fn instance() -> &'static Self {
         static RUNTIME: OnceLock<AndroidRuntime> = OnceLock::new();
 
-        RUNTIME.get_or_init(|| AndroidRuntime {
-            main: ExecutorQueue::new(),
-            default: ExecutorQueue::new(),
-            background: ExecutorQueue::new(),
+        RUNTIME.get_or_init(|| {
+            let workers = crate::config::current().android_worker_thread_count();
+            // The main thread blocks on `instance()` until initialization has
+            // finished.
+            let _ = thread::Builder::new()
+                .name(format!("{}.main", crate::label_prefix()))
+                .spawn(|| {
+                    ON_MAIN.set(true);
+                    let main = &Self::instance().main;
+                    loop {
+                        main.pop()();
+                    }
+                });
+            Self {
+                main: MainQueue::new(),
+                default: ExecutorQueue::new("default", workers),
+                background: ExecutorQueue::new("background", workers),
+            }
         })
     }
 
-    fn queue_for_priority(&self, priority: Priority) -> &ExecutorQueue {
-        match priority {
-            Priority::Background | Priority::Utility => &self.background,
-            Priority::UserInteractive => &self.main,
-            Priority::UserInitiated => &self.default,
-            Priority::Default => &self.default,
+    /// Returns the worker queue for `priority`, or `None` for the main thread.
+    const fn queue_for_priority(&self, priority: Priority) -> Option<&ExecutorQueue> {
+        match priority.class() {
+            SchedClass::Background | SchedClass::Utility => Some(&self.background),
+            SchedClass::Default | SchedClass::UserInitiated => Some(&self.default),
+            SchedClass::UserInteractive => None,
         }
     }
 }
@@ -88,20 +123,90 @@ impl AndroidRuntime {
 #[derive(Clone, Copy, Debug, Default)]
 pub struct AndroidPlatformExecutor;
 
+impl AndroidPlatformExecutor {
+    /// Blocks the current thread until `future` completes, and returns its
+    /// output.
+    ///
+    /// The main thread belongs to the runtime and serves its queue on its
+    /// own, so `future` is polled on the calling thread while main-thread
+    /// work keeps running.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from the main thread, which could then never serve
+    /// the work `future` waits for.
+    #[track_caller]
+    pub fn run_until<F: Future>(future: F) -> F::Output {
+        assert!(
+            !ON_MAIN.get(),
+            "`run_until` cannot be called from the main thread, which is already driven"
+        );
+        crate::mailbox::block_until(future, None)
+            .expect("`block_until` without a deadline only returns once the future completes")
+    }
+
+    /// Blocks the current thread, as [`run_until`](Self::run_until) does,
+    /// until no task has been alive for `grace`.
+    ///
+    /// Tasks and closures count as alive from the moment they are submitted
+    /// until they complete or are dropped, except for daemon tasks of
+    /// [`SpawnOptions::daemon`](crate::SpawnOptions::daemon).
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`run_until`](Self::run_until).
+    #[track_caller]
+    pub fn keep_alive_until_idle(grace: Duration) {
+        Self::run_until(crate::alive::idle(grace));
+    }
+}
+
 impl PlatformExecutor for AndroidPlatformExecutor {
-    fn exec_main(f: impl FnOnce() + Send + 'static) {
-        AndroidRuntime::instance().main.dispatch(Box::new(f));
+    fn exec_main(f: impl FnOnce() + Send + 'static, priority: Option<Priority>) {
+        AndroidRuntime::instance().main.push(Box::new(f), priority);
     }
 
     fn exec(f: impl FnOnce() + Send + 'static, priority: Priority) {
-        AndroidRuntime::instance()
-            .queue_for_priority(priority)
-            .dispatch(Box::new(f));
+        let runtime = AndroidRuntime::instance();
+        let job = with_nice(f, priority);
+        match runtime.queue_for_priority(priority) {
+            Some(queue) => queue.dispatch(job),
+            None => runtime.main.push(job, Some(priority)),
+        }
     }
 
+    /// About 136 years; `thread::sleep` handles longer delays too, but a
+    /// common bound keeps the backends alike.
+    const MAX_DELAY: Duration = Duration::from_secs(1 << 32);
+
     fn exec_after(delay: Duration, f: impl FnOnce() + Send + 'static, priority: Priority) {
-        AndroidRuntime::instance()
-            .queue_for_priority(priority)
-            .dispatch_after(delay, Box::new(f));
+        let runtime = AndroidRuntime::instance();
+        let job = with_nice(f, priority);
+        match runtime.queue_for_priority(priority) {
+            Some(queue) => queue.dispatch_after(delay, job),
+            None if delay.is_zero() => runtime.main.push(job, Some(priority)),
+            None => {
+                let _ = thread::spawn(move || {
+                    thread::sleep(delay);
+                    AndroidRuntime::instance().main.push(job, Some(priority));
+                });
+            }
+        }
+    }
+
+    fn exec_main_after(delay: Duration, f: impl FnOnce() + Send + 'static) {
+        let job: Job = Box::new(f);
+        if delay.is_zero() {
+            AndroidRuntime::instance().main.push(job, None);
+        } else {
+            let _ = thread::spawn(move || {
+                thread::sleep(delay);
+                AndroidRuntime::instance().main.push(job, None);
+            });
+        }
+    }
+
+    fn is_main_thread() -> bool {
+        ON_MAIN.get()
     }
 }
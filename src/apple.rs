@@ -3,23 +3,601 @@
 //! This module provides the native executor implementation for Apple platforms
 //! (macOS, iOS, tvOS, watchOS) by leveraging Grand Central Dispatch for optimal
 //! performance and system integration.
+//!
+//! Main-thread work goes to the GCD main queue unless the application serves
+//! main-thread work elsewhere, see [`set_main_queue`].
+//!
+//! For C APIs that take dispatch objects, [`queue_for`] and [`main_queue`]
+//! return the queues work is submitted to, and [`dispatch_time_from`]
+//! converts an [`Instant`](std::time::Instant) to a dispatch time.
+
+use alloc::{boxed::Box, ffi::CString, format};
+use core::{
+    ffi::{c_long, c_ulong, c_void},
+    fmt, ptr,
+    sync::atomic::{AtomicPtr, Ordering},
+    time::Duration,
+};
+
+#[cfg(feature = "std")]
+use alloc::{sync::Arc, task::Wake};
+#[cfg(feature = "std")]
+use core::{
+    future::Future,
+    pin::pin,
+    sync::atomic::AtomicBool,
+    task::{Context, Poll, Waker},
+};
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+use dispatch::{
+    QueuePriority,
+    ffi::{
+        DISPATCH_QUEUE_CONCURRENT, DISPATCH_TIME_FOREVER, DISPATCH_TIME_NOW, dispatch_after_f,
+        dispatch_async_f, dispatch_function_t, dispatch_get_global_queue, dispatch_get_main_queue,
+        dispatch_object_s, dispatch_queue_attr_t, dispatch_queue_create, dispatch_queue_t,
+        dispatch_release, dispatch_resume, dispatch_retain, dispatch_set_context,
+        dispatch_set_target_queue, dispatch_time, dispatch_time_t,
+    },
+};
 
-use core::time::Duration;
-use dispatch::{Queue, QueuePriority};
+use crate::{
+    PlatformExecutor, Priority, QosClass,
+    priority::{QOS_MIN_RELATIVE_PRIORITY, SchedClass},
+};
 
-use crate::{PlatformExecutor, Priority};
+unsafe extern "C" {
+    fn dispatch_queue_attr_make_with_qos_class(
+        attr: dispatch_queue_attr_t,
+        qos_class: u32,
+        relative_priority: i32,
+    ) -> dispatch_queue_attr_t;
+    fn dispatch_queue_set_specific(
+        queue: dispatch_queue_t,
+        key: *const c_void,
+        context: *mut c_void,
+        destructor: Option<dispatch_function_t>,
+    );
+}
+
+#[cfg(feature = "std")]
+unsafe extern "C" {
+    fn dispatch_get_specific(key: *const c_void) -> *mut c_void;
+}
+
+/// A dispatch source; the `dispatch` crate does not bind sources.
+pub(crate) type DispatchSource = *mut dispatch_object_s;
+
+/// The opaque `dispatch_source_type_t` pointee.
+#[repr(C)]
+pub(crate) struct SourceType {
+    _private: [u8; 0],
+}
+
+#[cfg(feature = "std")]
+unsafe extern "C" {
+    pub(crate) static _dispatch_source_type_data_add: SourceType;
+    pub(crate) static _dispatch_source_type_signal: SourceType;
+    pub(crate) static _dispatch_source_type_memorypressure: SourceType;
+
+    pub(crate) fn dispatch_source_get_data(source: DispatchSource) -> c_ulong;
+    pub(crate) fn dispatch_source_merge_data(source: DispatchSource, value: c_ulong);
+}
+
+unsafe extern "C" {
+    static _dispatch_source_type_timer: SourceType;
+
+    fn dispatch_source_set_timer(
+        source: DispatchSource,
+        start: dispatch_time_t,
+        interval: u64,
+        leeway: u64,
+    );
+
+    pub(crate) fn dispatch_source_create(
+        kind: *const SourceType,
+        handle: usize,
+        mask: c_ulong,
+        queue: dispatch_queue_t,
+    ) -> DispatchSource;
+    pub(crate) fn dispatch_source_set_event_handler_f(
+        source: DispatchSource,
+        handler: dispatch_function_t,
+    );
+    pub(crate) fn dispatch_source_set_cancel_handler_f(
+        source: DispatchSource,
+        handler: dispatch_function_t,
+    );
+    pub(crate) fn dispatch_source_cancel(source: DispatchSource);
+}
 
 impl From<Priority> for QueuePriority {
     fn from(val: Priority) -> Self {
-        match val {
-            Priority::Background => Self::Background,
-            Priority::Utility => Self::Low,
-            Priority::UserInitiated | Priority::UserInteractive => Self::High,
-            // Fallback to Default for any future variants
-            _ => Self::Default,
+        // The legacy queue priorities have no level above `High`.
+        match val.class() {
+            SchedClass::Background => Self::Background,
+            SchedClass::Utility => Self::Low,
+            SchedClass::Default => Self::Default,
+            SchedClass::UserInitiated | SchedClass::UserInteractive => Self::High,
+        }
+    }
+}
+
+/// Returns the quality-of-service class of the global queue for `class`.
+const fn qos_class(class: SchedClass) -> QosClass {
+    match class {
+        SchedClass::Background => QosClass::Background,
+        SchedClass::Utility => QosClass::Utility,
+        SchedClass::Default => QosClass::Default,
+        SchedClass::UserInitiated => QosClass::UserInitiated,
+        SchedClass::UserInteractive => QosClass::UserInteractive,
+    }
+}
+
+// More urgent priorities map to strictly higher quality-of-service classes.
+const _: () = {
+    let mut i = 1;
+    while i < Priority::ALL.len() {
+        assert!(
+            qos_class(Priority::ALL[i - 1].class()).as_raw()
+                < qos_class(Priority::ALL[i].class()).as_raw()
+        );
+        i += 1;
+    }
+};
+
+/// Number of relative priorities GCD accepts within a quality-of-service class.
+const RELATIVE_PRIORITIES: usize = QOS_MIN_RELATIVE_PRIORITY.unsigned_abs() as usize + 1;
+
+/// Labeled private concurrent queues for the standard priorities, indexed by
+/// [`SchedClass::index`].
+///
+/// Each queue targets the global queue of its priority, so work is scheduled
+/// exactly as on the global queues while debuggers and Instruments show the
+/// queue label.
+static STANDARD_QUEUES: [AtomicPtr<dispatch_object_s>; SchedClass::COUNT] =
+    [const { AtomicPtr::new(ptr::null_mut()) }; SchedClass::COUNT];
+
+/// Labeled serial queues that submit main-thread work with a priority
+/// override, indexed by [`SchedClass::index`].
+static MAIN_QUEUES: [AtomicPtr<dispatch_object_s>; SchedClass::COUNT] =
+    [const { AtomicPtr::new(ptr::null_mut()) }; SchedClass::COUNT];
+
+/// Private concurrent queues created for custom quality-of-service classes,
+/// indexed by class and relative priority.
+static CUSTOM_QUEUES: [AtomicPtr<dispatch_object_s>; 5 * RELATIVE_PRIORITIES] =
+    [const { AtomicPtr::new(ptr::null_mut()) }; 5 * RELATIVE_PRIORITIES];
+
+/// Returns the label suffix of the queue for `class`.
+const fn label_suffix(class: SchedClass) -> &'static str {
+    match class {
+        SchedClass::Background => "background",
+        SchedClass::Utility => "utility",
+        SchedClass::Default => "default",
+        SchedClass::UserInitiated => "user-initiated",
+        SchedClass::UserInteractive => "user-interactive",
+    }
+}
+
+/// Returns the queue cached in `slot`, creating it with `create` on first use.
+///
+/// Cached queues are never released, so a published pointer stays valid for
+/// the process lifetime. The cache is lock-free so it works without `std`.
+fn cached_queue(
+    slot: &AtomicPtr<dispatch_object_s>,
+    create: impl FnOnce() -> dispatch_queue_t,
+) -> dispatch_queue_t {
+    let existing = slot.load(Ordering::Acquire);
+    if !existing.is_null() {
+        return existing;
+    }
+    let queue = create();
+    match slot.compare_exchange(ptr::null_mut(), queue, Ordering::AcqRel, Ordering::Acquire) {
+        Ok(_) => queue,
+        Err(winner) => {
+            // Another thread created the queue first; ours was never used.
+            // SAFETY: `queue` was created above and is not shared.
+            unsafe { dispatch_release(queue) };
+            winner
         }
     }
 }
+
+/// Creates a concurrent queue labeled `<prefix>.<suffix>` with `attr`.
+fn labeled_queue(suffix: &str, attr: dispatch_queue_attr_t) -> dispatch_queue_t {
+    let label = CString::new(format!("{}.{suffix}", crate::label_prefix()))
+        .expect("queue label prefixes contain no NUL bytes");
+    // SAFETY: the label is a valid C string, which GCD copies.
+    unsafe { dispatch_queue_create(label.as_ptr(), attr) }
+}
+
+/// Returns the labeled queue for a standard class, creating it on first use.
+fn standard_queue(class: SchedClass) -> dispatch_queue_t {
+    cached_queue(&STANDARD_QUEUES[class.index()], || {
+        // `dispatch_get_global_queue` also accepts quality-of-service
+        // classes, which, unlike the legacy priorities, tell the two user
+        // levels apart. Their values are below 0x40 and fit any `c_long`.
+        #[allow(clippy::cast_lossless, clippy::cast_possible_wrap)]
+        let identifier = qos_class(class).as_raw() as c_long;
+        let queue = labeled_queue(
+            label_suffix(class),
+            ptr::from_ref(DISPATCH_QUEUE_CONCURRENT),
+        );
+        // SAFETY: both queues are valid, and the global queue is never
+        // deallocated.
+        unsafe { dispatch_set_target_queue(queue, dispatch_get_global_queue(identifier, 0)) };
+        queue
+    })
+}
+
+/// Returns the serial queue of the quality-of-service class for `class` that
+/// targets the main queue, creating it on first use.
+///
+/// GCD raises the main thread to the queue's class while it drains the queue.
+fn main_priority_queue(class: SchedClass) -> dispatch_queue_t {
+    cached_queue(&MAIN_QUEUES[class.index()], || {
+        // SAFETY: a null attribute is the serial queue attribute.
+        let attr = unsafe {
+            dispatch_queue_attr_make_with_qos_class(ptr::null(), qos_class(class).as_raw(), 0)
+        };
+        let queue = labeled_queue(&format!("main.{}", label_suffix(class)), attr);
+        // SAFETY: both queues are valid, and the main queue is never
+        // released.
+        unsafe { dispatch_set_target_queue(queue, main_target()) };
+        queue
+    })
+}
+
+/// Returns the cached queue for `class` and `relative`, creating it on first use.
+fn custom_queue(class: QosClass, relative: i8) -> dispatch_queue_t {
+    let class_index = match class {
+        QosClass::UserInteractive => 0,
+        QosClass::UserInitiated => 1,
+        QosClass::Default => 2,
+        QosClass::Utility => 3,
+        QosClass::Background => 4,
+    };
+    // `relative` is within `QOS_MIN_RELATIVE_PRIORITY..=0`.
+    let slot = &CUSTOM_QUEUES[class_index * RELATIVE_PRIORITIES + relative.unsigned_abs() as usize];
+    cached_queue(slot, || {
+        // SAFETY: the attribute is derived from the static concurrent attribute.
+        let attr = unsafe {
+            dispatch_queue_attr_make_with_qos_class(
+                ptr::from_ref(DISPATCH_QUEUE_CONCURRENT),
+                class.as_raw(),
+                i32::from(relative),
+            )
+        };
+        labeled_queue("custom", attr)
+    })
+}
+
+/// Returns the queue that work at `priority` is submitted to.
+///
+/// The returned queue is a cached queue and is never deallocated.
+pub(crate) fn raw_queue(priority: Priority) -> dispatch_queue_t {
+    if let Priority::Custom(custom) = priority
+        && let Some((class, relative)) = custom.as_qos()
+    {
+        return custom_queue(class, relative);
+    }
+    standard_queue(priority.class())
+}
+
+/// The queue that main-thread work is submitted to, null until it is fixed by
+/// [`set_main_queue`] or by the first submission.
+static MAIN_TARGET: AtomicPtr<dispatch_object_s> = AtomicPtr::new(ptr::null_mut());
+
+/// The key of the queue-specific data that marks a queue set with
+/// [`set_main_queue`]; its address is the key, and the value.
+static MAIN_KEY: u8 = 0;
+
+/// Returns the queue that main-thread work is submitted to, fixing it to the
+/// GCD main queue if [`set_main_queue`] was not called.
+fn main_target() -> dispatch_queue_t {
+    let existing = MAIN_TARGET.load(Ordering::Acquire);
+    if !existing.is_null() {
+        return existing;
+    }
+    let main = dispatch_get_main_queue();
+    match MAIN_TARGET.compare_exchange(ptr::null_mut(), main, Ordering::AcqRel, Ordering::Acquire) {
+        Ok(_) => main,
+        Err(winner) => winner,
+    }
+}
+
+/// Returns `true` if [`set_main_queue`] redirected main-thread work away from
+/// the GCD main queue.
+#[cfg(feature = "std")]
+fn main_overridden() -> bool {
+    let target = MAIN_TARGET.load(Ordering::Acquire);
+    !target.is_null() && target != dispatch_get_main_queue()
+}
+
+/// Returns `true` if `queue` is known to be concurrent: a global queue, or a
+/// concurrent queue of this crate.
+fn known_concurrent(queue: dispatch_queue_t) -> bool {
+    let global = Priority::ALL.iter().any(|priority| {
+        #[allow(clippy::cast_lossless, clippy::cast_possible_wrap)]
+        let identifier = qos_class(priority.class()).as_raw() as c_long;
+        // SAFETY: `dispatch_get_global_queue` has no preconditions.
+        queue == unsafe { dispatch_get_global_queue(identifier, 0) }
+    });
+    global
+        || STANDARD_QUEUES
+            .iter()
+            .chain(&CUSTOM_QUEUES)
+            .any(|slot| slot.load(Ordering::Acquire) == queue)
+}
+
+/// The error returned by [`set_main_queue`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SetMainQueueError {
+    /// Main-thread work was already submitted, or another queue was already
+    /// set.
+    AlreadyInUse,
+    /// The queue is concurrent, so main-thread work would not run one job at
+    /// a time.
+    Concurrent,
+}
+
+impl fmt::Display for SetMainQueueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::AlreadyInUse => {
+                "the main queue is already in use; set it once, before main-thread work is \
+                 submitted"
+            }
+            Self::Concurrent => "the main queue must be a serial queue",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SetMainQueueError {}
+
+/// Makes `queue` the queue that main-thread work is submitted to, in place of
+/// the GCD main queue.
+///
+/// This is for processes whose main thread is owned by a runtime that serves
+/// its own queue, such as XPC services: the work of
+/// [`spawn_main`](crate::spawn_main), [`spawn_local`](crate::spawn_local),
+/// main-thread mailboxes and timers then runs on `queue`, and
+/// [`MainToken`](crate::MainToken) and [`current_context`](crate::current_context)
+/// treat the code that `queue` runs as the main thread, the main thread of
+/// the process no longer. [`run_until`](ApplePlatformExecutor::run_until)
+/// panics, since the runtime serves `queue`.
+///
+/// Call it early, before anything is submitted to the main thread; the queue
+/// is kept for the process lifetime.
+///
+/// # Errors
+///
+/// Returns [`SetMainQueueError::AlreadyInUse`] if main-thread work was
+/// already submitted or another queue was set before, and
+/// [`SetMainQueueError::Concurrent`] for a global queue or a concurrent queue
+/// of this crate.
+///
+/// # Safety
+///
+/// `queue` must be a valid dispatch queue, and it must be serial: other
+/// concurrent queues cannot be told apart and would run main-thread work in
+/// parallel, which `!Send` tasks rely on not happening.
+///
+/// # Examples
+///
+/// ```rust
+/// use dispatch::ffi::{DISPATCH_QUEUE_SERIAL, dispatch_queue_create, dispatch_queue_get_label};
+/// use futures_lite::future::block_on;
+/// use native_executor::{ExecContext, MainToken, apple, current_context, spawn_local, spawn_main};
+/// use std::{ffi::CStr, ptr};
+///
+/// // Stands in for the queue the XPC runtime serves
+/// // SAFETY: the label is a valid C string.
+/// let queue = unsafe { dispatch_queue_create(c"com.example.service".as_ptr(), DISPATCH_QUEUE_SERIAL) };
+/// // SAFETY: `queue` is a valid serial queue.
+/// unsafe { apple::set_main_queue(queue) }.unwrap();
+/// assert_eq!(apple::main_queue(), queue);
+///
+/// let task = spawn_main(async {
+///     spawn_local(async {
+///         // SAFETY: a null queue stands for the current one.
+///         let label = unsafe { CStr::from_ptr(dispatch_queue_get_label(ptr::null_mut())) };
+///         (label.to_owned(), current_context(), MainToken::new().is_some())
+///     })
+///     .await
+/// });
+/// let (label, context, token) = block_on(task);
+/// assert_eq!(label.as_c_str(), c"com.example.service");
+/// assert_eq!(context, ExecContext::Main);
+/// assert!(token);
+///
+/// // The main thread of the process is not the main thread of the crate
+/// assert_eq!(current_context(), ExecContext::Foreign);
+/// assert_eq!(
+///     unsafe { apple::set_main_queue(queue) },
+///     Ok(()),
+///     "setting the same queue again is a no-op"
+/// );
+/// ```
+pub unsafe fn set_main_queue(queue: dispatch_queue_t) -> Result<(), SetMainQueueError> {
+    if known_concurrent(queue) {
+        return Err(SetMainQueueError::Concurrent);
+    }
+    let key = ptr::from_ref(&MAIN_KEY).cast::<c_void>();
+    // SAFETY: the caller guarantees that `queue` is valid; the context is
+    // never dereferenced, so it needs no destructor.
+    unsafe {
+        dispatch_retain(queue);
+        dispatch_queue_set_specific(queue, key, key.cast_mut(), None);
+    }
+    match MAIN_TARGET.compare_exchange(ptr::null_mut(), queue, Ordering::AcqRel, Ordering::Acquire)
+    {
+        Ok(_) => Ok(()),
+        Err(winner) => {
+            if winner != queue {
+                // SAFETY: as above; removes the marker set above.
+                unsafe { dispatch_queue_set_specific(queue, key, ptr::null_mut(), None) };
+            }
+            // SAFETY: balances the retain above.
+            unsafe { dispatch_release(queue) };
+            if winner == queue {
+                Ok(())
+            } else {
+                Err(SetMainQueueError::AlreadyInUse)
+            }
+        }
+    }
+}
+
+/// Returns the queue that main-thread work is submitted to: the one set with
+/// [`set_main_queue`], or the GCD main queue.
+///
+/// The queue is valid for the process lifetime.
+#[must_use]
+pub fn main_queue() -> dispatch_queue_t {
+    let target = MAIN_TARGET.load(Ordering::Acquire);
+    if target.is_null() {
+        dispatch_get_main_queue()
+    } else {
+        target
+    }
+}
+
+/// Returns the queue that work at `priority` is submitted to, for C APIs
+/// that take a queue.
+///
+/// Standard priorities map to the labeled queues of this crate, named
+/// `<prefix>.<level>` after [`Config::queue_label_prefix`](crate::Config::queue_label_prefix),
+/// which target the global queue of their quality-of-service class; a
+/// [`Priority::Custom`] with a quality-of-service class maps to a queue
+/// labeled `<prefix>.custom`. The queue is borrowed: it stays valid for the
+/// process lifetime, and must not be released or have its target changed.
+/// Work submitted to it runs outside the crate, without its hooks or its
+/// [priority bounds](crate::Config::priority_floor).
+///
+/// # Examples
+///
+/// ```rust
+/// use dispatch::ffi::dispatch_queue_get_label;
+/// use native_executor::{Priority, apple};
+/// use std::ffi::CStr;
+///
+/// let queue = apple::queue_for(Priority::UserInitiated);
+/// // SAFETY: the queue is valid for the process lifetime.
+/// let label = unsafe { CStr::from_ptr(dispatch_queue_get_label(queue)) };
+/// assert_eq!(label, c"dev.water.native-executor.user-initiated");
+/// assert_eq!(apple::queue_for(Priority::UserInitiated), queue);
+/// ```
+#[must_use]
+pub fn queue_for(priority: Priority) -> dispatch_queue_t {
+    raw_queue(priority)
+}
+
+/// Converts `instant` to the dispatch time of the same moment, for C APIs
+/// that take a `dispatch_time_t`, such as `dispatch_after` or
+/// `dispatch_semaphore_wait`.
+///
+/// The dispatch time is taken relative to now, as the crate does for its own
+/// [timers](crate::timer::Timer). A past `instant` is now, and one too far off to represent is
+/// `DISPATCH_TIME_FOREVER`. Pass
+/// [`Deadline::instant`](crate::timer::Deadline::instant) to convert a
+/// deadline.
+#[cfg(feature = "std")]
+#[must_use]
+pub fn dispatch_time_from(instant: Instant) -> dispatch_time_t {
+    let delay = instant.saturating_duration_since(Instant::now());
+    if delay > ApplePlatformExecutor::MAX_DELAY {
+        DISPATCH_TIME_FOREVER
+    } else {
+        time_after_delay(delay)
+    }
+}
+
+fn context_and_function<F: FnOnce() + Send + 'static>(f: F) -> (*mut c_void, dispatch_function_t) {
+    extern "C" fn trampoline<F: FnOnce()>(context: *mut c_void) {
+        // SAFETY: `context` was produced by `Box::into_raw` below and GCD calls
+        // the function exactly once.
+        let f = unsafe { Box::from_raw(context.cast::<F>()) };
+        f();
+    }
+    (Box::into_raw(Box::new(f)).cast(), trampoline::<F>)
+}
+
+/// Submits `f` to `queue` once `delay` has elapsed, allowing it to run up to
+/// `leeway` late, through a one-shot timer source.
+///
+/// `dispatch_after_f` picks the leeway itself; a timer source takes it as a
+/// parameter, where zero asks for the most precise wake-up the system allows.
+fn after_with_leeway<F: FnOnce() + Send + 'static>(
+    delay: Duration,
+    f: F,
+    queue: dispatch_queue_t,
+    leeway: Duration,
+) {
+    struct OneShot<F> {
+        source: DispatchSource,
+        /// `None` once fired.
+        f: Option<F>,
+    }
+
+    extern "C" fn on_event<F: FnOnce()>(context: *mut c_void) {
+        // SAFETY: the context is valid until the cancel handler runs, which
+        // GCD never runs concurrently with the event handler.
+        let context = unsafe { &mut *context.cast::<OneShot<F>>() };
+        if let Some(f) = context.f.take() {
+            // SAFETY: the source outlives its handlers.
+            unsafe { dispatch_source_cancel(context.source) };
+            f();
+        }
+    }
+
+    extern "C" fn on_cancel<F: FnOnce()>(context: *mut c_void) {
+        // SAFETY: this is the last handler invocation for the source.
+        let context = unsafe { Box::from_raw(context.cast::<OneShot<F>>()) };
+        // SAFETY: the reference of `dispatch_source_create` is released once.
+        unsafe { dispatch_release(context.source) };
+    }
+
+    // SAFETY: the timer source type takes no handle nor mask.
+    let source =
+        unsafe { dispatch_source_create(&raw const _dispatch_source_type_timer, 0, 0, queue) };
+    assert!(
+        !source.is_null(),
+        "failed to create a dispatch timer source"
+    );
+    let context = Box::into_raw(Box::new(OneShot { source, f: Some(f) }));
+    let leeway = u64::try_from(leeway.as_nanos()).unwrap_or(u64::MAX);
+    // SAFETY: the source is valid and suspended; `on_cancel` reclaims the
+    // context and the source after it fired. `DISPATCH_TIME_FOREVER` as the
+    // interval makes the timer fire once.
+    unsafe {
+        dispatch_set_context(source, context.cast());
+        dispatch_source_set_event_handler_f(source, on_event::<F>);
+        dispatch_source_set_cancel_handler_f(source, on_cancel::<F>);
+        dispatch_source_set_timer(
+            source,
+            time_after_delay(delay),
+            DISPATCH_TIME_FOREVER,
+            leeway,
+        );
+        dispatch_resume(source);
+    }
+}
+
+fn time_after_delay(delay: Duration) -> dispatch_time_t {
+    debug_assert!(
+        delay.as_nanos() < 1 << 63,
+        "delays are clamped to `MAX_DELAY` by `dispatch_after`"
+    );
+    i64::try_from(delay.as_nanos()).map_or(DISPATCH_TIME_FOREVER, |nanos| unsafe {
+        // SAFETY: `dispatch_time` has no preconditions.
+        dispatch_time(DISPATCH_TIME_NOW, nanos)
+    })
+}
+
 /// Apple platform executor implementation using Grand Central Dispatch.
 ///
 /// This executor provides optimal performance on Apple platforms by directly
@@ -27,19 +605,173 @@ impl From<Priority> for QueuePriority {
 #[derive(Debug, Clone, Copy, Default)]
 pub struct ApplePlatformExecutor;
 
+#[cfg(feature = "std")]
+#[link(name = "CoreFoundation", kind = "framework")]
+unsafe extern "C" {
+    fn CFRunLoopGetCurrent() -> *mut c_void;
+    fn CFRunLoopRun();
+    fn CFRunLoopStop(run_loop: *mut c_void);
+}
+
+/// Whether [`ApplePlatformExecutor::run_until`] runs the main run loop.
+#[cfg(feature = "std")]
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+#[cfg(feature = "std")]
+impl ApplePlatformExecutor {
+    /// Runs the main run loop until `future` completes, and returns its
+    /// output.
+    ///
+    /// `future` is polled on the main thread, so it can use
+    /// [`spawn_local`](crate::spawn_local) and main-thread mailboxes, and it
+    /// does not need to be `Send` or `'static`. In between, `CFRunLoopRun`
+    /// serves the GCD main queue along with the run loop's other sources;
+    /// waking `future` queues a job that stops the run loop so it can be
+    /// polled again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if not called on the main thread, if called while already
+    /// running, such as from `future`, or if [`set_main_queue`] redirected
+    /// main-thread work.
+    #[track_caller]
+    pub fn run_until<F: Future>(future: F) -> F::Output {
+        assert!(
+            !main_overridden(),
+            "`run_until` cannot serve the main queue set with `set_main_queue`"
+        );
+        assert!(
+            Self::is_main_thread(),
+            "`run_until` must be called on the main thread"
+        );
+        assert!(
+            !RUNNING.swap(true, Ordering::AcqRel),
+            "`run_until` cannot be nested"
+        );
+        let _running = Running;
+        crate::main_loop::entered();
+
+        let mut future = pin!(future);
+        let woken = Arc::new(MainWaker(AtomicBool::new(true)));
+        let waker = Waker::from(woken.clone());
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if woken.0.swap(false, Ordering::AcqRel)
+                && let Poll::Ready(output) = future.as_mut().poll(&mut cx)
+            {
+                return output;
+            }
+            // SAFETY: called on the main thread; returns once stopped.
+            unsafe { CFRunLoopRun() };
+        }
+    }
+
+    /// Runs the main run loop, as [`run_until`](Self::run_until) does, until
+    /// no task has been alive for `grace`.
+    ///
+    /// Tasks and closures count as alive from the moment they are submitted
+    /// until they complete or are dropped, except for daemon tasks of
+    /// [`SpawnOptions::daemon`](crate::SpawnOptions::daemon).
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`run_until`](Self::run_until).
+    #[track_caller]
+    pub fn keep_alive_until_idle(grace: Duration) {
+        Self::run_until(crate::alive::idle(grace));
+    }
+}
+
+/// Clears [`RUNNING`] when dropped.
+#[cfg(feature = "std")]
+struct Running;
+
+#[cfg(feature = "std")]
+impl Drop for Running {
+    fn drop(&mut self) {
+        RUNNING.store(false, Ordering::Release);
+    }
+}
+
+/// Wakes the future of [`ApplePlatformExecutor::run_until`] by stopping the
+/// run loop from a main-queue job.
+///
+/// Stopping from inside the run loop is never lost, unlike a `CFRunLoopStop`
+/// issued before `CFRunLoopRun` has started.
+#[cfg(feature = "std")]
+struct MainWaker(AtomicBool);
+
+#[cfg(feature = "std")]
+impl Wake for MainWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        if !self.0.swap(true, Ordering::AcqRel) {
+            ApplePlatformExecutor::exec_main(
+                // SAFETY: main-queue jobs run on the main thread, inside the
+                // run loop started by `run_until`.
+                || unsafe { CFRunLoopStop(CFRunLoopGetCurrent()) },
+                None,
+            );
+        }
+    }
+}
+
 impl PlatformExecutor for ApplePlatformExecutor {
-    fn exec_main(f: impl FnOnce() + Send + 'static) {
-        let main = Queue::main();
-        main.exec_async(f);
+    fn exec_main(f: impl FnOnce() + Send + 'static, priority: Option<Priority>) {
+        let queue = priority.map_or_else(main_target, |priority| {
+            main_priority_queue(priority.class())
+        });
+        let (context, work) = context_and_function(f);
+        // SAFETY: see `exec`.
+        unsafe { dispatch_async_f(queue, context, work) };
     }
 
     fn exec(f: impl FnOnce() + Send + 'static, priority: Priority) {
-        let queue = Queue::global(priority.into());
-        queue.exec_async(f);
+        let (context, work) = context_and_function(f);
+        // SAFETY: the queue is valid for the process lifetime and the context
+        // is consumed exactly once by `work`.
+        unsafe { dispatch_async_f(raw_queue(priority), context, work) };
     }
 
+    /// About 136 years, whose nanoseconds leave `dispatch_time` room for
+    /// the current time within its `i64` offset.
+    const MAX_DELAY: Duration = Duration::from_secs(1 << 32);
+
     fn exec_after(delay: Duration, f: impl FnOnce() + Send + 'static, priority: Priority) {
-        let queue = Queue::global(priority.into());
-        queue.exec_after(delay, f);
+        let (context, work) = context_and_function(f);
+        // SAFETY: see `exec`.
+        unsafe {
+            dispatch_after_f(time_after_delay(delay), raw_queue(priority), context, work);
+        }
+    }
+
+    fn exec_after_with_leeway(
+        delay: Duration,
+        f: impl FnOnce() + Send + 'static,
+        priority: Priority,
+        leeway: Duration,
+    ) {
+        after_with_leeway(delay, f, raw_queue(priority), leeway);
+    }
+
+    fn exec_main_after(delay: Duration, f: impl FnOnce() + Send + 'static) {
+        let (context, work) = context_and_function(f);
+        // SAFETY: see `exec`.
+        unsafe { dispatch_after_f(time_after_delay(delay), main_target(), context, work) };
+    }
+
+    #[cfg(feature = "std")]
+    fn is_main_thread() -> bool {
+        if main_overridden() {
+            // The marker is also found from the queues that target the one
+            // set, such as those of `main_priority_queue`.
+            // SAFETY: `dispatch_get_specific` has no preconditions.
+            return !unsafe { dispatch_get_specific(ptr::from_ref(&MAIN_KEY).cast()) }.is_null();
+        }
+        // SAFETY: `pthread_main_np` has no preconditions.
+        unsafe { libc::pthread_main_np() != 0 }
     }
 }
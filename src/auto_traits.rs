@@ -0,0 +1,106 @@
+//! Compile-time checks of the auto traits of public types.
+//!
+//! Types that are stored in shared structures or moved across tasks must stay
+//! `Send` and `Sync` as their internals change; these assertions fail the
+//! build if, for example, an `Rc` sneaks into one of them.
+
+#[cfg(feature = "std")]
+const fn send<T: Send>() {}
+const fn send_sync<T: Send + Sync>() {}
+
+const _: () = {
+    send_sync::<crate::timer::Timer>();
+    send_sync::<crate::timer::Delay<crate::timer::Timer>>();
+    send_sync::<crate::timer::FirstOf<crate::timer::Timer>>();
+    send_sync::<crate::timer::Either<u32, crate::timer::Elapsed>>();
+    send_sync::<crate::Priority>();
+    send_sync::<crate::hooks::TaskInfo>();
+    send_sync::<crate::SpawnOptions>();
+    send_sync::<crate::WebTaskType>();
+    send_sync::<crate::MainExecutor>();
+    send_sync::<crate::DefaultExecutor>();
+    send_sync::<crate::NativeExecutor>();
+    send_sync::<crate::Task<u32>>();
+    send_sync::<crate::TaskHandle<u32>>();
+};
+
+#[cfg(feature = "std")]
+const _: () = {
+    use std::rc::Rc;
+
+    send_sync::<crate::timer::Interval>();
+    send_sync::<crate::WithPriority<crate::timer::Timer>>();
+    send_sync::<crate::timer::Deadline>();
+    send_sync::<crate::timer::Measured>();
+    send_sync::<crate::timer::Debouncer>();
+    send_sync::<crate::timer::Throttle>();
+    send_sync::<crate::timer::DeadlineExceeded>();
+    send_sync::<crate::cancel::CancellationToken>();
+    send_sync::<crate::cancel::Cancelled>();
+    send_sync::<crate::timer::Timeout<crate::timer::Timer>>();
+    send_sync::<crate::timer::Race<crate::timer::Timer, crate::timer::Timer>>();
+    send_sync::<crate::timer::FramePacer>();
+    send_sync::<crate::bus::Bus<u32>>();
+    send_sync::<crate::bus::Subscription<u32>>();
+    send_sync::<crate::notify::Notify>();
+    send_sync::<crate::notify::WakeHandle<'static>>();
+    // Jobs run on the owning thread, so the handle is shareable even when
+    // the value itself is not `Send`.
+    send_sync::<crate::Mailbox<u32>>();
+    send_sync::<crate::Mailbox<Rc<u32>>>();
+    send_sync::<crate::mailbox::MailboxError>();
+    send_sync::<crate::service::ServiceHandle<u32, u32>>();
+    send_sync::<crate::service::ServiceError>();
+    send_sync::<crate::callback::Completer<u32>>();
+    send_sync::<crate::callback::CallbackFuture<u32>>();
+    send_sync::<crate::callback::MainCallback<u32>>();
+    send_sync::<crate::SupervisedTask<u32>>();
+    send_sync::<crate::AbortHandle>();
+    send_sync::<crate::ScheduledHandle>();
+    send_sync::<crate::MainValue<u32>>();
+    send_sync::<crate::DrainHandle>();
+    send_sync::<crate::ExecContext>();
+    send::<crate::JoinError>();
+    send::<crate::Error>();
+    send_sync::<crate::supervisor::RestartPolicy>();
+    send_sync::<crate::supervisor::SupervisorHandle>();
+    send_sync::<crate::supervisor::StateWatch>();
+    send_sync::<crate::hooks::TaskHooks>();
+    send_sync::<crate::Config>();
+    send_sync::<crate::ClampExemption>();
+    send_sync::<crate::AlreadyInitialized>();
+    send_sync::<crate::lifecycle::AppState>();
+    send_sync::<crate::main_loop::MainLoopState>();
+    send_sync::<crate::main_loop::Warning>();
+    send_sync::<crate::MainExecutorNotRunning<crate::timer::Timer>>();
+    send_sync::<crate::mailbox::Dropped>();
+    send_sync::<crate::mailbox::Budget>();
+    send_sync::<crate::mailbox::StepResult>();
+    send_sync::<crate::Handle>();
+    send_sync::<crate::TaskGroup<u32>>();
+    send_sync::<crate::ShutdownReport>();
+    send_sync::<crate::SpawnError>();
+    send_sync::<crate::SpawnOverflow>();
+};
+
+#[cfg(all(unix, feature = "std"))]
+const _: () = {
+    send_sync::<crate::signals::Signals>();
+    send_sync::<crate::signals::MemoryPressure>();
+};
+
+#[cfg(feature = "deadlock-detect")]
+const _: () = {
+    send_sync::<crate::deadlock::Warning>();
+};
+
+#[cfg(target_vendor = "apple")]
+const _: () = {
+    send_sync::<crate::apple::SetMainQueueError>();
+};
+
+#[cfg(all(target_vendor = "apple", feature = "dispatch-io"))]
+const _: () = {
+    send_sync::<crate::io::File>();
+    send_sync::<crate::io::ReadStream>();
+};
@@ -0,0 +1,657 @@
+//! Broadcasting events to several mailboxes.
+//!
+//! A [`Bus`] fans every published event out to its subscribers: mailboxes,
+//! which receive the event through their own queue, and plain functions. The
+//! producer no longer has to hold every mailbox, and subscribers whose
+//! mailbox is gone are pruned on the next publish.
+//!
+//! # Priorities
+//!
+//! By default, [`publish`](Bus::publish) hands each event to every
+//! subscriber itself: it queues the event on subscriber mailboxes, and calls
+//! subscriber functions. A subscriber registered with a priority, such as
+//! with [`subscribe_mailbox_with_priority`](Bus::subscribe_mailbox_with_priority),
+//! gets its own queue of events instead, which a job of the thread pool at
+//! that priority delivers. Publishing then only queues the event, so a slow
+//! subscriber, or a full bounded mailbox, delays neither the publisher nor
+//! the other subscribers. [`Subscription::lag`] tells how far behind a
+//! subscriber is.
+//!
+//! # Ordering
+//!
+//! Each subscriber sees events in the order they were published. Events
+//! published concurrently from several threads have no order among each
+//! other, and nothing is promised across subscribers: one mailbox may run an
+//! event before another has received it.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # #[cfg(not(target_vendor = "apple"))] {
+//! use futures_lite::future::block_on;
+//! use native_executor::{Mailbox, bus::Bus, polyfill, spawn_main};
+//! use std::thread;
+//!
+//! #[derive(Debug, Clone)]
+//! enum Event {
+//!     Renamed(String),
+//!     Deleted,
+//! }
+//!
+//! thread::spawn(polyfill::start_main_executor);
+//! let (title, history) =
+//!     block_on(spawn_main(async { (Mailbox::main(String::new()), Mailbox::main(Vec::new())) }));
+//!
+//! let bus = Bus::new();
+//! bus.subscribe_mailbox(&title, |title: &mut String, event| {
+//!     if let Event::Renamed(name) = event {
+//!         *title = name;
+//!     }
+//! })
+//! .detach();
+//! bus.subscribe_mailbox(&history, |history: &mut Vec<Event>, event| history.push(event))
+//!     .detach();
+//!
+//! bus.publish(Event::Renamed("Draft".into()));
+//! bus.publish(Event::Deleted);
+//!
+//! assert_eq!(title.call_blocking(|title| title.clone()), "Draft");
+//! assert_eq!(history.call_blocking(|history| history.len()), 2);
+//! # }
+//! ```
+
+use alloc::collections::VecDeque;
+use core::{
+    fmt,
+    panic::Location,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use std::{
+    sync::{Arc, Mutex, PoisonError, Weak},
+    thread,
+};
+
+use crate::{Mailbox, Priority, execute_with_priority};
+
+/// Delivers an event published at the given location to one subscriber,
+/// returning `false` once the subscriber is gone.
+type Deliver<E> = Box<dyn Fn(E, &'static Location<'static>) -> bool + Send + Sync>;
+
+/// What a producer does when the queue of a bounded mailbox is full.
+///
+/// [`Bus::publish`] applies the overflow of the bus to its subscribers, and
+/// [`Mailbox::handle`] that of the mailbox, as set with
+/// [`MailboxBuilder::overflow`](crate::mailbox::MailboxBuilder::overflow).
+/// Unbounded mailboxes are never full. Every discarded job is counted by
+/// [`Mailbox::dropped`] and reported to the
+/// [`on_drop`](crate::mailbox::MailboxBuilder::on_drop) callback of the
+/// mailbox.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Blocks the producing thread until the queue has room, like
+    /// [`Mailbox::blocking_handle`].
+    ///
+    /// A mailbox whose value is owned by the producing thread cannot drain
+    /// while the thread is blocked, so it is treated as
+    /// [`DropOldest`](Self::DropOldest) instead.
+    #[default]
+    Block,
+    /// Discards the oldest job in the queue to make room for the new one.
+    ///
+    /// The discarded job may be any update of the mailbox, not just an
+    /// earlier event; a discarded [`Mailbox::call`] panics as if the mailbox
+    /// had ended. Use this for mailboxes that only receive events.
+    DropOldest,
+    /// Discards the new job, keeping the queue as it is.
+    DropNewest,
+    /// Discards the new job like [`DropNewest`](Self::DropNewest), and makes
+    /// producers that report errors, such as [`Mailbox::blocking_handle`],
+    /// fail with [`MailboxError::Full`](crate::mailbox::MailboxError::Full)
+    /// instead of waiting for room.
+    Fail,
+}
+
+/// A broadcast channel for events of type `E`.
+///
+/// Cloning a bus is cheap, and every clone publishes to the same
+/// subscribers. See the [module documentation](self) for the ordering
+/// guarantees.
+pub struct Bus<E> {
+    inner: Arc<Inner<E>>,
+}
+
+struct Inner<E> {
+    subscribers: Mutex<Vec<Arc<Subscriber<E>>>>,
+    overflow: Overflow,
+    next_id: AtomicU64,
+}
+
+struct Subscriber<E> {
+    id: u64,
+    deliver: Deliver<E>,
+    /// The queue of events delivered by jobs of the thread pool; `None` for
+    /// subscribers delivered to by the publisher.
+    lane: Option<Lane<E>>,
+    counts: Arc<Counts>,
+}
+
+/// The events of a subscriber with a priority, waiting for its delivery job.
+struct Lane<E> {
+    priority: Priority,
+    /// The events in publish order, and whether a delivery job is queued or
+    /// running.
+    pending: Mutex<(VecDeque<(E, &'static Location<'static>)>, bool)>,
+}
+
+/// The progress of a subscriber, for [`Subscription::lag`].
+#[derive(Default)]
+struct Counts {
+    published: AtomicU64,
+    /// Events applied by the subscriber, or discarded by its full mailbox.
+    delivered: AtomicU64,
+}
+
+impl Counts {
+    fn lag(&self) -> u64 {
+        // Read in this order, `delivered` never exceeds `published`.
+        let delivered = self.delivered.load(Ordering::Acquire);
+        self.published
+            .load(Ordering::Acquire)
+            .saturating_sub(delivered)
+    }
+}
+
+/// Counts an event as delivered when dropped, whether it was applied or
+/// discarded.
+struct Delivered(Arc<Counts>);
+
+impl Drop for Delivered {
+    fn drop(&mut self) {
+        self.0.delivered.fetch_add(1, Ordering::Release);
+    }
+}
+
+impl<E: Send + 'static> Subscriber<E> {
+    /// Hands `event` to the subscriber, or queues it for its delivery job,
+    /// returning `false` once the subscriber is found gone.
+    fn publish(
+        self: &Arc<Self>,
+        bus: &Arc<Inner<E>>,
+        event: E,
+        caller: &'static Location<'static>,
+    ) -> bool {
+        self.counts.published.fetch_add(1, Ordering::Release);
+        let Some(lane) = &self.lane else {
+            return (self.deliver)(event, caller);
+        };
+        let mut pending = lane.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        pending.0.push_back((event, caller));
+        let idle = !core::mem::replace(&mut pending.1, true);
+        drop(pending);
+        if idle {
+            let (subscriber, bus) = (self.clone(), Arc::downgrade(bus));
+            execute_with_priority(move || subscriber.drain(&bus), lane.priority);
+        }
+        true
+    }
+
+    /// Delivers the queued events in order, until none is left; the job of
+    /// [`publish`](Self::publish).
+    fn drain(&self, bus: &Weak<Inner<E>>) {
+        /// Lets the next publish queue a new job if a delivery panics.
+        struct Idle<'a, E>(&'a Lane<E>);
+
+        impl<E> Drop for Idle<'_, E> {
+            fn drop(&mut self) {
+                if thread::panicking() {
+                    self.0
+                        .pending
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .1 = false;
+                }
+            }
+        }
+
+        let lane = self
+            .lane
+            .as_ref()
+            .expect("only subscribers with a lane drain");
+        let _idle = Idle(lane);
+        loop {
+            let mut pending = lane.pending.lock().unwrap_or_else(PoisonError::into_inner);
+            let Some((event, caller)) = pending.0.pop_front() else {
+                pending.1 = false;
+                return;
+            };
+            drop(pending);
+            if !(self.deliver)(event, caller) {
+                lane.pending
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .0
+                    .clear();
+                if let Some(bus) = bus.upgrade() {
+                    bus.remove(&[self.id]);
+                }
+                return;
+            }
+        }
+    }
+}
+
+impl<E> Inner<E> {
+    fn remove(&self, ids: &[u64]) {
+        self.subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|subscriber| !ids.contains(&subscriber.id));
+    }
+}
+
+impl<E> Clone for Bus<E> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<E> fmt::Debug for Bus<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Bus")
+            .field("subscribers", &self.subscriber_count())
+            .field("overflow", &self.inner.overflow)
+            .finish()
+    }
+}
+
+impl<E: Clone + Send + 'static> Default for Bus<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> Bus<E> {
+    /// Returns the number of subscribers, including those found gone but not
+    /// yet pruned by a publish.
+    #[must_use]
+    pub fn subscriber_count(&self) -> usize {
+        self.inner
+            .subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+}
+
+impl<E: Clone + Send + 'static> Bus<E> {
+    /// Creates a bus that blocks on full subscriber mailboxes.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_overflow(Overflow::Block)
+    }
+
+    /// Creates a bus that handles full subscriber mailboxes with `overflow`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(not(target_vendor = "apple"))] {
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{Mailbox, MainExecutor, bus::{Bus, Overflow}, polyfill, spawn_main};
+    /// use std::{sync::mpsc, thread};
+    ///
+    /// thread::spawn(polyfill::start_main_executor);
+    /// let frames = block_on(spawn_main(async { Mailbox::bounded(MainExecutor, Vec::new(), 1) }));
+    /// let bus = Bus::with_overflow(Overflow::DropOldest);
+    /// bus.subscribe_mailbox(&frames, |frames: &mut Vec<u32>, frame| frames.push(frame))
+    ///     .detach();
+    ///
+    /// // Stall the consumer
+    /// let (resume, stalled) = mpsc::channel::<()>();
+    /// let (started, running) = mpsc::channel();
+    /// frames.handle(move |_| {
+    ///     started.send(()).unwrap();
+    ///     let _ = stalled.recv();
+    /// });
+    /// running.recv().unwrap();
+    ///
+    /// // Only the newest frame fits in the queue
+    /// for frame in 0..3 {
+    ///     bus.publish(frame);
+    /// }
+    /// drop(resume);
+    /// assert_eq!(frames.call_blocking(|frames| frames.clone()), [2]);
+    /// assert_eq!(frames.dropped(), 2);
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn with_overflow(overflow: Overflow) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                subscribers: Mutex::new(Vec::new()),
+                overflow,
+                next_id: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Subscribes `mailbox`, which runs `apply` on its value for every event.
+    ///
+    /// The subscription does not keep the mailbox open. Once it is dropped
+    /// or closed, the subscriber is pruned on the next publish.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(not(target_vendor = "apple"))] {
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{Mailbox, bus::Bus, polyfill, spawn_main};
+    /// use std::thread;
+    ///
+    /// thread::spawn(polyfill::start_main_executor);
+    /// let bus = Bus::new();
+    /// let mailboxes = block_on(spawn_main(async { [Mailbox::main(0), Mailbox::main(0)] }));
+    /// for mailbox in &mailboxes {
+    ///     bus.subscribe_mailbox(mailbox, |sum: &mut u64, n| *sum += n).detach();
+    /// }
+    ///
+    /// // A dropped mailbox is pruned by the next publish
+    /// let [kept, dropped] = mailboxes;
+    /// drop(dropped);
+    /// assert_eq!(bus.subscriber_count(), 2);
+    /// bus.publish(1);
+    /// assert_eq!(bus.subscriber_count(), 1);
+    ///
+    /// // So is a closed one
+    /// block_on(kept.close());
+    /// bus.publish(2);
+    /// assert_eq!(bus.subscriber_count(), 0);
+    /// # }
+    /// ```
+    ///
+    /// Events published by one thread arrive in order:
+    ///
+    /// ```rust
+    /// # #[cfg(not(target_vendor = "apple"))] {
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{Mailbox, bus::Bus, polyfill, spawn_main};
+    /// use std::thread;
+    ///
+    /// thread::spawn(polyfill::start_main_executor);
+    /// let bus = Bus::new();
+    /// let mailboxes = block_on(spawn_main(async { [Mailbox::main(Vec::new()), Mailbox::main(Vec::new())] }));
+    /// for mailbox in &mailboxes {
+    ///     bus.subscribe_mailbox(mailbox, |seen: &mut Vec<u32>, n| seen.push(n)).detach();
+    /// }
+    ///
+    /// let publisher = bus.clone();
+    /// thread::spawn(move || (0..1_000).for_each(|n| publisher.publish(n)))
+    ///     .join()
+    ///     .unwrap();
+    /// for mailbox in &mailboxes {
+    ///     assert_eq!(mailbox.call_blocking(|seen| seen.clone()), (0..1_000).collect::<Vec<_>>());
+    /// }
+    /// # }
+    /// ```
+    pub fn subscribe_mailbox<T: 'static>(
+        &self,
+        mailbox: &Mailbox<T>,
+        apply: impl Fn(&mut T, E) + Send + Sync + 'static,
+    ) -> Subscription<E> {
+        self.subscribe_mailbox_in(mailbox, apply, None)
+    }
+
+    /// Subscribes `mailbox` as [`subscribe_mailbox`](Self::subscribe_mailbox)
+    /// does, queuing each event on it from a job of the thread pool at
+    /// `priority` rather than from the publisher.
+    ///
+    /// `apply` still runs wherever the mailbox runs its jobs. The publisher
+    /// never blocks on the mailbox, whatever the [`Overflow`] of the bus: a
+    /// full bounded mailbox with [`Overflow::Block`] blocks the delivery job
+    /// instead, and events wait in the subscriber's queue meanwhile.
+    pub fn subscribe_mailbox_with_priority<T: 'static>(
+        &self,
+        mailbox: &Mailbox<T>,
+        apply: impl Fn(&mut T, E) + Send + Sync + 'static,
+        priority: Priority,
+    ) -> Subscription<E> {
+        self.subscribe_mailbox_in(mailbox, apply, Some(priority))
+    }
+
+    fn subscribe_mailbox_in<T: 'static>(
+        &self,
+        mailbox: &Mailbox<T>,
+        apply: impl Fn(&mut T, E) + Send + Sync + 'static,
+        priority: Option<Priority>,
+    ) -> Subscription<E> {
+        let mailbox = mailbox.downgrade();
+        let apply = Arc::new(apply);
+        let overflow = self.inner.overflow;
+        let counts = Arc::new(Counts::default());
+        let delivered = counts.clone();
+        let deliver: Deliver<E> = Box::new(move |event, caller| {
+            let (apply, delivered) = (apply.clone(), Delivered(delivered.clone()));
+            let update = Box::new(move |value: &mut T| {
+                apply(value, event);
+                drop(delivered);
+            });
+            mailbox.send(update, overflow, caller)
+        });
+        self.subscribe(deliver, counts, priority)
+    }
+
+    /// Subscribes `f`, which is called on the publishing thread for every
+    /// event.
+    ///
+    /// `f` must not publish on or subscribe to this bus.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use native_executor::bus::Bus;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let bus = Bus::new();
+    /// let seen = Arc::new(Mutex::new(Vec::new()));
+    /// let log = seen.clone();
+    /// let subscription = bus.subscribe_fn(move |event| log.lock().unwrap().push(event));
+    ///
+    /// bus.publish("opened");
+    /// // Dropping the subscription unsubscribes
+    /// drop(subscription);
+    /// bus.publish("closed");
+    ///
+    /// assert_eq!(*seen.lock().unwrap(), ["opened"]);
+    /// assert_eq!(bus.subscriber_count(), 0);
+    /// ```
+    pub fn subscribe_fn(&self, f: impl Fn(E) + Send + Sync + 'static) -> Subscription<E> {
+        self.subscribe_fn_in(f, None)
+    }
+
+    /// Subscribes `f`, which is called for every event by a job of the
+    /// thread pool at `priority`, one event at a time and in publish order.
+    ///
+    /// Unlike [`subscribe_fn`](Self::subscribe_fn), publishing only queues
+    /// the event, so however long `f` takes, it delays neither the publisher
+    /// nor the other subscribers.
+    ///
+    /// # Examples
+    ///
+    /// A slow analytics recorder falls behind without holding back the view
+    /// model:
+    ///
+    /// ```rust
+    /// use native_executor::{Config, Priority, bus::Bus, init};
+    /// use std::{
+    ///     sync::mpsc,
+    ///     thread,
+    ///     time::{Duration, Instant},
+    /// };
+    ///
+    /// init(Config::new().polyfill_threads(2).android_worker_threads(2)).unwrap();
+    /// let bus = Bus::new();
+    ///
+    /// let (seen, view_model) = mpsc::channel();
+    /// let _view_model = bus.subscribe_fn_with_priority(
+    ///     move |event: u32| seen.send(event).unwrap(),
+    ///     Priority::UserInitiated,
+    /// );
+    /// let (recorded, recorder) = mpsc::channel();
+    /// let analytics = bus.subscribe_fn_with_priority(
+    ///     move |event: u32| {
+    ///         thread::sleep(Duration::from_millis(100));
+    ///         recorded.send(event).unwrap();
+    ///     },
+    ///     Priority::Background,
+    /// );
+    ///
+    /// let start = Instant::now();
+    /// for event in 0..10 {
+    ///     bus.publish(event);
+    /// }
+    /// let events: Vec<_> = view_model.iter().take(10).collect();
+    /// assert_eq!(events, (0..10).collect::<Vec<_>>());
+    /// assert!(start.elapsed() < Duration::from_millis(500), "{:?}", start.elapsed());
+    ///
+    /// // The recorder has a backlog, which it works off in order
+    /// assert!(analytics.lag() >= 5, "lag {}", analytics.lag());
+    /// assert_eq!(recorder.iter().take(10).collect::<Vec<_>>(), events);
+    /// // An event is counted once `f` has returned
+    /// while analytics.lag() > 0 {
+    ///     thread::yield_now();
+    /// }
+    /// ```
+    pub fn subscribe_fn_with_priority(
+        &self,
+        f: impl Fn(E) + Send + Sync + 'static,
+        priority: Priority,
+    ) -> Subscription<E> {
+        self.subscribe_fn_in(f, Some(priority))
+    }
+
+    fn subscribe_fn_in(
+        &self,
+        f: impl Fn(E) + Send + Sync + 'static,
+        priority: Option<Priority>,
+    ) -> Subscription<E> {
+        let counts = Arc::new(Counts::default());
+        let delivered = counts.clone();
+        let deliver: Deliver<E> = Box::new(move |event, _| {
+            let _delivered = Delivered(delivered.clone());
+            f(event);
+            true
+        });
+        self.subscribe(deliver, counts, priority)
+    }
+
+    fn subscribe(
+        &self,
+        deliver: Deliver<E>,
+        counts: Arc<Counts>,
+        priority: Option<Priority>,
+    ) -> Subscription<E> {
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        let lane = priority.map(|priority| Lane {
+            priority,
+            pending: Mutex::new((VecDeque::new(), false)),
+        });
+        self.inner
+            .subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Arc::new(Subscriber {
+                id,
+                deliver,
+                lane,
+                counts: counts.clone(),
+            }));
+        Subscription {
+            bus: Arc::downgrade(&self.inner),
+            id,
+            counts,
+        }
+    }
+
+    /// Sends `event` to every subscriber, pruning those that are gone.
+    ///
+    /// Mailbox subscribers receive the event through their queue, so this
+    /// returns before the event has been applied, unless a full bounded
+    /// mailbox makes it block (see [`Overflow`]). Subscribers with a
+    /// priority only have the event queued for their delivery job, and are
+    /// pruned by that job. Events discarded by a full mailbox are reported to
+    /// it as published here.
+    #[track_caller]
+    pub fn publish(&self, event: E) {
+        let caller = Location::caller();
+        // Subscribers are called without the lock held, so they may block.
+        let subscribers = self
+            .inner
+            .subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        let Some((last, rest)) = subscribers.split_last() else {
+            return;
+        };
+        let mut gone: Vec<u64> = rest
+            .iter()
+            .filter(|subscriber| !subscriber.publish(&self.inner, event.clone(), caller))
+            .map(|subscriber| subscriber.id)
+            .collect();
+        if !last.publish(&self.inner, event, caller) {
+            gone.push(last.id);
+        }
+        if !gone.is_empty() {
+            self.inner.remove(&gone);
+        }
+    }
+}
+
+/// Keeps a subscriber of a [`Bus`] subscribed until dropped.
+///
+/// Call [`detach`](Self::detach) to keep the subscriber for the lifetime of
+/// the bus instead; a detached mailbox subscriber is still pruned once its
+/// mailbox is gone.
+#[must_use = "dropping a subscription unsubscribes; call `detach` to keep it"]
+pub struct Subscription<E> {
+    bus: Weak<Inner<E>>,
+    id: u64,
+    counts: Arc<Counts>,
+}
+
+impl<E> Subscription<E> {
+    /// Keeps the subscriber subscribed after the subscription is dropped.
+    pub fn detach(mut self) {
+        self.bus = Weak::new();
+    }
+
+    /// Returns how many events the subscriber is behind: those published to
+    /// it minus those it has applied.
+    ///
+    /// An event counts as applied once the subscriber function has returned,
+    /// or the mailbox job has run. Events discarded by a full mailbox count
+    /// as applied too, as they will never be.
+    #[must_use]
+    pub fn lag(&self) -> u64 {
+        self.counts.lag()
+    }
+}
+
+impl<E> fmt::Debug for Subscription<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscription")
+            .field("id", &self.id)
+            .field("lag", &self.lag())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<E> Drop for Subscription<E> {
+    fn drop(&mut self) {
+        if let Some(bus) = self.bus.upgrade() {
+            bus.remove(&[self.id]);
+        }
+    }
+}
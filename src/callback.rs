@@ -0,0 +1,406 @@
+//! Futures completed by one-shot platform callbacks.
+//!
+//! Platform APIs such as GCD handlers or Objective-C completion blocks report
+//! their result through a callback. [`future`] creates a [`Completer`] that can
+//! be moved into such a callback together with a future that resolves once the
+//! callback completes it.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use futures_lite::future::block_on;
+//! use native_executor::callback;
+//! use std::thread;
+//!
+//! // Stand-in for a platform API that reports its result on another thread
+//! fn compute_async(on_done: impl FnOnce(u32) + Send + 'static) {
+//!     thread::spawn(move || on_done(42));
+//! }
+//!
+//! let (completer, result) = callback::future();
+//! compute_async(move |value| completer.complete(value));
+//! assert_eq!(block_on(result), 42);
+//! ```
+//!
+//! APIs that must also be started on the main thread are covered by
+//! [`MainCallback`].
+
+use core::{
+    any::type_name,
+    ffi::c_void,
+    fmt,
+    future::{Future, poll_fn},
+    pin::{Pin, pin},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::{dispatch_main, timer::Timer};
+
+struct State<T> {
+    value: Option<T>,
+    waker: Option<Waker>,
+    completed: bool,
+    cancelled: bool,
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// The sending half of a callback future.
+///
+/// A `Completer` is cheap to clone and can be completed from any thread. Only
+/// the first completion takes effect.
+pub struct Completer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Clone for Completer<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Completer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.shared.lock();
+        f.debug_struct("Completer")
+            .field("completed", &state.completed)
+            .field("cancelled", &state.cancelled)
+            .finish()
+    }
+}
+
+impl<T> Completer<T> {
+    /// Completes the future with `value`.
+    ///
+    /// Completing an already completed future has no effect; in debug builds
+    /// it triggers an assertion, since it usually means a callback fired
+    /// twice. Use [`try_complete`](Self::try_complete) where that is expected.
+    #[track_caller]
+    pub fn complete(&self, value: T) {
+        let completed = self.try_complete(value).is_ok();
+        debug_assert!(
+            completed,
+            "Completer<{}>: callback future completed more than once",
+            type_name::<T>()
+        );
+    }
+
+    /// Completes the future with `value`, returning it back if the future was
+    /// already completed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(value)` if the future was already completed.
+    pub fn try_complete(&self, value: T) -> Result<(), T> {
+        let mut state = self.shared.lock();
+        if state.completed {
+            return Err(value);
+        }
+        state.completed = true;
+        if !state.cancelled {
+            state.value = Some(value);
+        }
+        let waker = state.waker.take();
+        drop(state);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the future has already been completed.
+    #[must_use]
+    pub fn is_completed(&self) -> bool {
+        self.shared.lock().completed
+    }
+
+    /// Returns `true` if the future created by [`future_with_cancel`] has been
+    /// dropped, so nobody is waiting for the result anymore.
+    ///
+    /// Callbacks can check this to skip constructing an expensive result.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.shared.lock().cancelled
+    }
+
+    /// Converts the completer into a raw pointer, for passing it through a C
+    /// callback's context argument.
+    ///
+    /// The pointer must be turned back into a `Completer` with
+    /// [`from_raw`](Self::from_raw) exactly once, or the shared state leaks.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use futures_lite::future::block_on;
+    /// use native_executor::callback::{self, Completer};
+    /// use std::ffi::c_void;
+    ///
+    /// extern "C" fn on_done(context: *const c_void, value: i32) {
+    ///     let completer = unsafe { Completer::<i32>::from_raw(context) };
+    ///     completer.complete(value);
+    /// }
+    ///
+    /// let (completer, result) = callback::future::<i32>();
+    /// on_done(completer.into_raw(), 7);
+    /// assert_eq!(block_on(result), 7);
+    /// ```
+    #[must_use]
+    pub fn into_raw(self) -> *const c_void {
+        Arc::into_raw(self.shared).cast()
+    }
+
+    /// Reconstructs a completer from a pointer returned by
+    /// [`into_raw`](Self::into_raw).
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `Completer::<T>::into_raw` with the
+    /// same `T`, and must not be used again afterwards.
+    #[must_use]
+    pub unsafe fn from_raw(ptr: *const c_void) -> Self {
+        Self {
+            // SAFETY: guaranteed by the caller.
+            shared: unsafe { Arc::from_raw(ptr.cast()) },
+        }
+    }
+}
+
+/// The receiving half of a callback future, created by [`future`] or
+/// [`future_with_cancel`].
+///
+/// If every [`Completer`] is dropped without completing it, the future never
+/// resolves.
+#[must_use = "futures do nothing unless polled"]
+pub struct CallbackFuture<T> {
+    shared: Arc<Shared<T>>,
+    cancel_on_drop: bool,
+}
+
+impl<T> fmt::Debug for CallbackFuture<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CallbackFuture")
+            .field("completed", &self.shared.lock().completed)
+            .field("cancel_on_drop", &self.cancel_on_drop)
+            .finish()
+    }
+}
+
+impl<T> Future for CallbackFuture<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = self.shared.lock();
+        if let Some(value) = state.value.take() {
+            return Poll::Ready(value);
+        }
+        match &mut state.waker {
+            Some(waker) => waker.clone_from(cx.waker()),
+            None => state.waker = Some(cx.waker().clone()),
+        }
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for CallbackFuture<T> {
+    fn drop(&mut self) {
+        if self.cancel_on_drop {
+            let mut state = self.shared.lock();
+            state.cancelled = true;
+            state.waker = None;
+        }
+    }
+}
+
+/// Creates a future together with the [`Completer`] that resolves it.
+///
+/// # Examples
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::callback;
+///
+/// // Completing before the first poll
+/// let (completer, result) = callback::future();
+/// completer.complete("early");
+/// assert_eq!(block_on(result), "early");
+///
+/// // Polling before completion
+/// let (completer, result) = callback::future();
+/// std::thread::spawn(move || completer.complete("late"));
+/// assert_eq!(block_on(result), "late");
+///
+/// // Only the first completion counts
+/// let (completer, result) = callback::future();
+/// assert_eq!(completer.try_complete(1), Ok(()));
+/// assert_eq!(completer.try_complete(2), Err(2));
+/// assert_eq!(block_on(result), 1);
+/// ```
+pub fn future<T: Send>() -> (Completer<T>, CallbackFuture<T>) {
+    new(false)
+}
+
+/// Like [`future`], but dropping the returned future marks the completer as
+/// [cancelled](Completer::is_cancelled).
+///
+/// A value completed after cancellation is dropped right away.
+///
+/// # Examples
+///
+/// ```rust
+/// use native_executor::callback;
+///
+/// let (completer, result) = callback::future_with_cancel::<Vec<u8>>();
+/// drop(result);
+///
+/// assert!(completer.is_cancelled());
+/// if !completer.is_cancelled() {
+///     completer.complete(vec![0; 1 << 20]);
+/// }
+///
+/// // Completing a cancelled future is still allowed
+/// completer.complete(Vec::new());
+/// assert!(completer.is_completed());
+/// ```
+pub fn future_with_cancel<T: Send>() -> (Completer<T>, CallbackFuture<T>) {
+    new(true)
+}
+
+fn new<T>(cancel_on_drop: bool) -> (Completer<T>, CallbackFuture<T>) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            value: None,
+            waker: None,
+            completed: false,
+            cancelled: false,
+        }),
+    });
+    (
+        Completer {
+            shared: shared.clone(),
+        },
+        CallbackFuture {
+            shared,
+            cancel_on_drop,
+        },
+    )
+}
+
+/// A future for the result of an API that has to be started on the main
+/// thread and reports its result through a completion callback.
+///
+/// This is the usual shape of `AppKit` and `UIKit` calls taking a completion
+/// block: [`run`](Self::run) calls the starting closure on the main thread
+/// with a [`Completer`], which is moved into the completion block and may be
+/// completed later from any queue.
+///
+/// Dropping the future cancels it: if the main thread has not started the
+/// API yet, it is not started at all, and a completion arriving later is
+/// discarded. The completer owns its shared state, so a late completion never
+/// touches freed memory. Use [`Completer::try_complete`] in callbacks that may
+/// fire more than once.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(not(target_vendor = "apple"))] {
+/// use futures_lite::future::block_on;
+/// use native_executor::{callback::{Completer, MainCallback}, polyfill, spawn, timer::Timer};
+/// use std::{sync::mpsc, thread, time::Duration};
+///
+/// thread::spawn(polyfill::start_main_executor);
+///
+/// // Stand-in for a main-thread API that completes on a background queue
+/// fn fetch_title(delay: Duration, on_done: impl FnOnce(String) + Send + 'static) {
+///     spawn(async move {
+///         Timer::after(delay).await;
+///         on_done("Untitled".to_owned());
+///     })
+///     .detach();
+/// }
+///
+/// // Completion
+/// let title = block_on(MainCallback::run(|completer: Completer<String>| {
+///     fetch_title(Duration::from_millis(5), move |title| completer.complete(title));
+/// }));
+/// assert_eq!(title, "Untitled");
+///
+/// // Cancellation: the late completion is discarded
+/// let (sender, completers) = mpsc::channel();
+/// let title = block_on(
+///     MainCallback::run(move |completer: Completer<String>| {
+///         sender.send(completer.clone()).unwrap();
+///         fetch_title(Duration::from_millis(50), move |title| completer.complete(title));
+///     })
+///     .timeout(Duration::from_millis(5)),
+/// );
+/// assert_eq!(title, None);
+/// assert!(completers.recv().unwrap().is_cancelled());
+///
+/// // Double completion: only the first value counts
+/// let value = block_on(MainCallback::run(|completer| {
+///     assert_eq!(completer.try_complete(1), Ok(()));
+///     assert_eq!(completer.try_complete(2), Err(2));
+/// }));
+/// assert_eq!(value, 1);
+/// # }
+/// ```
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct MainCallback<T> {
+    result: CallbackFuture<T>,
+}
+
+impl<T: Send + 'static> MainCallback<T> {
+    /// Calls `start` on the main thread with the completer for the returned
+    /// future.
+    ///
+    /// `start` is skipped if the future has been dropped by the time the main
+    /// thread gets to it.
+    pub fn run(start: impl FnOnce(Completer<T>) + Send + 'static) -> Self {
+        let (completer, result) = future_with_cancel();
+        dispatch_main(
+            move || {
+                if !completer.is_cancelled() {
+                    start(completer);
+                }
+            },
+            None,
+        );
+        Self { result }
+    }
+
+    /// Waits for the result for at most `timeout`, returning `None` and
+    /// cancelling the call if it elapses first.
+    pub async fn timeout(self, timeout: Duration) -> Option<T> {
+        let mut result = pin!(self);
+        let mut timer = pin!(Timer::after(timeout));
+        poll_fn(|cx| {
+            if let Poll::Ready(value) = result.as_mut().poll(cx) {
+                return Poll::Ready(Some(value));
+            }
+            timer.as_mut().poll(cx).map(|()| None)
+        })
+        .await
+    }
+}
+
+impl<T> Future for MainCallback<T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        Pin::new(&mut self.result).poll(cx)
+    }
+}
@@ -0,0 +1,113 @@
+//! Cooperative cancellation of long-running jobs.
+//!
+//! A job that has started cannot be stopped from outside without risking the
+//! state it is working on. Instead, it can be handed a [`CancellationToken`]
+//! and check it at points where bailing out is safe, such as between the
+//! items of a long walk. [`Mailbox::call_cancellable`] passes the token into
+//! the job for this purpose.
+//!
+//! [`Mailbox::call_cancellable`]: crate::Mailbox::call_cancellable
+//!
+//! # Examples
+//!
+//! ```rust
+//! use native_executor::cancel::{Cancelled, CancellationToken};
+//!
+//! fn sum(items: &[u64], token: &CancellationToken) -> Result<u64, Cancelled> {
+//!     let mut total = 0;
+//!     for item in items {
+//!         token.check()?;
+//!         total += item;
+//!     }
+//!     Ok(total)
+//! }
+//!
+//! let token = CancellationToken::new();
+//! assert_eq!(sum(&[1, 2, 3], &token), Ok(6));
+//! token.clone().cancel();
+//! assert_eq!(sum(&[1, 2, 3], &token), Err(Cancelled));
+//! ```
+
+use core::{
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use std::{sync::Arc, time::Instant};
+
+/// A shared flag that asks a job to stop early.
+///
+/// Clones share the flag: cancelling any of them cancels all. A token can
+/// also carry a point in time after which it counts as cancelled, which is
+/// how deadlines reach jobs in
+/// [`Mailbox::call_until_cooperative`](crate::Mailbox::call_until_cooperative).
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    cancelled: AtomicBool,
+    expires: Option<Instant>,
+}
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a token that counts as cancelled from `instant` on.
+    pub(crate) fn expiring(instant: Instant) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                cancelled: AtomicBool::new(false),
+                expires: Some(instant),
+            }),
+        }
+    }
+
+    /// Cancels the token and all its clones.
+    pub fn cancel(&self) {
+        self.shared.cancelled.store(true, Ordering::Release);
+    }
+
+    /// Returns whether the token has been cancelled, or has expired.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.shared.cancelled.load(Ordering::Acquire)
+            || self
+                .shared
+                .expires
+                .is_some_and(|expires| Instant::now() >= expires)
+    }
+
+    /// A checkpoint for `?`: returns [`Cancelled`] once the token has been
+    /// cancelled.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Cancelled`] if [`is_cancelled`](Self::is_cancelled) is
+    /// `true`.
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The error of a job that stopped early, or never started, because its
+/// [`CancellationToken`] was cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("operation was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
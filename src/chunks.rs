@@ -0,0 +1,471 @@
+//! Incremental processing of large workloads on the main thread.
+
+use core::{
+    fmt,
+    future::{Future, poll_fn},
+    pin::{Pin, pin},
+    sync::atomic::{AtomicBool, AtomicU8, AtomicU64, Ordering},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    time::Instant,
+};
+
+use async_channel::Receiver;
+use async_task::Task;
+
+use crate::{is_main_thread, spawn_local, spawn_main};
+
+/// Processes `items` on the main thread in chunks that each take at most
+/// about `per_chunk_budget`.
+///
+/// After each chunk the work yields back to the main queue, so other main
+/// thread work such as rendering keeps running while a large workload is
+/// processed. Items are processed in iteration order.
+///
+/// Dropping the returned future stops processing before the next chunk.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(not(target_vendor = "apple"))] {
+/// use futures_lite::future::block_on;
+/// use native_executor::{main_chunks, polyfill, spawn_main};
+/// use std::{sync::{Arc, Mutex}, thread, time::{Duration, Instant}};
+///
+/// thread::spawn(polyfill::start_main_executor);
+///
+/// let events = Arc::new(Mutex::new(Vec::new()));
+/// let log = events.clone();
+/// let work = main_chunks(0..10_000, Duration::from_millis(1), move |item| {
+///     // Simulate a few microseconds of work per item
+///     let start = Instant::now();
+///     while start.elapsed() < Duration::from_micros(5) {}
+///     log.lock().unwrap().push(Some(item));
+/// });
+/// let log = events.clone();
+/// let other = spawn_main(async move { log.lock().unwrap().push(None) });
+///
+/// block_on(async {
+///     work.await;
+///     other.await;
+/// });
+///
+/// let events = events.lock().unwrap();
+/// assert_eq!(events.len(), 10_001);
+/// // The concurrently scheduled task ran in between two chunks
+/// let other_at = events.iter().position(Option::is_none).unwrap();
+/// assert!(0 < other_at && other_at < 10_000);
+/// # }
+/// ```
+pub fn main_chunks<I, F>(items: I, per_chunk_budget: Duration, f: F) -> impl Future<Output = ()>
+where
+    I: IntoIterator,
+    I::IntoIter: Send + 'static,
+    F: FnMut(I::Item) + Send + 'static,
+{
+    main_chunks_with_limit(items, per_chunk_budget, usize::MAX, f)
+}
+
+/// Like [`main_chunks`], but also ends each chunk after at most
+/// `max_items_per_chunk` items.
+///
+/// The item limit bounds chunks where the monotonic clock is too coarse to
+/// measure the time budget reliably, such as in browsers.
+///
+/// # Panics
+///
+/// Panics if `max_items_per_chunk` is zero.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(not(target_vendor = "apple"))] {
+/// use native_executor::{main_chunks_with_limit, polyfill};
+/// use std::{sync::{Mutex, mpsc}, thread, time::Duration};
+///
+/// thread::spawn(polyfill::start_main_executor);
+///
+/// let (processed, items) = mpsc::channel();
+/// let (resume, paused) = mpsc::channel::<()>();
+/// let paused = Mutex::new(paused);
+/// let work = main_chunks_with_limit(0..100, Duration::MAX, 10, move |item| {
+///     processed.send(item).unwrap();
+///     if item == 9 {
+///         // Hold the first chunk open until the future has been dropped
+///         let _ = paused.lock().unwrap().recv();
+///     }
+/// });
+///
+/// assert_eq!(items.iter().take(10).count(), 10);
+/// drop(work);
+/// drop(resume);
+///
+/// // Processing stopped after the first chunk
+/// assert!(items.recv().is_err());
+/// # }
+/// ```
+#[track_caller]
+pub fn main_chunks_with_limit<I, F>(
+    items: I,
+    per_chunk_budget: Duration,
+    max_items_per_chunk: usize,
+    mut f: F,
+) -> impl Future<Output = ()>
+where
+    I: IntoIterator,
+    I::IntoIter: Send + 'static,
+    F: FnMut(I::Item) + Send + 'static,
+{
+    assert!(
+        max_items_per_chunk > 0,
+        "chunks must hold at least one item"
+    );
+    let mut items = items.into_iter();
+    // Dropping the task handle cancels the task at its next yield point.
+    spawn_main(async move {
+        loop {
+            let start = Instant::now();
+            for _ in 0..max_items_per_chunk {
+                let Some(item) = items.next() else {
+                    return;
+                };
+                f(item);
+                if start.elapsed() >= per_chunk_budget {
+                    break;
+                }
+            }
+            YieldNow(false).await;
+        }
+    })
+}
+
+/// Applies the events of `receiver` on the main thread, in slices that each
+/// take at most about `budget`.
+///
+/// Each time events arrive, the spawned task applies them until the budget
+/// has elapsed or the channel is empty. After a slice that used up its
+/// budget it yields back to the main queue, so a burst of events does not
+/// hold up other main-thread work. Events are applied in channel order, and
+/// the task ends once the channel is closed and empty, or once it is
+/// [stopped](DrainHandle::stop).
+///
+/// `apply` does not need to be `Send`, so it can own main-thread state.
+///
+/// # Panics
+///
+/// Panics if not called on the main thread.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(not(any(target_vendor = "apple", target_os = "android")))] {
+/// use native_executor::{DrainStop, NativeExecutor, drain_on_main, spawn_local};
+/// use std::{cell::RefCell, rc::Rc, time::{Duration, Instant}};
+///
+/// let log = NativeExecutor::run_until(async {
+///     let (sender, receiver) = async_channel::unbounded();
+///     for event in 0..50_000 {
+///         sender.try_send(event).unwrap();
+///     }
+///
+///     let log = Rc::new(RefCell::new(Vec::new()));
+///     let applied = log.clone();
+///     let drain = drain_on_main(
+///         receiver,
+///         move |event| {
+///             // Simulate a little work per event
+///             let start = Instant::now();
+///             while start.elapsed() < Duration::from_micros(1) {}
+///             applied.borrow_mut().push(Some(event));
+///         },
+///         Duration::from_millis(1),
+///     );
+///     let other = log.clone();
+///     spawn_local(async move { other.borrow_mut().push(None) }).await;
+///
+///     drain.stop(DrainStop::AfterDraining).await;
+///     Rc::into_inner(log).unwrap().into_inner()
+/// });
+///
+/// // The other task ran between two slices
+/// let other_at = log.iter().position(Option::is_none).unwrap();
+/// assert!(0 < other_at && other_at < 50_000);
+/// // And every event was applied once, in order
+/// let events: Vec<_> = log.into_iter().flatten().collect();
+/// assert_eq!(events, (0..50_000).collect::<Vec<_>>());
+/// # }
+/// ```
+#[track_caller]
+pub fn drain_on_main<T: 'static>(
+    receiver: Receiver<T>,
+    mut apply: impl FnMut(T) + 'static,
+    budget: Duration,
+) -> DrainHandle {
+    assert!(
+        is_main_thread(),
+        "`drain_on_main` must be called on the main thread"
+    );
+    let control = Arc::new(Control {
+        paused: AtomicBool::new(false),
+        budget: AtomicU64::new(nanos(budget)),
+        stop: AtomicU8::new(RUNNING),
+        waker: Mutex::new(None),
+        applied: AtomicU64::new(0),
+        overruns: AtomicU64::new(0),
+    });
+    let shared = control.clone();
+    let task = spawn_local(async move {
+        let control = shared;
+        loop {
+            let Some(event) = control.next(&receiver).await else {
+                return;
+            };
+            let start = Instant::now();
+            let budget = control.budget();
+            apply(event);
+            let mut applied = 1;
+            let exhausted = loop {
+                if start.elapsed() >= budget {
+                    break true;
+                }
+                if control.interrupted() {
+                    break false;
+                }
+                let Ok(event) = receiver.try_recv() else {
+                    break false;
+                };
+                apply(event);
+                applied += 1;
+            };
+            control.applied.fetch_add(applied, Ordering::Relaxed);
+            if start.elapsed() > budget.saturating_mul(2) {
+                control.overruns.fetch_add(1, Ordering::Relaxed);
+            }
+            if exhausted {
+                YieldNow(false).await;
+            }
+        }
+    });
+    DrainHandle { control, task }
+}
+
+/// How [`DrainHandle::stop`] ends a drain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DrainStop {
+    /// Stops before the next event, leaving the rest in the channel.
+    Now,
+    /// Applies the events already in the channel first, in slices as
+    /// before, even while paused.
+    AfterDraining,
+}
+
+const RUNNING: u8 = 0;
+const STOP_NOW: u8 = 1;
+const STOP_AFTER_DRAINING: u8 = 2;
+
+/// The settings shared by a [`DrainHandle`] and its task.
+struct Control {
+    paused: AtomicBool,
+    /// The budget in nanoseconds.
+    budget: AtomicU64,
+    stop: AtomicU8,
+    /// The task, while it waits for events or for a change of the settings.
+    waker: Mutex<Option<Waker>>,
+    applied: AtomicU64,
+    overruns: AtomicU64,
+}
+
+impl Control {
+    fn budget(&self) -> Duration {
+        Duration::from_nanos(self.budget.load(Ordering::Relaxed))
+    }
+
+    /// Returns whether the current slice should end early.
+    fn interrupted(&self) -> bool {
+        match self.stop.load(Ordering::Acquire) {
+            STOP_NOW => true,
+            STOP_AFTER_DRAINING => false,
+            _ => self.paused.load(Ordering::Acquire),
+        }
+    }
+
+    /// Waits for the next event to apply, or returns `None` once the drain
+    /// has ended.
+    async fn next<T>(&self, receiver: &Receiver<T>) -> Option<T> {
+        let mut recv = pin!(receiver.recv());
+        poll_fn(|cx| {
+            let mut waker = self.waker.lock().unwrap_or_else(PoisonError::into_inner);
+            match &mut *waker {
+                Some(waker) => waker.clone_from(cx.waker()),
+                None => *waker = Some(cx.waker().clone()),
+            }
+            drop(waker);
+            match self.stop.load(Ordering::Acquire) {
+                STOP_NOW => return Poll::Ready(None),
+                STOP_AFTER_DRAINING => return Poll::Ready(receiver.try_recv().ok()),
+                _ if self.paused.load(Ordering::Acquire) => return Poll::Pending,
+                _ => {}
+            }
+            // A closed and empty channel ends the drain.
+            recv.as_mut().poll(cx).map(Result::ok)
+        })
+        .await
+    }
+
+    fn wake(&self) {
+        let waker = self
+            .waker
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// Controls a drain started by [`drain_on_main`].
+///
+/// Dropping the handle cancels the drain before its next slice; use
+/// [`detach`](Self::detach) to keep it running until its channel is closed
+/// and empty.
+#[derive(Debug)]
+#[must_use = "dropping the handle cancels the drain"]
+pub struct DrainHandle {
+    control: Arc<Control>,
+    task: Task<()>,
+}
+
+impl fmt::Debug for Control {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Control")
+            .field("paused", &self.paused.load(Ordering::Relaxed))
+            .field("budget", &self.budget())
+            .field("stop", &self.stop.load(Ordering::Relaxed))
+            .finish_non_exhaustive()
+    }
+}
+
+impl DrainHandle {
+    /// Stops applying events after the current slice, until
+    /// [`resume`](Self::resume) is called. Events keep queueing in the
+    /// channel meanwhile.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(not(any(target_vendor = "apple", target_os = "android")))] {
+    /// use native_executor::{DrainStop, NativeExecutor, drain_on_main, timer::Timer};
+    /// use std::{cell::Cell, rc::Rc, time::Duration};
+    ///
+    /// NativeExecutor::run_until(async {
+    ///     let (sender, receiver) = async_channel::unbounded();
+    ///     let total = Rc::new(Cell::new(0));
+    ///     let sum = total.clone();
+    ///     let drain = drain_on_main(receiver, move |n| sum.set(sum.get() + n), Duration::from_millis(1));
+    ///
+    ///     drain.pause();
+    ///     sender.try_send(1).unwrap();
+    ///     Timer::after(Duration::from_millis(10)).await;
+    ///     assert_eq!((total.get(), drain.applied()), (0, 0));
+    ///
+    ///     drain.resume();
+    ///     sender.try_send(2).unwrap();
+    ///     while drain.applied() < 2 {
+    ///         Timer::after(Duration::from_millis(1)).await;
+    ///     }
+    ///     assert_eq!(total.get(), 3);
+    ///
+    ///     // Events left in the channel are not applied
+    ///     drain.pause();
+    ///     sender.try_send(4).unwrap();
+    ///     drain.stop(DrainStop::Now).await;
+    ///     assert_eq!(total.get(), 3);
+    ///     assert_eq!(sender.len(), 1);
+    /// });
+    /// # }
+    /// ```
+    pub fn pause(&self) {
+        self.control.paused.store(true, Ordering::Release);
+    }
+
+    /// Resumes applying events after [`pause`](Self::pause).
+    pub fn resume(&self) {
+        self.control.paused.store(false, Ordering::Release);
+        self.control.wake();
+    }
+
+    /// Returns whether the drain is paused.
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.control.paused.load(Ordering::Acquire)
+    }
+
+    /// Sets the time budget of the slices that start from now on.
+    pub fn set_budget(&self, budget: Duration) {
+        self.control.budget.store(nanos(budget), Ordering::Relaxed);
+    }
+
+    /// Returns the time budget of a slice.
+    #[must_use]
+    pub fn budget(&self) -> Duration {
+        self.control.budget()
+    }
+
+    /// Returns the number of events applied so far.
+    #[must_use]
+    pub fn applied(&self) -> u64 {
+        self.control.applied.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of slices that took more than twice their budget,
+    /// which means single events took about as long as the whole budget.
+    ///
+    /// Frequent overruns hold up the main thread no matter the budget; such
+    /// events are better split up, or prepared off the main thread.
+    #[must_use]
+    pub fn overruns(&self) -> u64 {
+        self.control.overruns.load(Ordering::Relaxed)
+    }
+
+    /// Stops the drain as `mode` says, returning a task that completes once
+    /// it has stopped.
+    pub fn stop(self, mode: DrainStop) -> Task<()> {
+        let stop = match mode {
+            DrainStop::Now => STOP_NOW,
+            DrainStop::AfterDraining => STOP_AFTER_DRAINING,
+        };
+        self.control.stop.store(stop, Ordering::Release);
+        self.control.wake();
+        self.task
+    }
+
+    /// Lets the drain run until its channel is closed and empty, without
+    /// the handle.
+    pub fn detach(self) {
+        self.task.detach();
+    }
+}
+
+fn nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+/// Yields once, rescheduling the current task at the back of its queue.
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
@@ -0,0 +1,148 @@
+//! Timers with nearby deadlines sharing one platform timer.
+//!
+//! Deadlines are rounded up to a multiple of the configured granularity,
+//! counted from a process-wide epoch, so timers never fire early and fire at
+//! most one granularity late. All callbacks of a priority class rounded to the
+//! same tick form a bucket, and only the first callback of a bucket starts a
+//! platform timer. When it fires, it runs every callback of its class due at
+//! or before its tick.
+//!
+//! A cancelled callback is removed from its bucket. A bucket that empties is
+//! removed as well and its platform timer cancelled; where the backend cannot
+//! cancel timers, the platform timer then finds nothing to run.
+
+use alloc::{boxed::Box, collections::BTreeMap};
+use core::{mem, time::Duration};
+use std::{
+    sync::{Mutex, OnceLock, PoisonError},
+    time::Instant,
+};
+
+use crate::{Priority, TimerKey, priority::SchedClass};
+
+type Callback = Box<dyn FnOnce() + Send + 'static>;
+
+/// The callbacks due at one tick.
+#[derive(Default)]
+struct Bucket {
+    /// The callbacks, by id.
+    callbacks: BTreeMap<u64, Callback>,
+    /// The id of the callback that started the platform timer.
+    first: u64,
+    /// The platform timer, once started, if the backend can cancel it.
+    timer: Option<TimerKey>,
+}
+
+struct Buckets {
+    /// Buckets by tick, for each class indexed by [`SchedClass::index`].
+    by_class: [BTreeMap<u64, Bucket>; SchedClass::COUNT],
+    next_id: u64,
+}
+
+static BUCKETS: Mutex<Buckets> = Mutex::new(Buckets {
+    by_class: [const { BTreeMap::new() }; SchedClass::COUNT],
+    next_id: 0,
+});
+
+/// A callback scheduled with [`schedule`], which can be cancelled until it
+/// runs.
+#[derive(Debug, Clone, Copy)]
+pub struct Entry {
+    class: SchedClass,
+    tick: u64,
+    id: u64,
+}
+
+fn epoch() -> Instant {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    *EPOCH.get_or_init(Instant::now)
+}
+
+fn nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+/// Runs `f` at `priority` once `delay` has elapsed, rounded up to a multiple
+/// of `granularity`.
+pub fn schedule(
+    delay: Duration,
+    f: impl FnOnce() + Send + 'static,
+    priority: Priority,
+    granularity: Duration,
+) -> Entry {
+    let granularity = nanos(granularity).max(1);
+    let due = nanos(epoch().elapsed()).saturating_add(nanos(delay));
+    let tick = due.div_ceil(granularity);
+    let class = priority.class();
+
+    let mut buckets = BUCKETS.lock().unwrap_or_else(PoisonError::into_inner);
+    let id = buckets.next_id;
+    buckets.next_id += 1;
+    let bucket = buckets.by_class[class.index()].entry(tick).or_default();
+    let first = bucket.callbacks.is_empty();
+    if first {
+        bucket.first = id;
+    }
+    bucket.callbacks.insert(id, Box::new(f));
+    drop(buckets);
+
+    if first {
+        let fire_at = epoch() + Duration::from_nanos(tick.saturating_mul(granularity));
+        let timer = crate::dispatch_after_cancellable(
+            fire_at.saturating_duration_since(Instant::now()),
+            move || fire(class, tick),
+            priority,
+        );
+        if let Some(timer) = timer {
+            let mut buckets = BUCKETS.lock().unwrap_or_else(PoisonError::into_inner);
+            match buckets.by_class[class.index()].get_mut(&tick) {
+                // Still the bucket this call started, so it owns the timer
+                Some(bucket) if bucket.first == id => bucket.timer = Some(timer),
+                // Emptied in the meantime, or fired and started anew
+                _ => {
+                    drop(buckets);
+                    crate::cancel_after(timer);
+                }
+            }
+        }
+    }
+    Entry { class, tick, id }
+}
+
+/// Removes the callback of `entry` if it has not run yet.
+pub fn cancel(entry: Entry) {
+    let mut buckets = BUCKETS.lock().unwrap_or_else(PoisonError::into_inner);
+    let by_tick = &mut buckets.by_class[entry.class.index()];
+    if let Some(bucket) = by_tick.get_mut(&entry.tick) {
+        let callback = bucket.callbacks.remove(&entry.id);
+        let timer = if bucket.callbacks.is_empty() {
+            by_tick.remove(&entry.tick).and_then(|bucket| bucket.timer)
+        } else {
+            None
+        };
+        drop(buckets);
+        if let Some(timer) = timer {
+            crate::cancel_after(timer);
+        }
+        // The callback may own values whose destructors take the lock.
+        drop(callback);
+    }
+}
+
+/// Runs the callbacks of `class` due at or before `tick`.
+fn fire(class: SchedClass, tick: u64) {
+    let mut buckets = BUCKETS.lock().unwrap_or_else(PoisonError::into_inner);
+    let by_tick = &mut buckets.by_class[class.index()];
+    let later = by_tick.split_off(&tick.saturating_add(1));
+    let due = mem::replace(by_tick, later);
+    drop(buckets);
+    for (due_tick, bucket) in due {
+        // Earlier ticks whose timers are still pending have nothing left to run
+        if let Some(timer) = bucket.timer.filter(|_| due_tick != tick) {
+            crate::cancel_after(timer);
+        }
+        for callback in bucket.callbacks.into_values() {
+            callback();
+        }
+    }
+}
@@ -0,0 +1,778 @@
+//! Process-wide configuration applied before the executor is first used.
+
+use core::{fmt, time::Duration};
+use std::sync::OnceLock;
+
+use crate::{DEFAULT_LABEL_PREFIX, Priority};
+
+/// Settings for the platform backends.
+///
+/// Each setting belongs to one backend and is ignored on the others.
+/// [`Config::default`] matches the behavior without calling [`init`].
+// Hooks compare by address, which is as far as functions can be compared.
+#[allow(unpredictable_function_pointer_comparisons)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    label_prefix: &'static str,
+    polyfill_threads: Option<usize>,
+    android_worker_threads: usize,
+    timer_pool_capacity: usize,
+    timer_coalescing: Duration,
+    priority_floor: Option<Priority>,
+    priority_ceiling: Option<Priority>,
+    max_alive_tasks: Option<usize>,
+    spawn_overflow: SpawnOverflow,
+    worker_thread_start: Option<fn()>,
+    worker_thread_stop: Option<fn()>,
+}
+
+/// What spawning does beyond [`Config::max_alive_tasks`], as set by
+/// [`Config::overflow`].
+///
+/// The `try_spawn` functions, such as [`try_spawn`](crate::try_spawn), fail
+/// with [`SpawnError::AtCapacity`](crate::SpawnError::AtCapacity) either way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SpawnOverflow {
+    /// Spawning panics.
+    #[default]
+    Panic,
+    /// The task is spawned, but its future only starts once another task has
+    /// ended and freed a slot.
+    Wait,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Config {
+    /// Creates the default configuration.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            label_prefix: DEFAULT_LABEL_PREFIX,
+            polyfill_threads: None,
+            android_worker_threads: 1,
+            timer_pool_capacity: 64,
+            timer_coalescing: Duration::ZERO,
+            priority_floor: None,
+            priority_ceiling: None,
+            max_alive_tasks: None,
+            spawn_overflow: SpawnOverflow::Panic,
+            worker_thread_start: None,
+            worker_thread_stop: None,
+        }
+    }
+
+    /// Sets the prefix of the labels of the dispatch queues and the names of
+    /// the worker threads created by this crate.
+    ///
+    /// Debuggers, backtraces and profilers show these names, which makes
+    /// work spawned through this crate easy to attribute. Queues and threads
+    /// are named `<prefix>.<name>`, for example
+    /// `dev.water.native-executor.user-initiated` on Apple platforms with the
+    /// default prefix. Android and the polyfill backend name their worker
+    /// threads the same way, although the kernel truncates thread names to
+    /// 15 bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prefix` contains a NUL byte.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{Config, init, spawn};
+    ///
+    /// init(Config::new().queue_label_prefix("com.example.app")).unwrap();
+    ///
+    /// // The label of the queue, or the name of the thread, running a task
+    /// let name = block_on(spawn(async {
+    ///     #[cfg(target_vendor = "apple")]
+    ///     {
+    ///         use dispatch::ffi::dispatch_queue_get_label;
+    ///         use std::{ffi::CStr, ptr};
+    ///         // `DISPATCH_CURRENT_QUEUE_LABEL` is a null queue
+    ///         let label = unsafe { CStr::from_ptr(dispatch_queue_get_label(ptr::null_mut())) };
+    ///         label.to_str().unwrap().to_owned()
+    ///     }
+    ///     #[cfg(not(target_vendor = "apple"))]
+    ///     std::thread::current().name().unwrap().to_owned()
+    /// }));
+    /// # #[cfg(target_vendor = "apple")]
+    /// assert_eq!(name, "com.example.app.default");
+    /// # #[cfg(not(any(target_vendor = "apple", target_os = "android")))]
+    /// assert_eq!(name, "com.example.app.worker");
+    /// ```
+    #[must_use]
+    #[track_caller]
+    pub const fn queue_label_prefix(mut self, prefix: &'static str) -> Self {
+        let bytes = prefix.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            assert!(
+                bytes[i] != 0,
+                "queue label prefixes must not contain NUL bytes"
+            );
+            i += 1;
+        }
+        self.label_prefix = prefix;
+        self
+    }
+
+    /// Sets the number of worker threads of the polyfill backend.
+    ///
+    /// Defaults to the number of logical CPUs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threads` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(not(any(target_vendor = "apple", target_os = "android")))] {
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{Config, init, spawn};
+    /// use std::{collections::HashSet, thread, time::Duration};
+    ///
+    /// init(Config::new().polyfill_threads(2)).unwrap();
+    ///
+    /// let tasks: Vec<_> = (0..16)
+    ///     .map(|_| spawn(async {
+    ///         thread::sleep(Duration::from_millis(5));
+    ///         thread::current().id()
+    ///     }))
+    ///     .collect();
+    /// let threads: HashSet<_> = tasks.into_iter().map(block_on).collect();
+    /// assert!(threads.len() <= 2);
+    /// # }
+    /// ```
+    ///
+    /// Zero threads are reported at the call site:
+    ///
+    /// ```rust
+    /// use native_executor::Config;
+    /// use std::{panic, sync::Mutex};
+    ///
+    /// static LOCATION: Mutex<Option<(String, u32)>> = Mutex::new(None);
+    /// panic::set_hook(Box::new(|info| {
+    ///     let location = info.location().unwrap();
+    ///     *LOCATION.lock().unwrap() = Some((location.file().to_owned(), location.line()));
+    /// }));
+    ///
+    /// let (result, line) = (panic::catch_unwind(|| Config::new().polyfill_threads(0)), line!());
+    /// assert!(result.is_err());
+    /// assert_eq!(LOCATION.lock().unwrap().take(), Some((file!().to_owned(), line)));
+    /// ```
+    #[must_use]
+    #[track_caller]
+    pub const fn polyfill_threads(mut self, threads: usize) -> Self {
+        assert!(
+            threads > 0,
+            "the polyfill backend needs at least one thread"
+        );
+        self.polyfill_threads = Some(threads);
+        self
+    }
+
+    /// Sets the number of worker threads serving each non-main priority
+    /// queue of the Android backend.
+    ///
+    /// Defaults to one, which runs the jobs of a queue sequentially.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threads` is zero.
+    #[must_use]
+    #[track_caller]
+    pub const fn android_worker_threads(mut self, threads: usize) -> Self {
+        assert!(
+            threads > 0,
+            "Android queues need at least one worker thread"
+        );
+        self.android_worker_threads = threads;
+        self
+    }
+
+    /// Sets how many idle timer states each thread keeps for reuse.
+    ///
+    /// Every [`Timer`](crate::timer::Timer) shares a small state with its
+    /// platform callback. Instead of allocating it per timer, timers take it
+    /// from a pool and return it when dropped, so workloads that create and
+    /// cancel many timeouts do not allocate for it once the pool is warm.
+    /// Each thread caches up to `capacity` idle states; the rest go to a
+    /// shared free list. Pooled states are never freed, so the pool holds as
+    /// many states as timers were alive at once.
+    ///
+    /// Defaults to 64. Zero disables pooling and allocates the state per
+    /// timer.
+    ///
+    /// # Examples
+    ///
+    /// Cancelled timeouts reuse their state:
+    ///
+    /// ```rust
+    /// use native_executor::timer::Timer;
+    /// use std::{
+    ///     alloc::{GlobalAlloc, Layout, System},
+    ///     cell::Cell,
+    ///     sync::atomic::{AtomicUsize, Ordering},
+    ///     time::Duration,
+    /// };
+    ///
+    /// // Counts the allocations made by the current thread while enabled
+    /// struct Counting;
+    /// thread_local! {
+    ///     static COUNTING: Cell<bool> = const { Cell::new(false) };
+    /// }
+    /// static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+    ///
+    /// unsafe impl GlobalAlloc for Counting {
+    ///     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+    ///         if COUNTING.get() {
+    ///             ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+    ///         }
+    ///         unsafe { System.alloc(layout) }
+    ///     }
+    ///     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+    ///         unsafe { System.dealloc(ptr, layout) }
+    ///     }
+    /// }
+    ///
+    /// #[global_allocator]
+    /// static ALLOCATOR: Counting = Counting;
+    ///
+    /// // Warm up the configuration and the pool
+    /// drop(Timer::after(Duration::from_secs(1)));
+    ///
+    /// COUNTING.set(true);
+    /// for _ in 0..100_000 {
+    ///     // A per-request deadline that is never reached
+    ///     drop(Timer::after(Duration::from_secs(1)));
+    /// }
+    /// COUNTING.set(false);
+    /// assert_eq!(ALLOCATIONS.load(Ordering::SeqCst), 0);
+    /// ```
+    ///
+    /// A recycled state is not completed by the callback of the timer that
+    /// used it before:
+    ///
+    /// ```rust
+    /// use native_executor::timer::Timer;
+    /// use std::{future::Future, pin::pin, task::{Context, Poll, Waker}, thread, time::Duration};
+    ///
+    /// let mut cx = Context::from_waker(Waker::noop());
+    /// for _ in 0..200 {
+    ///     // Start a short timer and cancel it before it fires
+    ///     let mut short = Timer::after(Duration::from_micros(50));
+    ///     let _ = pin!(&mut short).poll(&mut cx);
+    ///     drop(short);
+    ///
+    ///     // The next timer reuses its state while the callback is pending
+    ///     let mut long = pin!(Timer::after(Duration::from_secs(60)));
+    ///     let _ = long.as_mut().poll(&mut cx);
+    ///     thread::sleep(Duration::from_micros(200));
+    ///     assert_eq!(long.as_mut().poll(&mut cx), Poll::Pending);
+    /// }
+    /// ```
+    #[must_use]
+    pub const fn timer_pool_capacity(mut self, capacity: usize) -> Self {
+        self.timer_pool_capacity = capacity;
+        self
+    }
+
+    /// Lets [`Timer`](crate::timer::Timer)s with nearby deadlines share one
+    /// platform timer.
+    ///
+    /// Deadlines are rounded up to a multiple of `granularity`, so a timer
+    /// fires up to `granularity` late but never early, and the timers of a
+    /// priority rounded to the same deadline are fired by one platform timer.
+    /// Workloads that start many timers with clustered deadlines, such as a
+    /// per-entity recheck a few seconds out, then start platform timers in
+    /// proportion to the distinct deadlines rather than to the timers.
+    /// Dropping a timer removes it from its group without touching the
+    /// platform timer. One millisecond is a good granularity for most
+    /// applications; see
+    /// [`diagnostics::platform_timers`](crate::diagnostics::platform_timers)
+    /// for a measurement.
+    ///
+    /// Off by default, and with a zero `granularity`: each timer starts a
+    /// platform timer of its own.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{Config, init, timer::Timer};
+    /// use std::time::{Duration, Instant};
+    ///
+    /// init(Config::new().coalesce_timers(Duration::from_millis(5))).unwrap();
+    ///
+    /// let start = Instant::now();
+    /// block_on(Timer::after(Duration::from_millis(12)));
+    /// assert!(start.elapsed() >= Duration::from_millis(12));
+    /// ```
+    #[must_use]
+    pub const fn coalesce_timers(mut self, granularity: Duration) -> Self {
+        self.timer_coalescing = granularity;
+        self
+    }
+
+    /// Sets the least urgent priority the thread pool runs work at.
+    ///
+    /// Tasks, closures and timers requested at a less urgent priority run at
+    /// `floor` instead. Together with
+    /// [`priority_ceiling`](Self::priority_ceiling), this keeps code the
+    /// application does not control, such as plugins, within a band of
+    /// priorities, whatever they pass to
+    /// [`spawn_with_priority`](crate::spawn_with_priority),
+    /// [`execute_with_priority`](crate::execute_with_priority) or
+    /// [`with_priority`](crate::with_priority). The bounds apply where work
+    /// is handed to the backend, so every way of spawning is covered. Hooks
+    /// observe the requested priority in
+    /// [`TaskInfo::requested_priority`](crate::hooks::TaskInfo::requested_priority)
+    /// and the one the task runs at in
+    /// [`TaskInfo::priority`](crate::hooks::TaskInfo::priority). The main
+    /// thread is not affected.
+    ///
+    /// Trusted work can opt out with
+    /// [`SpawnOptions::exempt_from_clamp`](crate::SpawnOptions::exempt_from_clamp).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `floor` is more urgent than a ceiling set before.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{
+    ///     Config, ExecContext, Priority, current_context,
+    ///     hooks::{self, TaskHooks},
+    ///     init, spawn_main, spawn_with_priority,
+    /// };
+    /// use std::sync::Mutex;
+    ///
+    /// init(
+    ///     Config::new()
+    ///         .priority_floor(Priority::Utility)
+    ///         .priority_ceiling(Priority::UserInitiated),
+    /// )
+    /// .unwrap();
+    ///
+    /// static SPAWNED: Mutex<Vec<(Priority, Priority)>> = Mutex::new(Vec::new());
+    /// hooks::register(TaskHooks::new().on_spawn(|info| {
+    ///     SPAWNED.lock().unwrap().push((info.requested_priority, info.priority));
+    /// }));
+    ///
+    /// // Promoted to the floor
+    /// let task = spawn_with_priority(async { current_context() }, Priority::Background);
+    /// assert_eq!(block_on(task), ExecContext::Worker(Priority::Utility));
+    ///
+    /// // Demoted to the ceiling
+    /// let task = spawn_with_priority(async { current_context() }, Priority::UserInteractive);
+    /// assert_eq!(block_on(task), ExecContext::Worker(Priority::UserInitiated));
+    ///
+    /// // The main thread is not bounded
+    /// spawn_main(async {}).detach();
+    ///
+    /// assert_eq!(
+    ///     *SPAWNED.lock().unwrap(),
+    ///     [
+    ///         (Priority::Background, Priority::Utility),
+    ///         (Priority::UserInteractive, Priority::UserInitiated),
+    ///         (Priority::UserInteractive, Priority::UserInteractive),
+    ///     ]
+    /// );
+    /// ```
+    #[must_use]
+    #[track_caller]
+    pub const fn priority_floor(mut self, floor: Priority) -> Self {
+        if let Some(ceiling) = self.priority_ceiling {
+            assert!(
+                floor.class().index() <= ceiling.class().index(),
+                "the priority floor must not be above the ceiling"
+            );
+        }
+        self.priority_floor = Some(floor);
+        self
+    }
+
+    /// Sets the most urgent priority the thread pool runs work at.
+    ///
+    /// Work requested at a more urgent priority runs at `ceiling` instead;
+    /// see [`priority_floor`](Self::priority_floor).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ceiling` is less urgent than a floor set before.
+    #[must_use]
+    #[track_caller]
+    pub const fn priority_ceiling(mut self, ceiling: Priority) -> Self {
+        if let Some(floor) = self.priority_floor {
+            assert!(
+                floor.class().index() <= ceiling.class().index(),
+                "the priority ceiling must not be below the floor"
+            );
+        }
+        self.priority_ceiling = Some(ceiling);
+        self
+    }
+
+    /// Caps the number of tasks alive at once, for processes that would
+    /// rather refuse work than run out of memory.
+    ///
+    /// A task counts from the moment it is spawned until it completes or is
+    /// dropped, on the thread pool and on the main thread alike. Daemon tasks
+    /// of [`SpawnOptions::daemon`](crate::SpawnOptions::daemon) are exempt.
+    /// Closures of [`execute`](crate::execute) and its variants count
+    /// towards the cap, but are never refused. The cap is enforced with a
+    /// compare-and-swap on the count, so racing spawns never exceed it.
+    ///
+    /// Beyond the cap, the `try_spawn` functions, such as
+    /// [`try_spawn`](crate::try_spawn), fail with
+    /// [`SpawnError::AtCapacity`](crate::SpawnError::AtCapacity), and the
+    /// other spawns do as set by [`overflow`](Self::overflow): they panic by
+    /// default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{Config, SpawnError, SpawnOptions, init, spawn, try_spawn};
+    /// use std::panic;
+    ///
+    /// init(Config::new().max_alive_tasks(4)).unwrap();
+    ///
+    /// let (releases, mut tasks): (Vec<_>, Vec<_>) = (0..4)
+    ///     .map(|_| {
+    ///         let (release, released) = async_channel::bounded::<()>(1);
+    ///         (release, spawn(async move { released.recv().await.unwrap() }))
+    ///     })
+    ///     .unzip();
+    ///
+    /// // Full
+    /// assert_eq!(try_spawn(async {}).unwrap_err(), SpawnError::AtCapacity);
+    /// assert!(panic::catch_unwind(|| spawn(async {})).is_err());
+    /// // Daemons are exempt
+    /// let daemon = SpawnOptions::new().daemon(true).try_spawn(async { "daemon" });
+    /// assert_eq!(block_on(daemon.unwrap()), "daemon");
+    ///
+    /// // A completed task frees its slot
+    /// releases[0].try_send(()).unwrap();
+    /// block_on(tasks.remove(0));
+    /// assert_eq!(block_on(try_spawn(async { "spawned" }).unwrap()), "spawned");
+    /// ```
+    #[must_use]
+    #[track_caller]
+    pub const fn max_alive_tasks(mut self, max: usize) -> Self {
+        assert!(max > 0, "the cap on alive tasks must not be zero");
+        self.max_alive_tasks = Some(max);
+        self
+    }
+
+    /// Sets what spawning does beyond [`max_alive_tasks`](Self::max_alive_tasks);
+    /// [`SpawnOverflow::Panic`] by default.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{Config, SpawnOverflow, init, spawn};
+    /// use std::{sync::mpsc, time::Duration};
+    ///
+    /// init(Config::new().max_alive_tasks(1).overflow(SpawnOverflow::Wait)).unwrap();
+    ///
+    /// let (release, released) = async_channel::bounded::<()>(1);
+    /// let first = spawn(async move { released.recv().await.unwrap() });
+    ///
+    /// // Spawned, but waiting for the first task to end
+    /// let (started, starts) = mpsc::channel();
+    /// let second = spawn(async move {
+    ///     started.send(()).unwrap();
+    ///     "second"
+    /// });
+    /// assert!(starts.recv_timeout(Duration::from_millis(50)).is_err());
+    ///
+    /// release.try_send(()).unwrap();
+    /// block_on(first);
+    /// assert_eq!(block_on(second), "second");
+    ///
+    /// // A waiting task that is cancelled gives up its place in the queue
+    /// let (release, released) = async_channel::bounded::<()>(1);
+    /// let first = spawn(async move { released.recv().await.unwrap() });
+    /// drop(spawn(async { "abandoned" }));
+    /// let third = spawn(async { "third" });
+    /// release.try_send(()).unwrap();
+    /// block_on(first);
+    /// assert_eq!(block_on(third), "third");
+    /// ```
+    #[must_use]
+    pub const fn overflow(mut self, overflow: SpawnOverflow) -> Self {
+        self.spawn_overflow = overflow;
+        self
+    }
+
+    /// Sets a function that each worker thread created by this crate calls
+    /// when it starts, before it runs any work.
+    ///
+    /// This prepares threads for code that needs per-thread setup, such as
+    /// setting floating-point flags or registering the thread with a garbage
+    /// collector. It applies to the backends that own their threads: the
+    /// polyfill backend, which serves Linux and Windows among others, and
+    /// Android. It is not called on Apple platforms, where Grand Central
+    /// Dispatch owns the threads, nor on the web. The main thread, and the
+    /// threads of the backends that only run timers, are not worker threads.
+    ///
+    /// A panic in `hook` ends the thread.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(not(any(target_vendor = "apple", target_os = "android")))] {
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{Config, init, spawn};
+    /// use std::{
+    ///     collections::HashSet,
+    ///     sync::Mutex,
+    ///     thread::{self, ThreadId},
+    ///     time::Duration,
+    /// };
+    ///
+    /// static STARTED: Mutex<Vec<ThreadId>> = Mutex::new(Vec::new());
+    /// init(
+    ///     Config::new()
+    ///         .polyfill_threads(3)
+    ///         .on_worker_thread_start(|| STARTED.lock().unwrap().push(thread::current().id())),
+    /// )
+    /// .unwrap();
+    ///
+    /// // Workers start with the first task
+    /// let tasks: Vec<_> = (0..30)
+    ///     .map(|_| spawn(async {
+    ///         thread::sleep(Duration::from_millis(1));
+    ///         thread::current().id()
+    ///     }))
+    ///     .collect();
+    /// let ran_on: HashSet<_> = tasks.into_iter().map(block_on).collect();
+    /// while STARTED.lock().unwrap().len() < 3 {
+    ///     thread::yield_now();
+    /// }
+    ///
+    /// // Once per worker, and before its first task
+    /// let started = STARTED.lock().unwrap().clone();
+    /// assert_eq!(started.len(), 3);
+    /// assert_eq!(started.iter().collect::<HashSet<_>>().len(), 3);
+    /// assert!(ran_on.iter().all(|thread| started.contains(thread)));
+    /// # }
+    /// ```
+    #[must_use]
+    pub const fn on_worker_thread_start(mut self, hook: fn()) -> Self {
+        self.worker_thread_start = Some(hook);
+        self
+    }
+
+    /// Sets a function that each worker thread created by this crate calls
+    /// when it stops, after its last work, on the backends of
+    /// [`on_worker_thread_start`](Self::on_worker_thread_start).
+    ///
+    /// Worker threads serve the process until it exits, which does not stop
+    /// them, so this is only called for a worker that ends early, as one
+    /// unwinding from a panic.
+    #[must_use]
+    pub const fn on_worker_thread_stop(mut self, hook: fn()) -> Self {
+        self.worker_thread_stop = Some(hook);
+        self
+    }
+
+    /// Returns a token that lets tasks opt out of the priority bounds of this
+    /// configuration, see
+    /// [`SpawnOptions::exempt_from_clamp`](crate::SpawnOptions::exempt_from_clamp).
+    ///
+    /// Hand it to the code that is trusted to choose its own priority.
+    #[must_use]
+    pub const fn clamp_exemption(&self) -> ClampExemption {
+        ClampExemption(())
+    }
+
+    /// Returns the prefix of queue labels and worker thread names.
+    #[must_use]
+    pub const fn label_prefix(&self) -> &'static str {
+        self.label_prefix
+    }
+
+    /// Returns the configured number of polyfill worker threads, if set.
+    #[must_use]
+    pub const fn polyfill_thread_count(&self) -> Option<usize> {
+        self.polyfill_threads
+    }
+
+    /// Returns the number of worker threads per Android queue.
+    #[must_use]
+    pub const fn android_worker_thread_count(&self) -> usize {
+        self.android_worker_threads
+    }
+
+    /// Returns the number of idle timer states each thread keeps for reuse.
+    #[must_use]
+    pub const fn timer_pool_capacity_per_thread(&self) -> usize {
+        self.timer_pool_capacity
+    }
+
+    /// Returns the granularity to which timer deadlines are rounded, or
+    /// `None` if timers are not coalesced.
+    #[must_use]
+    pub const fn timer_coalescing_granularity(&self) -> Option<Duration> {
+        if self.timer_coalescing.is_zero() {
+            None
+        } else {
+            Some(self.timer_coalescing)
+        }
+    }
+    /// Returns the least urgent priority of the thread pool, if bounded.
+    #[must_use]
+    pub const fn priority_floor_bound(&self) -> Option<Priority> {
+        self.priority_floor
+    }
+
+    /// Returns the most urgent priority of the thread pool, if bounded.
+    #[must_use]
+    pub const fn priority_ceiling_bound(&self) -> Option<Priority> {
+        self.priority_ceiling
+    }
+
+    /// Returns the cap on alive tasks, if set.
+    #[must_use]
+    pub const fn alive_task_limit(&self) -> Option<usize> {
+        self.max_alive_tasks
+    }
+
+    /// Returns what spawning does beyond the cap on alive tasks.
+    #[must_use]
+    pub const fn spawn_overflow(&self) -> SpawnOverflow {
+        self.spawn_overflow
+    }
+
+    /// Returns the function worker threads call when they start, if set.
+    #[must_use]
+    pub const fn worker_thread_start_hook(&self) -> Option<fn()> {
+        self.worker_thread_start
+    }
+
+    /// Returns the function worker threads call when they stop, if set.
+    #[must_use]
+    pub const fn worker_thread_stop_hook(&self) -> Option<fn()> {
+        self.worker_thread_stop
+    }
+
+    /// Returns the priority the thread pool runs work requested at
+    /// `priority` at: `priority` moved within the floor and the ceiling.
+    #[must_use]
+    pub const fn clamp_priority(&self, priority: Priority) -> Priority {
+        let class = priority.class().index();
+        if let Some(floor) = self.priority_floor
+            && class < floor.class().index()
+        {
+            return floor;
+        }
+        if let Some(ceiling) = self.priority_ceiling
+            && class > ceiling.class().index()
+        {
+            return ceiling;
+        }
+        priority
+    }
+}
+
+/// Permission to spawn outside the priority bounds of the [`Config`], created
+/// with [`Config::clamp_exemption`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClampExemption(());
+
+/// The error returned by [`init`] once the configuration has been fixed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlreadyInitialized;
+
+impl fmt::Display for AlreadyInitialized {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(
+            "native-executor is already configured; call `init` once, before the first task \
+             is spawned",
+        )
+    }
+}
+
+impl std::error::Error for AlreadyInitialized {}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Applies `config` to all backends.
+///
+/// Backends read their settings lazily the first time they are needed, so
+/// call `init` early in `main`, before anything is spawned. Afterwards the
+/// configuration is fixed for the process lifetime.
+///
+/// # Errors
+///
+/// Returns [`AlreadyInitialized`] if `init` was called before, or if a task
+/// or timer was already scheduled, since the backends then run with the
+/// configuration they started with.
+///
+/// # Examples
+///
+/// ```rust
+/// use native_executor::{Config, init, spawn};
+///
+/// init(Config::new().polyfill_threads(2).android_worker_threads(2)).unwrap();
+/// let task = spawn(async { 42 });
+/// # assert_eq!(futures_lite::future::block_on(task), 42);
+/// ```
+///
+/// Configuring after first use is rejected:
+///
+/// ```rust
+/// use native_executor::{AlreadyInitialized, Config, init, spawn};
+///
+/// spawn(async {}).detach();
+/// assert_eq!(init(Config::new()), Err(AlreadyInitialized));
+/// ```
+pub fn init(config: Config) -> Result<(), AlreadyInitialized> {
+    CONFIG.set(config).map_err(|_| AlreadyInitialized)
+}
+
+/// Returns the active configuration, fixing it to the defaults if [`init`]
+/// was not called.
+pub fn current() -> &'static Config {
+    CONFIG.get_or_init(Config::new)
+}
+
+/// Runs `body` as the body of a worker thread, between the hooks of the
+/// active configuration.
+#[cfg(any(target_os = "android", feature = "polyfill"))]
+pub fn run_worker(body: impl FnOnce()) {
+    /// Calls the stop hook when the thread leaves `body`, also by unwinding.
+    struct Stop(Option<fn()>);
+
+    impl Drop for Stop {
+        fn drop(&mut self) {
+            if let Some(hook) = self.0 {
+                hook();
+            }
+        }
+    }
+
+    let config = current();
+    if let Some(hook) = config.worker_thread_start_hook() {
+        hook();
+    }
+    let _stop = Stop(config.worker_thread_stop_hook());
+    body();
+}
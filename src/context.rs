@@ -0,0 +1,251 @@
+//! Which kind of thread the current code runs on.
+//!
+//! Every job of the thread pool runs with a thread-local marker, set by the
+//! crate around the job on every backend, so code can tell pool workers from
+//! the main thread and from threads the crate knows nothing about.
+//!
+//! Blocking round-trips, such as
+//! [`Mailbox::call_blocking`](crate::Mailbox::call_blocking) and
+//! [`MainToken::run_sync`](crate::MainToken::run_sync), use the marker to
+//! protect the pool, whose workers are a limited resource. Blocking a worker
+//! keeps it from the other jobs of its priority, and blocking enough of them
+//! deadlocks the round-trip itself if the main thread waits on pool work.
+//!
+//! - With the `strict-blocking` feature, such a round-trip panics on a
+//!   worker, which finds the call sites during development.
+//! - Otherwise, on the polyfill backend, a waiting worker runs other pending
+//!   pool jobs, a bounded number of them, before it parks. Other backends
+//!   park right away.
+#![cfg_attr(
+    all(feature = "polyfill", not(feature = "strict-blocking")),
+    doc = r#"
+# Examples
+
+A single worker that waits on the main thread, while the main thread waits
+on a job queued behind it, runs that job itself instead of deadlocking:
+
+```rust
+# #[cfg(not(any(target_vendor = "apple", target_os = "android")))] {
+use futures_lite::future::block_on;
+use native_executor::{Config, MainToken, execute_main, init, polyfill, spawn};
+use std::{sync::mpsc, thread};
+
+init(Config::new().polyfill_threads(1)).unwrap();
+thread::spawn(polyfill::start_main_executor);
+
+// Occupies the only worker until both tasks below are queued
+let (release, gate) = mpsc::channel::<()>();
+spawn(async move { gate.recv().unwrap() }).detach();
+
+// The main thread waits until the last task has run
+let (done, main_waits) = mpsc::channel();
+execute_main(move || main_waits.recv().unwrap());
+
+let round_trip = spawn(async { MainToken::run_sync(|_| "answered") });
+let last = spawn(async move { done.send(()).unwrap() });
+release.send(()).unwrap();
+
+assert_eq!(block_on(round_trip), "answered");
+block_on(last);
+# }
+```
+"#
+)]
+#![cfg_attr(
+    feature = "strict-blocking",
+    doc = r#"
+# Examples
+
+With `strict-blocking`, a worker that starts a round-trip panics:
+
+```rust
+# #[cfg(not(target_vendor = "apple"))] {
+use futures_lite::future::block_on;
+use native_executor::{Mailbox, MainToken, polyfill, spawn, spawn_main, spawn_supervised};
+use std::thread;
+
+thread::spawn(polyfill::start_main_executor);
+let mailbox = block_on(spawn_main(async { Mailbox::main(0) }));
+
+let error = block_on(spawn_supervised(async { MainToken::run_sync(|_| ()) })).unwrap_err();
+assert!(error.to_string().contains("`MainToken::run_sync` blocked a worker"));
+let error = block_on(spawn_supervised(async move { mailbox.call_blocking(|count| *count) }))
+    .unwrap_err();
+assert!(error.to_string().contains("`Mailbox::call_blocking` blocked a worker"));
+
+// Other threads may still block
+assert_eq!(thread::spawn(|| MainToken::run_sync(|_| 1)).join().unwrap(), 1);
+drop(spawn(async {}));
+# }
+```
+"#
+)]
+
+use core::cell::Cell;
+use std::sync::mpsc::{Receiver, RecvError, TryRecvError};
+
+use crate::{Priority, is_main_thread};
+
+/// The kind of thread the current code runs on, as returned by
+/// [`current_context`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecContext {
+    /// The main thread, which runs the tasks of
+    /// [`spawn_main`](crate::spawn_main) and [`spawn_local`](crate::spawn_local).
+    Main,
+    /// A thread-pool job of this crate, such as a task of
+    /// [`spawn`](crate::spawn) or a closure of [`execute`](crate::execute),
+    /// submitted at the given priority.
+    Worker(Priority),
+    /// Any other thread, such as one spawned by the application or by
+    /// another runtime.
+    Foreign,
+}
+
+std::thread_local! {
+    /// The priority of the pool job running on this thread, if any.
+    static WORKER: Cell<Option<Priority>> = const { Cell::new(None) };
+    /// Whether this thread is already running other jobs while it waits.
+    #[cfg(all(
+        not(any(target_vendor = "apple", target_arch = "wasm32", target_os = "android")),
+        feature = "polyfill"
+    ))]
+    static HELPING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Returns the kind of thread the current code runs on.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(not(target_vendor = "apple"))] {
+/// use futures_lite::future::block_on;
+/// use native_executor::{
+///     ExecContext, Priority, current_context, polyfill, spawn, spawn_main, spawn_with_priority,
+/// };
+/// use std::thread;
+///
+/// thread::spawn(polyfill::start_main_executor);
+///
+/// assert_eq!(block_on(spawn_main(async { current_context() })), ExecContext::Main);
+/// assert_eq!(block_on(spawn(async { current_context() })), ExecContext::Worker(Priority::Default));
+/// assert_eq!(
+///     block_on(spawn_with_priority(async { current_context() }, Priority::Background)),
+///     ExecContext::Worker(Priority::Background)
+/// );
+/// assert_eq!(current_context(), ExecContext::Foreign);
+/// assert_eq!(thread::spawn(current_context).join().unwrap(), ExecContext::Foreign);
+/// # }
+/// ```
+#[must_use]
+pub fn current_context() -> ExecContext {
+    if is_main_thread() {
+        return ExecContext::Main;
+    }
+    WORKER
+        .get()
+        .map_or(ExecContext::Foreign, ExecContext::Worker)
+}
+
+/// Wraps `f`, a job of the pool, to mark the thread running it as a worker
+/// of `priority`.
+pub fn worker_job(
+    f: impl FnOnce() + Send + 'static,
+    priority: Priority,
+) -> impl FnOnce() + Send + 'static {
+    /// Restores the previous marker, also when `f` panics.
+    struct Restore(Option<Priority>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            WORKER.set(self.0);
+        }
+    }
+
+    move || {
+        let _restore = Restore(WORKER.replace(Some(priority)));
+        f();
+    }
+}
+
+/// Checks that the current thread may block on a round-trip through
+/// `operation`.
+///
+/// # Panics
+///
+/// With the `strict-blocking` feature, panics on a pool worker.
+#[track_caller]
+// Only a no-op without the feature.
+#[cfg_attr(not(feature = "strict-blocking"), allow(clippy::missing_const_for_fn))]
+pub fn check_blocking(operation: &str) {
+    #[cfg(feature = "strict-blocking")]
+    if let ExecContext::Worker(priority) = current_context() {
+        panic!(
+            "`{operation}` blocked a worker of the {priority:?} pool, which starves the other jobs \
+             of its priority; await the async variant instead"
+        );
+    }
+    #[cfg(not(feature = "strict-blocking"))]
+    let _ = operation;
+}
+
+/// The number of pool jobs a waiting worker runs before it parks.
+#[cfg(all(
+    not(any(target_vendor = "apple", target_arch = "wasm32", target_os = "android")),
+    feature = "polyfill"
+))]
+const HELP_LIMIT: usize = 64;
+
+/// Runs pending pool jobs on the current thread, if it is a pool worker that
+/// waits on a round-trip, until `done` returns `true`, nothing is pending,
+/// or the budget is spent.
+///
+/// Only the polyfill backend can run pool jobs on demand. A job run this way
+/// that blocks itself parks instead of helping further, which bounds the
+/// stack depth.
+pub fn help_while(mut done: impl FnMut() -> bool) {
+    #[cfg(all(
+        not(any(target_vendor = "apple", target_arch = "wasm32", target_os = "android")),
+        feature = "polyfill"
+    ))]
+    if matches!(current_context(), ExecContext::Worker(_)) && !HELPING.replace(true) {
+        /// Clears the flag, also when a job panics.
+        struct Helping;
+
+        impl Drop for Helping {
+            fn drop(&mut self) {
+                HELPING.set(false);
+            }
+        }
+
+        let _helping = Helping;
+        for _ in 0..HELP_LIMIT {
+            if done() || !crate::polyfill::run_pending() {
+                break;
+            }
+        }
+    }
+    #[cfg(not(all(
+        not(any(target_vendor = "apple", target_arch = "wasm32", target_os = "android")),
+        feature = "polyfill"
+    )))]
+    let _ = &mut done;
+}
+
+/// Receives the reply of a round-trip, helping the pool first if the current
+/// thread is a worker.
+pub fn recv<T>(receiver: &Receiver<T>) -> Result<T, RecvError> {
+    let mut reply = None;
+    help_while(|| match receiver.try_recv() {
+        Ok(value) => {
+            reply = Some(Ok(value));
+            true
+        }
+        Err(TryRecvError::Disconnected) => {
+            reply = Some(Err(RecvError));
+            true
+        }
+        Err(TryRecvError::Empty) => false,
+    });
+    reply.unwrap_or_else(|| receiver.recv())
+}
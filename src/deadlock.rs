@@ -0,0 +1,225 @@
+//! Detection of threads stuck waiting on the main thread.
+//!
+//! The usual way to freeze an application built on this crate is a cycle
+//! through the main thread: a thread blocks on a round-trip to the main
+//! thread, with [`Mailbox::call_blocking`](crate::Mailbox::call_blocking) on
+//! a main-thread mailbox or [`MainToken::run_sync`](crate::MainToken::run_sync),
+//! while the main thread is itself blocked on that thread, for example on a
+//! lock it holds. Nothing crashes and nothing is logged.
+//!
+//! With the `deadlock-detect` feature, every such blocking wait is recorded
+//! along with the call site that started it. A monitor task checks the
+//! records periodically and reports a wait once it has lasted longer than the
+//! [threshold](set_threshold) while the oldest item pending on the main queue
+//! is older than the threshold as well: the main thread has stopped serving
+//! its queue, which a merely slow round-trip does not cause.
+//!
+//! Reports go to the [handler](set_handler), which prints them to standard
+//! error by default. Each wait is reported at most once.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # #[cfg(not(target_vendor = "apple"))] {
+//! use futures_lite::future::block_on;
+//! use native_executor::{Mailbox, MainToken, deadlock, execute_main, polyfill, spawn_main};
+//! use std::{sync::mpsc, thread, time::Duration};
+//!
+//! thread::spawn(polyfill::start_main_executor);
+//! let mailbox = block_on(spawn_main(async { Mailbox::main(0) }));
+//!
+//! deadlock::set_threshold(Duration::from_millis(50));
+//! let (sender, warnings) = mpsc::channel();
+//! deadlock::set_handler(move |warning| sender.send(warning.clone()).unwrap());
+//!
+//! // Keeps the main thread from serving its queue for a while
+//! let stall_main = || {
+//!     let (started, stalled) = mpsc::channel();
+//!     execute_main(move || {
+//!         started.send(()).unwrap();
+//!         thread::sleep(Duration::from_millis(300));
+//!     });
+//!     stalled.recv().unwrap();
+//! };
+//!
+//! // A main thread that stops serving its queue is reported
+//! stall_main();
+//! let (count, line) = (mailbox.call_blocking(|count| *count), line!());
+//! assert_eq!(count, 0);
+//! let warning = warnings.try_recv().unwrap();
+//! assert_eq!((warning.location.file(), warning.location.line()), (file!(), line));
+//! assert_eq!(warning.thread.id(), thread::current().id());
+//! assert!(warning.waited >= Duration::from_millis(50));
+//! assert!(warning.oldest_pending_main >= Duration::from_millis(50));
+//!
+//! stall_main();
+//! let line = line!() + 1;
+//! MainToken::run_sync(|_| {});
+//! assert_eq!(warnings.try_recv().unwrap().location.line(), line);
+//!
+//! // A slow job keeps the main thread busy, but its queue is not stuck
+//! let slow = mailbox.call_blocking(|count| {
+//!     thread::sleep(Duration::from_millis(200));
+//!     *count
+//! });
+//! assert_eq!(slow, 0);
+//! assert!(warnings.try_recv().is_err());
+//! # }
+//! ```
+
+use core::{
+    fmt,
+    panic::Location,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+use std::{
+    panic::{AssertUnwindSafe, catch_unwind},
+    sync::{Mutex, Once, PoisonError, RwLock},
+    thread::{self, Thread},
+    time::Instant,
+};
+
+use crate::{Priority, diagnostics, spawn_with_priority, timer::Timer};
+
+/// A thread found waiting on the main thread while the main queue is stuck.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Warning {
+    /// The waiting thread.
+    pub thread: Thread,
+    /// The call that started the wait.
+    pub location: &'static Location<'static>,
+    /// How long the thread had been waiting.
+    pub waited: Duration,
+    /// How long the oldest item pending on the main queue had been waiting,
+    /// as estimated by [`diagnostics::oldest_pending_main`].
+    pub oldest_pending_main: Duration,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "thread {:?} ({}) has waited {:?} on the main thread, called at {}, while the oldest \
+             main-queue item has waited {:?}; this is likely a deadlock",
+            self.thread.id(),
+            self.thread.name().unwrap_or("unnamed"),
+            self.waited,
+            self.location,
+            self.oldest_pending_main
+        )
+    }
+}
+
+type Handler = Box<dyn Fn(&Warning) + Send + Sync>;
+
+/// The threshold in nanoseconds.
+static THRESHOLD: AtomicU64 = AtomicU64::new(1_000_000_000);
+static HANDLER: RwLock<Option<Handler>> = RwLock::new(None);
+static WAITS: Mutex<Vec<Wait>> = Mutex::new(Vec::new());
+
+/// Sets how long a wait and the main queue must both be stuck before the
+/// wait is reported; one second by default.
+pub fn set_threshold(threshold: Duration) {
+    let nanos = u64::try_from(threshold.as_nanos()).unwrap_or(u64::MAX);
+    THRESHOLD.store(nanos, Ordering::Relaxed);
+}
+
+fn threshold() -> Duration {
+    Duration::from_nanos(THRESHOLD.load(Ordering::Relaxed))
+}
+
+/// Replaces the handler that receives the warnings, which prints them to
+/// standard error by default.
+///
+/// The handler runs on a pool thread. A panicking handler is caught; the
+/// panic is still reported by the process panic hook.
+pub fn set_handler(handler: impl Fn(&Warning) + Send + Sync + 'static) {
+    *HANDLER.write().unwrap_or_else(PoisonError::into_inner) = Some(Box::new(handler));
+}
+
+/// A blocking wait on the main thread.
+struct Wait {
+    id: u64,
+    thread: Thread,
+    location: &'static Location<'static>,
+    since: Instant,
+    reported: bool,
+}
+
+/// Records a blocking wait on the main thread until dropped.
+pub(crate) struct MainWait {
+    id: u64,
+}
+
+impl Drop for MainWait {
+    fn drop(&mut self) {
+        WAITS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|wait| wait.id != self.id);
+    }
+}
+
+/// Records that the current thread blocks until the main thread has run a
+/// job, for the call at `location`.
+pub(crate) fn wait_on_main(location: &'static Location<'static>) -> MainWait {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    static MONITOR: Once = Once::new();
+
+    MONITOR.call_once(|| spawn_with_priority(monitor(), Priority::Utility).detach());
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    WAITS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(Wait {
+            id,
+            thread: thread::current(),
+            location,
+            since: Instant::now(),
+            reported: false,
+        });
+    MainWait { id }
+}
+
+async fn monitor() {
+    loop {
+        Timer::after((threshold() / 4).max(Duration::from_millis(1))).await;
+        check();
+    }
+}
+
+/// Reports the waits that exceed the threshold while the main queue does.
+fn check() {
+    let threshold = threshold();
+    let Some(oldest_pending_main) =
+        diagnostics::oldest_pending_main().filter(|oldest| *oldest >= threshold)
+    else {
+        return;
+    };
+    let warnings: Vec<_> = WAITS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter_mut()
+        .filter(|wait| !wait.reported && wait.since.elapsed() >= threshold)
+        .map(|wait| {
+            wait.reported = true;
+            Warning {
+                thread: wait.thread.clone(),
+                location: wait.location,
+                waited: wait.since.elapsed(),
+                oldest_pending_main,
+            }
+        })
+        .collect();
+
+    let handler = HANDLER.read().unwrap_or_else(PoisonError::into_inner);
+    for warning in &warnings {
+        // The panic hook has already reported the panic at this point.
+        let _ = catch_unwind(AssertUnwindSafe(|| match handler.as_ref() {
+            Some(handler) => handler(warning),
+            None => eprintln!("native-executor: {warning}"),
+        }));
+    }
+}
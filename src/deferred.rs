@@ -0,0 +1,92 @@
+//! Tasks whose future is built on the thread that runs them.
+//!
+//! A future is usually built by its caller and moved into the task, so the
+//! setup it does and the buffers it captures cost the caller's thread. A
+//! deferred task takes a closure instead, and calls it in its first poll.
+
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use async_task::Task;
+
+use crate::{Priority, spawn_with_priority};
+
+/// A future that calls its closure on its first poll, then polls the future
+/// the closure returned in place.
+enum ClosureThenFuture<F, Fut> {
+    /// `None` while the closure runs, and afterwards if it panicked.
+    Closure(Option<F>),
+    Future(Fut),
+}
+
+impl<F, Fut> Future for ClosureThenFuture<F, Fut>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future,
+{
+    type Output = Fut::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Fut::Output> {
+        // SAFETY: the closure is not pinned, so it may be moved out; the
+        // `Future` variant is not touched.
+        if let Self::Closure(f) = unsafe { self.as_mut().get_unchecked_mut() } {
+            let f = f
+                .take()
+                .expect("`ClosureThenFuture` polled after its closure panicked");
+            // Replacing the variant drops the empty closure slot in place,
+            // and the future is pinned from here on.
+            self.set(Self::Future(f()));
+        }
+        // SAFETY: the future is structurally pinned and never moved out.
+        match unsafe { self.get_unchecked_mut() } {
+            Self::Future(future) => unsafe { Pin::new_unchecked(future) }.poll(cx),
+            Self::Closure(_) => unreachable!("the closure was replaced by its future"),
+        }
+    }
+}
+
+/// Spawns the future returned by `f` on the thread pool at `priority`,
+/// calling `f` on the thread that first runs the task.
+///
+/// With [`spawn_with_priority`], the future is built by the caller: the setup
+/// in its constructor and the buffers it captures cost the caller's thread,
+/// which matters when that is the main thread. Here the caller only moves `f`
+/// to the task, and `f` runs in the first poll, as part of the task: at
+/// `priority`, and within its [hooks](crate::hooks). The returned handle
+/// resolves to the output of the future, as with `spawn_with_priority`; a
+/// panic in `f` is a panic of the task.
+///
+/// # Examples
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::{Priority, spawn_fn};
+/// use std::thread;
+///
+/// let caller = thread::current().id();
+/// let task = spawn_fn(
+///     || {
+///         // Filled on the thread pool, not by the caller
+///         let buffer = vec![0xA5u8; 1 << 20];
+///         let built_on = thread::current().id();
+///         async move { (built_on, buffer.iter().map(|&byte| u64::from(byte)).sum::<u64>()) }
+///     },
+///     Priority::Background,
+/// );
+///
+/// let (built_on, sum) = block_on(task);
+/// assert_ne!(built_on, caller);
+/// assert_eq!(sum, 0xA5 << 20);
+/// ```
+#[track_caller]
+pub fn spawn_fn<F, Fut>(f: F, priority: Priority) -> Task<Fut::Output>
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future + Send + 'static,
+    Fut::Output: Send,
+{
+    spawn_with_priority(ClosureThenFuture::Closure(Some(f)), priority)
+}
@@ -0,0 +1,929 @@
+//! Queue-depth introspection for debugging.
+//!
+//! Platform queues such as GCD do not expose how much work is waiting, so the
+//! crate maintains its own counters: a closure counts as pending from the
+//! moment it is submitted to a queue until it starts running.
+//!
+//! This module is only available with the `stats` feature. Each dispatch costs
+//! two atomic updates on its queue's counter and one on the total of
+//! [`dispatches`]; main-thread dispatches additionally record timestamps used
+//! for the latency estimates.
+//!
+//! The `stats-detailed` feature additionally accounts the time spent polling
+//! each task, see [`busiest_tasks`], and tracks when each task was last
+//! polled, to find the tasks that hang, see [`stalled_tasks`]. It reads the
+//! monotonic clock twice per poll, wraps the waker of every task, and
+//! registers every task when it is spawned.
+//!
+//! The `leak-check` feature additionally counts the live tasks, timers,
+//! mailboxes and scheduled closures, see [`live_objects`]. The `soak` example
+//! uses it to check that a long-running mixed workload returns to its
+//! baseline.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # #[cfg(not(target_vendor = "apple"))] {
+//! use native_executor::{diagnostics, polyfill, spawn_main};
+//! use std::{thread, time::Duration};
+//!
+//! // The main executor is not running yet, so main-thread work piles up
+//! for _ in 0..100 {
+//!     spawn_main(async {}).detach();
+//! }
+//! assert_eq!(diagnostics::pending_main(), 100);
+//! assert_eq!(diagnostics::max_pending_main(), 100);
+//! assert!(diagnostics::oldest_pending_main().is_some());
+//!
+//! thread::spawn(polyfill::start_main_executor);
+//! while diagnostics::pending_main() > 0 {
+//!     thread::sleep(Duration::from_millis(1));
+//! }
+//! assert_eq!(diagnostics::oldest_pending_main(), None);
+//! assert!(diagnostics::last_main_wait().is_some());
+//! # }
+//! ```
+
+use core::{
+    future::Future,
+    pin::pin,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+    time::Duration,
+};
+#[cfg(feature = "stats-detailed")]
+use core::{panic::Location, pin::Pin, sync::atomic::AtomicBool};
+#[cfg(feature = "stats-detailed")]
+use std::{
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+    task::Wake,
+};
+use std::{
+    sync::{Arc, OnceLock},
+    time::Instant,
+};
+
+#[cfg(feature = "stats-detailed")]
+use crate::hooks::TaskInfo;
+use crate::{Priority, priority::SchedClass};
+
+/// Counter slots: one per standard priority, followed by the main queue.
+const MAIN_SLOT: usize = SchedClass::COUNT;
+
+static PENDING: [AtomicUsize; MAIN_SLOT + 1] = [const { AtomicUsize::new(0) }; MAIN_SLOT + 1];
+static MAX_PENDING_MAIN: AtomicUsize = AtomicUsize::new(0);
+static PLATFORM_TIMERS: AtomicU64 = AtomicU64::new(0);
+static DISPATCHES: AtomicU64 = AtomicU64::new(0);
+static CLAMPED: AtomicU64 = AtomicU64::new(0);
+
+/// Nanoseconds since [`epoch`] at which the main queue last became non-empty.
+static MAIN_BUSY_SINCE: AtomicU64 = AtomicU64::new(0);
+/// Submission time of the main-queue item that started most recently.
+static MAIN_LAST_STARTED_SUBMIT: AtomicU64 = AtomicU64::new(0);
+/// Queue wait of the main-queue item that started most recently, plus one.
+static MAIN_LAST_WAIT: AtomicU64 = AtomicU64::new(0);
+
+fn epoch() -> Instant {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    *EPOCH.get_or_init(Instant::now)
+}
+
+fn now_nanos() -> u64 {
+    u64::try_from(epoch().elapsed().as_nanos()).unwrap_or(u64::MAX)
+}
+
+const fn slot(priority: Priority) -> usize {
+    priority.class().index()
+}
+
+/// Returns the number of closures submitted to the main thread that have not
+/// started running yet.
+#[must_use]
+pub fn pending_main() -> usize {
+    PENDING[MAIN_SLOT].load(Ordering::Relaxed)
+}
+
+/// Returns the number of closures submitted at `priority` that have not
+/// started running yet.
+///
+/// Custom priorities are counted under their [standard](Priority::standard)
+/// level.
+#[must_use]
+pub fn pending(priority: Priority) -> usize {
+    PENDING[slot(priority)].load(Ordering::Relaxed)
+}
+
+/// Returns the highest [`pending_main`] value observed since the last call to
+/// [`reset_max_pending_main`].
+#[must_use]
+pub fn max_pending_main() -> usize {
+    MAX_PENDING_MAIN.load(Ordering::Relaxed)
+}
+
+/// Resets the high-water mark reported by [`max_pending_main`] to the
+/// current queue depth.
+pub fn reset_max_pending_main() {
+    MAX_PENDING_MAIN.store(pending_main(), Ordering::Relaxed);
+}
+
+/// Estimates how long the oldest pending main-thread item has been waiting.
+///
+/// Returns `None` when nothing is pending. The estimate never underestimates
+/// the true age: it is derived from when the queue became busy and from the
+/// submission time of the item that started most recently.
+#[must_use]
+pub fn oldest_pending_main() -> Option<Duration> {
+    if pending_main() == 0 {
+        return None;
+    }
+    let since = MAIN_BUSY_SINCE
+        .load(Ordering::Relaxed)
+        .max(MAIN_LAST_STARTED_SUBMIT.load(Ordering::Relaxed));
+    Some(Duration::from_nanos(now_nanos().saturating_sub(since)))
+}
+
+/// Returns how long the most recently started main-thread item waited in the
+/// queue, or `None` if no main-thread item has started yet.
+///
+/// This is a direct estimate of the current main queue latency.
+#[must_use]
+pub fn last_main_wait() -> Option<Duration> {
+    match MAIN_LAST_WAIT.load(Ordering::Relaxed) {
+        0 => None,
+        wait => Some(Duration::from_nanos(wait - 1)),
+    }
+}
+
+/// Returns how many timers the crate has submitted to the platform.
+///
+/// Timers of [`Timer`](crate::timer::Timer) that share a platform timer, see
+/// [`Config::coalesce_timers`](crate::Config::coalesce_timers), only count
+/// once.
+///
+/// # Examples
+///
+/// A hundred thousand timeouts clustered around a few deadlines need about
+/// as many platform timers as there are distinct deadlines:
+///
+/// ```rust
+/// use native_executor::{Config, diagnostics, init, timer::Timer};
+/// use std::{
+///     future::Future,
+///     pin::pin,
+///     sync::{
+///         Arc, Mutex,
+///         atomic::{AtomicUsize, Ordering},
+///     },
+///     task::{Context, Wake, Waker},
+///     thread,
+///     time::{Duration, Instant},
+/// };
+///
+/// init(Config::new().coalesce_timers(Duration::from_millis(1))).unwrap();
+///
+/// // Records when each timer fires
+/// struct Fired(AtomicUsize, Mutex<Option<(Instant, Instant)>>);
+/// impl Wake for Fired {
+///     fn wake(self: Arc<Self>) {
+///         let now = Instant::now();
+///         let mut span = self.1.lock().unwrap();
+///         let (first, last) = span.get_or_insert((now, now));
+///         *first = (*first).min(now);
+///         *last = (*last).max(now);
+///         self.0.fetch_add(1, Ordering::SeqCst);
+///     }
+/// }
+/// let fired = Arc::new(Fired(AtomicUsize::new(0), Mutex::new(None)));
+/// let waker = Waker::from(fired.clone());
+/// let mut cx = Context::from_waker(&waker);
+///
+/// const TIMERS: usize = 100_000;
+/// let before = diagnostics::platform_timers();
+/// let start = Instant::now();
+/// let mut timers: Vec<_> = (0..TIMERS)
+///     .map(|i| Timer::after(Duration::from_millis(200 + 10 * (i % 5) as u64)))
+///     .collect();
+/// for timer in &mut timers {
+///     let _ = pin!(timer).poll(&mut cx);
+/// }
+/// let created = start.elapsed();
+///
+/// while fired.0.load(Ordering::SeqCst) < TIMERS {
+///     thread::sleep(Duration::from_millis(5));
+/// }
+/// let (first, last) = fired.1.lock().unwrap().unwrap();
+/// assert!(first >= start + Duration::from_millis(200));
+/// assert!(last <= start + created + Duration::from_secs(2));
+///
+/// // One platform timer per bucket: five deadlines, shifted by the time it
+/// // took to create the timers
+/// let platform = diagnostics::platform_timers() - before;
+/// let millis = u64::try_from(created.as_millis()).unwrap();
+/// assert!(platform <= 5 * (millis + 2), "{platform} platform timers");
+/// ```
+#[must_use]
+pub fn platform_timers() -> u64 {
+    PLATFORM_TIMERS.load(Ordering::Relaxed)
+}
+
+/// Returns how many closures the crate has submitted to the platform queues,
+/// including the main queue.
+///
+/// Every poll of a task that went through a queue counts once. Wake-ups do
+/// not count by themselves: however often a task is woken before it runs, it
+/// is submitted once, and a task woken while it runs is submitted once more
+/// after its poll.
+///
+/// # Examples
+///
+/// A burst of wake-ups costs a handful of dispatches, and none is lost:
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::{diagnostics, spawn};
+/// use std::{
+///     future::poll_fn,
+///     sync::{
+///         Arc, Mutex,
+///         atomic::{AtomicBool, AtomicUsize, Ordering},
+///     },
+///     task::{Poll, Waker},
+///     thread,
+/// };
+///
+/// // A task that waits for `done`, publishing its waker for the producer
+/// fn waiter(
+///     done: Arc<AtomicBool>,
+///     waker: Arc<Mutex<Option<Waker>>>,
+///     polls: Arc<AtomicUsize>,
+/// ) -> impl Future<Output = ()> + Send + 'static {
+///     poll_fn(move |cx| {
+///         polls.fetch_add(1, Ordering::SeqCst);
+///         *waker.lock().unwrap() = Some(cx.waker().clone());
+///         if done.load(Ordering::SeqCst) { Poll::Ready(()) } else { Poll::Pending }
+///     })
+/// }
+///
+/// let done = Arc::new(AtomicBool::new(false));
+/// let waker = Arc::new(Mutex::new(None::<Waker>));
+/// let polls = Arc::new(AtomicUsize::new(0));
+/// let task = spawn(waiter(done.clone(), waker.clone(), polls.clone()));
+/// while waker.lock().unwrap().is_none() {
+///     thread::yield_now();
+/// }
+///
+/// // Ten thousand wake-ups in a tight loop
+/// const WAKES: u64 = 10_000;
+/// let before = diagnostics::dispatches();
+/// let waker = waker.lock().unwrap().clone().unwrap();
+/// for _ in 0..WAKES {
+///     waker.wake_by_ref();
+/// }
+/// done.store(true, Ordering::SeqCst);
+/// waker.wake();
+/// block_on(task);
+///
+/// // Each dispatch is one poll, and most wake-ups needed neither
+/// let dispatched = diagnostics::dispatches() - before;
+/// assert!(dispatched < WAKES / 10, "{dispatched} dispatches");
+/// assert!(polls.load(Ordering::SeqCst) as u64 <= dispatched + 1);
+///
+/// // A wake-up racing with the poll that registers the waker is never lost
+/// for _ in 0..1_000 {
+///     let done = Arc::new(AtomicBool::new(false));
+///     let waker = Arc::new(Mutex::new(None::<Waker>));
+///     let task = spawn(waiter(done.clone(), waker.clone(), Arc::default()));
+///     let producer = thread::spawn(move || {
+///         done.store(true, Ordering::SeqCst);
+///         if let Some(waker) = waker.lock().unwrap().take() {
+///             waker.wake();
+///         }
+///     });
+///     block_on(task);
+///     producer.join().unwrap();
+/// }
+/// ```
+#[must_use]
+pub fn dispatches() -> u64 {
+    DISPATCHES.load(Ordering::Relaxed)
+}
+
+/// Returns how many tasks and closures of the thread pool were moved to
+/// another priority by the bounds of the configuration.
+///
+/// The bounds are set with
+/// [`Config::priority_floor`](crate::Config::priority_floor) and
+/// [`Config::priority_ceiling`](crate::Config::priority_ceiling).
+///
+/// # Examples
+///
+/// ```rust
+/// use native_executor::{Config, Priority, diagnostics, execute_with_priority, init};
+///
+/// init(Config::new().priority_floor(Priority::Utility)).unwrap();
+///
+/// execute_with_priority(|| {}, Priority::Background);
+/// execute_with_priority(|| {}, Priority::Utility);
+/// assert_eq!(diagnostics::clamped_tasks(), 1);
+/// ```
+#[must_use]
+pub fn clamped_tasks() -> u64 {
+    CLAMPED.load(Ordering::Relaxed)
+}
+
+/// Counts a task spawned at another priority than requested.
+pub(crate) fn count_clamped() {
+    CLAMPED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Counts a timer submitted to the platform.
+pub(crate) fn count_platform_timer() {
+    PLATFORM_TIMERS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Wraps `f` so that it counts as pending on the queue for `priority` until it
+/// starts running.
+pub(crate) fn track(
+    f: impl FnOnce() + Send + 'static,
+    priority: Priority,
+) -> impl FnOnce() + Send + 'static {
+    let counter = &PENDING[slot(priority)];
+    counter.fetch_add(1, Ordering::Relaxed);
+    DISPATCHES.fetch_add(1, Ordering::Relaxed);
+    move || {
+        counter.fetch_sub(1, Ordering::Relaxed);
+        f();
+    }
+}
+
+/// Wraps `f` so that it counts as pending on the main queue until it starts
+/// running.
+pub(crate) fn track_main(f: impl FnOnce() + Send + 'static) -> impl FnOnce() + Send + 'static {
+    let submitted = now_nanos();
+    let previous = PENDING[MAIN_SLOT].fetch_add(1, Ordering::Relaxed);
+    if previous == 0 {
+        MAIN_BUSY_SINCE.store(submitted, Ordering::Relaxed);
+    }
+    MAX_PENDING_MAIN.fetch_max(previous + 1, Ordering::Relaxed);
+    DISPATCHES.fetch_add(1, Ordering::Relaxed);
+    move || {
+        PENDING[MAIN_SLOT].fetch_sub(1, Ordering::Relaxed);
+        MAIN_LAST_STARTED_SUBMIT.store(submitted, Ordering::Relaxed);
+        let wait = now_nanos().saturating_sub(submitted);
+        MAIN_LAST_WAIT.store(wait.saturating_add(1), Ordering::Relaxed);
+        f();
+    }
+}
+
+/// Polls `future` twice with the same waker, and panics if the second poll
+/// registers the waker again.
+///
+/// A future polled again by the task it is registered for should recognize
+/// the waker with [`Waker::will_wake`] and keep the one it holds, as
+/// [`Clone::clone_from`] does for wakers. Cloning the waker on every poll
+/// instead makes futures that are polled often, such as those of a `select!`
+/// loop, churn: each clone takes a reference, or allocates for wakers that
+/// are not reference counted, to replace a registration that was still
+/// valid. The waker given to `future` counts its clones, so a registration is
+/// seen wherever it is stored.
+///
+/// The two polls get clones of one waker, as a task passes its waker.
+/// Returns the result of the second poll, or of the first if `future`
+/// completed right away; pass `&mut future` to keep polling it afterwards.
+///
+/// # Panics
+///
+/// Panics if the second poll cloned the waker.
+///
+/// # Examples
+///
+/// The futures of the crate register their waker once:
+///
+/// ```rust
+/// use native_executor::{
+///     Mailbox, callback,
+///     diagnostics::assert_waker_efficient,
+///     notify::Notify,
+///     timer::{Deadline, Timer},
+/// };
+/// use std::time::Duration;
+///
+/// assert!(assert_waker_efficient(Timer::after(Duration::from_secs(60))).is_pending());
+/// assert!(assert_waker_efficient(Deadline::after(Duration::from_secs(60))).is_pending());
+///
+/// let notify = Notify::new().unwrap();
+/// assert!(assert_waker_efficient(notify.notified()).is_pending());
+///
+/// let (_completer, oneshot) = callback::future::<u32>();
+/// assert!(assert_waker_efficient(oneshot).is_pending());
+///
+/// // The main thread is not served, so the call stays queued
+/// let mailbox = Mailbox::main(0u32);
+/// assert!(assert_waker_efficient(mailbox.call(|value| *value)).is_pending());
+/// ```
+///
+/// A future that stores a clone on every poll is caught:
+///
+/// ```rust,should_panic
+/// use native_executor::diagnostics::assert_waker_efficient;
+/// use std::{future::poll_fn, sync::Mutex, task::Poll};
+///
+/// let registered = Mutex::new(None);
+/// let _ = assert_waker_efficient(poll_fn(|cx| {
+///     *registered.lock().unwrap() = Some(cx.waker().clone());
+///     Poll::<()>::Pending
+/// }));
+/// ```
+#[track_caller]
+pub fn assert_waker_efficient<F: Future>(future: F) -> Poll<F::Output> {
+    let clones = Arc::new(AtomicUsize::new(0));
+    let waker = counting_waker(&clones);
+    let first = waker.clone();
+    let mut future = pin!(future);
+    if let Poll::Ready(output) = future.as_mut().poll(&mut Context::from_waker(&first)) {
+        return Poll::Ready(output);
+    }
+    let before = clones.load(Ordering::Relaxed);
+    let polled = future.as_mut().poll(&mut Context::from_waker(&waker));
+    let registered = clones.load(Ordering::Relaxed) - before;
+    assert!(
+        registered == 0,
+        "the second poll made {registered} clones of the waker; compare it with \
+         `Waker::will_wake` before storing it, or use `Waker::clone_from`"
+    );
+    polled
+}
+
+/// Returns a waker that counts its clones in `clones`.
+///
+/// The waker holds a reference to `clones`, so it stays valid however long
+/// the polled future keeps it.
+fn counting_waker(clones: &Arc<AtomicUsize>) -> Waker {
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, release, |_| {}, release);
+
+    unsafe fn clone(data: *const ()) -> RawWaker {
+        // SAFETY: `data` comes from `Arc::into_raw` below, and the waker
+        // being cloned keeps its reference.
+        unsafe {
+            Arc::increment_strong_count(data.cast::<AtomicUsize>());
+            (*data.cast::<AtomicUsize>()).fetch_add(1, Ordering::Relaxed);
+        }
+        RawWaker::new(data, &VTABLE)
+    }
+
+    unsafe fn release(data: *const ()) {
+        // SAFETY: releases the reference of the waker being consumed.
+        unsafe { Arc::decrement_strong_count(data.cast::<AtomicUsize>()) };
+    }
+
+    let data = Arc::into_raw(clones.clone()).cast();
+    // SAFETY: the vtable upholds the `RawWaker` contract: each clone holds a
+    // reference, which `wake` and `drop` release.
+    unsafe { Waker::from_raw(RawWaker::new(data, &VTABLE)) }
+}
+
+/// Execution-time accounting of a single task, as returned by
+/// [`busiest_tasks`].
+#[cfg(feature = "stats-detailed")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TaskReport {
+    /// Process-unique identifier of the task.
+    pub id: u64,
+    /// Name of the task, if one was assigned.
+    pub name: Option<&'static str>,
+    /// Priority the task is scheduled at.
+    pub priority: Priority,
+    /// Total time spent polling the task.
+    pub cpu_time: Duration,
+    /// Number of times the task was polled.
+    pub polls: u64,
+    /// Whether the task is still alive, that is neither completed nor
+    /// cancelled.
+    pub alive: bool,
+    /// Where the task was spawned.
+    pub spawned_at: &'static Location<'static>,
+    /// When the task was last polled, if it has been.
+    pub last_poll: Option<Instant>,
+    /// Whether the task is queued to be polled, rather than waiting for a
+    /// wake-up. A task is queued from the moment it is spawned or woken
+    /// until its next poll starts.
+    pub scheduled: bool,
+}
+
+/// Number of finished tasks whose reports are retained.
+#[cfg(feature = "stats-detailed")]
+const FINISHED_REPORTS: usize = 64;
+
+#[cfg(feature = "stats-detailed")]
+#[derive(Debug)]
+struct TaskStats {
+    info: TaskInfo,
+    cpu_nanos: AtomicU64,
+    polls: AtomicU64,
+    /// Nanoseconds since [`epoch`] at which the last poll started, plus one,
+    /// or zero before the first poll.
+    last_poll: AtomicU64,
+    scheduled: AtomicBool,
+}
+
+#[cfg(feature = "stats-detailed")]
+impl TaskStats {
+    fn report(&self, alive: bool) -> TaskReport {
+        TaskReport {
+            id: self.info.id,
+            name: self.info.name,
+            priority: self.info.priority,
+            cpu_time: Duration::from_nanos(self.cpu_nanos.load(Ordering::Relaxed)),
+            polls: self.polls.load(Ordering::Relaxed),
+            alive,
+            spawned_at: self.info.spawned_at,
+            last_poll: match self.last_poll.load(Ordering::Relaxed) {
+                0 => None,
+                nanos => Some(epoch() + Duration::from_nanos(nanos - 1)),
+            },
+            scheduled: self.scheduled.load(Ordering::Acquire),
+        }
+    }
+}
+
+/// A waker that marks its task as scheduled before forwarding the wake-up.
+#[cfg(feature = "stats-detailed")]
+struct Scheduling {
+    stats: Arc<TaskStats>,
+    inner: Waker,
+}
+
+#[cfg(feature = "stats-detailed")]
+impl Wake for Scheduling {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.stats.scheduled.store(true, Ordering::Release);
+        self.inner.wake_by_ref();
+    }
+}
+
+#[cfg(feature = "stats-detailed")]
+#[derive(Debug, Default)]
+struct Registry {
+    live: HashMap<u64, Arc<TaskStats>>,
+    /// The busiest finished tasks, at most [`FINISHED_REPORTS`].
+    finished: Vec<TaskReport>,
+}
+
+#[cfg(feature = "stats-detailed")]
+fn registry() -> std::sync::MutexGuard<'static, Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    REGISTRY
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Returns the `n` tasks that spent the most time being polled, busiest first.
+///
+/// Live tasks are sampled at the time of the call. Of the finished tasks,
+/// only the busiest few dozen are remembered.
+///
+/// Only available with the `stats-detailed` feature.
+///
+/// # Examples
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::{Priority, diagnostics, spawn_with_priority, timer::Timer};
+/// use std::time::{Duration, Instant};
+///
+/// let spinning = spawn_with_priority(async {
+///     let start = Instant::now();
+///     while start.elapsed() < Duration::from_millis(30) {}
+/// }, Priority::Utility);
+/// let sleeping = spawn_with_priority(async {
+///     Timer::after(Duration::from_millis(30)).await;
+/// }, Priority::Background);
+/// block_on(async {
+///     spinning.await;
+///     sleeping.await;
+/// });
+///
+/// let report = diagnostics::busiest_tasks(2);
+/// assert_eq!(report[0].priority, Priority::Utility);
+/// assert!(report[0].cpu_time >= Duration::from_millis(30));
+/// assert_eq!(report[1].priority, Priority::Background);
+/// assert!(report[1].cpu_time < report[0].cpu_time);
+/// assert_eq!(report[1].polls, 2);
+/// ```
+#[cfg(feature = "stats-detailed")]
+#[must_use]
+pub fn busiest_tasks(n: usize) -> Vec<TaskReport> {
+    let registry = registry();
+    let mut reports: Vec<_> = registry
+        .live
+        .values()
+        .map(|stats| stats.report(true))
+        .chain(registry.finished.iter().cloned())
+        .collect();
+    drop(registry);
+    reports.sort_by_key(|report| core::cmp::Reverse(report.cpu_time));
+    reports.truncate(n);
+    reports
+}
+
+/// Returns the live tasks that have been waiting for a wake-up for at least
+/// `older_than` since their last poll, the longest waiting first.
+///
+/// A task that waits on something that never happens, such as a channel
+/// whose sender is forgotten, shows up here along with where it was spawned.
+/// Tasks that wait on long timers do too, so pick `older_than` above the
+/// longest expected wait.
+///
+/// Only available with the `stats-detailed` feature.
+///
+/// # Examples
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::{diagnostics, spawn};
+/// use std::{thread, time::Duration};
+///
+/// let (sender, receiver) = async_channel::bounded::<()>(1);
+/// let (task, line) = (spawn(async move { receiver.recv().await.ok() }), line!());
+///
+/// // Once it has waited long enough, the task is reported
+/// let stalled = loop {
+///     thread::sleep(Duration::from_millis(20));
+///     if let Some(report) = diagnostics::stalled_tasks(Duration::from_millis(10)).pop() {
+///         break report;
+///     }
+/// };
+/// assert_eq!((stalled.spawned_at.file(), stalled.spawned_at.line()), (file!(), line));
+/// assert!(!stalled.scheduled);
+/// assert!(stalled.last_poll.unwrap().elapsed() >= Duration::from_millis(10));
+///
+/// // Until it completes
+/// sender.try_send(()).unwrap();
+/// assert_eq!(block_on(task), Some(()));
+/// assert!(diagnostics::stalled_tasks(Duration::ZERO).is_empty());
+/// ```
+#[cfg(feature = "stats-detailed")]
+#[must_use]
+pub fn stalled_tasks(older_than: Duration) -> Vec<TaskReport> {
+    let now = now_nanos();
+    let threshold = u64::try_from(older_than.as_nanos()).unwrap_or(u64::MAX);
+    let registry = registry();
+    let mut reports: Vec<_> = registry
+        .live
+        .values()
+        .filter(|stats| {
+            let last_poll = stats.last_poll.load(Ordering::Relaxed);
+            !stats.scheduled.load(Ordering::Acquire)
+                && last_poll > 0
+                && now.saturating_sub(last_poll - 1) >= threshold
+        })
+        .map(|stats| stats.report(true))
+        .collect();
+    drop(registry);
+    reports.sort_by_key(|report| report.last_poll);
+    reports
+}
+
+/// Wraps `future` so that the time spent polling it is accounted to the task
+/// described by `info`.
+#[cfg(feature = "stats-detailed")]
+pub(crate) fn account<Fut: Future>(future: Fut, info: TaskInfo) -> Accounted<Fut> {
+    let stats = Arc::new(TaskStats {
+        info,
+        cpu_nanos: AtomicU64::new(0),
+        polls: AtomicU64::new(0),
+        last_poll: AtomicU64::new(0),
+        scheduled: AtomicBool::new(true),
+    });
+    registry().live.insert(info.id, stats.clone());
+    Accounted {
+        future,
+        stats,
+        waker: None,
+    }
+}
+
+/// A future whose poll time is accounted, see [`account`].
+#[cfg(feature = "stats-detailed")]
+pub(crate) struct Accounted<Fut> {
+    future: Fut,
+    stats: Arc<TaskStats>,
+    /// The waker passed in by the last poll, and the [`Scheduling`] waker
+    /// that forwards to it.
+    waker: Option<(Waker, Waker)>,
+}
+
+#[cfg(feature = "stats-detailed")]
+impl<Fut: Future> Future for Accounted<Fut> {
+    type Output = Fut::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Fut::Output> {
+        // SAFETY: `future` is structurally pinned and never moved out; the
+        // other fields are not pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        let stats = &this.stats;
+        let (_, waker) = match this.waker.take() {
+            Some((inner, scheduling)) if inner.will_wake(cx.waker()) => {
+                this.waker.insert((inner, scheduling))
+            }
+            _ => this.waker.insert((
+                cx.waker().clone(),
+                Waker::from(Arc::new(Scheduling {
+                    stats: stats.clone(),
+                    inner: cx.waker().clone(),
+                })),
+            )),
+        };
+        // Wake-ups during the poll schedule the task again.
+        stats.scheduled.store(false, Ordering::Release);
+        stats.last_poll.store(now_nanos() + 1, Ordering::Relaxed);
+        let start = Instant::now();
+        let poll = future.poll(&mut Context::from_waker(waker));
+        let elapsed = u64::try_from(start.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        stats.cpu_nanos.fetch_add(elapsed, Ordering::Relaxed);
+        stats.polls.fetch_add(1, Ordering::Relaxed);
+        poll
+    }
+}
+
+#[cfg(feature = "stats-detailed")]
+impl<Fut> Drop for Accounted<Fut> {
+    fn drop(&mut self) {
+        let report = self.stats.report(false);
+        let mut registry = registry();
+        registry.live.remove(&report.id);
+        let finished = &mut registry.finished;
+        if finished.len() < FINISHED_REPORTS {
+            finished.push(report);
+        } else if let Some(least) = finished.iter_mut().min_by_key(|report| report.cpu_time)
+            && least.cpu_time < report.cpu_time
+        {
+            *least = report;
+        }
+        drop(registry);
+    }
+}
+
+/// The kinds of objects counted by [`live_objects`].
+#[cfg(feature = "leak-check")]
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Class {
+    Task,
+    Timer,
+    Mailbox,
+    Scheduled,
+}
+
+#[cfg(feature = "leak-check")]
+const CLASSES: usize = 4;
+
+/// Counters of one shard, on a cache line of their own.
+#[cfg(feature = "leak-check")]
+#[repr(align(64))]
+struct Shard {
+    created: [AtomicU64; CLASSES],
+    dropped: [AtomicU64; CLASSES],
+}
+
+#[cfg(feature = "leak-check")]
+const SHARDS: usize = 16;
+
+#[cfg(feature = "leak-check")]
+static LIVE: [Shard; SHARDS] = [const {
+    Shard {
+        created: [const { AtomicU64::new(0) }; CLASSES],
+        dropped: [const { AtomicU64::new(0) }; CLASSES],
+    }
+}; SHARDS];
+
+/// Returns the shard of the current thread, so that threads creating and
+/// dropping objects at a high rate do not contend on one counter.
+#[cfg(feature = "leak-check")]
+fn shard() -> &'static Shard {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    std::thread_local! {
+        static INDEX: usize = NEXT.fetch_add(1, Ordering::Relaxed) % SHARDS;
+    }
+    &LIVE[INDEX.with(|index| *index)]
+}
+
+/// Counts an object of its class as live until dropped.
+#[cfg(feature = "leak-check")]
+#[derive(Debug)]
+pub(crate) struct Live(Class);
+
+#[cfg(feature = "leak-check")]
+impl Live {
+    pub(crate) fn new(class: Class) -> Self {
+        shard().created[class as usize].fetch_add(1, Ordering::Relaxed);
+        Self(class)
+    }
+}
+
+#[cfg(feature = "leak-check")]
+impl Drop for Live {
+    fn drop(&mut self) {
+        shard().dropped[self.0 as usize].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// The number of live objects of one kind, as reported by [`live_objects`].
+#[cfg(feature = "leak-check")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct LiveCount {
+    /// Objects created and not dropped yet.
+    pub live: u64,
+    /// Objects ever created, the generation count: a live count back at its
+    /// baseline with a higher generation means the objects of a phase were
+    /// all released.
+    pub created: u64,
+}
+
+/// The live objects of the crate by kind, as returned by [`live_objects`].
+#[cfg(feature = "leak-check")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct LiveReport {
+    /// Tasks, from spawn until they complete or are dropped.
+    pub tasks: LiveCount,
+    /// [`Timer`](crate::timer::Timer)s, including those of intervals, from
+    /// their first poll, which allocates their state, until dropped.
+    pub timers: LiveCount,
+    /// The background loops of [`Mailbox`](crate::Mailbox)es, until they
+    /// end.
+    pub mailboxes: LiveCount,
+    /// Closures of [`schedule_after`](crate::schedule_after) and
+    /// [`execute_after`](crate::execute_after), until they run or their
+    /// cancellation takes effect.
+    pub scheduled: LiveCount,
+}
+
+/// Returns the number of live objects by kind, for finding slow leaks in
+/// long-running processes.
+///
+/// Each creation and drop costs one uncontended atomic update on a counter
+/// of the current thread's shard; reading sums the shards. Objects created
+/// or dropped concurrently with the call may or may not be counted.
+///
+/// Only available with the `leak-check` feature.
+///
+/// # Examples
+///
+/// ```rust
+/// use futures_lite::future::{block_on, poll_once};
+/// use native_executor::{diagnostics, spawn, timer::Timer};
+/// use std::{mem, time::Duration};
+///
+/// let baseline = diagnostics::live_objects();
+///
+/// // Completed work returns to the baseline
+/// block_on(spawn(async { Timer::after(Duration::from_millis(1)).await }));
+/// let report = diagnostics::live_objects();
+/// assert_eq!(report.tasks.live, baseline.tasks.live);
+/// assert_eq!(report.tasks.created, baseline.tasks.created + 1);
+/// assert_eq!(report.timers.live, baseline.timers.live);
+///
+/// // A leaked timer shows up once it has been polled
+/// let mut leaked = Timer::after(Duration::from_hours(1));
+/// assert!(block_on(poll_once(&mut leaked)).is_none());
+/// mem::forget(leaked);
+/// let report = diagnostics::live_objects();
+/// assert_eq!(report.timers.live, baseline.timers.live + 1);
+/// assert_eq!(report.timers.created, baseline.timers.created + 2);
+/// ```
+#[cfg(feature = "leak-check")]
+#[must_use]
+pub fn live_objects() -> LiveReport {
+    let count = |class: Class| {
+        let index = class as usize;
+        // Drops are read first, so that no count is seen dropped before it
+        // is seen created.
+        let dropped: u64 = LIVE
+            .iter()
+            .map(|shard| shard.dropped[index].load(Ordering::Relaxed))
+            .sum();
+        let created: u64 = LIVE
+            .iter()
+            .map(|shard| shard.created[index].load(Ordering::Relaxed))
+            .sum();
+        LiveCount {
+            live: created.saturating_sub(dropped),
+            created,
+        }
+    };
+    LiveReport {
+        tasks: count(Class::Task),
+        timers: count(Class::Timer),
+        mailboxes: count(Class::Mailbox),
+        scheduled: count(Class::Scheduled),
+    }
+}
@@ -0,0 +1,166 @@
+//! The crate-wide error type.
+//!
+//! Fallible APIs return the error specific to their module, such as
+//! [`JoinError`](crate::JoinError) or [`std::io::Error`]. Each converts into
+//! [`Error`], so `?` works in code that combines several of them.
+//!
+//! The [`Display`](fmt::Display) messages are meant for logs and are kept
+//! stable across patch releases.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use futures_lite::future::block_on;
+//! use native_executor::{Error, spawn_supervised};
+//!
+//! async fn run() -> Result<u32, Error> {
+//!     let value: u32 = spawn_supervised(async { 40 }).await?;
+//!     let task = spawn_supervised(async move {
+//!         assert!(value < 40, "out of tickets");
+//!         value
+//!     });
+//!     Ok(value + task.await?)
+//! }
+//!
+//! let error = block_on(run()).unwrap_err();
+//! assert_eq!(error.to_string(), "task panicked: out of tickets");
+//! ```
+
+use core::fmt;
+use std::io;
+
+use crate::{
+    AlreadyInitialized, JoinError, MainExecutorNotRunning, SpawnError, cancel::Cancelled,
+    mailbox::MailboxError, main_loop, timer::DeadlineExceeded,
+};
+
+/// Any error reported by this crate.
+///
+/// Errors from the platform backend name the backend in their message.
+///
+/// # Examples
+///
+/// ```rust
+/// use native_executor::{Error, JoinError};
+/// use std::io;
+///
+/// assert_eq!(Error::from(JoinError::Cancelled).to_string(), "task was cancelled");
+///
+/// let error = Error::from(io::Error::from(io::ErrorKind::NotFound));
+/// # #[cfg(target_vendor = "apple")]
+/// assert_eq!(error.to_string(), "GCD backend: entity not found");
+/// # #[cfg(not(target_vendor = "apple"))]
+/// assert_eq!(error.to_string(), "polyfill backend: entity not found");
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// A supervised task was cancelled or panicked.
+    Join(JoinError),
+    /// A system call made by the platform backend failed.
+    Io(io::Error),
+    /// [`init`](crate::init) was called after the configuration was fixed.
+    AlreadyInitialized(AlreadyInitialized),
+    /// An update could not be queued on a [`Mailbox`](crate::Mailbox).
+    Mailbox(MailboxError),
+    /// An operation gave up because its deadline passed.
+    DeadlineExceeded(DeadlineExceeded),
+    /// A job stopped early because it was cancelled.
+    Cancelled(Cancelled),
+    /// Work for the main thread was refused because the main thread does not
+    /// serve its queue, see [`MainExecutorNotRunning`].
+    MainExecutorNotRunning,
+    /// A task could not be spawned.
+    Spawn(SpawnError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Join(error) => fmt::Display::fmt(error, f),
+            Self::Io(error) => write!(f, "{BACKEND} backend: {error}"),
+            Self::AlreadyInitialized(error) => fmt::Display::fmt(error, f),
+            Self::Mailbox(error) => fmt::Display::fmt(error, f),
+            Self::DeadlineExceeded(error) => fmt::Display::fmt(error, f),
+            Self::Cancelled(error) => fmt::Display::fmt(error, f),
+            Self::MainExecutorNotRunning => f.write_str(main_loop::NOT_RUNNING_MESSAGE),
+            Self::Spawn(error) => fmt::Display::fmt(error, f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Join(error) => Some(error),
+            Self::Io(error) => Some(error),
+            Self::AlreadyInitialized(error) => Some(error),
+            Self::Mailbox(error) => Some(error),
+            Self::DeadlineExceeded(error) => Some(error),
+            Self::Cancelled(error) => Some(error),
+            Self::MainExecutorNotRunning => None,
+            Self::Spawn(error) => Some(error),
+        }
+    }
+}
+
+impl From<JoinError> for Error {
+    fn from(error: JoinError) -> Self {
+        Self::Join(error)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<AlreadyInitialized> for Error {
+    fn from(error: AlreadyInitialized) -> Self {
+        Self::AlreadyInitialized(error)
+    }
+}
+
+impl From<MailboxError> for Error {
+    fn from(error: MailboxError) -> Self {
+        Self::Mailbox(error)
+    }
+}
+
+impl From<DeadlineExceeded> for Error {
+    fn from(error: DeadlineExceeded) -> Self {
+        Self::DeadlineExceeded(error)
+    }
+}
+
+/// Name of the platform backend, as shown in error messages.
+const BACKEND: &str = if cfg!(target_vendor = "apple") {
+    "GCD"
+} else if cfg!(target_os = "android") {
+    "Android"
+} else if cfg!(target_arch = "wasm32") {
+    "web"
+} else if cfg!(feature = "polyfill") {
+    "polyfill"
+} else {
+    "unsupported"
+};
+
+impl From<Cancelled> for Error {
+    fn from(error: Cancelled) -> Self {
+        Self::Cancelled(error)
+    }
+}
+
+impl From<SpawnError> for Error {
+    fn from(error: SpawnError) -> Self {
+        Self::Spawn(error)
+    }
+}
+
+impl<F> From<MainExecutorNotRunning<F>> for Error {
+    fn from(_: MainExecutorNotRunning<F>) -> Self {
+        Self::MainExecutorNotRunning
+    }
+}
@@ -0,0 +1,432 @@
+//! Fire-and-forget closures, submitted without creating a task.
+
+use alloc::{boxed::Box, collections::VecDeque};
+use core::{
+    cell::RefCell,
+    sync::atomic::{AtomicU8, Ordering},
+    time::Duration,
+};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::{
+    Priority, TimerKey, WebTaskType, alive, cancel_after, dispatch, dispatch_after_cancellable,
+    dispatch_main, dispatch_task, hooks, hooks::TaskInfo, is_main_thread,
+};
+
+/// Runs `f` on the thread pool at the default priority.
+///
+/// This submits the closure straight to the platform queue, without the
+/// future and task handle of [`spawn`](crate::spawn). The closure counts as
+/// a task of kind [`TaskKind::Closure`](hooks::TaskKind::Closure) for the
+/// [hooks](crate::hooks) and the queue statistics. A panic in `f` is reported
+/// by the panic hook and then contained, so it never unwinds into the
+/// platform callback.
+///
+/// # Examples
+///
+/// ```rust
+/// use native_executor::{
+///     execute,
+///     hooks::{self, TaskHooks, TaskKind},
+/// };
+/// use std::{
+///     panic,
+///     sync::{atomic::{AtomicUsize, Ordering}, mpsc},
+/// };
+///
+/// static REPORTED: AtomicUsize = AtomicUsize::new(0);
+/// static COMPLETED: AtomicUsize = AtomicUsize::new(0);
+/// panic::set_hook(Box::new(|_| {
+///     REPORTED.fetch_add(1, Ordering::SeqCst);
+/// }));
+/// hooks::register(TaskHooks::new().on_complete(|info| {
+///     if info.kind == TaskKind::Closure {
+///         COMPLETED.fetch_add(1, Ordering::SeqCst);
+///     }
+/// }));
+///
+/// // The panic is reported, and the pool keeps running closures
+/// execute(|| panic!("contained"));
+/// let (sender, receiver) = mpsc::channel();
+/// execute(move || sender.send(42).unwrap());
+/// assert_eq!(receiver.recv().unwrap(), 42);
+///
+/// while COMPLETED.load(Ordering::SeqCst) == 0 || REPORTED.load(Ordering::SeqCst) == 0 {
+///     std::thread::yield_now();
+/// }
+/// // Only the closure that returned completed
+/// assert_eq!(COMPLETED.load(Ordering::SeqCst), 1);
+/// assert_eq!(REPORTED.load(Ordering::SeqCst), 1);
+/// ```
+#[track_caller]
+pub fn execute(f: impl FnOnce() + Send + 'static) {
+    execute_with_priority(f, Priority::default());
+}
+
+/// Runs `f` on the thread pool at `priority`.
+///
+/// See [`execute`] for how the closure is observed and how panics are
+/// handled.
+///
+/// # Examples
+///
+/// ```rust
+/// use native_executor::{Priority, execute_with_priority};
+///
+/// // Prune a cache without competing with user-facing work
+/// execute_with_priority(|| { /* prune */ }, Priority::Background);
+/// ```
+#[track_caller]
+pub fn execute_with_priority(f: impl FnOnce() + Send + 'static, priority: Priority) {
+    let info = TaskInfo::closure(priority).clamped();
+    let alive = alive::track(&info);
+    hooks::spawned(&info);
+    dispatch(
+        move || {
+            hooks::run_closure(f, &info);
+            drop(alive);
+        },
+        info.priority,
+    );
+}
+
+/// Runs `f` on the main thread.
+///
+/// The closure is queued behind the main-thread work submitted before it,
+/// tasks of [`spawn_main`](crate::spawn_main) included, and runs ahead of
+/// the work submitted after it. See [`execute`] for how the closure is
+/// observed and how panics are handled.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(not(target_vendor = "apple"))] {
+/// use futures_lite::future::block_on;
+/// use native_executor::{execute_main, polyfill, spawn_main};
+/// use std::{sync::{Arc, Mutex, mpsc}, thread};
+///
+/// let log = Arc::new(Mutex::new(Vec::new()));
+/// let record = |entry| {
+///     let log = log.clone();
+///     move || log.lock().unwrap().push(entry)
+/// };
+///
+/// // Closures and tasks share the main queue
+/// execute_main(record("first"));
+/// let task = spawn_main({
+///     let record = record("second");
+///     async move { record() }
+/// });
+/// execute_main(|| panic!("contained"));
+/// execute_main(record("third"));
+///
+/// thread::spawn(polyfill::start_main_executor);
+/// block_on(task);
+/// let (sender, receiver) = mpsc::channel();
+/// execute_main(move || sender.send(()).unwrap());
+/// receiver.recv().unwrap();
+/// assert_eq!(*log.lock().unwrap(), ["first", "second", "third"]);
+/// # }
+/// ```
+#[track_caller]
+pub fn execute_main(f: impl FnOnce() + Send + 'static) {
+    let info = TaskInfo::closure(Priority::UserInteractive);
+    let alive = alive::track(&info);
+    hooks::spawned(&info);
+    dispatch_main(
+        move || {
+            hooks::run_closure(f, &info);
+            drop(alive);
+        },
+        None,
+    );
+}
+
+std::thread_local! {
+    /// The closures of [`execute_main_local`] submitted on this thread, in
+    /// submission order; only the main thread ever holds any.
+    static LOCAL: RefCell<VecDeque<Box<dyn FnOnce()>>> = RefCell::default();
+}
+
+/// Runs `f`, which need not be `Send`, on the main thread from the main
+/// thread.
+///
+/// This is [`execute_main`] for closures that capture main-thread-only
+/// state, such as an [`Rc`](alloc::rc::Rc), and are submitted by main-thread
+/// code to run later. The closure is queued in submission order with the
+/// other main-thread work, so it runs after the work submitted before it,
+/// [`execute_main`] closures included, and ahead of the work submitted after
+/// it. See [`execute`] for how the closure is observed and how panics are
+/// handled.
+///
+/// If the main thread changes before the closure runs, as when
+/// [`PolyfillExecutor::run_until`](crate::polyfill::PolyfillExecutor::run_until)
+/// is called from another thread next, the closure never runs.
+///
+/// # Panics
+///
+/// Panics if not called on the main thread.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(not(any(target_vendor = "apple", target_os = "android")))] {
+/// use native_executor::{execute_main, execute_main_local, polyfill};
+/// use std::{rc::Rc, sync::{Arc, Mutex, mpsc}, thread};
+///
+/// // Only the main thread may submit local closures
+/// assert!(thread::spawn(|| execute_main_local(|| {})).join().is_err());
+///
+/// let log = Arc::new(Mutex::new(Vec::new()));
+/// let (sender, receiver) = mpsc::channel();
+/// execute_main({
+///     let log = log.clone();
+///     move || {
+///         // An `Rc` is not `Send`, so `execute_main` could not take these
+///         let record = |entry| {
+///             let (log, entry) = (log.clone(), Rc::new(entry));
+///             move || log.lock().unwrap().push(*entry)
+///         };
+///         execute_main_local(record("first"));
+///         execute_main({
+///             let log = log.clone();
+///             move || log.lock().unwrap().push("second")
+///         });
+///         execute_main_local(record("third"));
+///         execute_main(move || sender.send(()).unwrap());
+///     }
+/// });
+///
+/// thread::spawn(polyfill::start_main_executor);
+/// receiver.recv().unwrap();
+/// assert_eq!(*log.lock().unwrap(), ["first", "second", "third"]);
+/// # }
+/// ```
+#[track_caller]
+pub fn execute_main_local(f: impl FnOnce() + 'static) {
+    assert!(
+        is_main_thread(),
+        "`execute_main_local` must be called on the main thread"
+    );
+    let info = TaskInfo::closure(Priority::UserInteractive);
+    let alive = alive::track(&info);
+    hooks::spawned(&info);
+    LOCAL.with_borrow_mut(|local| local.push_back(Box::new(f)));
+    // Only a marker crosses the main queue, while the closure stays in the
+    // queue of this thread. Markers run on this thread, in the order they were
+    // submitted, so each takes the closure submitted along with it.
+    dispatch_main(
+        move || {
+            if let Some(f) = LOCAL.with_borrow_mut(VecDeque::pop_front) {
+                hooks::run_closure(f, &info);
+            }
+            drop(alive);
+        },
+        None,
+    );
+}
+
+/// Runs `f` on the thread pool at the default priority once `delay` has
+/// elapsed.
+///
+/// The returned handle can cancel the closure until it starts; dropping the
+/// handle does not. See [`execute`] for how the closure is observed and how
+/// panics are handled. On the web, the closure runs as a
+/// [macrotask](WebTaskType::Macro).
+///
+/// # Examples
+///
+/// ```rust
+/// use native_executor::execute_after;
+/// use std::{
+///     sync::{Arc, atomic::{AtomicBool, Ordering}, mpsc},
+///     thread,
+///     time::Duration,
+/// };
+///
+/// let ran = Arc::new(AtomicBool::new(false));
+/// let flag = ran.clone();
+/// let retry = execute_after(Duration::from_millis(20), move || flag.store(true, Ordering::SeqCst));
+/// assert!(retry.cancel());
+/// thread::sleep(Duration::from_millis(50));
+/// assert!(!ran.load(Ordering::SeqCst));
+///
+/// // A closure that has started can no longer be cancelled
+/// let (sender, receiver) = mpsc::channel();
+/// let ping = execute_after(Duration::from_millis(1), move || sender.send(()).unwrap());
+/// receiver.recv().unwrap();
+/// assert!(!ping.cancel());
+/// ```
+#[track_caller]
+pub fn execute_after(delay: Duration, f: impl FnOnce() + Send + 'static) -> ScheduledHandle {
+    schedule_after(delay, Priority::default(), f)
+}
+
+/// Runs `f` on the thread pool at `priority` once `delay` has elapsed.
+///
+/// This is [`execute_after`] at another priority: the returned handle can
+/// [cancel](ScheduledHandle::cancel) the closure until it starts, and
+/// dropping the handle lets it run. The timer callback only checks that the
+/// closure was not cancelled and submits it to the queue of `priority`, so
+/// cancelling after that point still wins as long as the closure has not
+/// started.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(not(target_vendor = "apple"))] {
+/// use native_executor::{
+///     ExecContext, NativeExecutor, Priority, current_context, schedule_after,
+/// };
+/// use std::{
+///     sync::{Arc, atomic::{AtomicBool, Ordering}, mpsc},
+///     thread,
+///     time::{Duration, Instant},
+/// };
+///
+/// // Hide a toast in a while, unless the user interacts with it
+/// let hidden = Arc::new(AtomicBool::new(false));
+/// let hide = schedule_after(Duration::from_millis(30), Priority::UserInitiated, {
+///     let hidden = hidden.clone();
+///     move || hidden.store(true, Ordering::SeqCst)
+/// });
+/// // The user taps it
+/// assert!(hide.cancel());
+/// assert!(!hide.cancel());
+/// thread::sleep(Duration::from_millis(60));
+/// assert!(!hidden.load(Ordering::SeqCst));
+///
+/// // Dropping the handle detaches the closure, which runs at its priority
+/// let (sender, receiver) = mpsc::channel();
+/// drop(schedule_after(Duration::from_millis(10), Priority::Background, move || {
+///     sender.send(current_context()).unwrap();
+/// }));
+/// assert_eq!(receiver.recv().unwrap(), ExecContext::Worker(Priority::Background));
+///
+/// // A cancelled closure no longer keeps the process alive
+/// let reminder = schedule_after(Duration::from_secs(60), Priority::Utility, || {});
+/// assert!(reminder.cancel());
+/// let start = Instant::now();
+/// NativeExecutor::keep_alive_until_idle(Duration::from_millis(10));
+/// assert!(start.elapsed() < Duration::from_secs(60));
+/// # }
+/// ```
+#[track_caller]
+pub fn schedule_after(
+    delay: Duration,
+    priority: Priority,
+    f: impl FnOnce() + Send + 'static,
+) -> ScheduledHandle {
+    let info = TaskInfo {
+        web_task_type: WebTaskType::Macro,
+        ..TaskInfo::closure(priority)
+    }
+    .clamped();
+    let handle = ScheduledHandle {
+        shared: Arc::new(Scheduled {
+            state: AtomicU8::new(PENDING),
+            timer: Mutex::new(None),
+        }),
+    };
+    let shared = handle.shared.clone();
+    // Cancelling drops the closure along with its count, except on backends
+    // that cannot cancel timers, where it counts until its delay elapses.
+    let alive = alive::track(&info);
+    #[cfg(feature = "leak-check")]
+    let live = crate::diagnostics::Live::new(crate::diagnostics::Class::Scheduled);
+    hooks::spawned(&info);
+    // The timer callback only hands the closure over to the pool, which
+    // keeps long closures off the timer.
+    let timer = dispatch_after_cancellable(
+        delay,
+        move || {
+            if shared.state.load(Ordering::Acquire) != PENDING {
+                return;
+            }
+            dispatch_task(
+                move || {
+                    #[cfg(feature = "leak-check")]
+                    let _live = live;
+                    if shared
+                        .state
+                        .compare_exchange(PENDING, STARTED, Ordering::AcqRel, Ordering::Acquire)
+                        .is_ok()
+                    {
+                        hooks::run_closure(f, &info);
+                    }
+                    drop(alive);
+                },
+                &info,
+            );
+        },
+        priority,
+    );
+    if let Some(timer) = timer {
+        let mut slot = handle
+            .shared
+            .timer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        // A cancellation that found no timer to cancel leaves it to us
+        if handle.shared.state.load(Ordering::Acquire) == CANCELLED {
+            drop(slot);
+            cancel_after(timer);
+        } else {
+            *slot = Some(timer);
+        }
+    }
+    handle
+}
+
+const PENDING: u8 = 0;
+const STARTED: u8 = 1;
+const CANCELLED: u8 = 2;
+
+/// A closure scheduled with [`schedule_after`] or [`execute_after`], which
+/// can be cancelled until it starts.
+///
+/// Dropping the handle detaches the closure, which then runs once its delay
+/// has elapsed.
+#[derive(Debug)]
+pub struct ScheduledHandle {
+    shared: Arc<Scheduled>,
+}
+
+/// The state of a closure scheduled with [`schedule_after`], shared with its
+/// timer callback.
+#[derive(Debug)]
+struct Scheduled {
+    state: AtomicU8,
+    /// The timer running the callback, if the backend can cancel it.
+    timer: Mutex<Option<TimerKey>>,
+}
+
+impl ScheduledHandle {
+    /// Cancels the closure, returning whether it was prevented from running.
+    ///
+    /// Returns `false` if the closure has already started, or was cancelled
+    /// before. Where the backend can cancel timers, the closure and what it
+    /// captures are dropped right away; otherwise they are dropped once the
+    /// delay has elapsed.
+    // Callers often cancel without caring whether the closure already ran.
+    #[allow(clippy::must_use_candidate)]
+    pub fn cancel(&self) -> bool {
+        let cancelled = self
+            .shared
+            .state
+            .compare_exchange(PENDING, CANCELLED, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok();
+        if cancelled {
+            let timer = self
+                .shared
+                .timer
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take();
+            if let Some(timer) = timer {
+                cancel_after(timer);
+            }
+        }
+        cancelled
+    }
+}
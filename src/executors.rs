@@ -0,0 +1,121 @@
+//! Zero-sized executors with a fixed scheduling target.
+//!
+//! Each type implements [`Executor`] by spawning onto the platform queue of
+//! its priority, which makes it suitable as a default type parameter for
+//! components that are generic over their executor.
+
+use executor_core::Executor;
+#[cfg(feature = "std")]
+use executor_core::LocalExecutor;
+
+#[cfg(feature = "std")]
+use crate::spawn_local;
+use crate::{Priority, TaskHandle, spawn_main, spawn_with_priority};
+
+macro_rules! priority_executor {
+    ($(#[$meta:meta])* $name:ident => $priority:expr) => {
+        $(#[$meta])*
+        #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+        pub struct $name;
+
+        impl $name {
+            /// The priority tasks spawned by this executor run at.
+            pub const PRIORITY: Priority = $priority;
+        }
+
+        impl Executor for $name {
+            type Task<T: Send + 'static> = TaskHandle<T>;
+
+            #[track_caller]
+            fn spawn<Fut>(&self, fut: Fut) -> Self::Task<Fut::Output>
+            where
+                Fut: Future<Output: Send> + Send + 'static,
+            {
+                spawn_with_priority(fut, Self::PRIORITY).into()
+            }
+        }
+    };
+}
+
+priority_executor! {
+    /// Executor spawning tasks at [`Priority::Default`].
+    DefaultExecutor => Priority::Default
+}
+
+priority_executor! {
+    /// Executor spawning tasks at [`Priority::Background`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use native_executor::{BackgroundExecutor, DefaultExecutor, Executor, ExecutorTask};
+    /// use std::{sync::mpsc, thread};
+    ///
+    /// struct Service<E: Executor = DefaultExecutor> {
+    ///     executor: E,
+    /// }
+    ///
+    /// impl<E: Executor> Service<E> {
+    ///     fn refresh(&self, done: mpsc::Sender<thread::ThreadId>) {
+    ///         self.executor
+    ///             .spawn(async move { done.send(thread::current().id()).unwrap() })
+    ///             .detach();
+    ///     }
+    /// }
+    ///
+    /// let service = Service { executor: BackgroundExecutor };
+    /// let (sender, receiver) = mpsc::channel();
+    /// service.refresh(sender);
+    ///
+    /// // The task ran on one of the executor's worker threads
+    /// assert_ne!(receiver.recv().unwrap(), thread::current().id());
+    /// ```
+    BackgroundExecutor => Priority::Background
+}
+
+priority_executor! {
+    /// Executor spawning tasks at [`Priority::Utility`].
+    UtilityExecutor => Priority::Utility
+}
+
+priority_executor! {
+    /// Executor spawning tasks at [`Priority::UserInitiated`].
+    UserInitiatedExecutor => Priority::UserInitiated
+}
+
+priority_executor! {
+    /// Executor spawning tasks at [`Priority::UserInteractive`].
+    UserInteractiveExecutor => Priority::UserInteractive
+}
+
+/// Executor spawning tasks on the main thread.
+///
+/// [`LocalExecutor::spawn_local`] accepts non-`Send` futures like
+/// [`spawn_local`], while [`Executor::spawn`] behaves like [`spawn_main`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MainExecutor;
+
+impl Executor for MainExecutor {
+    type Task<T: Send + 'static> = TaskHandle<T>;
+
+    #[track_caller]
+    fn spawn<Fut>(&self, fut: Fut) -> Self::Task<Fut::Output>
+    where
+        Fut: Future<Output: Send> + Send + 'static,
+    {
+        spawn_main(fut).into()
+    }
+}
+
+#[cfg(feature = "std")]
+impl LocalExecutor for MainExecutor {
+    type Task<T: 'static> = TaskHandle<T>;
+
+    #[track_caller]
+    fn spawn_local<Fut>(&self, fut: Fut) -> Self::Task<Fut::Output>
+    where
+        Fut: Future + 'static,
+    {
+        spawn_local(fut).into()
+    }
+}
@@ -0,0 +1,121 @@
+//! A handle to the context the current code runs in, for spawning follow-up
+//! work there later.
+
+use core::{future::Future, time::Duration};
+
+use async_task::Task;
+
+use crate::{
+    ExecContext, Priority, current_context, current_priority, execute_main, execute_with_priority,
+    spawn_main, spawn_with_priority, timer::Timer,
+};
+
+/// Where the code that captured a [`Handle`] was running: the main thread,
+/// or the thread pool at a priority.
+///
+/// A library called from a task can capture the handle with
+/// [`current`](Self::current) and spawn its follow-up work through it, at
+/// the priority and on the thread of its caller, without taking an executor
+/// as a parameter. The handle is `Send` and `Copy`, so it can be stored and
+/// used from any thread later.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(not(target_vendor = "apple"))] {
+/// use futures_lite::future::block_on;
+/// use native_executor::{ExecContext, Handle, Priority, current_context, spawn_with_priority};
+/// use std::thread;
+///
+/// let handle = block_on(spawn_with_priority(async { Handle::current() }, Priority::Background));
+///
+/// // Used from a thread the crate knows nothing about
+/// let task = thread::spawn(move || handle.spawn(async { current_context() }))
+///     .join()
+///     .unwrap();
+/// assert_eq!(block_on(task), ExecContext::Worker(Priority::Background));
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Handle {
+    /// `None` for the main thread.
+    pool: Option<Priority>,
+}
+
+impl Handle {
+    /// Captures the context the current code runs in.
+    ///
+    /// On the main thread, this is the main thread. In a job of the thread
+    /// pool, it is the pool at the priority of the job, or that of the
+    /// enclosing [`with_priority`](crate::with_priority) section, if any.
+    /// Elsewhere, it falls back to the pool at the ambient priority, or at
+    /// [`Priority::Default`].
+    #[must_use]
+    pub fn current() -> Self {
+        let pool = match current_context() {
+            ExecContext::Main => None,
+            ExecContext::Worker(priority) => Some(current_priority().unwrap_or(priority)),
+            ExecContext::Foreign => Some(current_priority().unwrap_or_default()),
+        };
+        Self { pool }
+    }
+
+    /// Returns the handle of the main thread.
+    #[must_use]
+    pub const fn main() -> Self {
+        Self { pool: None }
+    }
+
+    /// Returns the handle of the thread pool at `priority`.
+    #[must_use]
+    pub const fn pool(priority: Priority) -> Self {
+        Self {
+            pool: Some(priority),
+        }
+    }
+
+    /// Returns the context the work spawned through the handle runs in.
+    #[must_use]
+    pub const fn context(&self) -> ExecContext {
+        match self.pool {
+            None => ExecContext::Main,
+            Some(priority) => ExecContext::Worker(priority),
+        }
+    }
+
+    /// Spawns `future` in the captured context.
+    #[track_caller]
+    pub fn spawn<Fut>(&self, future: Fut) -> Task<Fut::Output>
+    where
+        Fut: Future + Send + 'static,
+        Fut::Output: Send,
+    {
+        match self.pool {
+            None => spawn_main(future),
+            Some(priority) => spawn_with_priority(future, priority),
+        }
+    }
+
+    /// Spawns `future` in the captured context once `delay` has elapsed.
+    #[track_caller]
+    pub fn spawn_after<Fut>(&self, delay: Duration, future: Fut) -> Task<Fut::Output>
+    where
+        Fut: Future + Send + 'static,
+        Fut::Output: Send,
+    {
+        self.spawn(async move {
+            Timer::after(delay).await;
+            future.await
+        })
+    }
+
+    /// Runs `f` in the captured context, as [`execute_with_priority`] or
+    /// [`execute_main`] do.
+    #[track_caller]
+    pub fn execute(&self, f: impl FnOnce() + Send + 'static) {
+        match self.pool {
+            None => execute_main(f),
+            Some(priority) => execute_with_priority(f, priority),
+        }
+    }
+}
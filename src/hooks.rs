@@ -0,0 +1,412 @@
+//! Global task lifecycle hooks.
+//!
+//! Hooks let applications run code around every poll of every task spawned by
+//! this crate, for example to install a thread-local locale expected by a C
+//! library or to emit profiler frame markers, without instrumenting each spawn
+//! call site.
+//!
+//! ```rust
+//! use native_executor::hooks::{self, TaskHooks};
+//!
+//! hooks::register(
+//!     TaskHooks::new()
+//!         .before_poll(|info| println!("polling task {}", info.id))
+//!         .after_poll(|info| println!("task {} yielded", info.id)),
+//! );
+//! ```
+//!
+//! Hooks compose in registration order and apply to every poll that starts
+//! after registration, including polls of tasks spawned earlier. When no hooks
+//! are registered, the per-poll overhead is a single relaxed atomic load.
+//!
+//! A panicking hook is caught so that it cannot unwind through the scheduler
+//! or corrupt the task being polled; the panic is still reported by the
+//! process panic hook.
+
+#[cfg(feature = "std")]
+use core::{fmt, sync::atomic::AtomicBool};
+use core::{
+    future::Future,
+    panic::Location,
+    sync::atomic::{AtomicU64, Ordering},
+};
+#[cfg(feature = "std")]
+use std::{
+    panic::{AssertUnwindSafe, catch_unwind},
+    sync::{Arc, RwLock},
+};
+
+use async_task::Runnable;
+
+#[cfg(feature = "std")]
+use crate::alive::Slot;
+use crate::{Priority, WebTaskType};
+
+/// A single hook callback.
+#[cfg(feature = "std")]
+pub type Hook = Box<dyn Fn(&TaskInfo) + Send + Sync>;
+
+/// Metadata describing a task, passed to every hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TaskInfo {
+    /// Process-unique identifier of the task.
+    pub id: u64,
+    /// Name of the task, if one was assigned.
+    pub name: Option<&'static str>,
+    /// Priority the task is scheduled at.
+    ///
+    /// Tasks running on the main thread report [`Priority::UserInteractive`],
+    /// unless they were spawned with a priority override by
+    /// [`spawn_main_with_priority`](crate::spawn_main_with_priority). Tasks
+    /// of the thread pool report their priority after the bounds of
+    /// [`Config::priority_floor`](crate::Config::priority_floor) and
+    /// [`Config::priority_ceiling`](crate::Config::priority_ceiling).
+    pub priority: Priority,
+    /// Priority the task was spawned at, before the bounds of the
+    /// [`Config`](crate::Config) were applied.
+    pub requested_priority: Priority,
+    /// Whether the task was spawned outside the bounds of the
+    /// [`Config`](crate::Config) with
+    /// [`SpawnOptions::exempt_from_clamp`](crate::SpawnOptions::exempt_from_clamp).
+    pub clamp_exempt: bool,
+    /// What the task runs.
+    pub kind: TaskKind,
+    /// Whether the task was spawned as a daemon with
+    /// [`SpawnOptions::daemon`](crate::SpawnOptions::daemon), which does not
+    /// keep `NativeExecutor::keep_alive_until_idle` waiting.
+    pub daemon: bool,
+    /// How the task is queued on the web, as set by
+    /// [`SpawnOptions::web_task_type`](crate::SpawnOptions::web_task_type).
+    pub web_task_type: WebTaskType,
+    /// Where the task was spawned: the call of [`spawn`](crate::spawn), or
+    /// of one of its variants, in the code that spawned it.
+    pub spawned_at: &'static Location<'static>,
+}
+
+/// What a task runs, as reported in [`TaskInfo::kind`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TaskKind {
+    /// A future, spawned with [`spawn`](crate::spawn) or one of its variants.
+    #[default]
+    Future,
+    /// A plain closure, submitted with [`execute`](crate::execute) or one of
+    /// its variants.
+    ///
+    /// A closure is run in a single "poll": the poll hooks run once around
+    /// it, and the completion hooks only if it returns without panicking.
+    Closure,
+}
+
+impl TaskInfo {
+    #[track_caller]
+    pub(crate) fn new(priority: Priority) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            name: None,
+            priority,
+            requested_priority: priority,
+            clamp_exempt: false,
+            kind: TaskKind::Future,
+            daemon: false,
+            web_task_type: WebTaskType::Micro,
+            spawned_at: Location::caller(),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[track_caller]
+    pub(crate) fn closure(priority: Priority) -> Self {
+        Self {
+            kind: TaskKind::Closure,
+            ..Self::new(priority)
+        }
+    }
+
+    /// Returns the priority the thread pool schedules this task at when
+    /// `priority` is requested for it.
+    #[cfg_attr(not(feature = "std"), allow(clippy::missing_const_for_fn))]
+    pub(crate) fn effective(&self, priority: Priority) -> Priority {
+        if self.clamp_exempt {
+            priority
+        } else {
+            crate::effective_priority(priority)
+        }
+    }
+
+    /// Applies the bounds of the [`Config`](crate::Config) to the priority
+    /// of this task of the thread pool.
+    pub(crate) fn clamped(self) -> Self {
+        let priority = self.effective(self.requested_priority);
+        #[cfg(feature = "stats")]
+        if priority != self.requested_priority {
+            crate::diagnostics::count_clamped();
+        }
+        Self { priority, ..self }
+    }
+}
+
+/// A set of task lifecycle callbacks.
+///
+/// Every callback is optional. Build one with [`TaskHooks::new`] and the
+/// builder methods, then pass it to [`register`].
+///
+/// # Examples
+///
+/// ```rust
+/// use native_executor::{hooks::{self, TaskHooks}, spawn};
+/// use std::sync::{Arc, atomic::{AtomicUsize, Ordering}, mpsc};
+///
+/// let (sender, receiver) = mpsc::channel();
+/// // Spawned before any hook exists: no hook observes it being spawned
+/// spawn(async move { sender.send(()).unwrap() }).detach();
+/// receiver.recv().unwrap();
+///
+/// let spawned = Arc::new(AtomicUsize::new(0));
+/// let completed = Arc::new(AtomicUsize::new(0));
+/// let (spawned_hook, completed_hook) = (spawned.clone(), completed.clone());
+/// hooks::register(
+///     TaskHooks::new()
+///         .on_spawn(move |_| { spawned_hook.fetch_add(1, Ordering::SeqCst); })
+///         .on_complete(move |_| { completed_hook.fetch_add(1, Ordering::SeqCst); }),
+/// );
+///
+/// let (sender, receiver) = mpsc::channel();
+/// spawn(async move { sender.send(()).unwrap() }).detach();
+/// receiver.recv().unwrap();
+///
+/// assert_eq!(spawned.load(Ordering::SeqCst), 1);
+/// # while completed.load(Ordering::SeqCst) == 0 { std::thread::yield_now(); }
+/// assert_eq!(completed.load(Ordering::SeqCst), 1);
+/// ```
+#[cfg(feature = "std")]
+#[derive(Default)]
+pub struct TaskHooks {
+    /// Called on the executing thread right before a task is polled.
+    pub before_poll: Option<Hook>,
+    /// Called on the executing thread right after a task was polled.
+    pub after_poll: Option<Hook>,
+    /// Called on the spawning thread when a task is created.
+    pub on_spawn: Option<Hook>,
+    /// Called on the executing thread when a task's future returns.
+    pub on_complete: Option<Hook>,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Debug for TaskHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskHooks")
+            .field("before_poll", &self.before_poll.is_some())
+            .field("after_poll", &self.after_poll.is_some())
+            .field("on_spawn", &self.on_spawn.is_some())
+            .field("on_complete", &self.on_complete.is_some())
+            .finish()
+    }
+}
+
+#[cfg(feature = "std")]
+impl TaskHooks {
+    /// Creates an empty set of hooks.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the callback invoked before each poll.
+    #[must_use]
+    pub fn before_poll(mut self, hook: impl Fn(&TaskInfo) + Send + Sync + 'static) -> Self {
+        self.before_poll = Some(Box::new(hook));
+        self
+    }
+
+    /// Sets the callback invoked after each poll.
+    #[must_use]
+    pub fn after_poll(mut self, hook: impl Fn(&TaskInfo) + Send + Sync + 'static) -> Self {
+        self.after_poll = Some(Box::new(hook));
+        self
+    }
+
+    /// Sets the callback invoked when a task is spawned.
+    #[must_use]
+    pub fn on_spawn(mut self, hook: impl Fn(&TaskInfo) + Send + Sync + 'static) -> Self {
+        self.on_spawn = Some(Box::new(hook));
+        self
+    }
+
+    /// Sets the callback invoked when a task completes.
+    #[must_use]
+    pub fn on_complete(mut self, hook: impl Fn(&TaskInfo) + Send + Sync + 'static) -> Self {
+        self.on_complete = Some(Box::new(hook));
+        self
+    }
+}
+
+#[cfg(feature = "std")]
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+/// Snapshot of the registered hooks, replaced as a whole on registration so
+/// that hooks run without holding the lock.
+#[cfg(feature = "std")]
+static REGISTRY: RwLock<Option<Arc<[Arc<TaskHooks>]>>> = RwLock::new(None);
+
+/// Registers a set of hooks for all tasks.
+///
+/// Hooks run after previously registered hooks and apply to all subsequent
+/// polls, spawns and completions. Registered hooks cannot be removed.
+///
+/// Hooks may themselves register further hooks or spawn tasks; those only
+/// take effect from the next hook invocation.
+///
+/// # Examples
+///
+/// ```rust
+/// use native_executor::{hooks::{self, TaskHooks}, spawn};
+/// use std::sync::{Arc, Mutex, mpsc};
+///
+/// let events = Arc::new(Mutex::new(Vec::new()));
+/// for tag in ["first", "second"] {
+///     let events = events.clone();
+///     hooks::register(TaskHooks::new().before_poll(move |_| events.lock().unwrap().push(tag)));
+/// }
+/// // A panicking hook is contained and does not affect the task
+/// hooks::register(TaskHooks::new().after_poll(|_| panic!("faulty hook")));
+///
+/// let (sender, receiver) = mpsc::channel();
+/// spawn(async move { sender.send(42).unwrap() }).detach();
+/// assert_eq!(receiver.recv().unwrap(), 42);
+/// assert_eq!(events.lock().unwrap()[..2], ["first", "second"]);
+///
+/// // Hooks may register hooks and spawn tasks without deadlocking
+/// let (sender, receiver) = mpsc::channel();
+/// let sender = Mutex::new(Some(sender));
+/// hooks::register(TaskHooks::new().on_spawn(move |_| {
+///     let sender = sender.lock().unwrap().take();
+///     if let Some(sender) = sender {
+///         hooks::register(TaskHooks::new());
+///         spawn(async move { sender.send(()).unwrap() }).detach();
+///     }
+/// }));
+/// spawn(async {}).detach();
+/// receiver.recv().unwrap();
+/// ```
+#[cfg(feature = "std")]
+pub fn register(hooks: TaskHooks) {
+    let mut registry = REGISTRY
+        .write()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    let registered = registry
+        .iter()
+        .flat_map(|registered| registered.iter().cloned());
+    *registry = Some(registered.chain([Arc::new(hooks)]).collect());
+    drop(registry);
+    ACTIVE.store(true, Ordering::Relaxed);
+}
+
+#[cfg(feature = "std")]
+fn invoke(select: impl Fn(&TaskHooks) -> Option<&Hook>, info: &TaskInfo) {
+    let registry = REGISTRY
+        .read()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .clone();
+    for hooks in registry.iter().flat_map(|registry| registry.iter()) {
+        if let Some(hook) = select(hooks) {
+            // The panic hook has already reported the panic at this point.
+            let _ = catch_unwind(AssertUnwindSafe(|| hook(info)));
+        }
+    }
+}
+
+/// Runs `runnable`, surrounded by the registered poll hooks.
+#[cfg(feature = "std")]
+pub(crate) fn run(runnable: Runnable, info: &TaskInfo) {
+    if !ACTIVE.load(Ordering::Relaxed) {
+        runnable.run();
+        return;
+    }
+    invoke(|hooks| hooks.before_poll.as_ref(), info);
+    runnable.run();
+    invoke(|hooks| hooks.after_poll.as_ref(), info);
+}
+
+/// Runs `f`, a closure submitted with [`execute`](crate::execute), surrounded
+/// by the registered poll hooks.
+///
+/// A panic in `f` is reported by the process panic hook and then contained,
+/// so it never unwinds into the platform callback that runs the closure.
+#[cfg(feature = "std")]
+pub(crate) fn run_closure(f: impl FnOnce(), info: &TaskInfo) {
+    let active = ACTIVE.load(Ordering::Relaxed);
+    if active {
+        invoke(|hooks| hooks.before_poll.as_ref(), info);
+    }
+    let completed = catch_unwind(AssertUnwindSafe(f)).is_ok();
+    if active {
+        invoke(|hooks| hooks.after_poll.as_ref(), info);
+        if completed {
+            invoke(|hooks| hooks.on_complete.as_ref(), info);
+        }
+    }
+}
+
+/// Notifies the registered spawn hooks.
+#[cfg(feature = "std")]
+pub(crate) fn spawned(info: &TaskInfo) {
+    if ACTIVE.load(Ordering::Relaxed) {
+        invoke(|hooks| hooks.on_spawn.as_ref(), info);
+    }
+}
+
+/// Wraps `future` so that the completion hooks run once it returns, and so
+/// that it counts as alive from now until it completes or is dropped.
+///
+/// # Panics
+///
+/// Panics if the cap on alive tasks is reached, unless the configuration
+/// lets the task wait for a slot.
+#[cfg(feature = "std")]
+#[track_caller]
+pub(crate) fn instrument<Fut: Future>(
+    future: Fut,
+    info: TaskInfo,
+) -> impl Future<Output = Fut::Output> {
+    instrument_in(future, info, Slot::take(&info))
+}
+
+/// Wraps `future` as [`instrument`] does, in `slot`.
+#[cfg(feature = "std")]
+#[cfg_attr(not(feature = "leak-check"), allow(clippy::manual_async_fn))]
+pub(crate) fn instrument_in<Fut: Future>(
+    future: Fut,
+    info: TaskInfo,
+    slot: Slot,
+) -> impl Future<Output = Fut::Output> {
+    #[cfg(feature = "leak-check")]
+    let live = crate::diagnostics::Live::new(crate::diagnostics::Class::Task);
+    async move {
+        #[cfg(feature = "leak-check")]
+        let _live = live;
+        let alive = slot.acquire(&info).await;
+        let output = future.await;
+        if ACTIVE.load(Ordering::Relaxed) {
+            invoke(|hooks| hooks.on_complete.as_ref(), &info);
+        }
+        drop(alive);
+        output
+    }
+}
+
+// Hooks need the standard library; without it tasks are run unobserved.
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn run(runnable: Runnable, _info: &TaskInfo) {
+    runnable.run();
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) const fn spawned(_info: &TaskInfo) {}
+
+#[cfg(not(feature = "std"))]
+pub(crate) const fn instrument<Fut: Future>(future: Fut, _info: TaskInfo) -> Fut {
+    future
+}
@@ -0,0 +1,278 @@
+//! An inert backend for unit tests, driven by hand.
+//!
+//! With the `noop-backend` feature, targets without a native backend and
+//! without the `polyfill` feature get [`InertExecutor`] as
+//! [`NativeExecutor`](crate::NativeExecutor) instead of a backend that panics.
+//! It runs nothing on its own: every closure submitted to the pool or to the
+//! main thread is recorded in a queue, and every delayed closure against a
+//! virtual clock, until the test runs them with [`run_queued`] and moves the
+//! clock with [`advance_time`]. Code that merely needs the executor to exist
+//! can be tested without real concurrency, and in a deterministic order.
+//!
+//! The queue and the clock belong to the thread that submits the work, which
+//! is the thread that runs it too and counts as the main thread. Tests running
+//! in parallel on the threads of the test harness therefore do not observe
+//! each other's work. Work submitted from a thread spawned by a test lands in
+//! the queue of that thread.
+//!
+//! # Examples
+//!
+//! A timer and a mailbox, driven by hand:
+//!
+//! ```rust
+//! use futures_lite::future::{block_on, poll_once};
+//! use native_executor::{Mailbox, inert, spawn, timer::Timer};
+//! use std::{pin::pin, time::Duration};
+//!
+//! let mut timer = pin!(spawn(Timer::after(Duration::from_millis(50))));
+//! assert_eq!(inert::run_queued(usize::MAX), 1);
+//! assert_eq!(inert::pending_timers(), 1);
+//!
+//! // Not due yet
+//! inert::advance_time(Duration::from_millis(30));
+//! assert_eq!(inert::queued(), 0);
+//! assert!(block_on(poll_once(&mut timer)).is_none());
+//!
+//! // Due: its callback, then the task it wakes, are queued in turn
+//! inert::advance_time(Duration::from_millis(20));
+//! assert_eq!(inert::run_queued(usize::MAX), 2);
+//! assert!(block_on(poll_once(&mut timer)).is_some());
+//! assert_eq!(inert::now(), Duration::from_millis(50));
+//!
+//! let mailbox = Mailbox::main(Vec::new());
+//! mailbox.handle(|log| log.push("first"));
+//! let mut call = pin!(mailbox.call(|log| log.len()));
+//! assert!(block_on(poll_once(&mut call)).is_none());
+//! assert_eq!(inert::run_queued(usize::MAX), 1);
+//! assert_eq!(block_on(poll_once(&mut call)), Some(1));
+//! ```
+//!
+//! A delayed closure that is cancelled, such as the timer of a timeout whose
+//! future completed first, leaves the pending timers:
+//!
+//! ```rust
+//! use futures_lite::future::{block_on, poll_once, yield_now};
+//! use native_executor::{
+//!     inert,
+//!     timer::{Either, Timer, first_of, timeout},
+//! };
+//! use std::{pin::pin, time::Duration};
+//!
+//! let mut request = pin!(timeout(Duration::from_secs(60), yield_now()));
+//! assert!(block_on(poll_once(&mut request)).is_none());
+//! assert_eq!(inert::pending_timers(), 1);
+//! assert_eq!(block_on(request), Ok(()));
+//! assert_eq!(inert::pending_timers(), 0);
+//!
+//! // So does the timer of a race the future won, or a coalesced timer
+//! let mut race = pin!(first_of(yield_now(), Duration::from_secs(60)));
+//! assert!(block_on(poll_once(&mut race)).is_none());
+//! assert_eq!(inert::pending_timers(), 1);
+//! assert!(matches!(block_on(race), Either::Left(())));
+//! assert_eq!(inert::pending_timers(), 0);
+//!
+//! let mut timer = Timer::coarse(Duration::from_secs(60));
+//! assert!(block_on(poll_once(&mut timer)).is_none());
+//! assert_eq!(inert::pending_timers(), 1);
+//! drop(timer);
+//! assert_eq!(inert::pending_timers(), 0);
+//! ```
+
+use alloc::{boxed::Box, collections::VecDeque, vec::Vec};
+use core::{
+    cell::RefCell,
+    future::Future,
+    pin::pin,
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    task::Wake,
+};
+
+use crate::{PlatformExecutor, Priority};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Inert executor for unit tests; see the [module](self) documentation.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InertExecutor;
+
+#[derive(Default)]
+struct State {
+    /// Closures ready to run, in submission order.
+    queue: VecDeque<Job>,
+    /// Delayed closures with their due time and submission order.
+    timers: Vec<(Duration, u64, Job)>,
+    /// The virtual time elapsed since the first use on this thread.
+    now: Duration,
+}
+
+/// Orders the delayed closures submitted at the same time, and identifies
+/// them for cancellation, across threads.
+static NEXT_TIMER: AtomicU64 = AtomicU64::new(0);
+
+std::thread_local! {
+    static STATE: RefCell<State> = RefCell::default();
+}
+
+/// Returns the number of closures ready to run on the current thread.
+#[must_use]
+pub fn queued() -> usize {
+    STATE.with_borrow(|state| state.queue.len())
+}
+
+/// Returns the number of delayed closures of the current thread that are not
+/// due yet.
+#[must_use]
+pub fn pending_timers() -> usize {
+    STATE.with_borrow(|state| state.timers.len())
+}
+
+/// Returns the virtual time of the current thread, which starts at zero and
+/// only moves with [`advance_time`].
+#[must_use]
+pub fn now() -> Duration {
+    STATE.with_borrow(|state| state.now)
+}
+
+/// Runs up to `n` queued closures of the current thread in submission order,
+/// including those queued meanwhile, and returns how many ran.
+#[must_use]
+pub fn run_queued(n: usize) -> usize {
+    for ran in 0..n {
+        let Some(job) = STATE.with_borrow_mut(|state| state.queue.pop_front()) else {
+            return ran;
+        };
+        job();
+    }
+    n
+}
+
+/// Moves the virtual clock of the current thread forward by `by`, queueing
+/// the delayed closures that are due by then, earliest first.
+pub fn advance_time(by: Duration) {
+    STATE.with_borrow_mut(|state| {
+        state.now = state.now.saturating_add(by);
+        let now = state.now;
+        let (mut due, pending) = core::mem::take(&mut state.timers)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(at, _, _)| *at <= now);
+        state.timers = pending;
+        due.sort_by_key(|(at, order, _)| (*at, *order));
+        state.queue.extend(due.into_iter().map(|(_, _, job)| job));
+    });
+}
+
+/// Moves the virtual clock to the next delayed closure and queues it, along
+/// with those due at the same time; returns `false` if there is none.
+fn advance_to_next_timer() -> bool {
+    let next = STATE.with_borrow(|state| state.timers.iter().map(|(at, _, _)| *at).min());
+    next.is_some_and(|at| {
+        advance_time(at.saturating_sub(now()));
+        true
+    })
+}
+
+/// Records whether a future has been woken.
+struct Flag(AtomicBool);
+
+impl Wake for Flag {
+    fn wake(self: Arc<Self>) {
+        self.0.store(true, Ordering::Release);
+    }
+}
+
+impl InertExecutor {
+    /// Polls `future` on the current thread until it completes, running the
+    /// queued closures in between and, once none is left, advancing the
+    /// virtual clock to the next delayed closure.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `future` waits while nothing is queued or delayed, since it
+    /// could then never complete.
+    #[track_caller]
+    pub fn run_until<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let woken = Arc::new(Flag(AtomicBool::new(true)));
+        let waker = Waker::from(woken.clone());
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if woken.0.swap(false, Ordering::AcqRel)
+                && let Poll::Ready(output) = future.as_mut().poll(&mut cx)
+            {
+                return output;
+            }
+            if run_queued(1) == 0 && !advance_to_next_timer() {
+                assert!(
+                    woken.0.load(Ordering::Acquire),
+                    "`run_until` stalled: the future waits, but nothing is queued or delayed"
+                );
+            }
+        }
+    }
+
+    /// Runs as [`run_until`](Self::run_until) does until no task has been
+    /// alive for `grace` of virtual time.
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`run_until`](Self::run_until).
+    #[track_caller]
+    pub fn keep_alive_until_idle(grace: Duration) {
+        Self::run_until(crate::alive::idle(grace));
+    }
+}
+
+impl PlatformExecutor for InertExecutor {
+    fn exec_main(f: impl FnOnce() + Send + 'static, _priority: Option<Priority>) {
+        // The test serves the queue, so the main thread is never probed.
+        crate::main_loop::entered();
+        STATE.with_borrow_mut(|state| state.queue.push_back(Box::new(f)));
+    }
+
+    fn exec(f: impl FnOnce() + Send + 'static, _priority: Priority) {
+        STATE.with_borrow_mut(|state| state.queue.push_back(Box::new(f)));
+    }
+
+    const MAX_DELAY: Duration = Duration::MAX;
+
+    fn exec_after(delay: Duration, f: impl FnOnce() + Send + 'static, priority: Priority) {
+        Self::exec_after_cancellable(delay, f, priority);
+    }
+
+    fn exec_after_cancellable(
+        delay: Duration,
+        f: impl FnOnce() + Send + 'static,
+        _priority: Priority,
+    ) -> Option<u64> {
+        let order = NEXT_TIMER.fetch_add(1, Ordering::Relaxed);
+        STATE.with_borrow_mut(|state| {
+            let at = state.now.saturating_add(delay);
+            state.timers.push((at, order, Box::new(f)));
+        });
+        Some(order)
+    }
+
+    /// Only finds the closures submitted on the current thread.
+    fn cancel_after(key: u64) {
+        let cancelled = STATE.with_borrow_mut(|state| {
+            let index = state
+                .timers
+                .iter()
+                .position(|(_, order, _)| *order == key)?;
+            Some(state.timers.swap_remove(index))
+        });
+        drop(cancelled);
+    }
+
+    fn is_main_thread() -> bool {
+        // Every thread runs its own work.
+        true
+    }
+}
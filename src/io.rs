@@ -0,0 +1,376 @@
+//! Asynchronous file IO built on GCD's `dispatch_io`.
+//!
+//! Reads and writes are performed by the system without blocking a thread of
+//! this crate; completions are delivered on the queue for the file's
+//! [`Priority`], the same queues spawned tasks run on.
+//!
+//! This module is only available on Apple platforms with the `dispatch-io`
+//! feature.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use futures_lite::future::block_on;
+//! use native_executor::io::File;
+//!
+//! let path = std::env::temp_dir().join("native-executor-io-example");
+//! let data: Vec<u8> = (0..4 << 20).map(|i: u32| (i % 251) as u8).collect();
+//!
+//! block_on(async {
+//!     let file = File::create(&path).await.unwrap();
+//!     file.write_at(0, &data).await.unwrap();
+//!     drop(file);
+//!
+//!     let file = File::open(&path).await.unwrap();
+//!     assert_eq!(file.read_at(0, data.len()).await.unwrap(), data);
+//!     assert_eq!(file.read_at(1 << 20, 4).await.unwrap(), data[1 << 20..][..4]);
+//!
+//!     // Chunks arrive in file order
+//!     let mut streamed = Vec::new();
+//!     let mut chunks = file.read_stream();
+//!     while let Some(chunk) = chunks.next().await {
+//!         streamed.extend_from_slice(&chunk.unwrap());
+//!     }
+//!     assert_eq!(streamed, data);
+//! });
+//! # std::fs::remove_file(path).unwrap();
+//! ```
+
+use core::{
+    ffi::{c_int, c_ulong, c_void},
+    fmt, ptr, slice,
+};
+use std::{
+    fs::OpenOptions,
+    io,
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+};
+
+use async_channel::{Receiver, Sender};
+use dispatch::ffi::{dispatch_object_s, dispatch_queue_t, dispatch_release};
+
+use crate::{
+    Priority,
+    apple::raw_queue,
+    callback::{self, Completer},
+    spawn_with_priority,
+};
+
+type DispatchIo = *mut dispatch_object_s;
+type DispatchData = *mut dispatch_object_s;
+type IoHandler = extern "C" fn(context: *mut c_void, done: bool, data: DispatchData, error: c_int);
+
+const DISPATCH_IO_RANDOM: c_ulong = 1;
+
+unsafe extern "C" {
+    fn dispatch_io_create_f(
+        kind: c_ulong,
+        fd: c_int,
+        queue: dispatch_queue_t,
+        context: *mut c_void,
+        cleanup_handler: extern "C" fn(context: *mut c_void, error: c_int),
+    ) -> DispatchIo;
+    fn dispatch_io_read_f(
+        channel: DispatchIo,
+        offset: i64,
+        length: usize,
+        queue: dispatch_queue_t,
+        context: *mut c_void,
+        io_handler: IoHandler,
+    );
+    fn dispatch_io_write_f(
+        channel: DispatchIo,
+        offset: i64,
+        data: DispatchData,
+        queue: dispatch_queue_t,
+        context: *mut c_void,
+        io_handler: IoHandler,
+    );
+    fn dispatch_io_close(channel: DispatchIo, flags: c_ulong);
+    fn dispatch_data_create(
+        buffer: *const c_void,
+        size: usize,
+        queue: dispatch_queue_t,
+        destructor: *const c_void,
+    ) -> DispatchData;
+    fn dispatch_data_create_map(
+        data: DispatchData,
+        buffer: *mut *const c_void,
+        size: *mut usize,
+    ) -> DispatchData;
+}
+
+/// A file opened for asynchronous IO through a `dispatch_io` channel.
+///
+/// Operations may be issued concurrently from any thread. Dropping the file
+/// closes the channel once pending operations have finished.
+pub struct File {
+    channel: DispatchIo,
+    priority: Priority,
+}
+
+// SAFETY: dispatch IO channels are thread-safe reference-counted objects.
+unsafe impl Send for File {}
+// SAFETY: see above.
+unsafe impl Sync for File {}
+
+impl fmt::Debug for File {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("File")
+            .field("priority", &self.priority)
+            .finish_non_exhaustive()
+    }
+}
+
+impl File {
+    /// Opens an existing file for reading.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error reported by the system when opening the file.
+    pub async fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut options = OpenOptions::new();
+        options.read(true);
+        Self::open_with(path, options, Priority::default()).await
+    }
+
+    /// Opens a file for reading and writing, creating it if needed and
+    /// truncating it otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error reported by the system when opening the file.
+    pub async fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut options = OpenOptions::new();
+        options.read(true).write(true).create(true).truncate(true);
+        Self::open_with(path, options, Priority::default()).await
+    }
+
+    /// Opens a file with the given options.
+    ///
+    /// The file is opened on a queue for `priority`, and completions of all
+    /// later operations are delivered at that priority.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error reported by the system when opening the file or
+    /// creating the IO channel.
+    pub async fn open_with(
+        path: impl AsRef<Path>,
+        options: OpenOptions,
+        priority: Priority,
+    ) -> io::Result<Self> {
+        let path: PathBuf = path.as_ref().into();
+        let file = spawn_with_priority(async move { options.open(path) }, priority).await?;
+        let fd = file.as_raw_fd();
+        let context = Box::into_raw(Box::new(file));
+
+        // SAFETY: `fd` stays open until the cleanup handler drops the boxed
+        // file, which GCD calls once it no longer uses the descriptor.
+        let channel = unsafe {
+            dispatch_io_create_f(
+                DISPATCH_IO_RANDOM,
+                fd,
+                raw_queue(priority),
+                context.cast(),
+                close_file,
+            )
+        };
+        if channel.is_null() {
+            // SAFETY: the channel was not created, so GCD does not own `context`.
+            drop(unsafe { Box::from_raw(context) });
+            return Err(io::Error::other("failed to create dispatch IO channel"));
+        }
+        Ok(Self { channel, priority })
+    }
+
+    /// Reads up to `len` bytes starting at `offset`.
+    ///
+    /// Fewer bytes are returned if the end of the file is reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error reported by the system, or
+    /// [`io::ErrorKind::InvalidInput`] if `offset` is out of range.
+    pub async fn read_at(&self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        let offset = checked_offset(offset)?;
+        let (completer, result) = callback::future();
+        let context = Box::into_raw(Box::new(ReadState {
+            // `len` is an upper bound; don't trust it for the allocation.
+            buffer: Vec::with_capacity(len.min(1 << 20)),
+            completer,
+        }));
+        // SAFETY: the channel is valid and `read_handler` reclaims `context`
+        // on its final invocation.
+        unsafe {
+            dispatch_io_read_f(
+                self.channel,
+                offset,
+                len,
+                raw_queue(self.priority),
+                context.cast(),
+                read_handler,
+            );
+        }
+        result.await
+    }
+
+    /// Writes all of `buf` starting at `offset`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error reported by the system, or
+    /// [`io::ErrorKind::InvalidInput`] if `offset` is out of range.
+    pub async fn write_at(&self, offset: u64, buf: &[u8]) -> io::Result<()> {
+        let offset = checked_offset(offset)?;
+        let (completer, result) = callback::future();
+        let context = Box::into_raw(Box::new(completer));
+        // SAFETY: a null destructor makes GCD copy the buffer, so `buf` only
+        // has to outlive this call. The channel retains `data` while writing.
+        unsafe {
+            let data =
+                dispatch_data_create(buf.as_ptr().cast(), buf.len(), ptr::null_mut(), ptr::null());
+            dispatch_io_write_f(
+                self.channel,
+                offset,
+                data,
+                raw_queue(self.priority),
+                context.cast(),
+                write_handler,
+            );
+            dispatch_release(data);
+        }
+        result.await
+    }
+
+    /// Reads the whole file, yielding chunks in file order as the system
+    /// delivers them.
+    pub fn read_stream(&self) -> ReadStream {
+        let (sender, receiver) = async_channel::unbounded();
+        let context = Box::into_raw(Box::new(sender));
+        // SAFETY: the channel is valid and `stream_handler` reclaims `context`
+        // on its final invocation. A length of `usize::MAX` reads until EOF.
+        unsafe {
+            dispatch_io_read_f(
+                self.channel,
+                0,
+                usize::MAX,
+                raw_queue(self.priority),
+                context.cast(),
+                stream_handler,
+            );
+        }
+        ReadStream { receiver }
+    }
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        // SAFETY: the channel is valid; closing without `DISPATCH_IO_STOP`
+        // lets pending operations finish.
+        unsafe {
+            dispatch_io_close(self.channel, 0);
+            dispatch_release(self.channel);
+        }
+    }
+}
+
+/// Chunks of a file read with [`File::read_stream`].
+#[derive(Debug)]
+pub struct ReadStream {
+    receiver: Receiver<io::Result<Vec<u8>>>,
+}
+
+impl ReadStream {
+    /// Waits for the next chunk.
+    ///
+    /// Returns `None` once the whole file has been read or an error has been
+    /// yielded.
+    pub async fn next(&mut self) -> Option<io::Result<Vec<u8>>> {
+        self.receiver.recv().await.ok()
+    }
+}
+
+struct ReadState {
+    buffer: Vec<u8>,
+    completer: Completer<io::Result<Vec<u8>>>,
+}
+
+fn checked_offset(offset: u64) -> io::Result<i64> {
+    i64::try_from(offset).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))
+}
+
+fn result_of(error: c_int) -> io::Result<()> {
+    if error == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::from_raw_os_error(error))
+    }
+}
+
+/// Appends the bytes of `data` to `buffer`.
+///
+/// # Safety
+///
+/// `data` must be a valid dispatch data object.
+unsafe fn append(buffer: &mut Vec<u8>, data: DispatchData) {
+    let mut bytes = ptr::null();
+    let mut size = 0;
+    // SAFETY: guaranteed by the caller; the map keeps the bytes alive until it
+    // is released.
+    unsafe {
+        let map = dispatch_data_create_map(data, &raw mut bytes, &raw mut size);
+        if size > 0 {
+            buffer.extend_from_slice(slice::from_raw_parts(bytes.cast::<u8>(), size));
+        }
+        dispatch_release(map);
+    }
+}
+
+extern "C" fn close_file(context: *mut c_void, _error: c_int) {
+    // SAFETY: `context` is the boxed file passed to `dispatch_io_create_f`.
+    drop(unsafe { Box::from_raw(context.cast::<std::fs::File>()) });
+}
+
+extern "C" fn read_handler(context: *mut c_void, done: bool, data: DispatchData, error: c_int) {
+    let state = context.cast::<ReadState>();
+    if !data.is_null() {
+        // SAFETY: `state` stays valid until the final invocation below, and
+        // invocations for one operation are serialized.
+        unsafe { append(&mut (*state).buffer, data) };
+    }
+    if done {
+        // SAFETY: this is the final invocation for this operation.
+        let ReadState { buffer, completer } = *unsafe { Box::from_raw(state) };
+        completer.complete(result_of(error).map(|()| buffer));
+    }
+}
+
+extern "C" fn write_handler(context: *mut c_void, done: bool, _data: DispatchData, error: c_int) {
+    if done {
+        // SAFETY: this is the final invocation for this operation.
+        let completer = unsafe { Box::from_raw(context.cast::<Completer<io::Result<()>>>()) };
+        completer.complete(result_of(error));
+    }
+}
+
+extern "C" fn stream_handler(context: *mut c_void, done: bool, data: DispatchData, error: c_int) {
+    let sender = context.cast::<Sender<io::Result<Vec<u8>>>>();
+    if !data.is_null() {
+        let mut chunk = Vec::new();
+        // SAFETY: `data` is provided by GCD for this invocation.
+        unsafe { append(&mut chunk, data) };
+        if !chunk.is_empty() {
+            // SAFETY: `sender` stays valid until the final invocation below.
+            let _ = unsafe { &*sender }.try_send(Ok(chunk));
+        }
+    }
+    if done {
+        // SAFETY: this is the final invocation for this operation.
+        let sender = unsafe { Box::from_raw(sender) };
+        if let Err(error) = result_of(error) {
+            let _ = sender.try_send(Err(error));
+        }
+    }
+}
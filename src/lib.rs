@@ -1,8 +1,11 @@
 #![doc = include_str!("../README.md")]
+#![cfg_attr(not(feature = "std"), no_std)]
 #![warn(missing_docs, missing_debug_implementations)]
 
+extern crate alloc;
+
 #[cfg(target_vendor = "apple")]
-mod apple;
+pub mod apple;
 
 #[cfg(target_arch = "wasm32")]
 mod web;
@@ -10,13 +13,138 @@ mod web;
 #[cfg(target_os = "android")]
 mod android;
 
+// The Android backend drives its queues from worker threads.
+#[cfg(all(target_os = "android", not(feature = "std")))]
+compile_error!("the Android backend requires the `std` feature");
+
 #[cfg(feature = "polyfill")]
 pub mod polyfill;
 
-use async_task::Task;
-use executor_core::{Executor, LocalExecutor, async_task::AsyncTask};
+#[cfg(any(target_os = "android", feature = "polyfill"))]
+mod main_queue;
+
+#[cfg(any(target_os = "android", all(target_os = "linux", feature = "polyfill")))]
+mod nice;
+
+/// The task handle returned by the spawn functions.
+///
+/// Awaiting it yields the task's output; dropping it cancels the task unless
+/// it was detached.
+pub use async_task::Task;
+/// The trait of the task handles returned by [`Executor`] and
+/// [`LocalExecutor`] implementations, such as [`TaskHandle`].
+pub use executor_core::Task as ExecutorTask;
+/// Traits for components that are generic over where their tasks run.
+pub use executor_core::{Executor, LocalExecutor};
+#[cfg(feature = "std")]
+mod alive;
+#[cfg(feature = "std")]
+pub use alive::SpawnError;
+mod auto_traits;
+#[cfg(feature = "std")]
+pub mod bus;
+#[cfg(feature = "std")]
+pub mod callback;
+#[cfg(feature = "std")]
+pub mod cancel;
+#[cfg(feature = "std")]
+mod chunks;
+#[cfg(feature = "std")]
+pub use chunks::{DrainHandle, DrainStop, drain_on_main, main_chunks, main_chunks_with_limit};
+#[cfg(feature = "std")]
+mod coalesce;
+#[cfg(feature = "std")]
+mod context;
+#[cfg(feature = "std")]
+pub use context::{ExecContext, current_context};
+#[cfg(feature = "std")]
+mod config;
+#[cfg(feature = "std")]
+pub use config::{AlreadyInitialized, ClampExemption, Config, SpawnOverflow, init};
+#[cfg(feature = "deadlock-detect")]
+pub mod deadlock;
+mod deferred;
+pub use deferred::spawn_fn;
+#[cfg(feature = "stats")]
+pub mod diagnostics;
+#[cfg(feature = "std")]
+pub mod error;
+#[cfg(feature = "std")]
+pub use error::Error;
+#[cfg(feature = "std")]
+mod execute;
+#[cfg(feature = "std")]
+pub use execute::{
+    ScheduledHandle, execute, execute_after, execute_main, execute_main_local,
+    execute_with_priority, schedule_after,
+};
+mod executors;
+#[cfg(feature = "std")]
+mod handle;
+#[cfg(feature = "std")]
+pub use handle::Handle;
+pub mod hooks;
+#[cfg(all(target_vendor = "apple", feature = "dispatch-io"))]
+pub mod io;
+pub use executors::{
+    BackgroundExecutor, DefaultExecutor, MainExecutor, UserInitiatedExecutor,
+    UserInteractiveExecutor, UtilityExecutor,
+};
+use hooks::TaskInfo;
+#[cfg(feature = "std")]
 pub mod mailbox;
+#[cfg(feature = "std")]
+pub use mailbox::{Mailbox, MailboxBuilder};
+#[cfg(feature = "std")]
+pub mod lifecycle;
+#[cfg(feature = "std")]
+pub mod main_loop;
+#[cfg(feature = "std")]
+pub use main_loop::MainExecutorNotRunning;
+#[cfg(feature = "std")]
+mod main_token;
+#[cfg(feature = "std")]
+pub use main_token::MainToken;
+#[cfg(feature = "std")]
+mod main_value;
+#[cfg(feature = "std")]
+pub use main_value::{MainValue, send_to_main};
+#[cfg(feature = "std")]
+pub mod notify;
+pub mod prelude;
+mod priority;
+#[cfg(feature = "std")]
+mod supervised;
+pub use priority::{PlatformPriority, Priority, QosClass};
+#[cfg(feature = "std")]
+pub use supervised::{
+    AbortHandle, JoinError, SupervisedTask, spawn_supervised, spawn_supervised_with_priority,
+};
+#[cfg(feature = "std")]
+mod scoped_priority;
+#[cfg(feature = "std")]
+pub use scoped_priority::{WithPriority, current_priority, with_priority};
+#[cfg(feature = "std")]
+mod sequenced;
+#[cfg(feature = "std")]
+pub use sequenced::{sequenced_keys, spawn_sequenced};
+#[cfg(feature = "std")]
+pub mod service;
+#[cfg(all(unix, feature = "std"))]
+pub mod signals;
+mod spawn_options;
+pub use spawn_options::{SpawnOptions, WebTaskType};
+#[cfg(feature = "std")]
+pub mod supervisor;
+#[cfg(feature = "std")]
+mod task_group;
+#[cfg(feature = "std")]
+pub use task_group::{LaneReport, ShutdownReport, TaskGroup};
+mod task_handle;
+pub use task_handle::TaskHandle;
 pub mod timer;
+#[cfg(feature = "std")]
+mod timer_pool;
 use core::time::Duration;
 
 #[cfg(target_vendor = "apple")]
@@ -32,18 +160,61 @@ pub use android::AndroidPlatformExecutor as NativeExecutor;
     not(target_vendor = "apple"),
     not(target_arch = "wasm32"),
     not(target_os = "android"),
-    not(feature = "polyfill")
+    not(feature = "polyfill"),
+    feature = "noop-backend"
+))]
+pub mod inert;
+#[cfg(all(
+    not(target_vendor = "apple"),
+    not(target_arch = "wasm32"),
+    not(target_os = "android"),
+    not(feature = "polyfill"),
+    feature = "noop-backend"
+))]
+/// The native executor implementation.
+pub use inert::InertExecutor as NativeExecutor;
+
+#[cfg(all(
+    not(target_vendor = "apple"),
+    not(target_arch = "wasm32"),
+    not(target_os = "android"),
+    not(feature = "polyfill"),
+    not(feature = "noop-backend")
 ))]
 mod unsupported {
-    use core::time::Duration;
+    use core::{future::Future, time::Duration};
 
     use crate::{PlatformExecutor, Priority};
 
+    /// Executor of targets without a backend, which panics when used.
+    ///
+    /// Enable the `polyfill` feature for a thread-pool backend, or the
+    /// `noop-backend` feature for an inert backend driven by hand in tests.
     #[derive(Debug, Clone, Copy, Default)]
     pub struct UnsupportedExecutor;
 
+    impl UnsupportedExecutor {
+        /// Would drive the main thread until `future` completes.
+        ///
+        /// # Panics
+        ///
+        /// Always panics, as this target has no backend.
+        pub fn run_until<F: Future>(_future: F) -> F::Output {
+            panic!("run_until is not supported on this platform");
+        }
+
+        /// Would drive the main thread until no task is alive.
+        ///
+        /// # Panics
+        ///
+        /// Always panics, as this target has no backend.
+        pub fn keep_alive_until_idle(_grace: Duration) {
+            panic!("keep_alive_until_idle is not supported on this platform");
+        }
+    }
+
     impl PlatformExecutor for UnsupportedExecutor {
-        fn exec_main(_f: impl FnOnce() + Send + 'static) {
+        fn exec_main(_f: impl FnOnce() + Send + 'static, _priority: Option<Priority>) {
             panic!("exec_main is not supported on this platform");
         }
 
@@ -51,16 +222,24 @@ mod unsupported {
             panic!("exec is not supported on this platform");
         }
 
+        const MAX_DELAY: Duration = Duration::MAX;
+
         fn exec_after(_delay: Duration, _f: impl FnOnce() + Send + 'static, _priority: Priority) {
             panic!("exec_after is not supported on this platform");
         }
+
+        #[cfg(feature = "std")]
+        fn is_main_thread() -> bool {
+            false
+        }
     }
 }
 #[cfg(all(
     not(target_vendor = "apple"),
     not(target_arch = "wasm32"),
     not(target_os = "android"),
-    not(feature = "polyfill")
+    not(feature = "polyfill"),
+    not(feature = "noop-backend")
 ))]
 /// The native executor implementation.
 pub use unsupported::UnsupportedExecutor as NativeExecutor;
@@ -72,15 +251,81 @@ pub use unsupported::UnsupportedExecutor as NativeExecutor;
 pub use polyfill::PolyfillExecutor as NativeExecutor;
 
 trait PlatformExecutor {
-    fn exec_main(f: impl FnOnce() + Send + 'static);
+    /// Submits `f` to the main thread, ahead of queued work of a lower
+    /// `priority` where the platform allows it.
+    fn exec_main(f: impl FnOnce() + Send + 'static, priority: Option<Priority>);
     fn exec(f: impl FnOnce() + Send + 'static, priority: Priority);
 
+    /// The longest delay `exec_after` can represent. [`dispatch_after`]
+    /// splits longer delays into steps of at most this length.
+    const MAX_DELAY: Duration;
+
+    /// Submits `f` once `delay`, at most [`MAX_DELAY`](Self::MAX_DELAY), has
+    /// elapsed.
     fn exec_after(delay: Duration, f: impl FnOnce() + Send + 'static, priority: Priority);
+
+    /// Submits `f` as [`exec_after`](Self::exec_after) does, allowing it to
+    /// run up to `leeway` late so the system can batch wake-ups. Backends
+    /// without timer leeway ignore it.
+    fn exec_after_with_leeway(
+        delay: Duration,
+        f: impl FnOnce() + Send + 'static,
+        priority: Priority,
+        leeway: Duration,
+    ) {
+        let _ = leeway;
+        Self::exec_after(delay, f, priority);
+    }
+
+    /// Submits `f` to the main thread once `delay`, at most
+    /// [`MAX_DELAY`](Self::MAX_DELAY), has elapsed, without passing through
+    /// the thread pool where the platform allows it.
+    fn exec_main_after(delay: Duration, f: impl FnOnce() + Send + 'static) {
+        Self::exec_after(
+            delay,
+            move || Self::exec_main(f, None),
+            Priority::UserInteractive,
+        );
+    }
+
+    /// Submits `f` as [`exec_after`](Self::exec_after) does, returning a key
+    /// that [`cancel_after`](Self::cancel_after) takes to drop `f` before it
+    /// runs, or `None` if the backend cannot cancel its timers, in which case
+    /// `f` runs regardless.
+    #[cfg(feature = "std")]
+    fn exec_after_cancellable(
+        delay: Duration,
+        f: impl FnOnce() + Send + 'static,
+        priority: Priority,
+    ) -> Option<u64> {
+        Self::exec_after(delay, f, priority);
+        None
+    }
+
+    /// Drops the closure of `key`, returned by
+    /// [`exec_after_cancellable`](Self::exec_after_cancellable), unless it
+    /// has started.
+    #[cfg(feature = "std")]
+    fn cancel_after(key: u64) {
+        let _ = key;
+    }
+
+    /// Returns whether the current thread runs the work of `exec_main`.
+    #[cfg(feature = "std")]
+    fn is_main_thread() -> bool;
+}
+
+/// Returns whether the current thread is the main thread, the one that runs
+/// the tasks of [`spawn_main`] and [`spawn_local`].
+#[cfg(feature = "std")]
+fn is_main_thread() -> bool {
+    NativeExecutor::is_main_thread()
 }
 
 impl Executor for NativeExecutor {
-    type Task<T: Send + 'static> = AsyncTask<T>;
+    type Task<T: Send + 'static> = TaskHandle<T>;
 
+    #[track_caller]
     fn spawn<Fut>(&self, fut: Fut) -> Self::Task<Fut::Output>
     where
         Fut: Future<Output: Send> + Send + 'static,
@@ -89,8 +334,10 @@ impl Executor for NativeExecutor {
     }
 }
 
+#[cfg(feature = "std")]
 impl LocalExecutor for NativeExecutor {
-    type Task<T: 'static> = AsyncTask<T>;
+    type Task<T: 'static> = TaskHandle<T>;
+    #[track_caller]
     fn spawn_local<Fut>(&self, fut: Fut) -> Self::Task<Fut::Output>
     where
         Fut: Future + 'static,
@@ -99,42 +346,418 @@ impl LocalExecutor for NativeExecutor {
     }
 }
 
-use async_task::Runnable;
+#[cfg(feature = "std")]
+use alloc::collections::VecDeque;
+use async_task::{Runnable, ScheduleInfo, WithInfo};
+
+/// Default prefix of the queue labels and worker thread names of this crate.
+#[cfg(any(feature = "std", target_vendor = "apple"))]
+const DEFAULT_LABEL_PREFIX: &str = "dev.water.native-executor";
+
+/// Returns the prefix of the queue labels and worker thread names of this
+/// crate.
+#[cfg(all(
+    feature = "std",
+    any(target_vendor = "apple", target_os = "android", feature = "polyfill")
+))]
+fn label_prefix() -> &'static str {
+    config::current().label_prefix()
+}
+
+#[cfg(all(not(feature = "std"), target_vendor = "apple"))]
+const fn label_prefix() -> &'static str {
+    DEFAULT_LABEL_PREFIX
+}
 
-/// Task execution priority levels for controlling scheduler behavior.
+/// Maps the priority requested for work of the thread pool to the priority
+/// it is queued at, within the bounds of
+/// [`Config::priority_floor`](config::Config::priority_floor) and
+/// [`Config::priority_ceiling`](config::Config::priority_ceiling).
 ///
-/// These priority levels map to platform-native scheduling priorities,
-/// allowing fine-grained control over task execution order and resource allocation.
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
-#[non_exhaustive]
-pub enum Priority {
-    /// Standard priority level for most application tasks.
-    ///
-    /// This is the default priority that provides balanced execution
-    /// suitable for general-purpose async operations.
-    #[default]
-    Default,
-    /// Lower priority for background tasks and non-critical operations.
-    ///
-    /// Background tasks yield CPU time to higher-priority tasks and are
-    /// ideal for operations like cleanup, logging, or data processing
-    /// that don't require immediate completion.
-    Background,
-    /// Higher priority for user-initiated tasks that require prompt execution.
-    /// This priority is suitable for tasks that directly impact user experience,
-    /// such as responding to user input or updating the UI.
-    UserInitiated,
-    /// Highest priority for tasks that require immediate attention to maintain
-    /// application responsiveness.
-    /// This priority should be reserved for critical operations that must
-    /// complete as soon as possible, such as rendering UI updates or handling
-    /// real-time data.
-    UserInteractive,
-    /// Lowest priority for tasks that can be deferred until the system is idle.
-    /// This priority is suitable for maintenance tasks, prefetching data,
-    /// or other operations that do not need to run immediately and can wait
-    /// until the system is less busy.
-    Utility,
+/// [`dispatch`] and [`dispatch_after`] apply it, so no submission reaches a
+/// backend unbounded unless it comes from an exempt task.
+#[cfg(feature = "std")]
+fn effective_priority(priority: Priority) -> Priority {
+    config::current().clamp_priority(priority)
+}
+
+#[cfg(not(feature = "std"))]
+const fn effective_priority(priority: Priority) -> Priority {
+    priority
+}
+
+/// Submits `f` to the queue for `priority`, within the configured bounds.
+fn dispatch(f: impl FnOnce() + Send + 'static, priority: Priority) {
+    dispatch_exact(f, effective_priority(priority));
+}
+
+/// Submits `f` to the queue for `priority` as is.
+fn dispatch_exact(f: impl FnOnce() + Send + 'static, priority: Priority) {
+    #[cfg(feature = "std")]
+    config::current();
+    #[cfg(feature = "stats")]
+    let f = diagnostics::track(f, priority);
+    #[cfg(feature = "std")]
+    let f = context::worker_job(f, priority);
+    NativeExecutor::exec(f, priority);
+}
+
+/// Submits `f` to the main thread, optionally with a priority override.
+fn dispatch_main(f: impl FnOnce() + Send + 'static, priority: Option<Priority>) {
+    #[cfg(feature = "std")]
+    config::current();
+    #[cfg(feature = "stats")]
+    let f = diagnostics::track_main(f);
+    NativeExecutor::exec_main(f, priority);
+    #[cfg(feature = "std")]
+    main_loop::probe();
+}
+
+/// Whether the task described by `info` is queued as a macrotask, which
+/// only the web backend distinguishes.
+#[cfg(any(feature = "std", target_arch = "wasm32"))]
+fn is_macrotask(info: &TaskInfo) -> bool {
+    cfg!(target_arch = "wasm32") && info.web_task_type == WebTaskType::Macro
+}
+
+/// Submits `f`, which runs part of the task described by `info`, to the
+/// queue for its priority.
+fn dispatch_task(f: impl FnOnce() + Send + 'static, info: &TaskInfo) {
+    #[cfg(target_arch = "wasm32")]
+    if is_macrotask(info) {
+        #[cfg(feature = "stats")]
+        let f = diagnostics::track(f, info.priority);
+        web::exec_macro(f);
+        return;
+    }
+    if info.clamp_exempt {
+        dispatch_exact(f, info.priority);
+    } else {
+        dispatch(f, info.priority);
+    }
+}
+
+/// Submits `f`, which runs part of `task`, to the main thread.
+fn dispatch_main_task(f: impl FnOnce() + Send + 'static, task: &MainTask) {
+    #[cfg(target_arch = "wasm32")]
+    if is_macrotask(&task.info) {
+        #[cfg(feature = "stats")]
+        let f = diagnostics::track_main(f);
+        web::exec_macro(f);
+        return;
+    }
+    dispatch_main(f, task.priority);
+}
+
+/// The number of woken main-thread tasks run after a poll before the rest
+/// is queued, which keeps tasks that keep waking each other from starving the
+/// main queue.
+#[cfg(feature = "std")]
+const MAX_RUN_NEXT: usize = 16;
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    /// Whether a main-thread task is being polled on this thread.
+    static POLLING_MAIN: core::cell::Cell<bool> = const { core::cell::Cell::new(false) };
+    /// Main-thread tasks woken by the task being polled.
+    static RUN_NEXT: core::cell::RefCell<VecDeque<(Runnable, MainTask)>> =
+        const { core::cell::RefCell::new(VecDeque::new()) };
+}
+
+/// Marks the current thread as polling a main-thread task until dropped.
+#[cfg(feature = "std")]
+struct PollingMain {
+    nested: bool,
+}
+
+#[cfg(feature = "std")]
+impl PollingMain {
+    fn enter() -> Self {
+        Self {
+            nested: POLLING_MAIN.replace(true),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for PollingMain {
+    fn drop(&mut self) {
+        POLLING_MAIN.set(self.nested);
+    }
+}
+
+/// How a task bound to the main thread is run and queued.
+#[derive(Debug, Clone, Copy)]
+struct MainTask {
+    info: TaskInfo,
+    /// The priority override passed to the main queue, if any.
+    priority: Option<Priority>,
+}
+
+/// Polls `runnable`, the task described by `info`, surrounded by the hooks.
+fn poll_task(runnable: Runnable, info: &TaskInfo) {
+    #[cfg(feature = "std")]
+    scoped_priority::polling(info.id, || hooks::run(runnable, info));
+    #[cfg(not(feature = "std"))]
+    hooks::run(runnable, info);
+}
+
+/// Queues the first poll of `runnable`, a new task bound to the main thread.
+///
+/// New tasks always go through the main queue, even when spawned from a
+/// main-thread task, so their first polls keep the submission order relative
+/// to each other and to [`execute_main`] closures.
+fn submit_main(runnable: Runnable, task: MainTask) {
+    dispatch_main_task(move || run_main(runnable, &task), &task);
+}
+
+/// Schedules `runnable`, a task bound to the main thread, after a wake-up.
+///
+/// A task woken by another main-thread task runs as soon as the poll of that
+/// task returns, skipping the round trip through the main queue. The task is
+/// never polled from within `wake` itself, which foreign wakers, such as those
+/// of channels, may call while holding locks. Wake-ups from other threads, and
+/// a task waking itself to yield, go through the main queue, as do all
+/// wake-ups of macrotasks on the web. So do wake-ups tagged by
+/// [`with_priority`], which take its priority as the override.
+fn schedule_main(runnable: Runnable, task: MainTask, schedule: ScheduleInfo) {
+    #[cfg(feature = "std")]
+    let task = match scoped_priority::take_tag(task.info.id) {
+        Some(priority) => MainTask {
+            info: TaskInfo {
+                priority,
+                ..task.info
+            },
+            priority: Some(priority),
+        },
+        None if !schedule.woken_while_running
+            && !is_macrotask(&task.info)
+            && POLLING_MAIN.get() =>
+        {
+            RUN_NEXT.with_borrow_mut(|run_next| run_next.push_back((runnable, task)));
+            return;
+        }
+        None => task,
+    };
+    #[cfg(not(feature = "std"))]
+    let _ = schedule;
+    dispatch_main_task(move || run_main(runnable, &task), &task);
+}
+
+/// Polls `runnable`, a task bound to the main thread, on the main thread,
+/// followed by the main-thread tasks it woke.
+fn run_main(runnable: Runnable, task: &MainTask) {
+    run_main_waking(|| poll_task(runnable, &task.info));
+}
+
+/// Runs `f` on the main thread, followed by the main-thread tasks it woke, as
+/// if it were the poll of a main-thread task.
+fn run_main_waking(f: impl FnOnce()) {
+    #[cfg(feature = "std")]
+    let polling = PollingMain::enter();
+    f();
+
+    #[cfg(feature = "std")]
+    if !polling.nested {
+        for _ in 0..MAX_RUN_NEXT {
+            let Some((runnable, task)) = RUN_NEXT.with_borrow_mut(VecDeque::pop_front) else {
+                return;
+            };
+            poll_task(runnable, &task.info);
+        }
+        drop(polling);
+        while let Some((runnable, task)) = RUN_NEXT.with_borrow_mut(VecDeque::pop_front) {
+            dispatch_main_task(move || run_main(runnable, &task), &task);
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    /// Whether the task being polled on this thread may be re-run once,
+    /// inline, if it wakes itself.
+    static RERUN_ALLOWED: core::cell::Cell<bool> = const { core::cell::Cell::new(false) };
+    /// The task that woke itself during its poll on this thread.
+    static RERUN: core::cell::Cell<Option<Runnable>> = const { core::cell::Cell::new(None) };
+}
+
+/// Allows the task polled on the current thread to be re-run inline until
+/// dropped.
+#[cfg(feature = "std")]
+struct Rerun {
+    allowed: bool,
+}
+
+#[cfg(feature = "std")]
+impl Rerun {
+    fn enter() -> Self {
+        Self {
+            allowed: RERUN_ALLOWED.replace(true),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for Rerun {
+    fn drop(&mut self) {
+        RERUN_ALLOWED.set(self.allowed);
+    }
+}
+
+/// Schedules `runnable`, a task of the thread pool, after a wake-up.
+///
+/// Wake-ups are coalesced before they get here: `async_task` calls this once
+/// when an idle task is woken, ignores further wake-ups until the task runs,
+/// and calls it once more after the poll if the task was woken meanwhile. So
+/// each call is exactly one dispatch, however bursty the wake-ups are.
+///
+/// A task that wakes itself while being polled, as a task yielding to others
+/// does, is run again as soon as its poll returns, on the same thread and
+/// without a queue hop. It gets one such re-run per dispatch; waking itself
+/// again queues it behind the tasks already waiting. Macrotasks on the web
+/// are never re-run inline, and neither are wake-ups tagged by
+/// [`with_priority`], which are queued at its priority instead.
+fn schedule_pooled(runnable: Runnable, info: TaskInfo, schedule: ScheduleInfo) {
+    #[cfg(feature = "std")]
+    let info = match scoped_priority::take_tag(info.id) {
+        Some(priority) => TaskInfo {
+            priority: info.effective(priority),
+            ..info
+        },
+        None if schedule.woken_while_running
+            && !is_macrotask(&info)
+            && RERUN_ALLOWED.replace(false) =>
+        {
+            RERUN.set(Some(runnable));
+            return;
+        }
+        None => info,
+    };
+    #[cfg(not(feature = "std"))]
+    let _ = schedule;
+    dispatch_task(move || run_pooled(runnable, &info), &info);
+}
+
+/// Polls `runnable`, a task of the thread pool, re-running it once if it woke
+/// itself.
+fn run_pooled(runnable: Runnable, info: &TaskInfo) {
+    #[cfg(feature = "std")]
+    let rerun = Rerun::enter();
+    poll_task(runnable, info);
+
+    #[cfg(feature = "std")]
+    {
+        RERUN_ALLOWED.set(false);
+        if let Some(runnable) = RERUN.take() {
+            poll_task(runnable, info);
+        }
+        drop(rerun);
+    }
+}
+
+/// Submits `f` to the queue for `priority` once `delay` has elapsed.
+///
+/// Delays beyond what the backend can represent are reached by re-arming
+/// with the remainder, so `f` never runs early. [`Duration::MAX`] means
+/// never: `f` is dropped right away.
+fn dispatch_after(delay: Duration, f: impl FnOnce() + Send + 'static, priority: Priority) {
+    dispatch_after_with_leeway(delay, f, priority, None);
+}
+
+/// Submits `f` as [`dispatch_after`] does, allowing the last step to run up
+/// to `leeway` late, or with the backend's default leeway if `None`.
+fn dispatch_after_with_leeway(
+    delay: Duration,
+    f: impl FnOnce() + Send + 'static,
+    priority: Priority,
+    leeway: Option<Duration>,
+) {
+    #[cfg(feature = "std")]
+    config::current();
+    if delay == Duration::MAX {
+        return;
+    }
+    let priority = effective_priority(priority);
+    #[cfg(feature = "stats")]
+    diagnostics::count_platform_timer();
+    if let Some(remainder) = delay
+        .checked_sub(NativeExecutor::MAX_DELAY)
+        .filter(|remainder| !remainder.is_zero())
+    {
+        NativeExecutor::exec_after(
+            NativeExecutor::MAX_DELAY,
+            move || dispatch_after_with_leeway(remainder, f, priority, leeway),
+            priority,
+        );
+    } else if let Some(leeway) = leeway {
+        NativeExecutor::exec_after_with_leeway(delay, f, priority, leeway);
+    } else {
+        NativeExecutor::exec_after(delay, f, priority);
+    }
+}
+
+/// A closure submitted with [`dispatch_after_cancellable`], which
+/// [`cancel_after`] drops unless it has started.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TimerKey(u64);
+
+/// Submits `f` as [`dispatch_after`] does, returning the key that cancels it.
+///
+/// Returns `None` if the backend cannot cancel its timers, or if `delay`
+/// takes more than one step of [`PlatformExecutor::MAX_DELAY`]: `f` then
+/// runs regardless once it is due, and must find nothing to do if it was
+/// meant to be cancelled.
+#[cfg(feature = "std")]
+fn dispatch_after_cancellable(
+    delay: Duration,
+    f: impl FnOnce() + Send + 'static,
+    priority: Priority,
+) -> Option<TimerKey> {
+    if delay == Duration::MAX || delay > NativeExecutor::MAX_DELAY {
+        dispatch_after(delay, f, priority);
+        return None;
+    }
+    config::current();
+    let priority = effective_priority(priority);
+    #[cfg(feature = "stats")]
+    diagnostics::count_platform_timer();
+    NativeExecutor::exec_after_cancellable(delay, f, priority).map(TimerKey)
+}
+
+/// Drops the closure of `key` unless it has started.
+#[cfg(feature = "std")]
+fn cancel_after(key: TimerKey) {
+    NativeExecutor::cancel_after(key.0);
+}
+
+/// Submits `f` to the main thread once `delay` has elapsed.
+///
+/// The main-thread tasks that `f` wakes are polled right after it, without
+/// a round trip through the main queue. Delays are split as by
+/// [`dispatch_after`], and [`Duration::MAX`] drops `f` right away.
+fn dispatch_main_after(delay: Duration, f: impl FnOnce() + Send + 'static) {
+    #[cfg(feature = "std")]
+    config::current();
+    if delay == Duration::MAX {
+        return;
+    }
+    #[cfg(feature = "stats")]
+    diagnostics::count_platform_timer();
+    if let Some(remainder) = delay
+        .checked_sub(NativeExecutor::MAX_DELAY)
+        .filter(|remainder| !remainder.is_zero())
+    {
+        NativeExecutor::exec_after(
+            NativeExecutor::MAX_DELAY,
+            move || dispatch_main_after(remainder, f),
+            Priority::Default,
+        );
+    } else {
+        NativeExecutor::exec_main_after(delay, move || run_main_waking(f));
+        #[cfg(feature = "std")]
+        main_loop::probe();
+    }
 }
 
 /// Creates a new task with the specified execution priority.
@@ -165,20 +788,151 @@ pub enum Priority {
 ///     "done"
 /// }, Priority::Background);
 /// ```
+///
+/// Tasks at the same priority run concurrently:
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::{Config, Priority, init, spawn_with_priority};
+/// use std::sync::{Arc, Barrier};
+///
+/// init(Config::new().polyfill_threads(2).android_worker_threads(2)).unwrap();
+///
+/// // Each task waits for the other, which deadlocks if they are serialized
+/// let barrier = Arc::new(Barrier::new(2));
+/// let tasks: Vec<_> = (0..2)
+///     .map(|_| {
+///         let barrier = barrier.clone();
+///         spawn_with_priority(async move { barrier.wait(); }, Priority::UserInitiated)
+///     })
+///     .collect();
+/// for task in tasks {
+///     block_on(task);
+/// }
+/// ```
+///
+/// A task that wakes itself to yield is polled again right away, once, before
+/// it is queued behind the other tasks:
+///
+/// ```rust
+/// use futures_lite::future::{block_on, yield_now};
+/// use native_executor::{Config, Priority, init, spawn_with_priority};
+/// use std::sync::{Arc, Mutex};
+///
+/// init(Config::new().polyfill_threads(1).android_worker_threads(1)).unwrap();
+///
+/// let log = Arc::new(Mutex::new(Vec::new()));
+/// // Holding the log keeps the only worker busy until both tasks are queued
+/// let guard = log.lock().unwrap();
+/// let tasks: Vec<_> = ['a', 'b']
+///     .into_iter()
+///     .map(|name| {
+///         let log = log.clone();
+///         spawn_with_priority(
+///             async move {
+///                 for _ in 0..6 {
+///                     log.lock().unwrap().push(name);
+///                     yield_now().await;
+///                 }
+///             },
+///             Priority::Utility,
+///         )
+///     })
+///     .collect();
+/// drop(guard);
+/// for task in tasks {
+///     block_on(task);
+/// }
+///
+/// let log: String = log.lock().unwrap().iter().collect();
+/// # #[cfg(not(target_vendor = "apple"))]
+/// assert_eq!(log, "aabbaabbaabb");
+/// ```
+#[track_caller]
 pub fn spawn_with_priority<Fut>(future: Fut, priority: Priority) -> Task<Fut::Output>
 where
     Fut: Future + Send + 'static,
     Fut::Output: Send,
 {
-    let (runnable, task) = async_task::spawn(future, move |runnable: Runnable| {
-        NativeExecutor::exec(
-            move || {
-                runnable.run();
-            },
-            priority,
-        );
-    });
+    spawn_pooled(future, TaskInfo::new(priority))
+}
+
+/// Spawns `future` on the thread pool, as [`spawn_with_priority`] does,
+/// unless [`Config::max_alive_tasks`] is reached.
+///
+/// # Errors
+///
+/// Returns [`SpawnError::AtCapacity`] if as many tasks as
+/// [`Config::max_alive_tasks`] allows are alive; `future` is dropped.
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn try_spawn_with_priority<Fut>(
+    future: Fut,
+    priority: Priority,
+) -> Result<Task<Fut::Output>, SpawnError>
+where
+    Fut: Future + Send + 'static,
+    Fut::Output: Send,
+{
+    try_spawn_pooled(future, TaskInfo::new(priority))
+}
+
+/// Spawns `future` on the thread pool, as [`spawn`] does, unless
+/// [`Config::max_alive_tasks`] is reached.
+///
+/// # Errors
+///
+/// Returns [`SpawnError::AtCapacity`] if as many tasks as
+/// [`Config::max_alive_tasks`] allows are alive; `future` is dropped.
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn try_spawn<Fut>(future: Fut) -> Result<Task<Fut::Output>, SpawnError>
+where
+    Fut: Future + Send + 'static,
+    Fut::Output: Send,
+{
+    try_spawn_with_priority(future, current_priority().unwrap_or_default())
+}
+
+#[track_caller]
+fn spawn_pooled<Fut>(future: Fut, info: TaskInfo) -> Task<Fut::Output>
+where
+    Fut: Future + Send + 'static,
+    Fut::Output: Send,
+{
+    let info = info.clamped();
+    spawn_instrumented(hooks::instrument(future, info), info)
+}
+
+#[cfg(feature = "std")]
+fn try_spawn_pooled<Fut>(future: Fut, info: TaskInfo) -> Result<Task<Fut::Output>, SpawnError>
+where
+    Fut: Future + Send + 'static,
+    Fut::Output: Send,
+{
+    let info = info.clamped();
+    let slot = alive::Slot::try_take(&info)?;
+    Ok(spawn_instrumented(
+        hooks::instrument_in(future, info, slot),
+        info,
+    ))
+}
+
+/// Spawns `future`, instrumented for the task described by `info`, on the
+/// thread pool.
+fn spawn_instrumented<Fut>(future: Fut, info: TaskInfo) -> Task<Fut::Output>
+where
+    Fut: Future + Send + 'static,
+    Fut::Output: Send,
+{
+    #[cfg(feature = "stats-detailed")]
+    let future = diagnostics::account(future, info);
+    let (runnable, task) = async_task::spawn(
+        future,
+        WithInfo(move |runnable, schedule| schedule_pooled(runnable, info, schedule)),
+    );
 
+    hooks::spawned(&info);
     runnable.schedule();
     task
 }
@@ -208,24 +962,35 @@ where
 ///     *local_data + 58
 /// });
 /// ```
+#[cfg(feature = "std")]
+#[track_caller]
 pub fn spawn_local<Fut>(future: Fut) -> Task<Fut::Output>
 where
     Fut: Future + 'static,
 {
-    let (runnable, task) = async_task::spawn_local(future, move |runnable: Runnable| {
-        NativeExecutor::exec_main(move || {
-            runnable.run();
-        });
-    });
+    let info = TaskInfo::new(Priority::UserInteractive);
+    let future = hooks::instrument(future, info);
+    #[cfg(feature = "stats-detailed")]
+    let future = diagnostics::account(future, info);
+    let main = MainTask {
+        info,
+        priority: None,
+    };
+    let (runnable, task) = async_task::spawn_local(
+        future,
+        WithInfo(move |runnable, schedule| schedule_main(runnable, main, schedule)),
+    );
 
-    runnable.schedule();
+    hooks::spawned(&info);
+    submit_main(runnable, main);
     task
 }
 
 /// Creates a new task with default priority.
 ///
 /// This is the primary function for spawning async tasks. The task will be
-/// executed with default priority using platform-native scheduling.
+/// executed with default priority using platform-native scheduling, or with
+/// the priority of the enclosing [`with_priority`] section, if any.
 ///
 /// # Arguments
 /// * `future` - The future to execute asynchronously
@@ -242,12 +1007,17 @@ where
 ///     42
 /// });
 /// ```
+#[track_caller]
 pub fn spawn<Fut>(future: Fut) -> Task<Fut::Output>
 where
     Fut: Future + Send + 'static,
     Fut::Output: Send,
 {
-    spawn_with_priority(future, Priority::default())
+    #[cfg(feature = "std")]
+    let priority = current_priority().unwrap_or_default();
+    #[cfg(not(feature = "std"))]
+    let priority = Priority::default();
+    spawn_with_priority(future, priority)
 }
 
 /// Creates a new task that executes on the main thread.
@@ -272,17 +1042,192 @@ where
 ///     "done"
 /// });
 /// ```
+///
+/// # Ordering
+///
+/// Main-thread work submitted from one thread starts in submission order:
+/// the first polls of [`spawn_main`] and [`spawn_local`] tasks and the
+/// closures of [`execute_main`] run first in, first out, on every backend.
+/// This holds among work of the same priority, which includes all work
+/// without a priority override; see [`spawn_main_with_priority`] for how
+/// overrides are ordered. The order of work submitted from different threads
+/// is unspecified.
+///
+/// Later polls are not ordered by submission: they follow wake-ups, as
+/// described below.
+///
+/// ```rust
+/// # #[cfg(not(target_vendor = "apple"))] {
+/// use futures_lite::future::block_on;
+/// use native_executor::{MainToken, execute_main, polyfill, spawn_main};
+/// use std::{sync::{Arc, Mutex}, thread};
+///
+/// thread::spawn(polyfill::start_main_executor);
+///
+/// // Interleaves 1000 tasks and closures, recording when each starts
+/// fn submit(log: &Arc<Mutex<Vec<usize>>>) {
+///     for i in 0..1000 {
+///         let log = log.clone();
+///         if i % 3 == 0 {
+///             execute_main(move || log.lock().unwrap().push(i));
+///         } else {
+///             spawn_main(async move { log.lock().unwrap().push(i) }).detach();
+///         }
+///     }
+/// }
+///
+/// // Submitted from another thread, and from a main-thread task
+/// for from_main in [false, true] {
+///     let log = Arc::new(Mutex::new(Vec::new()));
+///     if from_main {
+///         let log = log.clone();
+///         block_on(spawn_main(async move { submit(&log) }));
+///     } else {
+///         submit(&log);
+///     }
+///     MainToken::run_sync(|_| {});
+///     assert_eq!(*log.lock().unwrap(), (0..1000).collect::<Vec<_>>());
+/// }
+/// # }
+/// ```
+///
+/// # Wake-ups
+///
+/// Main-thread tasks, including those of [`spawn_local`], can be woken from
+/// any thread, for example by a channel from another crate, and are always
+/// polled on the main thread again. A task woken by another main-thread task
+/// runs right after the poll of that task, ahead of the rest of the main
+/// queue.
+///
+/// ```rust
+/// # #[cfg(not(target_vendor = "apple"))] {
+/// use futures_lite::future::block_on;
+/// use native_executor::{callback::MainCallback, polyfill, spawn, spawn_main};
+/// use std::{sync::{Arc, Mutex}, thread};
+///
+/// thread::spawn(polyfill::start_main_executor);
+///
+/// let (sender, receiver) = async_channel::unbounded::<&str>();
+/// let log = Arc::new(Mutex::new(Vec::new()));
+/// let received = log.clone();
+/// let task = spawn_main(async move {
+///     while let Ok(origin) = receiver.recv().await {
+///         received.lock().unwrap().push(origin);
+///     }
+/// });
+///
+/// // Woken from a pool task and from a plain thread
+/// let from_pool = sender.clone();
+/// block_on(spawn(async move { from_pool.send("pool").await.unwrap() }));
+/// let from_thread = sender.clone();
+/// thread::spawn(move || from_thread.try_send("thread").unwrap()).join().unwrap();
+/// while log.lock().unwrap().len() < 2 {
+///     thread::yield_now();
+/// }
+///
+/// // Woken from the main thread: the task runs before the job queued first
+/// let (from_main, queued) = (sender.clone(), log.clone());
+/// let queued = block_on(spawn_main(async move {
+///     let queued = MainCallback::run(move |done| {
+///         queued.lock().unwrap().push("queued");
+///         done.complete(());
+///     });
+///     from_main.try_send("main").unwrap();
+///     queued
+/// }));
+/// block_on(queued);
+///
+/// drop(sender);
+/// block_on(task);
+/// assert_eq!(*log.lock().unwrap(), ["pool", "thread", "main", "queued"]);
+/// # }
+/// ```
+#[track_caller]
 pub fn spawn_main<Fut>(future: Fut) -> Task<Fut::Output>
 where
     Fut: Future + Send + 'static,
     Fut::Output: Send,
 {
-    let (runnable, task) = async_task::spawn(future, move |runnable: Runnable| {
-        NativeExecutor::exec_main(move || {
-            runnable.run();
-        });
-    });
+    spawn_main_task(future, TaskInfo::new(Priority::UserInteractive), None)
+}
 
-    runnable.schedule();
+/// Spawns `future` on the main thread, as [`spawn_main`] does, unless the
+/// main thread is known not to serve its queue.
+///
+/// # Errors
+///
+/// Returns [`MainExecutorNotRunning`], which gives `future` back, once the
+/// probe of [`main_loop`] has found that the main thread does not serve its
+/// queue and nothing has served it since.
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn try_spawn_main<Fut>(future: Fut) -> Result<Task<Fut::Output>, MainExecutorNotRunning<Fut>>
+where
+    Fut: Future + Send + 'static,
+    Fut::Output: Send,
+{
+    if main_loop::state() == main_loop::MainLoopState::NotRunning {
+        return Err(MainExecutorNotRunning::new(future));
+    }
+    Ok(spawn_main(future))
+}
+
+/// Creates a new task that executes on the main thread with a priority
+/// override, so urgent main-thread work is not held up by less urgent work.
+///
+/// - **Apple platforms**: the task is submitted through a serial queue of the
+///   priority's quality-of-service class that targets the main queue, so GCD
+///   raises the main thread's quality of service while running it. The main queue itself
+///   stays first in, first out.
+/// - **Android and the polyfill backend**: the task is queued ahead of
+///   main-thread work of a lower priority; see
+///   [`start_main_executor`](crate::polyfill::start_main_executor) for the
+///   ordering.
+/// - **Web**: the priority is ignored.
+///
+/// Work of equal priority runs in submission order. Tasks from [`spawn_main`]
+/// are ordered as [`Priority::default`] work, while hooks observe them at
+/// [`Priority::UserInteractive`], as all main-thread work without an
+/// override. The priority given here applies to every poll of the task, and
+/// hooks observe it in [`TaskInfo::priority`].
+///
+/// # Examples
+///
+/// ```rust
+/// use native_executor::{Priority, spawn_main_with_priority};
+///
+/// // Applies a layout change ahead of queued prefetching work
+/// let task = spawn_main_with_priority(async { "laid out" }, Priority::UserInteractive);
+/// ```
+#[track_caller]
+pub fn spawn_main_with_priority<Fut>(future: Fut, priority: Priority) -> Task<Fut::Output>
+where
+    Fut: Future + Send + 'static,
+    Fut::Output: Send,
+{
+    spawn_main_task(future, TaskInfo::new(priority), Some(priority))
+}
+
+#[track_caller]
+fn spawn_main_task<Fut>(
+    future: Fut,
+    info: TaskInfo,
+    priority: Option<Priority>,
+) -> Task<Fut::Output>
+where
+    Fut: Future + Send + 'static,
+    Fut::Output: Send,
+{
+    let future = hooks::instrument(future, info);
+    #[cfg(feature = "stats-detailed")]
+    let future = diagnostics::account(future, info);
+    let main = MainTask { info, priority };
+    let (runnable, task) = async_task::spawn(
+        future,
+        WithInfo(move |runnable, schedule| schedule_main(runnable, main, schedule)),
+    );
+
+    hooks::spawned(&info);
+    submit_main(runnable, main);
     task
 }
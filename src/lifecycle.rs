@@ -0,0 +1,80 @@
+//! The foreground and background states of the app.
+//!
+//! Mobile platforms penalize apps that wake the CPU while in the background,
+//! up to terminating them. The platform glue of the app reports its state
+//! with [`set_app_state`], and timers that opted in with
+//! [`Interval::pause_in_background`](crate::timer::Interval::pause_in_background)
+//! stop arming platform timers until the app is active again.
+//!
+//! The state is process-wide and starts as [`AppState::Active`]; nothing
+//! observes the platform on its own.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use native_executor::lifecycle::{self, AppState};
+//!
+//! // From the `applicationDidEnterBackground:` of the app delegate
+//! lifecycle::set_app_state(AppState::Background);
+//! assert_eq!(lifecycle::app_state(), AppState::Background);
+//!
+//! // From `applicationWillEnterForeground:`
+//! lifecycle::set_app_state(AppState::Active);
+//! ```
+
+use alloc::vec::Vec;
+use core::{
+    sync::atomic::{AtomicBool, Ordering},
+    task::Waker,
+};
+use std::sync::{Mutex, PoisonError};
+
+/// Whether the app is in the foreground, as reported with [`set_app_state`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum AppState {
+    /// The app is in the foreground.
+    #[default]
+    Active,
+    /// The app is in the background, where it should not wake the CPU.
+    Background,
+}
+
+static BACKGROUND: AtomicBool = AtomicBool::new(false);
+/// Woken when the app becomes active again.
+static RESUMED: Mutex<Vec<Waker>> = Mutex::new(Vec::new());
+
+/// Returns the state last reported with [`set_app_state`].
+#[must_use]
+pub fn app_state() -> AppState {
+    if BACKGROUND.load(Ordering::Acquire) {
+        AppState::Background
+    } else {
+        AppState::Active
+    }
+}
+
+/// Reports the state of the app, which defaults to [`AppState::Active`].
+///
+/// Entering [`AppState::Active`] resumes the timers paused in the background.
+pub fn set_app_state(state: AppState) {
+    let background = state == AppState::Background;
+    if BACKGROUND.swap(background, Ordering::AcqRel) && !background {
+        let wakers = core::mem::take(&mut *RESUMED.lock().unwrap_or_else(PoisonError::into_inner));
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+/// Returns `true` if the app is in the background, in which case `waker` is
+/// woken once it becomes active again.
+pub(crate) fn paused(waker: &Waker) -> bool {
+    if !BACKGROUND.load(Ordering::Acquire) {
+        return false;
+    }
+    let mut resumed = RESUMED.lock().unwrap_or_else(PoisonError::into_inner);
+    if !resumed.iter().any(|other| other.will_wake(waker)) {
+        resumed.push(waker.clone());
+    }
+    drop(resumed);
+    // The app may have become active before the waker was stored.
+    BACKGROUND.load(Ordering::Acquire)
+}
@@ -27,17 +27,90 @@
 //! });
 //!
 //! // Make async calls that return values
+//! # async {
 //! let value = mailbox.call(|map| {
 //!     map.get("key").copied().unwrap_or(0)
 //! }).await;
+//! # };
 //! ```
 
-use async_channel::{Sender, unbounded};
-use executor_core::LocalExecutor;
+use core::{
+    any::{Any, type_name},
+    cell::RefCell,
+    fmt,
+    future::{Future, poll_fn},
+    panic::{AssertUnwindSafe, Location},
+    pin::{Pin, pin},
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+use std::{
+    collections::HashMap,
+    panic::catch_unwind,
+    rc::Rc,
+    sync::{Arc, Mutex, PoisonError, mpsc},
+    task::Wake,
+    thread::{self, Thread},
+    time::Instant,
+};
 
-use crate::NativeExecutor;
+use async_channel::{Receiver, Sender, TrySendError, WeakSender, bounded, unbounded};
+use executor_core::{LocalExecutor, Task};
 
-type Job<T> = Box<dyn Send + FnOnce(&T)>;
+use crate::{
+    MainExecutor,
+    bus::Overflow,
+    cancel::{CancellationToken, Cancelled},
+    context,
+    timer::{self, Deadline, DeadlineExceeded},
+};
+
+type Job<T> = Box<dyn Send + FnOnce(&mut T)>;
+
+/// Consumes the value once the queue of a mailbox has been drained.
+type Finalizer<T> = Box<dyn FnOnce(T) -> Pin<Box<dyn Future<Output = ()>>>>;
+
+/// Observes the jobs a mailbox discards because its queue is full.
+type DropHook = Box<dyn Fn(&Dropped) + Send + Sync>;
+
+/// The value of a mailbox together with its queue, as seen from the owning
+/// thread.
+struct Slot<T> {
+    value: RefCell<T>,
+    receiver: Receiver<Job<T>>,
+}
+
+thread_local! {
+    /// Slots of the mailboxes whose background loop runs on this thread, keyed
+    /// by mailbox id.
+    static SLOTS: RefCell<HashMap<u64, Rc<dyn Any>>> = RefCell::new(HashMap::new());
+}
+
+/// Removes a slot from the owning thread's registry when its loop ends.
+struct SlotRegistration(u64);
+
+impl SlotRegistration {
+    fn new<T: 'static>(id: u64, slot: Rc<Slot<T>>) -> Self {
+        SLOTS.with(|slots| slots.borrow_mut().insert(id, slot));
+        Self(id)
+    }
+}
+
+impl Drop for SlotRegistration {
+    fn drop(&mut self) {
+        let _ = SLOTS.try_with(|slots| slots.borrow_mut().remove(&self.0));
+    }
+}
+
+/// Returns the slot of mailbox `id` if its loop runs on the current thread.
+fn local_slot<T: 'static>(id: u64) -> Option<Rc<Slot<T>>> {
+    SLOTS
+        .try_with(|slots| slots.borrow().get(&id).cloned())
+        .ok()
+        .flatten()
+        .and_then(|slot| slot.downcast().ok())
+}
 
 /// A mailbox for sending messages to a value owned by a background task.
 ///
@@ -58,9 +131,331 @@ type Job<T> = Box<dyn Send + FnOnce(&T)>;
 /// through an async message queue, providing lock-free concurrent access. When `T` is
 /// not `Send`, the value remains pinned to its original thread but can still be safely
 /// accessed from other threads through the mailbox.
-#[derive(Debug)]
+///
+/// # Ordering
+///
+/// Every operation enqueues exactly one job into a single FIFO queue, and jobs
+/// run one at a time in enqueue order. [`handle`](Self::handle),
+/// [`call`](Self::call) and [`call_blocking`](Self::call_blocking) all share
+/// this order, regardless of the thread they are called from: a job never
+/// runs before a job that was enqueued before it. When `call_blocking` runs
+/// inline on the owning thread, it first runs every job that is already
+/// queued.
+///
+/// ```rust
+/// # #[cfg(not(target_vendor = "apple"))] {
+/// use futures_lite::future::block_on;
+/// use native_executor::{Mailbox, polyfill, spawn_main};
+/// use std::{sync::Arc, thread};
+///
+/// thread::spawn(polyfill::start_main_executor);
+/// let mailbox = block_on(spawn_main(async { Arc::new(Mailbox::main(Vec::new())) }));
+///
+/// let producers: Vec<_> = (0..8)
+///     .map(|producer| {
+///         let mailbox = mailbox.clone();
+///         thread::spawn(move || {
+///             for step in 0..500 {
+///                 if step % 7 == 0 {
+///                     // Every earlier job of this thread has already run
+///                     let seen = mailbox.call_blocking(move |log: &mut Vec<(i32, i32)>| {
+///                         log.iter().filter(|(p, _)| *p == producer).count()
+///                     });
+///                     assert_eq!(seen, step as usize);
+///                 }
+///                 mailbox.handle(move |log| log.push((producer, step)));
+///             }
+///         })
+///     })
+///     .collect();
+/// for producer in producers {
+///     producer.join().unwrap();
+/// }
+///
+/// let log = mailbox.call_blocking(|log| log.clone());
+/// assert_eq!(log.len(), 8 * 500);
+/// for producer in 0..8 {
+///     let steps: Vec<_> = log.iter().filter(|(p, _)| *p == producer).map(|(_, s)| *s).collect();
+///     assert_eq!(steps, (0..500).collect::<Vec<_>>());
+/// }
+/// # }
+/// ```
+///
+/// # Debugging
+///
+/// The [`Debug`](fmt::Debug) output shows the state of the queue, which
+/// helps to find a stuck pipeline in logs:
+///
+/// ```rust
+/// # #[cfg(not(target_vendor = "apple"))] {
+/// use futures_lite::future::block_on;
+/// use native_executor::{Mailbox, MainExecutor, polyfill, spawn_main};
+/// use std::{sync::mpsc, thread};
+///
+/// thread::spawn(polyfill::start_main_executor);
+/// let mailbox = block_on(spawn_main(async {
+///     Mailbox::builder(0u32).name("renderer").capacity(8).spawn(MainExecutor)
+/// }));
+///
+/// // Stall the consumer with two jobs queued behind it
+/// let (resume, stalled) = mpsc::channel::<()>();
+/// let (started, running) = mpsc::channel();
+/// mailbox.handle(move |_| {
+///     started.send(()).unwrap();
+///     let _ = stalled.recv();
+/// });
+/// running.recv().unwrap();
+/// mailbox.handle(|count| *count += 1);
+/// mailbox.handle(|count| *count += 1);
+///
+/// let debug = format!("{mailbox:?}");
+/// assert!(debug.contains(r#"name: Some("renderer")"#), "{debug}");
+/// assert!(debug.contains("len: 2, capacity: Some(8), closed: false, alive: true"), "{debug}");
+/// drop(resume);
+/// # }
+/// ```
 pub struct Mailbox<T: 'static> {
     sender: Sender<Job<T>>,
+    id: u64,
+    name: Option<&'static str>,
+    /// Number of mutating jobs queued or run so far, bumped once each has
+    /// been handed to the queue. Shared with the [`WeakMailbox`]es.
+    version: Arc<AtomicU64>,
+    /// The most recent clone handed out by [`Mailbox::cached_snapshot`].
+    cache: Mutex<Option<Snapshot>>,
+    /// Never receives; fails once the background task, including the
+    /// finalizer, has ended.
+    ended: Receiver<()>,
+    /// Shared with the [`WeakMailbox`]es.
+    drops: Arc<Drops>,
+    /// Set once the background task has started on the main thread.
+    #[cfg(feature = "deadlock-detect")]
+    on_main: Arc<core::sync::atomic::AtomicBool>,
+}
+
+/// Configures a [`Mailbox`] before its background task is spawned.
+///
+/// Created by [`Mailbox::builder`].
+///
+/// # Examples
+///
+/// ```rust
+/// use native_executor::{Mailbox, MainExecutor};
+///
+/// let mailbox = Mailbox::builder(Vec::<u8>::new())
+///     .capacity(64)
+///     .on_close(|buffer| async move {
+///         // Flush `buffer` to its destination
+///     })
+///     .spawn(MainExecutor);
+/// ```
+pub struct MailboxBuilder<T: 'static> {
+    value: T,
+    name: Option<&'static str>,
+    capacity: Option<usize>,
+    overflow: Overflow,
+    on_drop: Option<DropHook>,
+    on_close: Option<Finalizer<T>>,
+}
+
+impl<T: 'static> fmt::Debug for MailboxBuilder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MailboxBuilder")
+            .field("name", &self.name)
+            .field("capacity", &self.capacity)
+            .field("overflow", &self.overflow)
+            .field("on_drop", &self.on_drop.is_some())
+            .field("on_close", &self.on_close.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: 'static> MailboxBuilder<T> {
+    /// Names the mailbox in its [`Debug`](fmt::Debug) output.
+    #[must_use]
+    pub const fn name(mut self, name: &'static str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Bounds the queue to `capacity` pending jobs, as in
+    /// [`Mailbox::bounded`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    #[must_use]
+    #[track_caller]
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        assert!(
+            capacity > 0,
+            "Mailbox<{}>: the capacity of a bounded mailbox must be non-zero",
+            type_name::<T>()
+        );
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Sets what [`Mailbox::handle`] does when the queue is full, which
+    /// defaults to [`Overflow::DropNewest`].
+    ///
+    /// Only a mailbox with a [`capacity`](Self::capacity) is ever full.
+    /// [`Mailbox::call`] always waits for room, and a [`Bus`](crate::bus::Bus)
+    /// applies its own overflow to the mailboxes that subscribe to it.
+    ///
+    /// # Examples
+    ///
+    /// Each overflow with a stalled consumer:
+    ///
+    /// ```rust
+    /// # #[cfg(not(target_vendor = "apple"))] {
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{
+    ///     Mailbox, MainExecutor,
+    ///     bus::Overflow,
+    ///     mailbox::{Dropped, MailboxError},
+    ///     polyfill, spawn_main,
+    /// };
+    /// use std::{sync::{Arc, Mutex, mpsc}, thread, time::Duration};
+    ///
+    /// thread::spawn(polyfill::start_main_executor);
+    ///
+    /// // Handles 1, 2 and 3 while the consumer of a queue of one is stalled,
+    /// // and returns what ran, along with the reported drops
+    /// fn overrun(overflow: Overflow) -> (Vec<u32>, Vec<Dropped>) {
+    ///     let reports = Arc::new(Mutex::new(Vec::new()));
+    ///     let log = reports.clone();
+    ///     let mailbox = block_on(spawn_main(async move {
+    ///         Mailbox::builder(Vec::new())
+    ///             .name("frames")
+    ///             .capacity(1)
+    ///             .overflow(overflow)
+    ///             .on_drop(move |dropped| log.lock().unwrap().push(*dropped))
+    ///             .spawn(MainExecutor)
+    ///     }));
+    ///     let (resume, stalled) = mpsc::channel::<()>();
+    ///     let (started, running) = mpsc::channel();
+    ///     mailbox.handle(move |_| {
+    ///         started.send(()).unwrap();
+    ///         let _ = stalled.recv();
+    ///     });
+    ///     running.recv().unwrap();
+    ///
+    ///     let mailbox = Arc::new(mailbox);
+    ///     let producer = mailbox.clone();
+    ///     let producer = thread::spawn(move || (1..=3).for_each(|n| producer.handle(move |ran| ran.push(n))));
+    ///     thread::sleep(Duration::from_millis(20));
+    ///     drop(resume);
+    ///     producer.join().unwrap();
+    ///
+    ///     let ran = mailbox.call_blocking(|ran| ran.clone());
+    ///     assert_eq!(mailbox.dropped(), reports.lock().unwrap().len() as u64);
+    ///     (ran, reports.lock().unwrap().clone())
+    /// }
+    ///
+    /// // Blocking waits for the consumer
+    /// assert_eq!(overrun(Overflow::Block), (vec![1, 2, 3], vec![]));
+    ///
+    /// // The others lose two updates, reported along with their producer
+    /// let (ran, drops) = overrun(Overflow::DropNewest);
+    /// assert_eq!(ran, [1]);
+    /// let (ran, drops_oldest) = overrun(Overflow::DropOldest);
+    /// assert_eq!(ran, [3]);
+    /// for (drops, overflow) in [(drops, Overflow::DropNewest), (drops_oldest, Overflow::DropOldest)] {
+    ///     assert_eq!(drops.iter().map(|dropped| dropped.total).collect::<Vec<_>>(), [1, 2]);
+    ///     for dropped in drops {
+    ///         assert_eq!((dropped.mailbox, dropped.overflow), (Some("frames"), overflow));
+    ///         assert_eq!(dropped.caller.file(), file!());
+    ///     }
+    /// }
+    ///
+    /// // Failing also drops, and makes blocking producers fail
+    /// assert_eq!(overrun(Overflow::Fail).0, [1]);
+    /// let mailbox = block_on(spawn_main(async {
+    ///     Mailbox::builder(()).capacity(1).overflow(Overflow::Fail).spawn(MainExecutor)
+    /// }));
+    /// let (resume, stalled) = mpsc::channel::<()>();
+    /// let (started, running) = mpsc::channel();
+    /// mailbox.handle(move |()| {
+    ///     started.send(()).unwrap();
+    ///     let _ = stalled.recv();
+    /// });
+    /// running.recv().unwrap();
+    /// assert_eq!(mailbox.blocking_handle(|()| {}), Ok(()));
+    /// assert_eq!(mailbox.blocking_handle(|()| {}), Err(MailboxError::Full));
+    /// assert_eq!(mailbox.dropped(), 1);
+    /// drop(resume);
+    /// # }
+    /// ```
+    #[must_use]
+    pub const fn overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Sets a callback that is told about every job the mailbox discards
+    /// because its queue is full, be it under its own
+    /// [`overflow`](Self::overflow) or that of a [`Bus`](crate::bus::Bus).
+    ///
+    /// The callback runs on the producing thread, with the lock of no queue
+    /// held, and receives a description of the drop rather than the job, so
+    /// it can log which producer overruns the mailbox.
+    #[must_use]
+    pub fn on_drop(mut self, on_drop: impl Fn(&Dropped) + Send + Sync + 'static) -> Self {
+        self.on_drop = Some(Box::new(on_drop));
+        self
+    }
+
+    /// Sets a finalizer that receives the value once the mailbox has closed.
+    ///
+    /// The background task runs the finalizer after the last handle was
+    /// dropped, or [`Mailbox::close`] was called, and every queued job has
+    /// run. It runs on the owning thread and may await, for example to send a
+    /// goodbye message over a connection. [`Mailbox::close`] and
+    /// [`Mailbox::flush`] wait for it to finish.
+    ///
+    /// If the finalizer panics, the panic is reported to the panic hook and
+    /// the value is dropped.
+    #[must_use]
+    pub fn on_close<F, Fut>(mut self, finalizer: F) -> Self
+    where
+        F: FnOnce(T) -> Fut + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.on_close = Some(Box::new(move |value| Box::pin(finalizer(value))));
+        self
+    }
+
+    /// Spawns the background task on `executor` and returns the mailbox.
+    pub fn spawn<E: LocalExecutor>(self, executor: E) -> Mailbox<T> {
+        let channel = self.capacity.map_or_else(unbounded, bounded);
+        let drops = Drops {
+            name: self.name,
+            overflow: self.overflow,
+            count: AtomicU64::new(0),
+            on_drop: self.on_drop,
+        };
+        let mut mailbox =
+            Mailbox::with_channel(executor, self.value, channel, drops, self.on_close);
+        mailbox.name = self.name;
+        mailbox
+    }
+}
+
+/// A type-erased clone of a mailbox value.
+struct Snapshot {
+    value: Box<dyn Any + Send>,
+    version: u64,
+    taken: Instant,
+}
+
+impl fmt::Debug for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Snapshot")
+            .field("version", &self.version)
+            .field("taken", &self.taken)
+            .finish_non_exhaustive()
+    }
 }
 
 impl<T: 'static> Mailbox<T> {
@@ -69,6 +464,10 @@ impl<T: 'static> Mailbox<T> {
     /// The value will be moved to a background task that processes incoming
     /// messages. The executor is consumed to spawn the background task.
     ///
+    /// The background task is detached and keeps running until the last
+    /// handle to the mailbox is dropped. It then processes the messages that
+    /// are still queued, exits, and drops the value on its owning thread.
+    ///
     /// # Parameters
     ///
     /// * `executor` - The executor to spawn the background task on
@@ -82,16 +481,160 @@ impl<T: 'static> Mailbox<T> {
     ///
     /// let mailbox = Mailbox::new(MainExecutor, HashMap::<String, i32>::new());
     /// ```
-    #[allow(clippy::needless_pass_by_value)]
+    ///
+    /// Dropping the mailbox ends the background task and releases the value:
+    ///
+    /// ```rust
+    /// # #[cfg(not(target_vendor = "apple"))] {
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{Mailbox, polyfill, spawn_main};
+    /// use std::{sync::atomic::{AtomicUsize, Ordering}, thread, time::Duration};
+    ///
+    /// static DROPPED: AtomicUsize = AtomicUsize::new(0);
+    ///
+    /// struct Payload(u32);
+    ///
+    /// impl Drop for Payload {
+    ///     fn drop(&mut self) {
+    ///         DROPPED.fetch_add(1, Ordering::SeqCst);
+    ///     }
+    /// }
+    ///
+    /// thread::spawn(polyfill::start_main_executor);
+    ///
+    /// // A mailbox is usable right after creation
+    /// let mailbox = block_on(spawn_main(async { Mailbox::main(Payload(1)) }));
+    /// mailbox.handle(|payload| payload.0 += 1);
+    /// assert_eq!(block_on(mailbox.call(|payload| payload.0)), 2);
+    ///
+    /// drop(mailbox);
+    /// while DROPPED.load(Ordering::SeqCst) == 0 {
+    ///     thread::sleep(Duration::from_millis(1));
+    /// }
+    /// assert_eq!(DROPPED.load(Ordering::SeqCst), 1);
+    /// # }
+    /// ```
     pub fn new<E: LocalExecutor>(executor: E, value: T) -> Self {
-        let (sender, receiver) = unbounded::<Box<dyn Send + FnOnce(&T)>>();
+        Self::builder(value).spawn(executor)
+    }
 
-        let _fut = executor.spawn_local(async move {
-            while let Ok(update) = receiver.recv().await {
-                update(&value);
-            }
-        });
-        Self { sender }
+    /// Returns a builder for a mailbox owning `value`, for mailboxes that
+    /// need a finalizer or other settings.
+    pub const fn builder(value: T) -> MailboxBuilder<T> {
+        MailboxBuilder {
+            value,
+            name: None,
+            capacity: None,
+            overflow: Overflow::DropNewest,
+            on_drop: None,
+            on_close: None,
+        }
+    }
+
+    /// Creates a mailbox whose queue holds at most `capacity` pending jobs.
+    ///
+    /// Producers apply backpressure with [`call`](Self::call) and
+    /// [`blocking_handle`](Self::blocking_handle), which wait for room in the
+    /// queue, while [`handle`](Self::handle) drops the update when the queue is
+    /// full.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use native_executor::{Mailbox, MainExecutor};
+    ///
+    /// let mailbox = Mailbox::bounded(MainExecutor, Vec::<u32>::new(), 8);
+    /// ```
+    ///
+    /// A zero capacity is reported at the call site:
+    ///
+    /// ```rust
+    /// use native_executor::{Mailbox, MainExecutor};
+    /// use std::{panic, sync::Mutex};
+    ///
+    /// static LOCATION: Mutex<Option<(String, u32)>> = Mutex::new(None);
+    /// panic::set_hook(Box::new(|info| {
+    ///     let location = info.location().unwrap();
+    ///     *LOCATION.lock().unwrap() = Some((location.file().to_owned(), location.line()));
+    /// }));
+    ///
+    /// let (result, line) = (panic::catch_unwind(|| Mailbox::bounded(MainExecutor, 0u8, 0)), line!());
+    /// let message = result.unwrap_err().downcast::<String>().unwrap();
+    /// assert_eq!(*message, "Mailbox<u8>: the capacity of a bounded mailbox must be non-zero");
+    /// assert_eq!(LOCATION.lock().unwrap().take(), Some((file!().to_owned(), line)));
+    /// ```
+    #[track_caller]
+    pub fn bounded<E: LocalExecutor>(executor: E, value: T, capacity: usize) -> Self {
+        Self::builder(value).capacity(capacity).spawn(executor)
+    }
+
+    #[allow(clippy::needless_pass_by_value)]
+    fn with_channel<E: LocalExecutor>(
+        executor: E,
+        value: T,
+        (sender, receiver): (Sender<Job<T>>, Receiver<Job<T>>),
+        drops: Drops,
+        on_close: Option<Finalizer<T>>,
+    ) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let (end, ended) = bounded(1);
+        #[cfg(feature = "deadlock-detect")]
+        let on_main = Arc::new(core::sync::atomic::AtomicBool::new(false));
+        #[cfg(feature = "deadlock-detect")]
+        let started_on_main = on_main.clone();
+        #[cfg(feature = "leak-check")]
+        let live = crate::diagnostics::Live::new(crate::diagnostics::Class::Mailbox);
+
+        // The loop ends once the queue is closed or every sender, and thus
+        // every `Mailbox`, is gone.
+        executor
+            .spawn_local(async move {
+                let _end = end;
+                #[cfg(feature = "leak-check")]
+                let _live = live;
+                #[cfg(feature = "deadlock-detect")]
+                started_on_main.store(crate::is_main_thread(), Ordering::Relaxed);
+                let slot = Rc::new(Slot {
+                    value: RefCell::new(value),
+                    receiver,
+                });
+                let registration = SlotRegistration::new(id, slot.clone());
+                while let Ok(update) = slot.receiver.recv().await {
+                    update(&mut slot.value.borrow_mut());
+                }
+                drop(registration);
+
+                // The registry held the only other reference to the slot.
+                if let Some(finalizer) = on_close
+                    && let Some(slot) = Rc::into_inner(slot)
+                {
+                    let mut finalizing = finalizer(slot.value.into_inner());
+                    // A panic drops the value along with the future; the panic
+                    // hook has already reported it.
+                    poll_fn(|cx| {
+                        catch_unwind(AssertUnwindSafe(|| finalizing.as_mut().poll(cx)))
+                            .unwrap_or(Poll::Ready(()))
+                    })
+                    .await;
+                }
+            })
+            .detach();
+        Self {
+            sender,
+            id,
+            name: None,
+            version: Arc::new(AtomicU64::new(0)),
+            cache: Mutex::new(None),
+            ended,
+            drops: Arc::new(drops),
+            #[cfg(feature = "deadlock-detect")]
+            on_main,
+        }
     }
 
     /// Creates a new mailbox with the given value on the main executor.
@@ -112,21 +655,23 @@ impl<T: 'static> Mailbox<T> {
     /// let mailbox = Mailbox::main(HashMap::<String, i32>::new());
     /// ```
     pub fn main(value: T) -> Self {
-        Self::new(NativeExecutor, value)
+        Self::new(MainExecutor, value)
     }
 
     /// Sends a non-blocking update to the mailbox value.
     ///
-    /// The provided closure will be called with a reference to the value
+    /// The provided closure will be called with a mutable reference to the value
     /// in the background task. This operation is non-blocking and will
     /// not wait for the update to be processed.
     ///
-    /// If the background task has been dropped or the channel is full,
-    /// the update may be silently discarded.
+    /// If the background task has ended, the update is silently discarded.
+    /// If the queue of a bounded mailbox is full, the
+    /// [overflow](MailboxBuilder::overflow) of the mailbox applies, which
+    /// discards the update by default.
     ///
     /// # Parameters
     ///
-    /// * `update` - A closure that will be called with a reference to the value
+    /// * `update` - A closure that will be called with a mutable reference to the value
     ///
     /// # Examples
     ///
@@ -141,19 +686,30 @@ impl<T: 'static> Mailbox<T> {
     ///     map.insert("key".to_string(), 42);
     /// });
     /// ```
-    pub fn handle(&self, update: impl FnOnce(&T) + Send + 'static) {
-        let _ = self.sender.try_send(Box::new(update));
+    #[track_caller]
+    pub fn handle(&self, update: impl FnOnce(&mut T) + Send + 'static) {
+        let sent = send(
+            &self.sender,
+            self.id,
+            Box::new(update),
+            self.drops.overflow,
+            &self.drops,
+            Location::caller(),
+        );
+        if sent {
+            self.mutated();
+        }
     }
 
     /// Makes an asynchronous call to the mailbox value and returns the result.
     ///
-    /// The provided closure will be called with a reference to the value
+    /// The provided closure will be called with a mutable reference to the value
     /// in the background task, and the result will be returned to the caller.
     /// This operation blocks until the call is processed and the result is available.
     ///
     /// # Parameters
     ///
-    /// * `f` - A closure that will be called with a reference to the value and returns a result
+    /// * `f` - A closure that will be called with a mutable reference to the value and returns a result
     ///
     /// # Returns
     ///
@@ -179,15 +735,1201 @@ impl<T: 'static> Mailbox<T> {
     /// }).await;
     /// # }
     /// ```
-    pub async fn call<R>(&self, f: impl FnOnce(&T) -> R + Send + 'static) -> R
+    #[track_caller]
+    pub fn call<R>(&self, f: impl FnOnce(&mut T) -> R + Send + 'static) -> impl Future<Output = R>
+    where
+        R: Send + 'static,
+    {
+        let caller = Location::caller();
+        async move {
+            self.try_call(f)
+                .await
+                .unwrap_or_else(|| self.unanswered(caller))
+        }
+    }
+
+    /// Like [`call`](Self::call), but gives up once `deadline` passes.
+    ///
+    /// Without a deadline, the ambient deadline of
+    /// [`with_deadline`](crate::timer::with_deadline) applies, if any. A call
+    /// that gives up while still queued is skipped when its turn comes, so
+    /// `f` never runs past the deadline.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DeadlineExceeded`] if the deadline passed before `f` ran.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the background task ends before answering.
+    #[track_caller]
+    pub fn call_until<R>(
+        &self,
+        deadline: Option<Deadline>,
+        f: impl FnOnce(&mut T) -> R + Send + 'static,
+    ) -> impl Future<Output = Result<R, DeadlineExceeded>>
+    where
+        R: Send + 'static,
+    {
+        let caller = Location::caller();
+        async move {
+            let Some(deadline) = deadline.or_else(timer::current_deadline) else {
+                return Ok(self
+                    .try_call(f)
+                    .await
+                    .unwrap_or_else(|| self.unanswered(caller)));
+            };
+            let expiry = deadline.clone();
+            let call = self.try_call(move |value| (!expiry.expired()).then(|| f(value)));
+            timer::timeout_at(deadline, call)
+                .await?
+                .unwrap_or_else(|| self.unanswered(caller))
+                .ok_or(DeadlineExceeded)
+        }
+    }
+
+    /// Like [`call`](Self::call), but lets `f` stop early once `token` is
+    /// cancelled.
+    ///
+    /// `f` receives the token and checks it at points where stopping is safe,
+    /// typically with [`CancellationToken::check`] and `?`. A job that stops
+    /// early is an ordinary return, not a panic, so the mailbox keeps serving
+    /// later calls. A job whose token is cancelled while it is still queued is
+    /// skipped when its turn comes. The call completes once the job has
+    /// returned or been skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Cancelled`] if the token was cancelled before `f` ran, or if
+    /// `f` returned it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the background task ends before answering.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(not(target_vendor = "apple"))] {
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{
+    ///     Mailbox,
+    ///     cancel::{CancellationToken, Cancelled},
+    ///     polyfill, spawn_main,
+    /// };
+    /// use std::{hint::black_box, sync::mpsc, thread, time::Duration};
+    ///
+    /// thread::spawn(polyfill::start_main_executor);
+    /// let mailbox = block_on(spawn_main(async { Mailbox::main(0_u32) }));
+    ///
+    /// let token = CancellationToken::new();
+    /// let (started, has_started) = mpsc::channel();
+    /// let walk = mailbox.call_cancellable(&token, move |progress, token| {
+    ///     started.send(()).unwrap();
+    ///     for i in 0..1_000_000 {
+    ///         *progress = i;
+    ///         token.check()?;
+    ///         black_box(thread::yield_now());
+    ///     }
+    ///     Ok("done")
+    /// });
+    /// let walk = thread::scope(|scope| {
+    ///     let walk = scope.spawn(|| block_on(walk));
+    ///     has_started.recv().unwrap();
+    ///     thread::sleep(Duration::from_millis(10));
+    ///     token.cancel();
+    ///     walk.join().unwrap()
+    /// });
+    /// assert_eq!(walk, Err(Cancelled));
+    ///
+    /// // The mailbox is still healthy, and kept the progress of the walk
+    /// let progress = block_on(mailbox.call(|progress| *progress));
+    /// assert!(progress > 0 && progress < 999_999);
+    ///
+    /// // A cancelled token skips the job altogether
+    /// let skipped = mailbox.call_cancellable(&token, |progress, _| Ok(*progress = 0));
+    /// assert_eq!(block_on(skipped), Err(Cancelled));
+    /// assert_eq!(block_on(mailbox.call(|progress| *progress)), progress);
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn call_cancellable<R, F>(
+        &self,
+        token: &CancellationToken,
+        f: F,
+    ) -> impl Future<Output = Result<R, Cancelled>> + use<'_, T, R, F>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut T, &CancellationToken) -> Result<R, Cancelled> + Send + 'static,
+    {
+        let caller = Location::caller();
+        let token = token.clone();
+        async move {
+            self.try_call_cancellable(token, f)
+                .await
+                .unwrap_or_else(|| self.unanswered(caller))
+        }
+    }
+
+    /// Like [`call_until`](Self::call_until), but also hands `f` a token that
+    /// is cancelled once `deadline` passes, so a job that has started can
+    /// stop early too.
+    ///
+    /// Unlike `call_until`, the call waits for a job that has started, which
+    /// returns soon after the deadline if it checks its token often enough.
+    /// See [`call_cancellable`](Self::call_cancellable) for how the token is
+    /// used.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DeadlineExceeded`] if the deadline passed before `f` ran, or
+    /// if `f` returned [`Cancelled`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the background task ends before answering.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(not(target_vendor = "apple"))] {
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{Mailbox, polyfill, spawn_main, timer::{Deadline, DeadlineExceeded}};
+    /// use std::{thread, time::Duration};
+    ///
+    /// thread::spawn(polyfill::start_main_executor);
+    /// let mailbox = block_on(spawn_main(async { Mailbox::main(0_u32) }));
+    ///
+    /// let deadline = Deadline::after(Duration::from_millis(10));
+    /// let walk = mailbox.call_until_cooperative(Some(deadline), |progress, token| {
+    ///     for i in 0..1_000_000 {
+    ///         *progress = i;
+    ///         token.check()?;
+    ///         thread::yield_now();
+    ///     }
+    ///     Ok(())
+    /// });
+    /// assert_eq!(block_on(walk), Err(DeadlineExceeded));
+    /// assert!(block_on(mailbox.call(|progress| *progress)) < 999_999);
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn call_until_cooperative<R>(
+        &self,
+        deadline: Option<Deadline>,
+        f: impl FnOnce(&mut T, &CancellationToken) -> Result<R, Cancelled> + Send + 'static,
+    ) -> impl Future<Output = Result<R, DeadlineExceeded>>
+    where
+        R: Send + 'static,
+    {
+        let caller = Location::caller();
+        async move {
+            let token = deadline
+                .or_else(timer::current_deadline)
+                .and_then(|deadline| deadline.instant())
+                .map_or_else(CancellationToken::new, CancellationToken::expiring);
+            self.try_call_cancellable(token, f)
+                .await
+                .unwrap_or_else(|| self.unanswered(caller))
+                .map_err(|Cancelled| DeadlineExceeded)
+        }
+    }
+
+    /// Runs `f` with `token` unless the token is cancelled by the time the
+    /// job is dequeued, returning `None` if the background task ends before
+    /// answering.
+    async fn try_call_cancellable<R>(
+        &self,
+        token: CancellationToken,
+        f: impl FnOnce(&mut T, &CancellationToken) -> Result<R, Cancelled> + Send + 'static,
+    ) -> Option<Result<R, Cancelled>>
+    where
+        R: Send + 'static,
+    {
+        self.try_call(move |value| {
+            token.check()?;
+            f(value, &token)
+        })
+        .await
+    }
+
+    /// Like [`call`](Self::call), but returns `None` instead of panicking if
+    /// the background task ends before answering.
+    pub(crate) async fn try_call<R>(
+        &self,
+        f: impl FnOnce(&mut T) -> R + Send + 'static,
+    ) -> Option<R>
     where
         R: Send + 'static,
     {
         let (s, r) = async_channel::bounded(1);
-        self.handle(move |v| {
-            let res = f(v);
-            let _ = s.try_send(res);
+        if self
+            .sender
+            .send(Box::new(move |v: &mut T| {
+                let res = f(v);
+                let _ = s.try_send(res);
+            }))
+            .await
+            .is_ok()
+        {
+            self.mutated();
+        }
+        r.recv().await.ok()
+    }
+
+    /// Applies `apply` to the mailbox value for each of `items`, yielding to
+    /// the other queued jobs whenever `deadline` has expired.
+    ///
+    /// Without a deadline, all items are applied in a single job. With one,
+    /// the job stops after the first item that finishes past the deadline and
+    /// the remaining items are queued again behind the jobs that arrived in
+    /// the meantime, so a long batch cannot stall the owning thread. Every
+    /// later job applies at least one item, which guarantees progress once the
+    /// deadline has passed. The future completes when all items have been
+    /// applied; dropping it stops the transaction after the current job.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(not(target_vendor = "apple"))] {
+    /// use futures_lite::future::{block_on, zip};
+    /// use native_executor::{Mailbox, polyfill, spawn_main, timer::Deadline};
+    /// use std::{thread, time::Duration};
+    ///
+    /// thread::spawn(polyfill::start_main_executor);
+    /// let mailbox = block_on(spawn_main(async { Mailbox::main(Vec::new()) }));
+    ///
+    /// // An expired deadline lets other jobs in after every item
+    /// let expired = Deadline::after(Duration::ZERO);
+    /// block_on(zip(
+    ///     mailbox.transaction(0..4, Some(expired), |items, item| items.push(item)),
+    ///     mailbox.call(|items| items.push(u32::MAX)),
+    /// ));
+    /// assert_eq!(block_on(mailbox.snapshot()), [0, u32::MAX, 1, 2, 3]);
+    ///
+    /// // A deadline far enough away keeps the batch together
+    /// let later = Deadline::after(Duration::from_secs(60));
+    /// block_on(zip(
+    ///     mailbox.transaction(4..8, Some(later), |items, item| items.push(item)),
+    ///     mailbox.call(|items| items.clear()),
+    /// ));
+    /// assert!(block_on(mailbox.snapshot()).is_empty());
+    /// # }
+    /// ```
+    pub async fn transaction<I, F>(&self, items: I, deadline: Option<Deadline>, apply: F)
+    where
+        I: IntoIterator,
+        I::IntoIter: Send + 'static,
+        F: FnMut(&mut T, I::Item) + Send + 'static,
+    {
+        let mut pending = Some((items.into_iter(), apply));
+        while let Some((mut items, mut apply)) = pending.take() {
+            let deadline = deadline.clone();
+            pending = self
+                .call(move |value| {
+                    while let Some(item) = items.next() {
+                        apply(value, item);
+                        if deadline.as_ref().is_some_and(Deadline::expired) {
+                            return Some((items, apply));
+                        }
+                    }
+                    None
+                })
+                .await;
+        }
+    }
+
+    /// Runs `step` on the mailbox value and `state` until it returns
+    /// [`StepResult::Done`], in jobs that each take about `budget`, and
+    /// returns the final state.
+    ///
+    /// This is one unit of work too large for a single job, written as a
+    /// resumable step. Each job calls `step` until it is done or the
+    /// [`Budget`] is spent; the rest of the work is then queued again, with
+    /// `state`, behind the jobs that arrived in the meantime, so other
+    /// updates are not held up for long. Every job runs at least one step,
+    /// which guarantees progress however small the budget. Dropping the
+    /// returned future stops the work after the current job.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the background task ends before the work is done.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(not(target_vendor = "apple"))] {
+    /// use futures_lite::future::{block_on, zip};
+    /// use native_executor::{Mailbox, mailbox::StepResult, polyfill, spawn_main};
+    /// use std::{thread, time::Duration};
+    ///
+    /// thread::spawn(polyfill::start_main_executor);
+    /// let mailbox = block_on(spawn_main(async { Mailbox::main(Vec::new()) }));
+    ///
+    /// // Sums 10 000 numbers in slices of 20 µs, logging every step
+    /// let sum = mailbox.chunked((0u64, 0u64), Duration::from_micros(20), |log, (next, sum), _| {
+    ///     log.push(*next);
+    ///     *sum += *next;
+    ///     *next += 1;
+    ///     if *next == 10_000 { StepResult::Done } else { StepResult::MoreWork }
+    /// });
+    /// let other = async {
+    ///     for _ in 0..3 {
+    ///         mailbox.call(|log| log.push(u64::MAX)).await;
+    ///     }
+    /// };
+    /// let ((_, sum), ()) = block_on(zip(sum, other));
+    /// assert_eq!(sum, (0..10_000).sum::<u64>());
+    ///
+    /// // The other calls ran in between slices, and the steps in order
+    /// let log = block_on(mailbox.snapshot());
+    /// assert!(log[0] != u64::MAX && log.last() != Some(&u64::MAX));
+    /// let steps: Vec<_> = log.into_iter().filter(|&entry| entry != u64::MAX).collect();
+    /// assert_eq!(steps, (0..10_000).collect::<Vec<_>>());
+    /// # }
+    /// ```
+    pub async fn chunked<S, F>(&self, state: S, budget: Duration, step: F) -> S
+    where
+        S: Send + 'static,
+        F: FnMut(&mut T, &mut S, &Budget) -> StepResult + Send + 'static,
+    {
+        let mut pending = (state, step);
+        loop {
+            let (state, mut step) = pending;
+            let (state, done, step) = self
+                .call(move |value| {
+                    let mut state = state;
+                    let budget = Budget::new(budget);
+                    loop {
+                        match step(value, &mut state, &budget) {
+                            StepResult::Done => return (state, true, step),
+                            StepResult::MoreWork if budget.is_exhausted() => {
+                                return (state, false, step);
+                            }
+                            StepResult::MoreWork => {}
+                        }
+                    }
+                })
+                .await;
+            if done {
+                return state;
+            }
+            pending = (state, step);
+        }
+    }
+
+    /// Calls `f` with a shared reference to the mailbox value and returns the
+    /// result.
+    ///
+    /// This is the read path of a mailbox: unlike [`call`](Self::call), `f`
+    /// cannot modify the value, so it does not invalidate
+    /// [`cached_snapshot`](Self::cached_snapshot).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the background task has been dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use native_executor::Mailbox;
+    ///
+    /// # async fn example() {
+    /// let mailbox = Mailbox::main(vec![1, 2, 3]);
+    /// let len = mailbox.snapshot_with(|items| items.len()).await;
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn snapshot_with<R>(
+        &self,
+        f: impl FnOnce(&T) -> R + Send + 'static,
+    ) -> impl Future<Output = R>
+    where
+        R: Send + 'static,
+    {
+        let caller = Location::caller();
+        async move {
+            let (s, r) = async_channel::bounded(1);
+            let _ = self
+                .sender
+                .send(Box::new(move |v: &mut T| {
+                    let _ = s.try_send(f(v));
+                }))
+                .await;
+            r.recv().await.unwrap_or_else(|_| self.unanswered(caller))
+        }
+    }
+
+    /// Returns a clone of the mailbox value.
+    ///
+    /// The clone reflects every update queued before the call.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the background task has been dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use native_executor::Mailbox;
+    ///
+    /// # async fn example() {
+    /// let mailbox = Mailbox::main(vec![1, 2, 3]);
+    /// mailbox.handle(|items| items.push(4));
+    /// assert_eq!(mailbox.snapshot().await, [1, 2, 3, 4]);
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn snapshot(&self) -> impl Future<Output = T>
+    where
+        T: Clone + Send,
+    {
+        self.snapshot_with(T::clone)
+    }
+
+    /// Returns a clone of the mailbox value, reusing the clone from a previous
+    /// call if it is at most `max_age` old and no update was queued since.
+    ///
+    /// Serving from the cache skips the round-trip through the queue, which
+    /// suits render loops reading state far more often than it changes. Any
+    /// [`handle`](Self::handle), [`call`](Self::call) or other mutating call
+    /// that has returned before this one invalidates the cache.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the background task has been dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(not(target_vendor = "apple"))] {
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{Mailbox, polyfill, spawn_main};
+    /// use std::{sync::atomic::{AtomicUsize, Ordering}, thread, time::Duration};
+    ///
+    /// // Counts its clones, which makes cache hits observable
+    /// static CLONES: AtomicUsize = AtomicUsize::new(0);
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct State(u32);
+    ///
+    /// impl Clone for State {
+    ///     fn clone(&self) -> Self {
+    ///         CLONES.fetch_add(1, Ordering::SeqCst);
+    ///         Self(self.0)
+    ///     }
+    /// }
+    ///
+    /// thread::spawn(polyfill::start_main_executor);
+    /// let mailbox = block_on(spawn_main(async { Mailbox::main(State(0)) }));
+    /// let max_age = Duration::from_secs(60);
+    /// let clones = || CLONES.load(Ordering::SeqCst);
+    ///
+    /// block_on(async {
+    ///     assert_eq!(mailbox.cached_snapshot(max_age).await, State(0));
+    ///
+    ///     // A hit only clones the cached value
+    ///     let before = clones();
+    ///     assert_eq!(mailbox.cached_snapshot(max_age).await, State(0));
+    ///     assert_eq!(clones(), before + 1);
+    ///
+    ///     // A queued update invalidates the cache even before it has run
+    ///     mailbox.handle(|state| state.0 += 1);
+    ///     assert_eq!(mailbox.cached_snapshot(max_age).await, State(1));
+    ///
+    ///     // Reads leave the cache intact
+    ///     mailbox.snapshot_with(|state| state.0).await;
+    ///     let before = clones();
+    ///     assert_eq!(mailbox.cached_snapshot(max_age).await, State(1));
+    ///     assert_eq!(clones(), before + 1);
+    ///
+    ///     // Entries older than `max_age` are refreshed
+    ///     thread::sleep(Duration::from_millis(5));
+    ///     let before = clones();
+    ///     mailbox.cached_snapshot(Duration::from_millis(1)).await;
+    ///     assert!(clones() > before + 1);
+    /// });
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn cached_snapshot(&self, max_age: Duration) -> impl Future<Output = T>
+    where
+        T: Clone + Send,
+    {
+        let snapshot = self.snapshot();
+        async move { self.cached_snapshot_from(snapshot, max_age).await }
+    }
+
+    async fn cached_snapshot_from(&self, snapshot: impl Future<Output = T>, max_age: Duration) -> T
+    where
+        T: Clone + Send,
+    {
+        let version = self.version.load(Ordering::Acquire);
+        {
+            let cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Some(value) = cache
+                .as_ref()
+                .filter(|cached| cached.version == version && cached.taken.elapsed() <= max_age)
+                .and_then(|cached| cached.value.downcast_ref::<T>())
+            {
+                return value.clone();
+            }
+        }
+
+        // Every update that bumped `version` before this point was queued
+        // ahead of the snapshot, so the clone reflects at least `version`.
+        let value = snapshot.await;
+        *self.cache.lock().unwrap_or_else(PoisonError::into_inner) = Some(Snapshot {
+            value: Box::new(value.clone()),
+            version,
+            taken: Instant::now(),
         });
-        r.recv().await.expect("Mailbox call failed")
+        value
+    }
+
+    /// Reports a call made at `caller` whose job was dropped unanswered, since
+    /// the background task has ended.
+    #[cold]
+    fn unanswered(&self, caller: &Location<'_>) -> ! {
+        panic!(
+            "Mailbox<{}> #{}: the background task ended before answering the call made at \
+             {caller}",
+            type_name::<T>(),
+            self.id
+        )
+    }
+
+    /// Closes the queue and waits until the background task has ended.
+    ///
+    /// Jobs queued before the call still run, followed by the
+    /// [finalizer](MailboxBuilder::on_close), if any. Afterwards the
+    /// mailbox rejects new jobs: [`handle`](Self::handle) drops them and
+    /// [`call`](Self::call) panics. Closing affects every handle to the
+    /// mailbox.
+    ///
+    /// Awaiting `close` inside a task on the owning thread is fine, but
+    /// blocking that thread on it deadlocks.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(not(target_vendor = "apple"))] {
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{Mailbox, MainExecutor, polyfill, spawn_main, timer::Timer};
+    /// use std::{
+    ///     sync::{Arc, Mutex, atomic::{AtomicUsize, Ordering}},
+    ///     thread,
+    ///     time::Duration,
+    /// };
+    ///
+    /// thread::spawn(polyfill::start_main_executor);
+    ///
+    /// // An asynchronous finalizer that flushes the value
+    /// let flushed = Arc::new(Mutex::new(Vec::new()));
+    /// let sink = flushed.clone();
+    /// let mailbox = block_on(spawn_main(async move {
+    ///     Mailbox::builder(Vec::new())
+    ///         .on_close(move |pending: Vec<u32>| async move {
+    ///             Timer::after(Duration::from_millis(10)).await;
+    ///             sink.lock().unwrap().extend(pending);
+    ///         })
+    ///         .spawn(MainExecutor)
+    /// }));
+    /// mailbox.handle(|pending| pending.push(1));
+    /// mailbox.handle(|pending| pending.push(2));
+    /// block_on(mailbox.close());
+    /// assert_eq!(*flushed.lock().unwrap(), [1, 2]);
+    ///
+    /// // A panicking finalizer still drops the value
+    /// static DROPPED: AtomicUsize = AtomicUsize::new(0);
+    /// struct Session;
+    /// impl Drop for Session {
+    ///     fn drop(&mut self) {
+    ///         DROPPED.fetch_add(1, Ordering::SeqCst);
+    ///     }
+    /// }
+    ///
+    /// let mailbox = block_on(spawn_main(async {
+    ///     Mailbox::builder(Session)
+    ///         .on_close(|_session| async { panic!("goodbye failed") })
+    ///         .spawn(MainExecutor)
+    /// }));
+    /// block_on(mailbox.flush());
+    /// assert_eq!(DROPPED.load(Ordering::SeqCst), 0);
+    /// block_on(mailbox.close());
+    /// assert_eq!(DROPPED.load(Ordering::SeqCst), 1);
+    /// # }
+    /// ```
+    pub async fn close(&self) {
+        self.sender.close();
+        let _ = self.ended.recv().await;
+    }
+
+    /// Waits until the background task has ended, without closing the queue.
+    pub(crate) async fn ended(&self) {
+        let _ = self.ended.recv().await;
+    }
+
+    /// Waits until every job queued before the call has run.
+    ///
+    /// Once the mailbox is closed, this waits until the background task has
+    /// ended, including its [finalizer](MailboxBuilder::on_close).
+    pub async fn flush(&self) {
+        let (s, r) = async_channel::bounded(1);
+        let queued = self
+            .sender
+            .send(Box::new(move |_: &mut T| {
+                let _ = s.try_send(());
+            }))
+            .await;
+        if queued.is_ok() && r.recv().await.is_ok() {
+            return;
+        }
+        let _ = self.ended.recv().await;
+    }
+
+    /// Records that a mutating job was handed to the queue or run inline.
+    fn mutated(&self) {
+        self.version.fetch_add(1, Ordering::Release);
+    }
+
+    /// Returns the number of jobs discarded so far because the queue was
+    /// full.
+    ///
+    /// See [`MailboxBuilder::overflow`] for an example.
+    #[must_use]
+    pub fn dropped(&self) -> u64 {
+        self.drops.count.load(Ordering::Relaxed)
+    }
+
+    /// Returns the name set with [`MailboxBuilder::name`].
+    #[must_use]
+    pub const fn name(&self) -> Option<&'static str> {
+        self.name
+    }
+
+    /// Returns whether the background task is still running.
+    ///
+    /// This turns `false` once the task has ended, after the mailbox was
+    /// [closed](Self::close) and its finalizer has run, or because a job
+    /// panicked. Jobs sent afterwards never run, so a supervisor can use
+    /// this to replace the mailbox.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(not(target_vendor = "apple"))] {
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{Mailbox, polyfill, spawn_main};
+    /// use std::{thread, time::Duration};
+    ///
+    /// thread::spawn(polyfill::start_main_executor);
+    /// let [closed, crashed] = block_on(spawn_main(async { [Mailbox::main(()), Mailbox::main(())] }));
+    ///
+    /// assert!(closed.is_alive());
+    /// block_on(closed.close());
+    /// assert!(!closed.is_alive());
+    ///
+    /// // A panicking job ends the background task
+    /// crashed.handle(|()| panic!("corrupted state"));
+    /// while crashed.is_alive() {
+    ///     thread::sleep(Duration::from_millis(1));
+    /// }
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn is_alive(&self) -> bool {
+        // The background task holds the only sender until it ends.
+        !self.ended.is_closed()
+    }
+
+    /// Returns a handle that queues jobs without keeping the mailbox open.
+    pub(crate) fn downgrade(&self) -> WeakMailbox<T> {
+        WeakMailbox {
+            sender: self.sender.downgrade(),
+            id: self.id,
+            version: self.version.clone(),
+            drops: self.drops.clone(),
+        }
+    }
+
+    /// Calls `f` on the mailbox value and blocks the current thread until the
+    /// result is available.
+    ///
+    /// This is meant for synchronous callers such as FFI callbacks. When called
+    /// on the thread that owns the value while no job is running, `f` runs
+    /// inline after any previously queued jobs, so calling this from the main
+    /// thread on a [`Mailbox::main`] value does not deadlock. On any other
+    /// thread, the call is queued like [`call`](Self::call) and the thread
+    /// blocks until it has been processed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called on the owning thread from within a job of this same
+    /// mailbox, which could never complete, and if the background task has
+    /// been dropped.
+    ///
+    /// Blocking on the owning thread before its background loop has started
+    /// cannot be detected and deadlocks.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(not(target_vendor = "apple"))] {
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{Mailbox, polyfill, spawn_main};
+    /// use std::{panic::{AssertUnwindSafe, catch_unwind}, sync::Arc, thread};
+    ///
+    /// thread::spawn(polyfill::start_main_executor);
+    /// // `Mailbox::main` spawns a local task, so it is created on the main thread
+    /// let mailbox = block_on(spawn_main(async { Arc::new(Mailbox::main(0)) }));
+    ///
+    /// // Off the main thread, the caller blocks until the job has run
+    /// mailbox.handle(|count| *count += 1);
+    /// assert_eq!(mailbox.call_blocking(|count| *count), 1);
+    ///
+    /// // On the main thread, the call runs inline instead of deadlocking
+    /// let main_mailbox = mailbox.clone();
+    /// let count = block_on(spawn_main(async move {
+    ///     main_mailbox.call_blocking(|count| {
+    ///         *count += 1;
+    ///         *count
+    ///     })
+    /// }));
+    /// assert_eq!(count, 2);
+    ///
+    /// // Blocking on the mailbox from inside one of its own jobs is reported
+    /// let reentrant = mailbox.clone();
+    /// let deadlocked = mailbox.call_blocking(move |_| {
+    ///     catch_unwind(AssertUnwindSafe(|| reentrant.call_blocking(|count| *count))).is_err()
+    /// });
+    /// assert!(deadlocked);
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn call_blocking<R>(&self, f: impl FnOnce(&mut T) -> R + Send + 'static) -> R
+    where
+        R: Send + 'static,
+    {
+        if let Some(slot) = local_slot::<T>(self.id) {
+            let Ok(mut value) = slot.value.try_borrow_mut() else {
+                panic!(
+                    "Mailbox<{}> #{}: `call_blocking` was called from inside one of its own jobs \
+                     on the owning thread, which would deadlock; use `handle` or `call` instead",
+                    type_name::<T>(),
+                    self.id
+                );
+            };
+            while let Ok(update) = slot.receiver.try_recv() {
+                update(&mut value);
+            }
+            let result = f(&mut value);
+            self.mutated();
+            return result;
+        }
+
+        context::check_blocking("Mailbox::call_blocking");
+        let caller = Location::caller();
+        #[cfg(feature = "deadlock-detect")]
+        let _wait = self
+            .on_main
+            .load(Ordering::Relaxed)
+            .then(|| crate::deadlock::wait_on_main(caller));
+        let (s, r) = mpsc::sync_channel(1);
+        if block_until(
+            self.sender.send(Box::new(move |v: &mut T| {
+                let _ = s.send(f(v));
+            })),
+            None,
+        )
+        .is_some_and(|sent| sent.is_ok())
+        {
+            self.mutated();
+        }
+        context::recv(&r).unwrap_or_else(|_| self.unanswered(caller))
+    }
+
+    /// Queues `update`, blocking the current thread while the queue is full.
+    ///
+    /// This lets synchronous producers, such as a decoder thread, apply
+    /// backpressure to a [`bounded`](Self::bounded) mailbox. On an unbounded
+    /// mailbox it never blocks.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MailboxError::Closed`] if the background task has ended, and
+    /// [`MailboxError::WouldDeadlock`] without queueing `update` if called on
+    /// the thread that owns the value, since blocking there would stop the
+    /// queue from draining. With an [overflow](MailboxBuilder::overflow) of
+    /// [`Overflow::Fail`], returns [`MailboxError::Full`] instead of waiting
+    /// while the queue is full.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(not(target_vendor = "apple"))] {
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{Mailbox, MainExecutor, mailbox::MailboxError, polyfill, spawn_main};
+    /// use std::{sync::Arc, thread, time::Duration};
+    ///
+    /// thread::spawn(polyfill::start_main_executor);
+    /// let mailbox = block_on(spawn_main(async {
+    ///     Arc::new(Mailbox::bounded(MainExecutor, 0u32, 8))
+    /// }));
+    ///
+    /// // A fast producer feeding a slow consumer loses nothing
+    /// let producer = mailbox.clone();
+    /// thread::spawn(move || {
+    ///     for i in 0..10_000 {
+    ///         producer
+    ///             .blocking_handle(move |count| {
+    ///                 if i % 1_000 == 0 {
+    ///                     thread::sleep(Duration::from_millis(1));
+    ///                 }
+    ///                 *count += 1;
+    ///             })
+    ///             .unwrap();
+    ///     }
+    /// })
+    /// .join()
+    /// .unwrap();
+    /// assert_eq!(mailbox.call_blocking(|count| *count), 10_000);
+    ///
+    /// // Blocking from inside a job fails immediately
+    /// let inner = mailbox.clone();
+    /// let result = mailbox.call_blocking(move |_| inner.blocking_handle(|_| {}));
+    /// assert_eq!(result, Err(MailboxError::WouldDeadlock));
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn blocking_handle(
+        &self,
+        update: impl FnOnce(&mut T) + Send + 'static,
+    ) -> Result<(), MailboxError> {
+        self.send_blocking(Box::new(update), None)
+    }
+
+    /// Like [`blocking_handle`](Self::blocking_handle), but gives up once
+    /// `timeout` has elapsed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MailboxError::Timeout`] if the queue stayed full for
+    /// `timeout`, in which case `update` is dropped without running, and the
+    /// errors of [`blocking_handle`](Self::blocking_handle).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(not(target_vendor = "apple"))] {
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{Mailbox, MainExecutor, mailbox::MailboxError, polyfill, spawn_main};
+    /// use std::{sync::mpsc, thread, time::Duration};
+    ///
+    /// thread::spawn(polyfill::start_main_executor);
+    /// let mailbox = block_on(spawn_main(async { Mailbox::bounded(MainExecutor, (), 1) }));
+    ///
+    /// // Stall the consumer, then fill the queue
+    /// let (resume, stalled) = mpsc::channel::<()>();
+    /// let (started, running) = mpsc::channel();
+    /// mailbox.handle(move |()| {
+    ///     started.send(()).unwrap();
+    ///     let _ = stalled.recv();
+    /// });
+    /// running.recv().unwrap();
+    /// mailbox.handle(|()| {});
+    ///
+    /// let result = mailbox.handle_timeout(Duration::from_millis(20), |()| {});
+    /// assert_eq!(result, Err(MailboxError::Timeout));
+    /// drop(resume);
+    ///
+    /// // A timeout too long to represent waits for as long as it takes
+    /// assert_eq!(mailbox.handle_timeout(Duration::MAX, |()| {}), Ok(()));
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn handle_timeout(
+        &self,
+        timeout: Duration,
+        update: impl FnOnce(&mut T) + Send + 'static,
+    ) -> Result<(), MailboxError> {
+        self.send_blocking(Box::new(update), Some(timeout))
+    }
+
+    #[track_caller]
+    fn send_blocking(&self, job: Job<T>, timeout: Option<Duration>) -> Result<(), MailboxError> {
+        if local_slot::<T>(self.id).is_some() {
+            return Err(MailboxError::WouldDeadlock);
+        }
+        let job = if self.drops.overflow == Overflow::Fail {
+            match self.sender.try_send(job) {
+                Ok(()) => {
+                    self.mutated();
+                    return Ok(());
+                }
+                Err(TrySendError::Full(_)) => {
+                    self.drops.record(Overflow::Fail, Location::caller());
+                    return Err(MailboxError::Full);
+                }
+                Err(TrySendError::Closed(job)) => job,
+            }
+        } else {
+            job
+        };
+        context::check_blocking("Mailbox::blocking_handle");
+        // A timeout too long for an `Instant` never expires.
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+        match block_until(self.sender.send(job), deadline) {
+            Some(Ok(())) => {
+                self.mutated();
+                Ok(())
+            }
+            Some(Err(_)) => Err(MailboxError::Closed),
+            None => Err(MailboxError::Timeout),
+        }
+    }
+}
+
+impl<T: 'static> fmt::Debug for Mailbox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mailbox")
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .field("len", &self.sender.len())
+            .field("capacity", &self.sender.capacity())
+            .field("closed", &self.sender.is_closed())
+            .field("alive", &self.is_alive())
+            .field("dropped", &self.dropped())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Queues jobs on a [`Mailbox`] for as long as it is open, without keeping
+/// it open.
+pub(crate) struct WeakMailbox<T: 'static> {
+    sender: WeakSender<Job<T>>,
+    id: u64,
+    version: Arc<AtomicU64>,
+    drops: Arc<Drops>,
+}
+
+impl<T: 'static> WeakMailbox<T> {
+    /// Queues `update` on behalf of `caller`, applying `overflow` if the
+    /// queue is full.
+    ///
+    /// Returns `false` once the mailbox has been dropped or closed, and if
+    /// `update` was discarded.
+    pub(crate) fn send(
+        &self,
+        update: Job<T>,
+        overflow: Overflow,
+        caller: &'static Location<'static>,
+    ) -> bool {
+        let Some(sender) = self.sender.upgrade() else {
+            return false;
+        };
+        let sent = send(&sender, self.id, update, overflow, &self.drops, caller);
+        if sent {
+            self.version.fetch_add(1, Ordering::Release);
+        }
+        // A discarded event does not make the subscriber gone.
+        sent || !sender.is_closed()
+    }
+}
+
+/// Queues `job` on the queue of the mailbox `id` on behalf of `caller`,
+/// applying `overflow` if the queue is full, and returns whether it was
+/// queued.
+fn send<T: 'static>(
+    sender: &Sender<Job<T>>,
+    id: u64,
+    job: Job<T>,
+    overflow: Overflow,
+    drops: &Drops,
+    caller: &'static Location<'static>,
+) -> bool {
+    match overflow {
+        // Blocking on the thread that owns the value would never return.
+        Overflow::Block if local_slot::<T>(id).is_none() => {
+            context::check_blocking("Overflow::Block");
+            block_until(sender.send(job), None).is_some_and(|sent| sent.is_ok())
+        }
+        Overflow::Block | Overflow::DropOldest => {
+            let Ok(evicted) = sender.force_send(job) else {
+                return false;
+            };
+            if evicted.is_some() {
+                drops.record(Overflow::DropOldest, caller);
+            }
+            true
+        }
+        Overflow::DropNewest | Overflow::Fail => match sender.try_send(job) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                drops.record(overflow, caller);
+                false
+            }
+            Err(TrySendError::Closed(_)) => false,
+        },
+    }
+}
+
+/// The jobs a mailbox has discarded because its queue was full.
+struct Drops {
+    name: Option<&'static str>,
+    /// The overflow of [`Mailbox::handle`].
+    overflow: Overflow,
+    count: AtomicU64,
+    on_drop: Option<DropHook>,
+}
+
+impl Drops {
+    fn record(&self, overflow: Overflow, caller: &'static Location<'static>) {
+        let total = self.count.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(on_drop) = &self.on_drop {
+            on_drop(&Dropped {
+                mailbox: self.name,
+                overflow,
+                caller,
+                total,
+            });
+        }
+    }
+}
+
+/// A job discarded by a [`Mailbox`] because its queue was full, as reported
+/// to [`MailboxBuilder::on_drop`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Dropped {
+    /// The name of the mailbox, set with [`MailboxBuilder::name`].
+    pub mailbox: Option<&'static str>,
+    /// The overflow that discarded the job:
+    /// [`DropOldest`](Overflow::DropOldest) if an older job made room for a
+    /// new one, otherwise the new job was discarded.
+    pub overflow: Overflow,
+    /// Where the producer queued the job that overflowed the queue, such as
+    /// a call of [`Mailbox::handle`] or [`Bus::publish`](crate::bus::Bus::publish).
+    pub caller: &'static Location<'static>,
+    /// The number of jobs the mailbox has discarded so far, this one
+    /// included.
+    pub total: u64,
+}
+
+/// The time left to a job of [`Mailbox::chunked`].
+#[derive(Debug, Clone, Copy)]
+pub struct Budget {
+    started: Instant,
+    budget: Duration,
+}
+
+impl Budget {
+    fn new(budget: Duration) -> Self {
+        Self {
+            started: Instant::now(),
+            budget,
+        }
+    }
+
+    /// Returns the time left before the job yields to the other jobs of the
+    /// mailbox.
+    #[must_use]
+    pub fn remaining(&self) -> Duration {
+        self.budget.saturating_sub(self.started.elapsed())
+    }
+
+    /// Returns `true` once the job has used up its budget, after which it
+    /// yields at the end of the current step.
+    #[must_use]
+    pub fn is_exhausted(&self) -> bool {
+        self.started.elapsed() >= self.budget
+    }
+}
+
+/// Whether a step of [`Mailbox::chunked`] finished the work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepResult {
+    /// Work remains: `step` is called again, in this job while the budget
+    /// lasts, otherwise in the next one.
+    MoreWork,
+    /// The work is finished, and the future resolves to the state.
+    Done,
+}
+
+/// The reason a blocking send to a [`Mailbox`] failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MailboxError {
+    /// The background task has ended, so the update can never run.
+    Closed,
+    /// The queue stayed full until the timeout elapsed.
+    Timeout,
+    /// The queue was full, and the [overflow](MailboxBuilder::overflow) of
+    /// the mailbox is [`Overflow::Fail`].
+    Full,
+    /// The call was made on the thread that owns the value, where blocking
+    /// would stop the queue from draining.
+    WouldDeadlock,
+}
+
+impl fmt::Display for MailboxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Closed => "mailbox is closed: its background task has ended",
+            Self::Timeout => "mailbox queue stayed full until the timeout elapsed",
+            Self::Full => "mailbox queue is full",
+            Self::WouldDeadlock => {
+                "cannot block on a mailbox from the thread that owns its value; use `handle` or \
+                 `call` instead"
+            }
+        })
+    }
+}
+
+impl std::error::Error for MailboxError {}
+
+/// Wakes a thread parked in [`block_until`].
+struct Unparker(Thread);
+
+impl Wake for Unparker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Polls `future` on the current thread until it completes or `deadline`
+/// passes.
+pub(crate) fn block_until<F: Future>(future: F, deadline: Option<Instant>) -> Option<F::Output> {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(Unparker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut output = None;
+    context::help_while(|| {
+        if let Poll::Ready(ready) = future.as_mut().poll(&mut cx) {
+            output = Some(ready);
+        }
+        output.is_some()
+    });
+    if output.is_some() {
+        return output;
+    }
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return Some(output);
+        }
+        match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return None;
+                }
+                thread::park_timeout(deadline - now);
+            }
+            None => thread::park(),
+        }
     }
 }
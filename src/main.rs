@@ -1,22 +1,44 @@
 //! Example binary demonstrating native-executor capabilities.
 //!
-//! This binary showcases the basic usage of the native-executor library,
-//! demonstrating task spawning and high-precision timing with platform-native
-//! scheduling primitives.
+//! Three pool tasks running at different priorities report progress to a
+//! mailbox whose value lives on the main thread, an interval ticks, work
+//! races against a timeout, and a task group is shut down. The process exits
+//! once every scenario has finished.
+//!
+//! The main thread drives the main queue with `NativeExecutor::run_until`
+//! until the demo has finished, on the polyfill backend and on Apple
+//! platforms alike. The scenarios live in `scenarios.rs`, and each has an
+//! integration test in `tests/scenarios.rs`.
+//!
+//! See `examples/kitchen_sink.rs` for a tour of the rest of the API.
 
-use native_executor::{spawn, timer::Timer};
-use std::time::Duration;
+mod scenarios;
+
+use native_executor::{NativeExecutor, Priority};
 
 fn main() {
-    spawn(hello()).detach();
-}
+    NativeExecutor::run_until(async {
+        println!("{}", scenarios::hello().await);
+
+        for (name, step) in scenarios::report_progress().await {
+            println!("{name} worker finished step {step}");
+        }
+
+        for (tick, elapsed) in scenarios::ticks(3).await.into_iter().enumerate() {
+            println!("tick {} after {} ms", tick + 1, elapsed.as_millis());
+        }
+
+        let (quick, stalled) = scenarios::timeout_race().await;
+        println!("quick work: {quick:?}");
+        println!("stalled work: {stalled:?}");
 
-/// Example async function demonstrating timer usage.
-///
-/// This function showcases a simple async operation with platform-native
-/// timing, executed using the native-executor task system.
-pub async fn hello() {
-    // Use high-precision platform-native timing
-    Timer::after(Duration::from_millis(100)).await;
-    println!("Hello, world from native-executor!");
+        let report = scenarios::shutdown().await;
+        for priority in [
+            Priority::Background,
+            Priority::Default,
+            Priority::UserInitiated,
+        ] {
+            println!("{priority:?} lane: {:?}", report.lane(priority));
+        }
+    });
 }
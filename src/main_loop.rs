@@ -0,0 +1,216 @@
+//! Detection of a main thread that does not serve its queue.
+//!
+//! Work for the main thread waits in the main queue until the application
+//! serves it: on Apple platforms by running the main run loop or
+//! `dispatch_main`, with the polyfill backend by calling
+//! [`NativeExecutor::run_until`](crate::NativeExecutor::run_until) or
+//! `polyfill::start_main_executor`. Until then it stays queued, which is
+//! easy to miss in a command-line tool or early in `main`, for example when
+//! a [`Mailbox::main`](crate::Mailbox::main) is created before the
+//! application has started.
+//!
+//! The first time work is submitted to the main thread, a no-op probe is
+//! queued along with it. If the probe has not run once the
+//! [timeout](set_timeout) elapses, and no `run_until` has started, the main
+//! thread is considered not running: the [handler](set_handler) is told, by
+//! default printing a warning to standard error, and
+//! [`try_spawn_main`](crate::try_spawn_main) refuses work. The work already
+//! submitted stays queued and runs if the main thread is served later, at
+//! which point the state turns to [`MainLoopState::Running`].
+//!
+//! A main thread that is busy for longer than the timeout right when work is
+//! first submitted to it is reported too.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # #[cfg(not(any(target_vendor = "apple", target_os = "android")))] {
+//! use native_executor::{
+//!     NativeExecutor,
+//!     main_loop::{self, MainLoopState},
+//!     spawn_main, try_spawn_main,
+//! };
+//! use std::{sync::mpsc, time::Duration};
+//!
+//! main_loop::set_timeout(Duration::from_millis(50));
+//! let (sender, warnings) = mpsc::channel();
+//! main_loop::set_handler(move |warning| sender.send(warning.clone()).unwrap());
+//!
+//! // Nothing serves the main thread yet
+//! let task = spawn_main(async { "ran" });
+//! let warning = warnings.recv_timeout(Duration::from_secs(5)).unwrap();
+//! assert_eq!(warning.waited, Duration::from_millis(50));
+//! assert_eq!(main_loop::state(), MainLoopState::NotRunning);
+//! let refused = try_spawn_main(async { "refused" }).unwrap_err();
+//!
+//! // The work was kept, and runs once the main thread is served
+//! assert_eq!(NativeExecutor::run_until(task), "ran");
+//! assert_eq!(main_loop::state(), MainLoopState::Running);
+//! let task = try_spawn_main(refused.into_inner()).unwrap();
+//! assert_eq!(NativeExecutor::run_until(task), "refused");
+//! # }
+//! ```
+
+use core::{
+    fmt,
+    sync::atomic::{AtomicU8, AtomicU64, Ordering},
+    time::Duration,
+};
+use std::{
+    panic::{AssertUnwindSafe, catch_unwind},
+    sync::{PoisonError, RwLock},
+};
+
+use crate::{NativeExecutor, PlatformExecutor, Priority};
+
+/// Whether the main thread serves its queue, as returned by [`state`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MainLoopState {
+    /// Nothing has been submitted to the main thread yet, or the probe has
+    /// not concluded.
+    Unknown,
+    /// The main thread has served its queue.
+    Running,
+    /// The main thread did not serve its queue within the timeout.
+    NotRunning,
+}
+
+/// The report of a main thread that does not serve its queue.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Warning {
+    /// How long the probe waited for the main thread.
+    pub waited: Duration,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the main thread has not served its queue within {:?} of the first work submitted to \
+             it; the work stays queued until its run loop is started",
+            self.waited
+        )
+    }
+}
+
+/// The error returned by [`try_spawn_main`](crate::try_spawn_main) while the
+/// main thread does not serve its queue, holding the future that was not
+/// spawned.
+pub struct MainExecutorNotRunning<F> {
+    future: F,
+}
+
+impl<F> MainExecutorNotRunning<F> {
+    pub(crate) const fn new(future: F) -> Self {
+        Self { future }
+    }
+
+    /// Returns the future that was not spawned.
+    pub fn into_inner(self) -> F {
+        self.future
+    }
+}
+
+impl<F> fmt::Debug for MainExecutorNotRunning<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MainExecutorNotRunning")
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F> fmt::Display for MainExecutorNotRunning<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(NOT_RUNNING_MESSAGE)
+    }
+}
+
+impl<F> std::error::Error for MainExecutorNotRunning<F> {}
+
+/// The message of [`MainExecutorNotRunning`], shared with
+/// [`Error`](crate::Error).
+pub(crate) const NOT_RUNNING_MESSAGE: &str =
+    "the main thread does not serve its queue; start its run loop before spawning on it";
+
+type Handler = Box<dyn Fn(&Warning) + Send + Sync>;
+
+const UNKNOWN: u8 = 0;
+const PROBING: u8 = 1;
+const RUNNING: u8 = 2;
+const NOT_RUNNING: u8 = 3;
+
+static STATE: AtomicU8 = AtomicU8::new(UNKNOWN);
+/// The timeout in nanoseconds.
+static TIMEOUT: AtomicU64 = AtomicU64::new(1_000_000_000);
+static HANDLER: RwLock<Option<Handler>> = RwLock::new(None);
+
+/// Returns whether the main thread serves its queue, as far as is known.
+#[must_use]
+pub fn state() -> MainLoopState {
+    match STATE.load(Ordering::Acquire) {
+        RUNNING => MainLoopState::Running,
+        NOT_RUNNING => MainLoopState::NotRunning,
+        _ => MainLoopState::Unknown,
+    }
+}
+
+/// Sets how long the probe waits for the main thread; one second by default.
+///
+/// Only probes started afterwards use the new timeout.
+pub fn set_timeout(timeout: Duration) {
+    let nanos = u64::try_from(timeout.as_nanos()).unwrap_or(u64::MAX);
+    TIMEOUT.store(nanos, Ordering::Relaxed);
+}
+
+/// Replaces the handler that receives the warning, which prints it to
+/// standard error by default.
+///
+/// The handler runs on a thread of the backend, at most once per process. A
+/// panicking handler is caught; the panic is still reported by the process
+/// panic hook.
+pub fn set_handler(handler: impl Fn(&Warning) + Send + Sync + 'static) {
+    *HANDLER.write().unwrap_or_else(PoisonError::into_inner) = Some(Box::new(handler));
+}
+
+/// Records that the main thread is being served, by the backend or by a
+/// `run_until`.
+pub(crate) fn entered() {
+    STATE.store(RUNNING, Ordering::Release);
+}
+
+/// Starts the probe unless it has run before; called after each submission
+/// to the main thread.
+pub(crate) fn probe() {
+    if STATE.load(Ordering::Relaxed) != UNKNOWN
+        || STATE
+            .compare_exchange(UNKNOWN, PROBING, Ordering::AcqRel, Ordering::Relaxed)
+            .is_err()
+    {
+        return;
+    }
+    NativeExecutor::exec_main(entered, None);
+    let timeout = Duration::from_nanos(TIMEOUT.load(Ordering::Relaxed));
+    NativeExecutor::exec_after(
+        timeout.min(NativeExecutor::MAX_DELAY),
+        move || {
+            if STATE
+                .compare_exchange(PROBING, NOT_RUNNING, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+            {
+                report(&Warning { waited: timeout });
+            }
+        },
+        Priority::Utility,
+    );
+}
+
+fn report(warning: &Warning) {
+    let handler = HANDLER.read().unwrap_or_else(PoisonError::into_inner);
+    match handler.as_ref() {
+        Some(handler) => {
+            // The panic hook has already reported the panic at this point.
+            let _ = catch_unwind(AssertUnwindSafe(|| handler(warning)));
+        }
+        None => eprintln!("native-executor: {warning}"),
+    }
+}
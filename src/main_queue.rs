@@ -0,0 +1,50 @@
+//! The main-thread job queue of the backends without a native main queue.
+//!
+//! Jobs are kept in one FIFO lane per scheduling class, and the most urgent
+//! non-empty lane is served first. Jobs submitted without a priority share the
+//! lane of [`Priority::default`].
+
+use alloc::{boxed::Box, collections::VecDeque};
+use std::sync::{Condvar, Mutex, PoisonError};
+
+use crate::{Priority, priority::SchedClass};
+
+/// A job submitted to the main thread.
+pub type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Main-thread jobs ordered by priority, then by submission.
+pub struct MainQueue {
+    lanes: Mutex<[VecDeque<Job>; SchedClass::COUNT]>,
+    ready: Condvar,
+}
+
+impl MainQueue {
+    pub const fn new() -> Self {
+        Self {
+            lanes: Mutex::new([const { VecDeque::new() }; SchedClass::COUNT]),
+            ready: Condvar::new(),
+        }
+    }
+
+    /// Queues `job` behind the jobs of the same or a more urgent priority.
+    pub fn push(&self, job: Job, priority: Option<Priority>) {
+        let class = priority.unwrap_or_default().class();
+        self.lanes.lock().unwrap_or_else(PoisonError::into_inner)[class.index()].push_back(job);
+        self.ready.notify_one();
+    }
+
+    /// Waits for the next job, taking it from the most urgent non-empty lane.
+    pub fn pop(&self) -> Job {
+        let mut lanes = self.lanes.lock().unwrap_or_else(PoisonError::into_inner);
+        loop {
+            // Lanes are indexed from least to most urgent.
+            if let Some(job) = lanes.iter_mut().rev().find_map(VecDeque::pop_front) {
+                return job;
+            }
+            lanes = self
+                .ready
+                .wait(lanes)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+}
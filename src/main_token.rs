@@ -0,0 +1,146 @@
+//! A compile-time proof of running on the main thread.
+
+use core::{future::Future, marker::PhantomData};
+use std::sync::mpsc;
+
+use async_task::Task;
+
+use crate::{context, dispatch_main, is_main_thread, spawn_local, spawn_main};
+
+/// A zero-sized proof that the current thread is the main thread, the one
+/// that runs the tasks of [`spawn_main`] and [`spawn_local`].
+///
+/// A token can only be obtained on the main thread and is neither `Send` nor
+/// `Sync`, so it never leaves it. Wrappers around main-thread-only APIs can
+/// take a `MainToken` parameter instead of checking the thread at runtime.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(not(target_vendor = "apple"))] {
+/// use futures_lite::future::block_on;
+/// use native_executor::{MainToken, polyfill, spawn, spawn_main};
+/// use std::thread;
+///
+/// thread::spawn(polyfill::start_main_executor);
+///
+/// // Only the main thread gets a token
+/// assert!(block_on(spawn_main(async { MainToken::new().is_some() })));
+/// assert!(block_on(spawn(async { MainToken::new().is_none() })));
+/// assert!(MainToken::new().is_none());
+///
+/// // A wrapper that can only be called on the main thread
+/// fn set_title(_: MainToken, title: &str) -> String {
+///     format!("window: {title}")
+/// }
+/// assert_eq!(MainToken::run_sync(|token| set_title(token, "Inbox")), "window: Inbox");
+/// # }
+/// ```
+///
+/// A token cannot be sent to another thread:
+///
+/// ```compile_fail
+/// use native_executor::MainToken;
+///
+/// let token = MainToken::new().unwrap();
+/// std::thread::spawn(move || drop(token));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MainToken {
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl MainToken {
+    /// Returns a token if the current thread is the main thread.
+    #[must_use]
+    pub fn new() -> Option<Self> {
+        // SAFETY: checked to be on the main thread.
+        is_main_thread().then(|| unsafe { Self::new_unchecked() })
+    }
+
+    /// Returns a token without checking the current thread.
+    ///
+    /// # Safety
+    ///
+    /// The current thread must be the main thread.
+    #[must_use]
+    pub const unsafe fn new_unchecked() -> Self {
+        Self {
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Calls `f` with a token on the main thread, blocking the current thread
+    /// until it returns.
+    ///
+    /// On the main thread, `f` runs inline. From any other thread it is
+    /// queued on the main thread, so the main thread must not be blocked on
+    /// the caller.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` panics.
+    #[track_caller]
+    pub fn run_sync<R: Send + 'static>(f: impl FnOnce(Self) -> R + Send + 'static) -> R {
+        if let Some(token) = Self::new() {
+            return f(token);
+        }
+        context::check_blocking("MainToken::run_sync");
+        #[cfg(feature = "deadlock-detect")]
+        let _wait = crate::deadlock::wait_on_main(core::panic::Location::caller());
+        let (sender, receiver) = mpsc::sync_channel(1);
+        dispatch_main(
+            move || {
+                // SAFETY: `dispatch_main` runs jobs on the main thread.
+                let token = unsafe { Self::new_unchecked() };
+                let _ = sender.send(f(token));
+            },
+            None,
+        );
+        context::recv(&receiver)
+            .expect("the main thread panicked in a `MainToken::run_sync` closure")
+    }
+
+    /// Spawns a task on the main thread that calls `f` with a token.
+    ///
+    /// This is the asynchronous counterpart of [`run_sync`](Self::run_sync).
+    pub fn on_main<R: Send + 'static>(f: impl FnOnce(Self) -> R + Send + 'static) -> Task<R> {
+        spawn_main(async move {
+            // SAFETY: main tasks are polled on the main thread.
+            f(unsafe { Self::new_unchecked() })
+        })
+    }
+
+    /// Spawns a task on the main thread whose future holds a token.
+    ///
+    /// Unlike with [`spawn_main`], the future does not need to be `Send`, so
+    /// it can keep the token across await points.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(not(target_vendor = "apple"))] {
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{MainToken, polyfill, timer::Timer};
+    /// use std::{thread, time::Duration};
+    ///
+    /// thread::spawn(polyfill::start_main_executor);
+    ///
+    /// let task = MainToken::run_sync(|token| {
+    ///     token.spawn_local(|token| async move {
+    ///         Timer::after(Duration::from_millis(1)).await;
+    ///         // Still on the main thread
+    ///         token == MainToken::new().unwrap()
+    ///     })
+    /// });
+    /// assert!(block_on(task));
+    /// # }
+    /// ```
+    #[track_caller]
+    pub fn spawn_local<Fut>(self, f: impl FnOnce(Self) -> Fut + 'static) -> Task<Fut::Output>
+    where
+        Fut: Future + 'static,
+    {
+        spawn_local(async move { f(self).await })
+    }
+}
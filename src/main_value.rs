@@ -0,0 +1,155 @@
+//! Values handed over to the main thread for good.
+
+use core::{any::type_name, fmt, future::Future, mem::ManuallyDrop};
+
+use crate::{MainToken, callback, dispatch_main, is_main_thread};
+
+/// A value owned by the main thread.
+///
+/// The handle can be stored and moved anywhere, but the value is only
+/// reachable with a [`MainToken`], so it is only ever used on the main
+/// thread. Dropping the handle off the main thread drops the value on the
+/// main thread.
+///
+/// Created by [`send_to_main`]; [`extract`](Self::extract) moves the value
+/// back out.
+pub struct MainValue<T: Send + 'static> {
+    /// Dropped by `Drop`, or taken out by `extract`, which skips `Drop`.
+    value: ManuallyDrop<T>,
+}
+
+impl<T: Send + 'static> fmt::Debug for MainValue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MainValue")
+            .field("type", &type_name::<T>())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Send + 'static> MainValue<T> {
+    /// Returns a reference to the value.
+    #[must_use]
+    pub fn get(&self, _token: MainToken) -> &T {
+        &self.value
+    }
+
+    /// Returns a mutable reference to the value.
+    #[must_use]
+    pub fn get_mut(&mut self, _token: MainToken) -> &mut T {
+        &mut self.value
+    }
+
+    /// Moves the value back out of the main thread.
+    ///
+    /// The value is taken on the main thread, behind the main-thread work
+    /// queued before this call. If the returned future is dropped before it
+    /// resolves, the value is dropped on the main thread.
+    pub fn extract(self) -> impl Future<Output = T> + Send + 'static {
+        let (completer, value) = callback::future_with_cancel();
+        // A value arriving after cancellation is dropped by the completer,
+        // on the main thread.
+        dispatch_main(move || completer.complete(self), None);
+        async move {
+            let mut handle = ManuallyDrop::new(value.await);
+            // SAFETY: the handle is never used or dropped again.
+            unsafe { ManuallyDrop::take(&mut handle.value) }
+        }
+    }
+}
+
+impl<T: Send + 'static> Drop for MainValue<T> {
+    fn drop(&mut self) {
+        // SAFETY: the value is not used after the handle is dropped.
+        let value = unsafe { ManuallyDrop::take(&mut self.value) };
+        if is_main_thread() {
+            drop(value);
+        } else {
+            dispatch_main(move || drop(value), None);
+        }
+    }
+}
+
+/// Moves `value` to the main thread for good, resolving to the
+/// [`MainValue`] that owns it there.
+///
+/// This suits state that is built on a worker, for example by parsing, and
+/// then lives next to the UI. The transfer starts right away, not when the
+/// future is first polled. Dropping the future never drops the value on the
+/// current thread: a value that has not reached the main thread yet is
+/// dropped there once it does.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(not(any(target_vendor = "apple", target_os = "android")))] {
+/// use futures_lite::future::block_on;
+/// use native_executor::{MainToken, polyfill, send_to_main, spawn};
+/// use std::thread;
+///
+/// thread::spawn(polyfill::start_main_executor);
+///
+/// // Parsed on a worker, then owned by the main thread
+/// let mut settings = block_on(spawn(async {
+///     let parsed: Vec<String> = "dark,compact".split(',').map(str::to_owned).collect();
+///     send_to_main(parsed).await
+/// }));
+/// settings = MainToken::run_sync(move |token| {
+///     settings.get_mut(token).push("large-text".to_owned());
+///     settings
+/// });
+///
+/// // And back out again
+/// assert_eq!(block_on(settings.extract()), ["dark", "compact", "large-text"]);
+/// # }
+/// ```
+///
+/// Values whose transfer is abandoned are dropped on the main thread:
+///
+/// ```rust
+/// # #[cfg(not(any(target_vendor = "apple", target_os = "android")))] {
+/// use futures_lite::future::block_on;
+/// use native_executor::{MainToken, polyfill, send_to_main};
+/// use std::{sync::mpsc, thread};
+///
+/// // Reports whether it was dropped on the main thread
+/// struct Recorder(mpsc::Sender<bool>);
+/// impl Drop for Recorder {
+///     fn drop(&mut self) {
+///         self.0.send(MainToken::new().is_some()).unwrap();
+///     }
+/// }
+/// let (sender, dropped) = mpsc::channel();
+///
+/// // Dropped before the value reaches the main thread
+/// drop(send_to_main(Recorder(sender.clone())));
+/// thread::spawn(polyfill::start_main_executor);
+/// assert!(dropped.recv().unwrap());
+///
+/// // Dropped after the value has reached the main thread
+/// let transfer = send_to_main(Recorder(sender.clone()));
+/// MainToken::run_sync(|_| {});
+/// drop(transfer);
+/// assert!(dropped.recv().unwrap());
+///
+/// // Handles dropped elsewhere, and abandoned extractions
+/// drop(block_on(send_to_main(Recorder(sender.clone()))));
+/// assert!(dropped.recv().unwrap());
+/// drop(block_on(send_to_main(Recorder(sender))).extract());
+/// assert!(dropped.recv().unwrap());
+/// assert!(dropped.try_recv().is_err());
+/// # }
+/// ```
+pub fn send_to_main<T: Send + 'static>(
+    value: T,
+) -> impl Future<Output = MainValue<T>> + Send + 'static {
+    let (completer, handle) = callback::future_with_cancel();
+    dispatch_main(
+        move || {
+            completer.complete(MainValue {
+                value: ManuallyDrop::new(value),
+            });
+        },
+        None,
+    );
+    handle
+}
@@ -0,0 +1,127 @@
+//! Nice values requested with [`PlatformPriority::nice`](crate::PlatformPriority::nice).
+//!
+//! Linux applies nice values per thread, so the worker running a job takes on
+//! the requested value for the duration of the job and then restores its own.
+//! Restoring a lower value needs `CAP_SYS_NICE` or a sufficient
+//! `RLIMIT_NICE`, so a value the worker could not return from is not applied
+//! at all: the worker would otherwise run every later job at it.
+
+use core::{
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use std::{io, sync::OnceLock, thread};
+
+#[cfg(target_os = "android")]
+use libc::__errno as errno_location;
+#[cfg(not(target_os = "android"))]
+use libc::__errno_location as errno_location;
+
+use crate::{Priority, main_queue::Job};
+
+/// Wraps `f` so that it runs with the nice value requested by a custom priority.
+///
+/// The worker's previous nice value is restored once `f` returns. If the nice
+/// value cannot be applied, or could not be undone afterwards, `f` runs at the
+/// worker's own value and the failure is reported on stderr once per process.
+pub fn with_nice(f: impl FnOnce() + Send + 'static, priority: Priority) -> Job {
+    let Priority::Custom(custom) = priority else {
+        return Box::new(f);
+    };
+    let Some(nice) = custom.as_nice() else {
+        return Box::new(f);
+    };
+    Box::new(move || {
+        // SAFETY: `gettid` has no preconditions.
+        let tid = unsafe { libc::gettid() }.cast_unsigned();
+        let nice = i32::from(nice);
+        let previous = current(tid)
+            .filter(|&previous| {
+                let restorable = nice <= previous || can_lower_to(previous);
+                if !restorable {
+                    report(format_args!(
+                        "skipped nice value {nice}, as the worker could not return to {previous} \
+                         without CAP_SYS_NICE or a higher RLIMIT_NICE"
+                    ));
+                }
+                restorable
+            })
+            .filter(|_| set(tid, nice));
+        f();
+        if let Some(previous) = previous {
+            set(tid, previous);
+        }
+    })
+}
+
+/// Returns the nice value of thread `tid`.
+fn current(tid: libc::id_t) -> Option<i32> {
+    // `getpriority` may return -1 on success, so failures are told apart by
+    // clearing `errno` first.
+    // SAFETY: `errno_location` points to the calling thread's `errno`, and
+    // `getpriority` only reads the scheduling parameters of `tid`.
+    let nice = unsafe {
+        *errno_location() = 0;
+        libc::getpriority(libc::PRIO_PROCESS, tid)
+    };
+    let error = io::Error::last_os_error();
+    if nice == -1 && error.raw_os_error() != Some(0) {
+        report(format_args!(
+            "failed to read the nice value of a worker thread: {error}"
+        ));
+        return None;
+    }
+    Some(nice)
+}
+
+/// Sets the nice value of thread `tid`, returning whether it succeeded.
+fn set(tid: libc::id_t, nice: i32) -> bool {
+    // SAFETY: `setpriority` only changes the scheduling parameters of `tid`.
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, tid, nice) } == 0 {
+        return true;
+    }
+    let error = io::Error::last_os_error();
+    report(format_args!(
+        "failed to set nice value {nice} on a worker thread: {error}"
+    ));
+    false
+}
+
+/// Returns whether a thread may lower its nice value to `nice`.
+fn can_lower_to(nice: i32) -> bool {
+    // Probed once on a throwaway thread, whose nice value the probe changes
+    static UNRESTRICTED: OnceLock<bool> = OnceLock::new();
+    let unrestricted = *UNRESTRICTED.get_or_init(|| {
+        thread::spawn(|| {
+            // SAFETY: `gettid` has no preconditions, and `setpriority` only
+            // changes the scheduling parameters of the calling thread.
+            unsafe {
+                libc::setpriority(libc::PRIO_PROCESS, libc::gettid().cast_unsigned(), -20) == 0
+            }
+        })
+        .join()
+        .unwrap_or(false)
+    });
+    if unrestricted {
+        return true;
+    }
+    // Without `CAP_SYS_NICE`, the lowest value is `20 - RLIMIT_NICE`
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: `limit` is a valid `rlimit` to write to.
+    if unsafe { libc::getrlimit(libc::RLIMIT_NICE, &raw mut limit) } != 0 {
+        return false;
+    }
+    let ceiling = i32::try_from(limit.rlim_cur.min(40)).unwrap_or(0);
+    nice >= 20 - ceiling
+}
+
+/// Reports the first failure to apply a nice value.
+fn report(message: fmt::Arguments<'_>) {
+    static REPORTED: AtomicBool = AtomicBool::new(false);
+    if !REPORTED.swap(true, Ordering::Relaxed) {
+        eprintln!("native-executor: {message}");
+    }
+}
@@ -0,0 +1,384 @@
+//! Waking async code from realtime threads.
+//!
+//! Audio render callbacks and display-link callbacks must not allocate or
+//! take locks, which rules out spawning tasks or sending on most channels. A
+//! [`Notify`] is armed ahead of time; its [`WakeHandle`] can then be
+//! signalled from such a callback, and a task awaits the signals with
+//! [`Notify::notified`].
+//!
+//! # Realtime safety
+//!
+//! [`WakeHandle::signal`] never allocates, never takes a lock and never
+//! blocks. It performs one atomic read-modify-write on a counter, followed by
+//! a wake of the platform primitive armed by [`Notify::new`]:
+//!
+//! - **Apple platforms**: `dispatch_source_merge_data` on a `DATA_ADD`
+//!   dispatch source, which GCD coalesces and delivers on a queue.
+//! - **Other platforms**: [`Thread::unpark`](std::thread::Thread::unpark) of
+//!   a helper thread, an atomic swap plus a futex wake on Linux.
+//!
+//! The task itself is woken from the dispatch queue or the helper thread, so
+//! none of the executor's allocations happen on the signalling thread.
+//! `signal` is not promised to be async-signal-safe.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use futures_lite::future::block_on;
+//! use native_executor::notify::Notify;
+//! use std::thread;
+//!
+//! let notify = Notify::new().unwrap();
+//! let frames = 10_000;
+//!
+//! thread::scope(|scope| {
+//!     // Stand-in for a render callback
+//!     let handle = notify.wake_handle();
+//!     scope.spawn(move || {
+//!         for _ in 0..frames {
+//!             handle.signal();
+//!         }
+//!     });
+//!
+//!     // Signals arriving in quick succession are coalesced, never lost
+//!     let mut seen = 0;
+//!     while seen < frames {
+//!         seen += block_on(notify.notified());
+//!     }
+//!     assert_eq!(seen, frames);
+//! });
+//! ```
+
+use core::{
+    fmt,
+    future::poll_fn,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Poll, Waker},
+};
+use std::{
+    io,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+/// A counter of signals that a task can wait on.
+///
+/// Signals are sent through [`WakeHandle`]s, which are cheap to copy into the
+/// context of a realtime callback. See the [module documentation](self) for
+/// the guarantees of the signalling side.
+pub struct Notify {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    /// Signals not yet returned by [`Notify::notified`].
+    pending: AtomicU64,
+    /// The task waiting in [`Notify::notified`]; only touched off the
+    /// signalling thread.
+    waker: Mutex<Option<Waker>>,
+    wake: imp::Target,
+}
+
+impl Shared {
+    /// Wakes the waiting task; called from the platform primitive.
+    fn wake(&self) {
+        let waker = self
+            .waker
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl fmt::Debug for Notify {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Notify")
+            .field("pending", &self.shared.pending.load(Ordering::Relaxed))
+            .finish_non_exhaustive()
+    }
+}
+
+impl Notify {
+    /// Creates a notifier and arms its platform wake primitive.
+    ///
+    /// # Errors
+    ///
+    /// Returns the system error if the dispatch source or the helper thread
+    /// cannot be created.
+    pub fn new() -> io::Result<Self> {
+        let shared = Arc::new(Shared {
+            pending: AtomicU64::new(0),
+            waker: Mutex::new(None),
+            wake: imp::Target::new()?,
+        });
+        imp::start(&shared)?;
+        Ok(Self { shared })
+    }
+
+    /// Returns a handle that signals this notifier.
+    #[must_use]
+    pub const fn wake_handle(&self) -> WakeHandle<'_> {
+        WakeHandle::for_notify(self)
+    }
+
+    /// Waits for at least one signal and returns the number of signals since
+    /// the previous call.
+    ///
+    /// Only one task should wait at a time; a second waiter replaces the
+    /// first one's wake-up.
+    pub async fn notified(&self) -> u64 {
+        poll_fn(|cx| {
+            let pending = self.take();
+            if pending > 0 {
+                return Poll::Ready(pending);
+            }
+            let mut waker = self
+                .shared
+                .waker
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            match &mut *waker {
+                Some(waker) => waker.clone_from(cx.waker()),
+                None => *waker = Some(cx.waker().clone()),
+            }
+            drop(waker);
+            // A signal sent before the waker was stored has already run its wake.
+            match self.take() {
+                0 => Poll::Pending,
+                pending => Poll::Ready(pending),
+            }
+        })
+        .await
+    }
+
+    /// Returns and resets the number of signals since the previous call,
+    /// without waiting.
+    #[must_use]
+    pub fn take(&self) -> u64 {
+        self.shared.pending.swap(0, Ordering::Acquire)
+    }
+}
+
+impl Drop for Notify {
+    fn drop(&mut self) {
+        self.shared.wake.stop();
+    }
+}
+
+/// A pre-armed, allocation-free way to signal a [`Notify`].
+///
+/// A handle is `Copy` and `Send`, so it can be stored in the context of an
+/// audio or display-link callback. See the [module documentation](self) for
+/// its realtime guarantees.
+///
+/// # Examples
+///
+/// Signalling does not allocate:
+///
+/// ```rust
+/// use native_executor::notify::Notify;
+/// use std::{
+///     alloc::{GlobalAlloc, Layout, System},
+///     cell::Cell,
+///     sync::atomic::{AtomicUsize, Ordering},
+/// };
+///
+/// // Counts the allocations made by the current thread while enabled
+/// struct Counting;
+/// thread_local! {
+///     static COUNTING: Cell<bool> = const { Cell::new(false) };
+/// }
+/// static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+///
+/// unsafe impl GlobalAlloc for Counting {
+///     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+///         if COUNTING.get() {
+///             ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+///         }
+///         unsafe { System.alloc(layout) }
+///     }
+///     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+///         unsafe { System.dealloc(ptr, layout) }
+///     }
+/// }
+///
+/// #[global_allocator]
+/// static ALLOCATOR: Counting = Counting;
+///
+/// let notify = Notify::new().unwrap();
+/// let handle = notify.wake_handle();
+///
+/// COUNTING.set(true);
+/// for _ in 0..1_000 {
+///     handle.signal();
+/// }
+/// COUNTING.set(false);
+///
+/// assert_eq!(ALLOCATIONS.load(Ordering::SeqCst), 0);
+/// assert_eq!(futures_lite::future::block_on(notify.notified()), 1_000);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct WakeHandle<'a> {
+    notify: &'a Notify,
+}
+
+impl<'a> WakeHandle<'a> {
+    /// Creates a handle that signals `notify`.
+    #[must_use]
+    pub const fn for_notify(notify: &'a Notify) -> Self {
+        Self { notify }
+    }
+
+    /// Signals the notifier, waking the task waiting in
+    /// [`Notify::notified`].
+    ///
+    /// Never allocates, takes a lock or blocks.
+    pub fn signal(self) {
+        let shared = &self.notify.shared;
+        shared.pending.fetch_add(1, Ordering::Release);
+        shared.wake.wake();
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+mod imp {
+    use core::ffi::c_void;
+    use std::{io, sync::Arc};
+
+    use dispatch::ffi::{dispatch_release, dispatch_resume, dispatch_set_context};
+
+    use super::Shared;
+    use crate::{
+        Priority,
+        apple::{
+            _dispatch_source_type_data_add, DispatchSource, dispatch_source_cancel,
+            dispatch_source_create, dispatch_source_merge_data,
+            dispatch_source_set_cancel_handler_f, dispatch_source_set_event_handler_f, raw_queue,
+        },
+    };
+
+    /// The `DATA_ADD` source that signals merge into.
+    pub struct Target(DispatchSource);
+
+    // SAFETY: dispatch sources are thread-safe reference-counted objects.
+    unsafe impl Send for Target {}
+    // SAFETY: see above.
+    unsafe impl Sync for Target {}
+
+    impl Target {
+        /// Creates the source, suspended until `start`.
+        pub fn new() -> io::Result<Self> {
+            let kind = &raw const _dispatch_source_type_data_add;
+            let queue = raw_queue(Priority::UserInteractive);
+            // SAFETY: `kind` is a valid source type.
+            let source = unsafe { dispatch_source_create(kind, 0, 0, queue) };
+            if source.is_null() {
+                return Err(io::Error::other("failed to create dispatch source"));
+            }
+            Ok(Self(source))
+        }
+
+        pub fn wake(&self) {
+            // SAFETY: the source is released only when the `Notify` is dropped,
+            // which outlives its wake handles.
+            unsafe { dispatch_source_merge_data(self.0, 1) };
+        }
+
+        pub fn stop(&self) {
+            // SAFETY: the cancel handler releases the context once no event
+            // handler is running anymore.
+            unsafe {
+                dispatch_source_cancel(self.0);
+                dispatch_release(self.0);
+            }
+        }
+    }
+
+    extern "C" fn on_event(context: *mut c_void) {
+        // SAFETY: the context is valid until the cancel handler runs, which
+        // GCD never runs concurrently with the event handler.
+        let shared = unsafe { &*context.cast::<Shared>() };
+        shared.wake();
+    }
+
+    extern "C" fn on_cancel(context: *mut c_void) {
+        // SAFETY: this is the last handler invocation for the source.
+        drop(unsafe { Arc::from_raw(context.cast::<Shared>()) });
+    }
+
+    // Mirrors the fallible implementation of other platforms.
+    #[allow(clippy::unnecessary_wraps)]
+    pub fn start(shared: &Arc<Shared>) -> io::Result<()> {
+        let source = shared.wake.0;
+        // SAFETY: the source is valid and suspended; `on_cancel` reclaims the
+        // context after the last event.
+        unsafe {
+            dispatch_set_context(source, Arc::into_raw(shared.clone()).cast_mut().cast());
+            dispatch_source_set_event_handler_f(source, on_event);
+            dispatch_source_set_cancel_handler_f(source, on_cancel);
+            dispatch_resume(source);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(target_vendor = "apple"))]
+mod imp {
+    use core::sync::atomic::{AtomicBool, Ordering};
+    use std::{
+        io,
+        sync::{Arc, OnceLock},
+        thread::{self, Thread},
+    };
+
+    use super::Shared;
+
+    /// The helper thread that signals unpark.
+    pub struct Target {
+        thread: OnceLock<Thread>,
+        stopped: AtomicBool,
+    }
+
+    impl Target {
+        // Mirrors the fallible Apple implementation.
+        #[allow(clippy::unnecessary_wraps)]
+        pub const fn new() -> io::Result<Self> {
+            Ok(Self {
+                thread: OnceLock::new(),
+                stopped: AtomicBool::new(false),
+            })
+        }
+
+        pub fn wake(&self) {
+            if let Some(thread) = self.thread.get() {
+                thread.unpark();
+            }
+        }
+
+        pub fn stop(&self) {
+            self.stopped.store(true, Ordering::Release);
+            self.wake();
+        }
+    }
+
+    /// Starts the helper thread, which wakes the waiting task after each
+    /// unpark and exits once the `Notify` is dropped.
+    pub fn start(shared: &Arc<Shared>) -> io::Result<()> {
+        let helper = shared.clone();
+        let thread = thread::Builder::new()
+            .name(format!(
+                "{}.notify",
+                crate::config::current().label_prefix()
+            ))
+            .spawn(move || {
+                while !helper.wake.stopped.load(Ordering::Acquire) {
+                    thread::park();
+                    helper.wake();
+                }
+            })?;
+        let _ = shared.wake.thread.set(thread.thread().clone());
+        Ok(())
+    }
+}
@@ -1,9 +1,28 @@
 //! Polyfill executor implementation using async-executor.
 
+use core::{
+    future::Future,
+    pin::pin,
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
 use futures_lite::future::block_on;
-use std::{panic::catch_unwind, sync::OnceLock};
+use std::{
+    cell::Cell,
+    collections::{BTreeMap, BTreeSet},
+    panic::{AssertUnwindSafe, catch_unwind},
+    sync::{
+        Arc, Condvar, Mutex, Once, OnceLock, PoisonError,
+        atomic::{AtomicBool, Ordering},
+    },
+    task::Wake,
+    time::Instant,
+};
 
-use crate::PlatformExecutor;
+use crate::{
+    PlatformExecutor, Priority,
+    main_queue::{Job, MainQueue},
+};
 
 /// Polyfill executor implementation using async-executor.
 /// This executor is used on platforms that do not have a native executor implementation.
@@ -14,66 +33,417 @@ static EXECUTOR: OnceLock<async_executor::Executor<'static>> = OnceLock::new();
 
 fn global() -> &'static async_executor::Executor<'static> {
     EXECUTOR.get_or_init(|| {
-        let exec = async_executor::Executor::new();
-        let num_threads = num_cpus::get().max(1);
+        let num_threads = crate::config::current()
+            .polyfill_thread_count()
+            .unwrap_or_else(|| num_cpus::get().max(1));
         for _ in 0..num_threads {
-            let executor = global();
-            std::thread::spawn(move || {
-                loop {
-                    let _ = catch_unwind(|| {
-                        block_on(executor.run(std::future::pending::<()>()));
+            // Workers block on `global()` until initialization below has finished.
+            let _ = std::thread::Builder::new()
+                .name(format!("{}.worker", crate::label_prefix()))
+                .spawn(|| {
+                    crate::config::run_worker(|| {
+                        let executor = global();
+                        // Ticking takes jobs from the shared queue only,
+                        // rather than batching them into a queue of this
+                        // worker, so a worker waiting in `run_pending` sees
+                        // every pending job.
+                        loop {
+                            let _ = catch_unwind(|| block_on(executor.tick()));
+                        }
                     });
-                }
-            });
+                });
         }
-        exec
+        async_executor::Executor::new()
     })
 }
 
-static MAIN_EXECUTOR: OnceLock<async_executor::Executor<'static>> = OnceLock::new();
+/// Runs one pending job of the worker pool on the current thread.
+///
+/// Returns `false` if no job was pending.
+#[cfg(not(any(target_vendor = "apple", target_arch = "wasm32", target_os = "android")))]
+pub(crate) fn run_pending() -> bool {
+    global().try_tick()
+}
+
+/// Delayed jobs, run by a single thread of their own.
+///
+/// Timers only wake tasks, so they need neither the worker pool nor the main
+/// executor, and awaiting one from a foreign executor, before anything has
+/// been spawned, starts no more than this thread.
+struct TimerQueue {
+    due: Mutex<Due>,
+    changed: Condvar,
+}
+
+struct Due {
+    /// The jobs with their due time, by sequence number, which is the key
+    /// that cancels them.
+    jobs: BTreeMap<u64, (Instant, Job)>,
+    /// The sequence numbers of the jobs in the order they are due; the
+    /// sequence number breaks ties in submission order.
+    order: BTreeSet<(Instant, u64)>,
+    next_seq: u64,
+}
+
+static TIMERS: TimerQueue = TimerQueue {
+    due: Mutex::new(Due {
+        jobs: BTreeMap::new(),
+        order: BTreeSet::new(),
+        next_seq: 0,
+    }),
+    changed: Condvar::new(),
+};
+
+impl TimerQueue {
+    /// Runs `job` once `delay` has elapsed, returning the key that
+    /// [`cancel`](Self::cancel) takes, or `None` if the delay is too long to
+    /// ever elapse.
+    fn push(&self, delay: Duration, job: Job) -> Option<u64> {
+        static STARTED: Once = Once::new();
+
+        // A delay too long to represent never elapses.
+        let at = Instant::now().checked_add(delay)?;
+        STARTED.call_once(|| {
+            let _ = std::thread::Builder::new()
+                .name(format!("{}.timer", crate::label_prefix()))
+                .spawn(|| TIMERS.run());
+        });
+
+        let mut due = self.due.lock().unwrap_or_else(PoisonError::into_inner);
+        let seq = due.next_seq;
+        due.next_seq += 1;
+        due.jobs.insert(seq, (at, job));
+        due.order.insert((at, seq));
+        drop(due);
+        self.changed.notify_one();
+        Some(seq)
+    }
+
+    /// Removes the job of `seq` unless it has started.
+    fn cancel(&self, seq: u64) {
+        let mut due = self.due.lock().unwrap_or_else(PoisonError::into_inner);
+        let job = due.jobs.remove(&seq).map(|(at, job)| {
+            due.order.remove(&(at, seq));
+            job
+        });
+        drop(due);
+        // The job may own values whose destructors submit timers.
+        drop(job);
+    }
+
+    fn run(&self) -> ! {
+        let mut due = self.due.lock().unwrap_or_else(PoisonError::into_inner);
+        loop {
+            let now = Instant::now();
+            due = match due.order.first().copied() {
+                Some((at, seq)) if at <= now => {
+                    due.order.pop_first();
+                    let Some((_, job)) = due.jobs.remove(&seq) else {
+                        continue;
+                    };
+                    drop(due);
+                    let _ = catch_unwind(AssertUnwindSafe(job));
+                    self.due.lock().unwrap_or_else(PoisonError::into_inner)
+                }
+                Some((at, _)) => {
+                    let timeout = at.duration_since(now);
+                    self.changed
+                        .wait_timeout(due, timeout)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+                None => self
+                    .changed
+                    .wait(due)
+                    .unwrap_or_else(PoisonError::into_inner),
+            };
+        }
+    }
+}
+
+static MAIN_QUEUE: MainQueue = MainQueue::new();
+static MAIN_STARTED: AtomicBool = AtomicBool::new(false);
+
+std::thread_local! {
+    /// Whether this thread runs the main executor.
+    static ON_MAIN: Cell<bool> = const { Cell::new(false) };
+}
 
 /// Starts the main executor on a dedicated thread.
 /// This function is blocking and should be called once at the start of the program.
 ///
+/// Work submitted to the main executor before this function is called is queued
+/// and runs as soon as the main executor starts. To drive the main executor
+/// only until a future completes, use [`PolyfillExecutor::run_until`] instead.
+///
+/// # Ordering
+///
+/// Main-thread work runs by priority: work submitted with
+/// [`spawn_main_with_priority`](crate::spawn_main_with_priority) runs ahead
+/// of queued work of a lower priority, and work of equal priority runs in
+/// submission order. Work without a priority, such as that of
+/// [`spawn_main`](crate::spawn_main), is ordered as [`Priority::default`]
+/// work; hooks still observe such tasks at [`Priority::UserInteractive`].
+///
+/// ```rust
+/// # #[cfg(not(any(target_vendor = "apple", target_os = "android")))] {
+/// use futures_lite::future::block_on;
+/// use native_executor::{Priority, polyfill, spawn_main, spawn_main_with_priority};
+/// use std::{sync::{Arc, Mutex}, thread};
+///
+/// let log = Arc::new(Mutex::new(Vec::new()));
+/// let record = |name| {
+///     let log = log.clone();
+///     async move { log.lock().unwrap().push(name) }
+/// };
+///
+/// // Queued before the main executor starts
+/// let tasks = [
+///     spawn_main_with_priority(record("background"), Priority::Background),
+///     spawn_main(record("first")),
+///     spawn_main_with_priority(record("urgent"), Priority::UserInteractive),
+///     spawn_main_with_priority(record("second"), Priority::Default),
+///     spawn_main(record("third")),
+///     spawn_main_with_priority(record("initiated"), Priority::UserInitiated),
+///     spawn_main_with_priority(record("also urgent"), Priority::UserInteractive),
+/// ];
+/// thread::spawn(polyfill::start_main_executor);
+/// tasks.into_iter().for_each(block_on);
+///
+/// assert_eq!(
+///     *log.lock().unwrap(),
+///     ["urgent", "also urgent", "initiated", "first", "second", "third", "background"]
+/// );
+/// # }
+/// ```
+///
 /// # Panics
 ///
 /// Panics if the main executor has already been started.
+#[track_caller]
 pub fn start_main_executor() {
-    let main_exec = async_executor::Executor::new();
-    MAIN_EXECUTOR
-        .set(main_exec)
-        .expect("Main executor already started");
-    let main_exec = MAIN_EXECUTOR
-        .get()
-        .expect("Unexpected error: main executor not set");
+    assert!(
+        !MAIN_STARTED.swap(true, Ordering::AcqRel),
+        "Main executor already started"
+    );
+    ON_MAIN.set(true);
+    crate::main_loop::entered();
     loop {
-        let _ = catch_unwind(|| {
-            block_on(main_exec.run(std::future::pending::<()>()));
-        });
+        let job = MAIN_QUEUE.pop();
+        let _ = catch_unwind(AssertUnwindSafe(job));
+    }
+}
+
+impl PolyfillExecutor {
+    /// Runs the main executor on the current thread until `future` completes,
+    /// and returns its output.
+    ///
+    /// The current thread becomes the main thread for the duration of the
+    /// call: it polls `future` and serves the main queue in between, so
+    /// `future` can use [`spawn_local`](crate::spawn_local) and main-thread
+    /// mailboxes. It does not need to be `Send` or `'static`. Work left on the
+    /// main queue when `future` completes stays queued for the next call or
+    /// for [`start_main_executor`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(not(any(target_vendor = "apple", target_os = "android")))] {
+    /// use native_executor::{Mailbox, NativeExecutor, spawn, spawn_local};
+    /// use std::{panic, rc::Rc, sync::Arc};
+    ///
+    /// let total = NativeExecutor::run_until(async {
+    ///     // Runs on the main thread
+    ///     let mailbox = Arc::new(Mailbox::main(0));
+    ///     let shared = Rc::new(2);
+    ///     let doubled = spawn_local({
+    ///         let shared = shared.clone();
+    ///         async move { *shared * 2 }
+    ///     });
+    ///     let sender = mailbox.clone();
+    ///     spawn(async move { sender.handle(|count| *count += 10) }).await;
+    ///     doubled.await + mailbox.call(|count| *count).await
+    /// });
+    /// assert_eq!(total, 14);
+    ///
+    /// // The main thread is only borrowed, so it can be driven again
+    /// assert_eq!(NativeExecutor::run_until(async { 1 }), 1);
+    ///
+    /// // But not from inside
+    /// let nested = NativeExecutor::run_until(async {
+    ///     panic::catch_unwind(|| NativeExecutor::run_until(async {})).is_err()
+    /// });
+    /// assert!(nested);
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from the main thread, which includes a nested call,
+    /// or while [`start_main_executor`] runs on another thread.
+    #[track_caller]
+    pub fn run_until<F: Future>(future: F) -> F::Output {
+        assert!(
+            !ON_MAIN.get(),
+            "`run_until` cannot be called from the main thread, which is already driven"
+        );
+        assert!(
+            !MAIN_STARTED.swap(true, Ordering::AcqRel),
+            "`run_until` cannot drive the main executor while it runs on another thread"
+        );
+        let _main = BorrowedMain::enter();
+        crate::main_loop::entered();
+
+        let mut future = pin!(future);
+        let woken = Arc::new(MainWaker(AtomicBool::new(true)));
+        let waker = Waker::from(woken.clone());
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if woken.0.swap(false, Ordering::AcqRel)
+                && let Poll::Ready(output) = future.as_mut().poll(&mut cx)
+            {
+                return output;
+            }
+            let job = MAIN_QUEUE.pop();
+            let _ = catch_unwind(AssertUnwindSafe(job));
+        }
+    }
+
+    /// Runs the main executor on the current thread, as
+    /// [`run_until`](Self::run_until) does, until no task has been alive for
+    /// `grace`.
+    ///
+    /// A task counts as alive from the moment it is spawned until it
+    /// completes or is dropped, and so does a closure submitted with
+    /// [`execute`](crate::execute) or one of its variants; a closure of
+    /// [`schedule_after`](crate::schedule_after) or
+    /// [`execute_after`](crate::execute_after) counts until it has run or
+    /// been cancelled. Tasks spawned as daemons with
+    /// [`SpawnOptions::daemon`](crate::SpawnOptions::daemon) never count.
+    /// The grace period restarts whenever a task is spawned during it, so
+    /// programs that spawn detached work and exit once it is all done need
+    /// not join it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(not(any(target_vendor = "apple", target_os = "android")))] {
+    /// use native_executor::{NativeExecutor, SpawnOptions, spawn, timer::{Interval, Timer}};
+    /// use std::{
+    ///     sync::{Arc, atomic::{AtomicBool, Ordering}},
+    ///     time::{Duration, Instant},
+    /// };
+    ///
+    /// let start = Instant::now();
+    ///
+    /// // Ticks forever, but does not keep the process alive
+    /// SpawnOptions::new()
+    ///     .daemon(true)
+    ///     .spawn(async {
+    ///         let mut interval = Interval::every(Duration::from_millis(5));
+    ///         loop {
+    ///             interval.tick().await;
+    ///         }
+    ///     })
+    ///     .detach();
+    ///
+    /// let done = Arc::new(AtomicBool::new(false));
+    /// let flag = done.clone();
+    /// spawn(async move {
+    ///     Timer::after(Duration::from_millis(30)).await;
+    ///     flag.store(true, Ordering::SeqCst);
+    /// })
+    /// .detach();
+    ///
+    /// NativeExecutor::keep_alive_until_idle(Duration::from_millis(20));
+    /// assert!(done.load(Ordering::SeqCst));
+    /// assert!(start.elapsed() >= Duration::from_millis(50));
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`run_until`](Self::run_until).
+    #[track_caller]
+    pub fn keep_alive_until_idle(grace: Duration) {
+        Self::run_until(crate::alive::idle(grace));
+    }
+}
+
+/// Makes the current thread the main thread until dropped.
+struct BorrowedMain;
+
+impl BorrowedMain {
+    fn enter() -> Self {
+        ON_MAIN.set(true);
+        Self
+    }
+}
+
+impl Drop for BorrowedMain {
+    fn drop(&mut self) {
+        ON_MAIN.set(false);
+        MAIN_STARTED.store(false, Ordering::Release);
     }
 }
 
-fn main_executor() -> &'static async_executor::Executor<'static> {
-    MAIN_EXECUTOR.get().expect("Main executor not started")
+/// Wakes the future of [`PolyfillExecutor::run_until`] by queueing an empty
+/// job, which ends the wait for the next main job.
+struct MainWaker(AtomicBool);
+
+impl Wake for MainWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        if !self.0.swap(true, Ordering::AcqRel) {
+            MAIN_QUEUE.push(Box::new(|| {}), Some(Priority::UserInteractive));
+        }
+    }
 }
 
 impl PlatformExecutor for PolyfillExecutor {
-    fn exec(f: impl FnOnce() + Send + 'static, _priority: crate::Priority) {
+    fn exec(f: impl FnOnce() + Send + 'static, priority: Priority) {
+        #[cfg(target_os = "linux")]
+        let f = crate::nice::with_nice(f, priority);
+        #[cfg(not(target_os = "linux"))]
+        let _ = priority;
         global().spawn(async move { f() }).detach();
     }
-    fn exec_after(
-        delay: std::time::Duration,
+    /// About 136 years, which `Instant` represents on every platform.
+    const MAX_DELAY: Duration = Duration::from_secs(1 << 32);
+
+    /// Runs `f` on the timer thread, which suits the short callbacks of
+    /// timers that only wake a task.
+    fn exec_after(delay: Duration, f: impl FnOnce() + Send + 'static, priority: Priority) {
+        Self::exec_after_cancellable(delay, f, priority);
+    }
+
+    /// Removes the closure from the timer thread's queue on cancellation.
+    fn exec_after_cancellable(
+        delay: Duration,
         f: impl FnOnce() + Send + 'static,
-        _priority: crate::Priority,
-    ) {
-        global()
-            .spawn(async move {
-                async_io::Timer::after(delay).await;
-                f();
-            })
-            .detach();
-    }
-    fn exec_main(f: impl FnOnce() + Send + 'static) {
-        main_executor().spawn(async move { f() }).detach();
+        priority: Priority,
+    ) -> Option<u64> {
+        #[cfg(target_os = "linux")]
+        let f = crate::nice::with_nice(f, priority);
+        #[cfg(not(target_os = "linux"))]
+        let f: Job = {
+            let _ = priority;
+            Box::new(f)
+        };
+        TIMERS.push(delay, f)
+    }
+
+    fn cancel_after(key: u64) {
+        TIMERS.cancel(key);
+    }
+    fn exec_main(f: impl FnOnce() + Send + 'static, priority: Option<Priority>) {
+        MAIN_QUEUE.push(Box::new(f), priority);
+    }
+
+    fn is_main_thread() -> bool {
+        ON_MAIN.get()
     }
 }
@@ -0,0 +1,38 @@
+//! Commonly used items, for glob import.
+//!
+//! ```rust
+//! use native_executor::prelude::*;
+//! ```
+//!
+//! The prelude only re-exports items that are part of the stable API of this
+//! crate; adding to it is not considered a breaking change.
+//!
+//! # Examples
+//!
+//! Generic code can be written against [`Executor`] without depending on
+//! `executor_core` directly:
+//!
+//! ```rust
+//! use native_executor::prelude::*;
+//! use std::sync::mpsc;
+//!
+//! fn run_on<E: Executor>(executor: &E) -> i32 {
+//!     let (sender, receiver) = mpsc::channel();
+//!     executor
+//!         .spawn(async move { sender.send(40 + 2).unwrap() })
+//!         .detach();
+//!     receiver.recv().unwrap()
+//! }
+//!
+//! assert_eq!(run_on(&DefaultExecutor), 42);
+//! assert_eq!(run_on(&BackgroundExecutor), 42);
+//! ```
+
+pub use crate::{
+    BackgroundExecutor, DefaultExecutor, Executor, ExecutorTask as _, LocalExecutor, MainExecutor,
+    Priority, Task, UserInitiatedExecutor, UserInteractiveExecutor, UtilityExecutor, spawn,
+    spawn_main, spawn_main_with_priority, spawn_with_priority,
+    timer::{Timer, TimerExt as _, sleep},
+};
+#[cfg(feature = "std")]
+pub use crate::{Mailbox, execute, execute_main, spawn_local};
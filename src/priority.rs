@@ -0,0 +1,336 @@
+//! Task priorities and their platform-specific extensions.
+
+/// Task execution priority levels for controlling scheduler behavior.
+///
+/// These priority levels map to platform-native scheduling priorities,
+/// allowing fine-grained control over task execution order and resource allocation.
+///
+/// From least to most urgent, the standard levels are [`Background`],
+/// [`Utility`], [`Default`], [`UserInitiated`] and [`UserInteractive`].
+/// Backends with fewer levels may run neighbouring levels alike, but never
+/// run a less urgent level ahead of a more urgent one.
+///
+/// [`Background`]: Self::Background
+/// [`Utility`]: Self::Utility
+/// [`Default`]: Self::Default
+/// [`UserInitiated`]: Self::UserInitiated
+/// [`UserInteractive`]: Self::UserInteractive
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Priority {
+    /// Standard priority level for most application tasks.
+    ///
+    /// This is the default priority that provides balanced execution
+    /// suitable for general-purpose async operations.
+    #[default]
+    Default,
+    /// Lower priority for background tasks and non-critical operations.
+    ///
+    /// Background tasks yield CPU time to higher-priority tasks and are
+    /// ideal for operations like cleanup, logging, or data processing
+    /// that don't require immediate completion.
+    Background,
+    /// Higher priority for user-initiated tasks that require prompt execution.
+    /// This priority is suitable for tasks that directly impact user experience,
+    /// such as responding to user input or updating the UI.
+    UserInitiated,
+    /// Highest priority for tasks that require immediate attention to maintain
+    /// application responsiveness.
+    /// This priority should be reserved for critical operations that must
+    /// complete as soon as possible, such as rendering UI updates or handling
+    /// real-time data.
+    UserInteractive,
+    /// Low priority for tasks that can be deferred until the system is less
+    /// busy, above only [`Background`](Self::Background).
+    /// This priority is suitable for maintenance tasks, prefetching data,
+    /// or other operations that do not need to run immediately.
+    Utility,
+    /// Platform-specific scheduling parameters that do not fit one of the
+    /// standard levels.
+    ///
+    /// Backends that cannot express the requested parameters fall back to
+    /// the nearest standard level, see [`PlatformPriority::fallback`].
+    Custom(PlatformPriority),
+}
+
+impl Priority {
+    /// Returns the standard priority level this priority is scheduled at on
+    /// backends without support for custom parameters.
+    ///
+    /// Standard levels map to themselves; [`Priority::Custom`] maps to
+    /// [`PlatformPriority::fallback`].
+    #[must_use]
+    pub const fn standard(self) -> Self {
+        match self {
+            Self::Custom(custom) => custom.fallback(),
+            other => other,
+        }
+    }
+
+    /// Every standard level, from least to most urgent.
+    pub(crate) const ALL: &[Self] = &[
+        Self::Background,
+        Self::Utility,
+        Self::Default,
+        Self::UserInitiated,
+        Self::UserInteractive,
+    ];
+
+    /// Returns the scheduling class backends map this priority to.
+    pub(crate) const fn class(self) -> SchedClass {
+        match self {
+            Self::Background => SchedClass::Background,
+            Self::Utility => SchedClass::Utility,
+            Self::Default => SchedClass::Default,
+            Self::UserInitiated => SchedClass::UserInitiated,
+            Self::UserInteractive => SchedClass::UserInteractive,
+            Self::Custom(custom) => custom.fallback().class(),
+        }
+    }
+}
+
+/// The standard level a [`Priority`] is scheduled at, from least to most
+/// urgent.
+///
+/// Unlike [`Priority`], this enum is exhaustive within the crate, so a backend
+/// matching on it fails to compile until a new level is mapped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SchedClass {
+    Background,
+    Utility,
+    Default,
+    UserInitiated,
+    UserInteractive,
+}
+
+impl SchedClass {
+    /// The number of classes.
+    pub const COUNT: usize = 5;
+
+    /// Returns the position of this class in urgency order, below
+    /// [`COUNT`](Self::COUNT).
+    pub const fn index(self) -> usize {
+        self as usize
+    }
+}
+
+// `Priority::ALL` lists every class once, in urgency order.
+const _: () = {
+    assert!(Priority::ALL.len() == SchedClass::COUNT);
+    let mut i = 0;
+    while i < Priority::ALL.len() {
+        assert!(Priority::ALL[i].class().index() == i);
+        i += 1;
+    }
+};
+
+/// Quality-of-service classes understood by Apple's scheduler.
+///
+/// These mirror the `qos_class_t` constants used by Grand Central Dispatch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QosClass {
+    /// `QOS_CLASS_USER_INTERACTIVE`
+    UserInteractive,
+    /// `QOS_CLASS_USER_INITIATED`
+    UserInitiated,
+    /// `QOS_CLASS_DEFAULT`
+    Default,
+    /// `QOS_CLASS_UTILITY`
+    Utility,
+    /// `QOS_CLASS_BACKGROUND`
+    Background,
+}
+
+impl QosClass {
+    /// Returns the raw `qos_class_t` value of this class.
+    #[must_use]
+    pub const fn as_raw(self) -> u32 {
+        match self {
+            Self::UserInteractive => 0x21,
+            Self::UserInitiated => 0x19,
+            Self::Default => 0x15,
+            Self::Utility => 0x11,
+            Self::Background => 0x09,
+        }
+    }
+}
+
+/// Opaque platform scheduling parameters carried by [`Priority::Custom`].
+///
+/// A `PlatformPriority` is built from backend-specific parameters. Every value
+/// also records the nearest standard [`Priority`], which is used by backends
+/// that cannot express the requested parameters.
+///
+/// # Examples
+///
+/// ```rust
+/// use native_executor::{PlatformPriority, Priority, QosClass, spawn_with_priority};
+///
+/// // Slightly below other user-initiated work on Apple platforms
+/// let priority = Priority::Custom(PlatformPriority::qos(QosClass::UserInitiated, -4));
+/// assert_eq!(priority.standard(), Priority::UserInitiated);
+///
+/// // A nice value on Linux-based backends
+/// let priority = Priority::Custom(PlatformPriority::nice(7));
+/// assert_eq!(priority.standard(), Priority::Utility);
+///
+/// let task = spawn_with_priority(async { 1 + 1 }, priority);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PlatformPriority {
+    kind: PlatformPriorityKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum PlatformPriorityKind {
+    Qos { class: QosClass, relative: i8 },
+    Nice(i8),
+}
+
+/// The lowest relative priority accepted within a quality-of-service class (`QOS_MIN_RELATIVE_PRIORITY`).
+pub const QOS_MIN_RELATIVE_PRIORITY: i8 = -15;
+
+impl PlatformPriority {
+    /// Creates a priority from an Apple quality-of-service class and a relative priority
+    /// within that class.
+    ///
+    /// `relative` is clamped to the range accepted by GCD, `-15..=0`.
+    /// On Apple platforms tasks run on a queue created with
+    /// `dispatch_queue_attr_make_with_qos_class`; other backends use the
+    /// standard level matching `class`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(target_vendor = "apple")] {
+    /// use native_executor::{PlatformPriority, Priority, QosClass, spawn_with_priority};
+    /// use std::sync::mpsc;
+    ///
+    /// unsafe extern "C" {
+    ///     fn pthread_self() -> *mut core::ffi::c_void;
+    ///     fn pthread_get_qos_class_np(
+    ///         thread: *mut core::ffi::c_void,
+    ///         qos_class: *mut u32,
+    ///         relative_priority: *mut i32,
+    ///     ) -> i32;
+    /// }
+    ///
+    /// let (sender, receiver) = mpsc::channel();
+    /// let priority = Priority::Custom(PlatformPriority::qos(QosClass::UserInitiated, -4));
+    /// spawn_with_priority(
+    ///     async move {
+    ///         let (mut class, mut relative) = (0, 0);
+    ///         unsafe { pthread_get_qos_class_np(pthread_self(), &mut class, &mut relative) };
+    ///         sender.send((class, relative)).unwrap();
+    ///     },
+    ///     priority,
+    /// )
+    /// .detach();
+    ///
+    /// assert_eq!(receiver.recv().unwrap(), (QosClass::UserInitiated.as_raw(), -4));
+    /// # }
+    /// ```
+    #[must_use]
+    pub const fn qos(class: QosClass, relative: i8) -> Self {
+        let relative = if relative > 0 {
+            0
+        } else if relative < QOS_MIN_RELATIVE_PRIORITY {
+            QOS_MIN_RELATIVE_PRIORITY
+        } else {
+            relative
+        };
+        Self {
+            kind: PlatformPriorityKind::Qos { class, relative },
+        }
+    }
+
+    /// Creates a priority from a Linux nice value.
+    ///
+    /// `value` is clamped to `-20..=19`. On Android, and with the polyfill
+    /// backend on Linux, the thread running the job applies this nice value
+    /// for the duration of the job; other backends use the nearest standard
+    /// level. The thread returns to its own nice value after the job.
+    ///
+    /// Lowering a thread's nice value requires `CAP_SYS_NICE` or a sufficient
+    /// `RLIMIT_NICE`. Without them, a value below the thread's own cannot be
+    /// applied, and a value above it is not applied either, as the thread
+    /// could not return from it. The job then runs at the thread's own value,
+    /// and the first such case is reported on stderr.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(all(target_os = "linux", feature = "polyfill"))] {
+    /// use native_executor::{PlatformPriority, Priority, spawn_with_priority};
+    /// use std::sync::mpsc;
+    ///
+    /// let (sender, receiver) = mpsc::channel();
+    /// spawn_with_priority(
+    ///     async move {
+    ///         let tid = unsafe { libc::gettid() }.cast_unsigned();
+    ///         let nice = unsafe { libc::getpriority(libc::PRIO_PROCESS, tid) };
+    ///         sender.send(nice).unwrap();
+    ///     },
+    ///     Priority::Custom(PlatformPriority::nice(7)),
+    /// )
+    /// .detach();
+    ///
+    /// // Unprivileged processes usually cannot return from a raised value
+    /// assert!(matches!(receiver.recv().unwrap(), 0 | 7));
+    /// # }
+    /// ```
+    #[must_use]
+    pub const fn nice(value: i8) -> Self {
+        let value = if value < -20 {
+            -20
+        } else if value > 19 {
+            19
+        } else {
+            value
+        };
+        Self {
+            kind: PlatformPriorityKind::Nice(value),
+        }
+    }
+
+    /// Returns the quality-of-service class and relative priority, if this value was built
+    /// with [`PlatformPriority::qos`].
+    #[must_use]
+    pub const fn as_qos(self) -> Option<(QosClass, i8)> {
+        match self.kind {
+            PlatformPriorityKind::Qos { class, relative } => Some((class, relative)),
+            PlatformPriorityKind::Nice(_) => None,
+        }
+    }
+
+    /// Returns the nice value, if this value was built with
+    /// [`PlatformPriority::nice`].
+    #[must_use]
+    pub const fn as_nice(self) -> Option<i8> {
+        match self.kind {
+            PlatformPriorityKind::Nice(value) => Some(value),
+            PlatformPriorityKind::Qos { .. } => None,
+        }
+    }
+
+    /// Returns the standard priority level closest to these parameters.
+    #[must_use]
+    pub const fn fallback(self) -> Priority {
+        match self.kind {
+            PlatformPriorityKind::Qos { class, .. } => match class {
+                QosClass::UserInteractive => Priority::UserInteractive,
+                QosClass::UserInitiated => Priority::UserInitiated,
+                QosClass::Default => Priority::Default,
+                QosClass::Utility => Priority::Utility,
+                QosClass::Background => Priority::Background,
+            },
+            PlatformPriorityKind::Nice(value) => match value {
+                ..=-10 => Priority::UserInteractive,
+                -9..=-1 => Priority::UserInitiated,
+                0 => Priority::Default,
+                1..=9 => Priority::Utility,
+                _ => Priority::Background,
+            },
+        }
+    }
+}
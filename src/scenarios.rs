@@ -0,0 +1,96 @@
+//! The scenarios of the demo binary.
+//!
+//! Each scenario is an async function to be driven on the main thread, and
+//! returns what it observed so that `tests/scenarios.rs` can check it. The
+//! binary prints the results; the file is not part of the library.
+
+use native_executor::{
+    Mailbox, Priority, ShutdownReport, TaskGroup, spawn_main, spawn_with_priority,
+    timer::{Interval, TimeoutError, Timer, timeout},
+};
+use std::{
+    future::pending,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// The period of the ticks of [`ticks`].
+pub const TICK_PERIOD: Duration = Duration::from_millis(20);
+
+/// Waits on a platform-native timer, then greets.
+pub async fn hello() -> &'static str {
+    Timer::after(Duration::from_millis(100)).await;
+    "Hello, world from native-executor!"
+}
+
+/// Collects progress reports from workers at different priorities in a
+/// main-thread mailbox.
+///
+/// Returns the reports sorted by worker name, then by step.
+pub async fn report_progress() -> Vec<(&'static str, u32)> {
+    // `Mailbox::main` spawns a local task, so it is created on the main thread.
+    let mailbox = Arc::new(spawn_main(async { Mailbox::main(Vec::new()) }).await);
+
+    let workers = [
+        ("background", Priority::Background),
+        ("default", Priority::Default),
+        ("user-initiated", Priority::UserInitiated),
+    ]
+    .map(|(name, priority)| {
+        let mailbox = mailbox.clone();
+        spawn_with_priority(
+            async move {
+                for step in 1..=3 {
+                    Timer::after(Duration::from_millis(10)).await;
+                    mailbox.handle(move |log: &mut Vec<(&str, u32)>| log.push((name, step)));
+                }
+            },
+            priority,
+        )
+    });
+    for worker in workers {
+        worker.await;
+    }
+
+    let mut log = mailbox.call(|log| log.clone()).await;
+    // Workers interleave freely; sort for a stable transcript.
+    log.sort_unstable();
+    log
+}
+
+/// Waits for `count` ticks of an [`Interval`] of [`TICK_PERIOD`].
+///
+/// Returns the time elapsed at each tick, the first of which is immediate.
+pub async fn ticks(count: usize) -> Vec<Duration> {
+    let start = Instant::now();
+    let mut interval = Interval::every(TICK_PERIOD);
+    let mut elapsed = Vec::with_capacity(count);
+    for _ in 0..count {
+        interval.tick().await;
+        elapsed.push(start.elapsed());
+    }
+    elapsed
+}
+
+/// Races quick work and stalled work against a deadline of 50 ms each.
+///
+/// Returns the outcome of the quick work, then of the stalled work.
+pub async fn timeout_race() -> (Result<&'static str, TimeoutError>, Result<(), TimeoutError>) {
+    let deadline = Duration::from_millis(50);
+    let quick = timeout(deadline, async { "quick work" }).await;
+    let stalled = timeout(deadline, pending::<()>()).await;
+    (quick, stalled)
+}
+
+/// Shuts down a group of workers: the background lane right away, the
+/// others after a grace period of 100 ms.
+///
+/// A default-priority worker finishes within the grace period, and one
+/// that never finishes is aborted once it has passed.
+pub async fn shutdown() -> ShutdownReport {
+    let mut group = TaskGroup::new();
+    group.spawn_with_priority(pending(), Priority::Background);
+    group.spawn_with_priority(Timer::after(Duration::from_millis(10)), Priority::Default);
+    group.spawn_with_priority(pending(), Priority::UserInitiated);
+    group.shutdown(Duration::from_millis(100)).await
+}
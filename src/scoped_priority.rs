@@ -0,0 +1,259 @@
+//! Running a section of a task at another priority, without spawning.
+//!
+//! [`with_priority`] makes a priority ambient while its future is polled, so
+//! tasks spawned with [`spawn`](crate::spawn) and timers started there
+//! inherit it. Its waker also tags the wake-ups it forwards, and a tagged
+//! wake-up reschedules the owning task at the overridden priority: whenever
+//! the task is suspended at this await point, its next poll is queued at the
+//! section's priority rather than its own.
+//!
+//! Tags are kept by task id in a small table, consulted only while it holds
+//! any, so tasks that never use [`with_priority`] pay a single atomic load
+//! per reschedule.
+
+use core::{
+    cell::Cell,
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    task::{Context, Poll, Waker},
+};
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    task::Wake,
+};
+
+use crate::Priority;
+
+std::thread_local! {
+    /// The ambient priority of the future being polled on this thread.
+    static AMBIENT: Cell<Option<Priority>> = const { Cell::new(None) };
+    /// The id of the task being polled on this thread.
+    static POLLED: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// The priorities that the next reschedule of tasks is tagged with, by task
+/// id.
+static TAGS: Mutex<Vec<(u64, Priority)>> = Mutex::new(Vec::new());
+/// The number of entries in [`TAGS`].
+static TAGGED: AtomicUsize = AtomicUsize::new(0);
+
+/// Tags the next reschedule of `task` with `priority`, unless a section
+/// nested deeper has already tagged it.
+fn tag(task: u64, priority: Priority) {
+    let mut tags = TAGS.lock().unwrap_or_else(PoisonError::into_inner);
+    if tags.iter().all(|(tagged, _)| *tagged != task) {
+        tags.push((task, priority));
+        TAGGED.fetch_add(1, Ordering::AcqRel);
+    }
+    drop(tags);
+}
+
+/// Takes the priority that the next reschedule of `task` is tagged with, if
+/// any.
+pub fn take_tag(task: u64) -> Option<Priority> {
+    if TAGGED.load(Ordering::Acquire) == 0 {
+        return None;
+    }
+    let mut tags = TAGS.lock().unwrap_or_else(PoisonError::into_inner);
+    let index = tags.iter().position(|(tagged, _)| *tagged == task)?;
+    TAGGED.fetch_sub(1, Ordering::AcqRel);
+    Some(tags.swap_remove(index).1)
+}
+
+/// Runs `poll`, a poll of the task `task`, marking it as the task being
+/// polled on this thread.
+pub fn polling<R>(task: u64, poll: impl FnOnce() -> R) -> R {
+    /// Restores the previous marker, also when `poll` panics.
+    struct Restore(Option<u64>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            POLLED.set(self.0);
+        }
+    }
+
+    let _restore = Restore(POLLED.replace(Some(task)));
+    poll()
+}
+
+/// Returns the ambient priority set by [`with_priority`] for the future being
+/// polled, if any.
+#[must_use]
+pub fn current_priority() -> Option<Priority> {
+    AMBIENT.get()
+}
+
+/// Runs `future` as a section of the current task at `priority`.
+///
+/// While `future` is polled, `priority` is ambient: [`current_priority`]
+/// returns it, and [`spawn`](crate::spawn) and the timers of
+/// [`timer`](crate::timer) use it. Wake-ups that reach the task through
+/// `future` reschedule the whole task at `priority`, so while the task is
+/// suspended at this await point, its next poll is queued as if it had been
+/// spawned at `priority`. Hooks observe the override in
+/// [`TaskInfo::priority`](crate::hooks::TaskInfo::priority). Once `future`
+/// completes, the task is scheduled at its own priority again.
+///
+/// Wake-ups that reach the task some other way, such as those of a future
+/// joined with this one, are not affected. Outside of the tasks of this
+/// crate, only the ambient priority applies.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(not(any(target_vendor = "apple", target_os = "android")))] {
+/// use futures_lite::future::block_on;
+/// use native_executor::{
+///     ExecContext, Priority, current_context, spawn, timer::Timer, with_priority,
+/// };
+/// use std::time::Duration;
+///
+/// let task = spawn(async {
+///     let before = current_context();
+///     let (inside, nested) = with_priority(
+///         async {
+///             // Resumed by the timer, at the section's priority
+///             Timer::after(Duration::from_millis(1)).await;
+///             let nested = spawn(async { current_context() });
+///             (current_context(), nested.await)
+///         },
+///         Priority::Background,
+///     )
+///     .await;
+///     Timer::after(Duration::from_millis(1)).await;
+///     (before, inside, nested, current_context())
+/// });
+///
+/// let worker = ExecContext::Worker;
+/// assert_eq!(
+///     block_on(task),
+///     (
+///         worker(Priority::Default),
+///         worker(Priority::Background),
+///         worker(Priority::Background),
+///         worker(Priority::Default),
+///     )
+/// );
+/// # }
+/// ```
+pub const fn with_priority<F: Future>(future: F, priority: Priority) -> WithPriority<F> {
+    WithPriority {
+        future,
+        priority,
+        section: None,
+        waker: None,
+    }
+}
+
+/// The future returned by [`with_priority`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct WithPriority<F> {
+    future: F,
+    priority: Priority,
+    /// The section, once first polled in a task.
+    section: Option<Arc<Section>>,
+    /// The waker passed in by the last poll, and the tagging waker that
+    /// forwards to it.
+    waker: Option<(Waker, Waker)>,
+}
+
+/// A [`with_priority`] section of a task.
+#[derive(Debug)]
+struct Section {
+    task: u64,
+    priority: Priority,
+    /// Cleared once the section has ended, after which late wake-ups are not
+    /// tagged.
+    active: AtomicBool,
+}
+
+/// A waker that tags wake-ups with the priority of its section before
+/// forwarding them.
+struct Tagging {
+    section: Arc<Section>,
+    inner: Waker,
+}
+
+impl Tagging {
+    fn tag(&self) {
+        if self.section.active.load(Ordering::Acquire) {
+            tag(self.section.task, self.section.priority);
+        }
+    }
+}
+
+impl Wake for Tagging {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.tag();
+        self.inner.wake_by_ref();
+    }
+}
+
+impl<F: Future> Future for WithPriority<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        /// Restores the previous ambient priority, also when `poll` panics.
+        struct Restore(Option<Priority>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                AMBIENT.set(self.0);
+            }
+        }
+
+        // SAFETY: `future` is structurally pinned and never moved out; the
+        // other fields are not pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        let _restore = Restore(AMBIENT.replace(Some(this.priority)));
+
+        if this.section.is_none() {
+            this.section = POLLED.get().map(|task| {
+                Arc::new(Section {
+                    task,
+                    priority: this.priority,
+                    active: AtomicBool::new(true),
+                })
+            });
+        }
+        let Some(section) = &this.section else {
+            return future.poll(cx);
+        };
+        let (_, waker) = match this.waker.take() {
+            Some((inner, tagging)) if inner.will_wake(cx.waker()) => {
+                this.waker.insert((inner, tagging))
+            }
+            _ => this.waker.insert((
+                cx.waker().clone(),
+                Waker::from(Arc::new(Tagging {
+                    section: section.clone(),
+                    inner: cx.waker().clone(),
+                })),
+            )),
+        };
+
+        let poll = future.poll(&mut Context::from_waker(waker));
+        if poll.is_ready() {
+            section.active.store(false, Ordering::Release);
+        }
+        poll
+    }
+}
+
+impl<F> Drop for WithPriority<F> {
+    fn drop(&mut self) {
+        // A tag left behind by a task dropped while woken would keep every
+        // reschedule looking up the table.
+        if let Some(section) = &self.section {
+            section.active.store(false, Ordering::Release);
+            take_tag(section.task);
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! Tasks that run one at a time per key.
+//!
+//! Each key in use has a lane: the task that runs, and those waiting for it
+//! in submission order. A task waits for its turn before its first poll, and
+//! hands the turn to the next one when it completes or is dropped. A lane is
+//! removed once its last task has ended, so keys that come and go do not
+//! accumulate.
+
+use alloc::{boxed::Box, collections::VecDeque, vec::Vec};
+use core::{
+    any::{Any, TypeId},
+    future::Future,
+    hash::Hash,
+    pin::Pin,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    task::{Context, Poll, Waker},
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use async_task::Task;
+
+use crate::spawn;
+
+/// The tasks of one key.
+struct Lane {
+    /// The task holding the turn.
+    active: u64,
+    /// The tasks waiting for the turn, in submission order, with the waker of
+    /// their last poll.
+    waiting: VecDeque<(u64, Option<Waker>)>,
+}
+
+/// The lanes of every key type, each a `HashMap<Arc<K>, Lane>`.
+static LANES: Mutex<Vec<(TypeId, Box<dyn Any + Send>)>> = Mutex::new(Vec::new());
+/// The number of lanes of every key type.
+static LANE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Runs `f` on the lanes of the key type `K`.
+fn with_lanes<K, R>(f: impl FnOnce(&mut HashMap<Arc<K>, Lane>) -> R) -> R
+where
+    K: Hash + Eq + Send + Sync + 'static,
+{
+    let mut types = LANES.lock().unwrap_or_else(PoisonError::into_inner);
+    let index = types
+        .iter()
+        .position(|(id, _)| *id == TypeId::of::<K>())
+        .unwrap_or_else(|| {
+            types.push((TypeId::of::<K>(), Box::new(HashMap::<Arc<K>, Lane>::new())));
+            types.len() - 1
+        });
+    let lanes = types[index]
+        .1
+        .downcast_mut()
+        .expect("lanes are stored under the type id of their key");
+    let result = f(lanes);
+    drop(types);
+    result
+}
+
+/// The place of a task in the lane of its key.
+struct Turn<K: Hash + Eq + Send + Sync + 'static> {
+    key: Arc<K>,
+    id: u64,
+    /// Whether the task has been seen holding the turn, after which it keeps
+    /// it without looking up the lane.
+    started: bool,
+}
+
+impl<K: Hash + Eq + Send + Sync + 'static> Turn<K> {
+    /// Queues a task in the lane of `key`, creating the lane if needed.
+    fn new(key: K) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let key = Arc::new(key);
+        let started = with_lanes(|lanes| {
+            if let Some(lane) = lanes.get_mut(&key) {
+                lane.waiting.push_back((id, None));
+                false
+            } else {
+                lanes.insert(
+                    key.clone(),
+                    Lane {
+                        active: id,
+                        waiting: VecDeque::new(),
+                    },
+                );
+                LANE_COUNT.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+        });
+        Self { key, id, started }
+    }
+
+    /// Returns `true` if the task holds the turn; otherwise the waker of `cx`
+    /// is woken once it does.
+    fn poll_started(&mut self, cx: &Context<'_>) -> bool {
+        if !self.started {
+            self.started = with_lanes(|lanes: &mut HashMap<Arc<K>, Lane>| {
+                let lane = lanes.get_mut(&self.key).expect("a queued task has a lane");
+                if lane.active == self.id {
+                    return true;
+                }
+                let (_, waker) = lane
+                    .waiting
+                    .iter_mut()
+                    .find(|(id, _)| *id == self.id)
+                    .expect("a queued task is in its lane");
+                match waker {
+                    Some(waker) if waker.will_wake(cx.waker()) => {}
+                    _ => *waker = Some(cx.waker().clone()),
+                }
+                false
+            });
+        }
+        self.started
+    }
+}
+
+impl<K: Hash + Eq + Send + Sync + 'static> Drop for Turn<K> {
+    fn drop(&mut self) {
+        let next = with_lanes(|lanes: &mut HashMap<Arc<K>, Lane>| {
+            let lane = lanes.get_mut(&self.key).expect("a queued task has a lane");
+            if lane.active != self.id {
+                // Dropped while waiting
+                lane.waiting.retain(|(id, _)| *id != self.id);
+                return None;
+            }
+            if let Some((next, waker)) = lane.waiting.pop_front() {
+                lane.active = next;
+                return waker;
+            }
+            lanes.remove(&self.key);
+            LANE_COUNT.fetch_sub(1, Ordering::Relaxed);
+            None
+        });
+        if let Some(waker) = next {
+            waker.wake();
+        }
+    }
+}
+
+/// A future that waits for its turn before it is polled, and hands the turn
+/// on once it completes or is dropped.
+struct Sequenced<K: Hash + Eq + Send + Sync + 'static, Fut> {
+    future: Fut,
+    /// `None` once the future has completed.
+    turn: Option<Turn<K>>,
+}
+
+impl<K: Hash + Eq + Send + Sync + 'static, Fut: Future> Future for Sequenced<K, Fut> {
+    type Output = Fut::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Fut::Output> {
+        // SAFETY: `future` is structurally pinned and never moved out; `turn`
+        // is not pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        let turn = this
+            .turn
+            .as_mut()
+            .expect("`Sequenced` polled after completion");
+        if !turn.poll_started(cx) {
+            return Poll::Pending;
+        }
+        let output = unsafe { Pin::new_unchecked(&mut this.future) }.poll(cx);
+        if output.is_ready() {
+            // The next task may start before this one is dropped.
+            this.turn = None;
+        }
+        output
+    }
+}
+
+/// Spawns `future` on the thread pool, running it only once the tasks spawned
+/// before with the same `key` have ended.
+///
+/// Tasks that share a key run one at a time, in the order they were spawned;
+/// tasks with different keys run in parallel, as [`spawn`] tasks do. A task
+/// ends when its future completes or when it is cancelled, whether it was
+/// running or still waiting for its turn. This serializes the work on an
+/// entity without a [`Mailbox`](crate::Mailbox) when there is no state to
+/// own.
+///
+/// A key is only kept while a task of it is running or waiting; see
+/// [`sequenced_keys`].
+///
+/// # Examples
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::{sequenced_keys, spawn_sequenced, timer::Timer};
+/// use std::{
+///     sync::Mutex,
+///     time::{Duration, Instant},
+/// };
+///
+/// static LOG: Mutex<Vec<(u32, u32)>> = Mutex::new(Vec::new());
+/// static RUNNING: Mutex<[bool; 10]> = Mutex::new([false; 10]);
+///
+/// // 100 updates of 10 entities, each taking a pseudo-random 1 to 8 ms
+/// let start = Instant::now();
+/// let mut total = Duration::ZERO;
+/// let tasks: Vec<_> = (0..100u32)
+///     .map(|n| {
+///         let (entity, delay) = (n % 10, Duration::from_millis(u64::from(1 + n * 7919 % 8)));
+///         total += delay;
+///         spawn_sequenced(entity, async move {
+///             let index = entity as usize;
+///             assert!(!std::mem::replace(&mut RUNNING.lock().unwrap()[index], true));
+///             Timer::after(delay).await;
+///             RUNNING.lock().unwrap()[index] = false;
+///             LOG.lock().unwrap().push((entity, n));
+///         })
+///     })
+///     .collect();
+/// tasks.into_iter().for_each(block_on);
+///
+/// // In submission order per entity
+/// for entity in 0..10 {
+///     let updates: Vec<_> = LOG.lock().unwrap().iter().filter(|(e, _)| *e == entity).map(|(_, n)| *n).collect();
+///     assert!(updates.is_sorted() && updates.len() == 10);
+/// }
+/// // Entities in parallel
+/// assert!(start.elapsed() < total / 2, "{:?} of {total:?}", start.elapsed());
+/// // Idle keys are forgotten
+/// assert_eq!(sequenced_keys(), 0);
+/// ```
+///
+/// A cancelled task hands its turn on, also while it waits:
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::spawn_sequenced;
+/// use std::sync::mpsc;
+///
+/// let (release, released) = mpsc::channel::<()>();
+/// let first = spawn_sequenced("user-42", async move { released.recv().unwrap() });
+/// let waiting = spawn_sequenced("user-42", async { unreachable!() });
+/// let last = spawn_sequenced("user-42", async { "done" });
+///
+/// drop(waiting);
+/// release.send(()).unwrap();
+/// block_on(first);
+/// assert_eq!(block_on(last), "done");
+/// ```
+#[track_caller]
+pub fn spawn_sequenced<K, Fut>(key: K, future: Fut) -> Task<Fut::Output>
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    Fut: Future + Send + 'static,
+    Fut::Output: Send,
+{
+    spawn(Sequenced {
+        future,
+        turn: Some(Turn::new(key)),
+    })
+}
+
+/// Returns the number of keys of [`spawn_sequenced`] with a task running or
+/// waiting.
+#[must_use]
+pub fn sequenced_keys() -> usize {
+    LANE_COUNT.load(Ordering::Relaxed)
+}
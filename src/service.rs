@@ -0,0 +1,570 @@
+//! Request/response services backed by a mailbox.
+//!
+//! A [`Service`] is a value that answers requests, such as a connection pool
+//! or a cache in front of a backend. Owned by a [`Mailbox`], its state is only
+//! ever touched by one job at a time; a [`ServiceHandle`] then adds what most
+//! callers of such a value end up needing: a limit on the requests in flight,
+//! a bounded queue of requests waiting for one of them, and a per-request
+//! timeout.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # #[cfg(not(target_vendor = "apple"))] {
+//! use futures_lite::future::block_on;
+//! use native_executor::{Mailbox, polyfill, service::{Service, ServiceBuilder}, spawn_main};
+//! use std::time::Duration;
+//!
+//! struct Greeter {
+//!     visitors: u32,
+//! }
+//!
+//! impl Service<String> for Greeter {
+//!     type Response = String;
+//!
+//!     fn call(&mut self, name: String) -> impl Future<Output = String> + Send + 'static {
+//!         self.visitors += 1;
+//!         let visitor = self.visitors;
+//!         async move { format!("hello {name}, visitor #{visitor}") }
+//!     }
+//! }
+//!
+//! std::thread::spawn(polyfill::start_main_executor);
+//! let greeter = block_on(spawn_main(async { Mailbox::main(Greeter { visitors: 0 }) }));
+//! let greeter = ServiceBuilder::new()
+//!     .concurrency_limit(4)
+//!     .max_queued(64)
+//!     .timeout(Duration::from_secs(1))
+//!     .build(greeter);
+//!
+//! assert_eq!(block_on(greeter.call("Ada".into())).unwrap(), "hello Ada, visitor #1");
+//! assert_eq!(block_on(greeter.call("Alan".into())).unwrap(), "hello Alan, visitor #2");
+//! # }
+//! ```
+
+use core::{
+    fmt,
+    future::{Future, poll_fn},
+    pin::{Pin, pin},
+    sync::atomic::{AtomicUsize, Ordering},
+    task::Poll,
+    time::Duration,
+};
+use std::sync::Arc;
+
+use async_channel::{Receiver, Sender, bounded};
+
+use crate::{Mailbox, timer::timeout};
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// A value that answers requests of type `Req`.
+///
+/// `call` runs as a job of the mailbox that owns the value, with exclusive
+/// access to it, and returns the future of the response. That future does
+/// not borrow the value: it is awaited by the caller of
+/// [`ServiceHandle::call`] while the mailbox goes on with its next job. State
+/// changes therefore happen in `call` itself, and the future holds whatever
+/// it needs to finish the request, such as a cloned client.
+///
+/// A panic in `call` ends the background task of the mailbox, after which
+/// every request fails with [`ServiceError::Closed`].
+pub trait Service<Req> {
+    /// The answer to a request.
+    type Response: Send + 'static;
+
+    /// Starts handling `req`, returning the future of the response.
+    fn call(&mut self, req: Req) -> impl Future<Output = Self::Response> + Send + 'static;
+}
+
+/// The mailbox of a service, with the service type erased.
+trait Backend<Req, Res>: Send + Sync {
+    /// Returns the response, or `None` if the mailbox ended first.
+    fn call(&self, req: Req) -> BoxFuture<'_, Option<Res>>;
+    fn ended(&self) -> BoxFuture<'_, ()>;
+    fn close(&self) -> BoxFuture<'_, ()>;
+}
+
+impl<T, Req> Backend<Req, T::Response> for Mailbox<T>
+where
+    T: Service<Req> + 'static,
+    Req: Send + 'static,
+{
+    fn call(&self, req: Req) -> BoxFuture<'_, Option<T::Response>> {
+        Box::pin(async move {
+            // The returned future captures the lifetime of `service` in its
+            // type, although it outlives it; erasing the type drops it.
+            let response = self
+                .try_call(move |service| -> BoxFuture<'static, _> { Box::pin(service.call(req)) })
+                .await?;
+            Some(response.await)
+        })
+    }
+
+    fn ended(&self) -> BoxFuture<'_, ()> {
+        Box::pin(Self::ended(self))
+    }
+
+    fn close(&self) -> BoxFuture<'_, ()> {
+        Box::pin(Self::close(self))
+    }
+}
+
+/// The reason a [`ServiceHandle::call`] failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ServiceError {
+    /// The mailbox of the service was closed, or its background task ended,
+    /// before the request was answered.
+    Closed,
+    /// The request was not answered within the
+    /// [timeout](ServiceBuilder::timeout).
+    Timeout,
+    /// Every request slot was taken and the queue of waiting requests was
+    /// full.
+    Overloaded,
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Closed => "service is closed: its mailbox has ended",
+            Self::Timeout => "service did not answer before the timeout elapsed",
+            Self::Overloaded => "service is overloaded: its queue of waiting requests is full",
+        })
+    }
+}
+
+impl std::error::Error for ServiceError {}
+
+/// Configures the limits of a [`ServiceHandle`].
+///
+/// By default, requests are neither limited nor timed out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServiceBuilder {
+    concurrency_limit: Option<usize>,
+    max_queued: usize,
+    timeout: Option<Duration>,
+}
+
+impl Default for ServiceBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ServiceBuilder {
+    /// Creates a builder without limits.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            concurrency_limit: None,
+            max_queued: usize::MAX,
+            timeout: None,
+        }
+    }
+
+    /// Allows at most `limit` requests in flight at once.
+    ///
+    /// A request is in flight from the moment it is queued on the mailbox
+    /// until its response future completes. Further requests wait, in the
+    /// queue bounded by [`max_queued`](Self::max_queued), for one of them to
+    /// finish.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `limit` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(not(target_vendor = "apple"))] {
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{
+    ///     Mailbox, polyfill, service::{Service, ServiceBuilder}, spawn, spawn_main, timer::Timer,
+    /// };
+    /// use std::{
+    ///     sync::atomic::{AtomicUsize, Ordering},
+    ///     thread,
+    ///     time::{Duration, Instant},
+    /// };
+    ///
+    /// static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+    /// static PEAK: AtomicUsize = AtomicUsize::new(0);
+    ///
+    /// struct Upload;
+    ///
+    /// impl Service<u32> for Upload {
+    ///     type Response = u32;
+    ///
+    ///     fn call(&mut self, chunk: u32) -> impl Future<Output = u32> + Send + 'static {
+    ///         async move {
+    ///             let in_flight = IN_FLIGHT.fetch_add(1, Ordering::SeqCst) + 1;
+    ///             PEAK.fetch_max(in_flight, Ordering::SeqCst);
+    ///             Timer::after(Duration::from_millis(50)).await;
+    ///             IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+    ///             chunk
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// thread::spawn(polyfill::start_main_executor);
+    /// let upload = block_on(spawn_main(async { Mailbox::main(Upload) }));
+    /// let upload = ServiceBuilder::new().concurrency_limit(2).build(upload);
+    ///
+    /// let start = Instant::now();
+    /// let tasks: Vec<_> = (0..6)
+    ///     .map(|chunk| {
+    ///         let upload = upload.clone();
+    ///         spawn(async move { upload.call(chunk).await })
+    ///     })
+    ///     .collect();
+    /// for (chunk, task) in tasks.into_iter().enumerate() {
+    ///     assert_eq!(block_on(task), Ok(chunk as u32));
+    /// }
+    ///
+    /// // Six uploads, two at a time, take three rounds
+    /// assert!(start.elapsed() >= Duration::from_millis(150));
+    /// assert_eq!(PEAK.load(Ordering::SeqCst), 2);
+    /// # }
+    /// ```
+    #[must_use]
+    #[track_caller]
+    pub const fn concurrency_limit(mut self, limit: usize) -> Self {
+        assert!(
+            limit > 0,
+            "the concurrency limit of a service must be non-zero"
+        );
+        self.concurrency_limit = Some(limit);
+        self
+    }
+
+    /// Lets at most `max_queued` requests wait for the
+    /// [concurrency limit](Self::concurrency_limit); further requests fail
+    /// with [`ServiceError::Overloaded`].
+    ///
+    /// Waiting requests take freed slots in the order they arrived, ahead of
+    /// any request made later. A waiting request that is cancelled, by
+    /// dropping its future, gives up its place in the queue.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(not(target_vendor = "apple"))] {
+    /// use futures_lite::future::{block_on, pending, poll_once};
+    /// use native_executor::{
+    ///     Mailbox, polyfill, service::{Service, ServiceBuilder, ServiceError}, spawn_main,
+    /// };
+    /// use std::pin::pin;
+    ///
+    /// // Never answers, so every request stays in flight
+    /// struct Stalled;
+    ///
+    /// impl Service<()> for Stalled {
+    ///     type Response = ();
+    ///
+    ///     fn call(&mut self, (): ()) -> impl Future<Output = ()> + Send + 'static {
+    ///         pending()
+    ///     }
+    /// }
+    ///
+    /// std::thread::spawn(polyfill::start_main_executor);
+    /// let stalled = block_on(spawn_main(async { Mailbox::main(Stalled) }));
+    /// let stalled = ServiceBuilder::new().concurrency_limit(1).max_queued(1).build(stalled);
+    ///
+    /// let mut in_flight = Box::pin(stalled.call(()));
+    /// assert_eq!(block_on(poll_once(in_flight.as_mut())), None);
+    /// let mut waiting = Box::pin(stalled.call(()));
+    /// assert_eq!(block_on(poll_once(waiting.as_mut())), None);
+    /// assert_eq!(stalled.queued(), 1);
+    ///
+    /// // The queue is full
+    /// assert_eq!(block_on(stalled.call(())), Err(ServiceError::Overloaded));
+    ///
+    /// // Cancelling the waiting request makes room
+    /// drop(waiting);
+    /// assert_eq!(stalled.queued(), 0);
+    /// let mut next = pin!(stalled.call(()));
+    /// assert_eq!(block_on(poll_once(next.as_mut())), None);
+    /// assert_eq!(stalled.queued(), 1);
+    ///
+    /// // The slot freed by `in_flight` is kept for `next`, so a later request
+    /// // still finds the queue full
+    /// drop(in_flight);
+    /// assert_eq!(block_on(stalled.call(())), Err(ServiceError::Overloaded));
+    /// assert_eq!(block_on(poll_once(next.as_mut())), None);
+    /// assert_eq!(stalled.queued(), 0);
+    /// # }
+    /// ```
+    #[must_use]
+    pub const fn max_queued(mut self, max_queued: usize) -> Self {
+        self.max_queued = max_queued;
+        self
+    }
+
+    /// Fails requests that are not answered within `timeout` with
+    /// [`ServiceError::Timeout`].
+    ///
+    /// The timeout covers the wait in the queue as well as the response
+    /// future. A request that times out after its job has run has still
+    /// changed the service; only its response is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(not(target_vendor = "apple"))] {
+    /// use futures_lite::future::{block_on, pending};
+    /// use native_executor::{
+    ///     Mailbox, polyfill, service::{Service, ServiceBuilder, ServiceError}, spawn_main,
+    /// };
+    /// use std::time::{Duration, Instant};
+    ///
+    /// struct Stalled;
+    ///
+    /// impl Service<()> for Stalled {
+    ///     type Response = ();
+    ///
+    ///     fn call(&mut self, (): ()) -> impl Future<Output = ()> + Send + 'static {
+    ///         pending()
+    ///     }
+    /// }
+    ///
+    /// std::thread::spawn(polyfill::start_main_executor);
+    /// let stalled = block_on(spawn_main(async { Mailbox::main(Stalled) }));
+    /// let stalled = ServiceBuilder::new().timeout(Duration::from_millis(20)).build(stalled);
+    ///
+    /// let start = Instant::now();
+    /// assert_eq!(block_on(stalled.call(())), Err(ServiceError::Timeout));
+    /// assert!(start.elapsed() >= Duration::from_millis(20));
+    /// # }
+    /// ```
+    #[must_use]
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Returns a handle that sends requests to the service owned by
+    /// `mailbox`.
+    pub fn build<T, Req>(self, mailbox: Mailbox<T>) -> ServiceHandle<Req, T::Response>
+    where
+        T: Service<Req> + 'static,
+        Req: Send + 'static,
+    {
+        let limit = self.concurrency_limit.map(|limit| {
+            let (permits, returned) = bounded(limit);
+            Limit {
+                permits,
+                returned,
+                queued: AtomicUsize::new(0),
+                max_queued: self.max_queued,
+            }
+        });
+        ServiceHandle {
+            shared: Arc::new(Shared {
+                backend: Box::new(mailbox),
+                limit,
+                timeout: self.timeout,
+            }),
+        }
+    }
+}
+
+/// Sends requests to a [`Service`] owned by a mailbox, within the limits set
+/// by a [`ServiceBuilder`].
+///
+/// Cloning a handle is cheap, and every clone shares the same limits. The
+/// mailbox is dropped along with the last handle.
+pub struct ServiceHandle<Req, Res> {
+    shared: Arc<Shared<Req, Res>>,
+}
+
+struct Shared<Req, Res> {
+    backend: Box<dyn Backend<Req, Res>>,
+    limit: Option<Limit>,
+    timeout: Option<Duration>,
+}
+
+/// The concurrency limit of a service.
+///
+/// Each request in flight holds one message in `permits`, whose capacity is
+/// the limit, so a full channel makes the next request wait.
+struct Limit {
+    permits: Sender<()>,
+    returned: Receiver<()>,
+    /// The number of requests waiting for a permit.
+    queued: AtomicUsize,
+    max_queued: usize,
+}
+
+impl Limit {
+    /// Takes a permit, waiting in the queue if none is free, unless the
+    /// mailbox of `backend` ends first.
+    async fn acquire<Req, Res>(
+        &self,
+        backend: &dyn Backend<Req, Res>,
+    ) -> Result<Permit<'_>, ServiceError> {
+        // A free permit is only taken directly if nobody is waiting for one,
+        // so that a new request cannot overtake the queue.
+        if self.queued.load(Ordering::Acquire) == 0 && self.permits.try_send(()).is_ok() {
+            return Ok(Permit(&self.returned));
+        }
+        let position = self.queued.fetch_add(1, Ordering::AcqRel);
+        let _queued = Queued(&self.queued);
+        if position >= self.max_queued {
+            return Err(ServiceError::Overloaded);
+        }
+
+        let mut acquired = pin!(self.permits.send(()));
+        let mut ended = pin!(backend.ended());
+        poll_fn(|cx| {
+            if ended.as_mut().poll(cx).is_ready() {
+                return Poll::Ready(Err(ServiceError::Closed));
+            }
+            // The channel cannot close while `returned` is alive.
+            acquired
+                .as_mut()
+                .poll(cx)
+                .map(|_| Ok(Permit(&self.returned)))
+        })
+        .await
+    }
+}
+
+/// A request slot, given back when dropped.
+struct Permit<'a>(&'a Receiver<()>);
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        let _ = self.0.try_recv();
+    }
+}
+
+/// A place in the queue of waiting requests, given back when dropped.
+struct Queued<'a>(&'a AtomicUsize);
+
+impl Drop for Queued<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl<Req, Res> Clone for ServiceHandle<Req, Res> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<Req, Res> fmt::Debug for ServiceHandle<Req, Res> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let limit = self.shared.limit.as_ref();
+        f.debug_struct("ServiceHandle")
+            .field(
+                "concurrency_limit",
+                &limit.and_then(|limit| limit.permits.capacity()),
+            )
+            .field("in_flight", &limit.map(|limit| limit.permits.len()))
+            .field("queued", &self.queued())
+            .field("timeout", &self.shared.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Req, Res> ServiceHandle<Req, Res> {
+    /// Returns the number of requests waiting for the concurrency limit.
+    #[must_use]
+    pub fn queued(&self) -> usize {
+        self.shared
+            .limit
+            .as_ref()
+            .map_or(0, |limit| limit.queued.load(Ordering::Acquire))
+    }
+}
+
+impl<Req: Send + 'static, Res: Send + 'static> ServiceHandle<Req, Res> {
+    /// Returns a handle to the service owned by `mailbox`, without limits.
+    pub fn new<T>(mailbox: Mailbox<T>) -> Self
+    where
+        T: Service<Req, Response = Res> + 'static,
+    {
+        ServiceBuilder::new().build(mailbox)
+    }
+
+    /// Sends `req` to the service and waits for the response.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ServiceError::Overloaded`] if the request would exceed the
+    /// queue, [`ServiceError::Timeout`] if it is not answered in time, and
+    /// [`ServiceError::Closed`] if the mailbox has been closed or its
+    /// background task has ended.
+    pub async fn call(&self, req: Req) -> Result<Res, ServiceError> {
+        let shared = &*self.shared;
+        let respond = async {
+            let _permit = match &shared.limit {
+                Some(limit) => Some(limit.acquire(&*shared.backend).await?),
+                None => None,
+            };
+            shared.backend.call(req).await.ok_or(ServiceError::Closed)
+        };
+        let Some(duration) = shared.timeout else {
+            return respond.await;
+        };
+        timeout(duration, respond)
+            .await
+            .unwrap_or(Err(ServiceError::Timeout))
+    }
+
+    /// Closes the mailbox of the service and waits until its background
+    /// task has ended.
+    ///
+    /// Requests already handed to the mailbox are still answered, while
+    /// requests waiting for the concurrency limit, and every later request,
+    /// fail with [`ServiceError::Closed`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(not(target_vendor = "apple"))] {
+    /// use futures_lite::future::{block_on, poll_once};
+    /// use native_executor::{
+    ///     Mailbox, polyfill, service::{Service, ServiceBuilder, ServiceError}, spawn_main,
+    ///     timer::Timer,
+    /// };
+    /// use std::{pin::pin, time::Duration};
+    ///
+    /// struct Slow;
+    ///
+    /// impl Service<u32> for Slow {
+    ///     type Response = u32;
+    ///
+    ///     fn call(&mut self, n: u32) -> impl Future<Output = u32> + Send + 'static {
+    ///         async move {
+    ///             Timer::after(Duration::from_millis(20)).await;
+    ///             n
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// std::thread::spawn(polyfill::start_main_executor);
+    /// let slow = block_on(spawn_main(async { Mailbox::main(Slow) }));
+    /// let slow = ServiceBuilder::new().concurrency_limit(1).build(slow);
+    ///
+    /// let mut in_flight = pin!(slow.call(1));
+    /// assert_eq!(block_on(poll_once(in_flight.as_mut())), None);
+    /// let mut waiting = pin!(slow.call(2));
+    /// assert_eq!(block_on(poll_once(waiting.as_mut())), None);
+    ///
+    /// block_on(slow.close());
+    /// assert_eq!(block_on(waiting), Err(ServiceError::Closed));
+    /// assert_eq!(block_on(slow.call(3)), Err(ServiceError::Closed));
+    /// // The request in flight was still answered
+    /// assert_eq!(block_on(in_flight), Ok(1));
+    /// # }
+    /// ```
+    pub async fn close(&self) {
+        self.shared.backend.close().await;
+    }
+}
@@ -0,0 +1,467 @@
+//! Asynchronous notification of UNIX signals and memory pressure.
+//!
+//! On Apple platforms, each stream is backed by a GCD dispatch source whose
+//! handler runs on the queue for the requested [`Priority`]; the source is
+//! cancelled when the stream is dropped. Other UNIX platforms fall back to a
+//! process-wide signal handler that forwards signals to a helper thread.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use futures_lite::future::block_on;
+//! use native_executor::signals::{SignalKind, signal};
+//!
+//! let mut hangups = signal(SignalKind::hangup()).unwrap();
+//! unsafe { libc::raise(libc::SIGHUP) };
+//! assert_eq!(block_on(hangups.next()), Some(()));
+//! ```
+
+use core::ffi::c_int;
+use std::io;
+
+use async_channel::Receiver;
+
+use crate::Priority;
+
+/// A UNIX signal that can be listened for with [`signal`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SignalKind(c_int);
+
+impl SignalKind {
+    /// Creates a kind from a raw signal number.
+    #[must_use]
+    pub const fn from_raw(signum: c_int) -> Self {
+        Self(signum)
+    }
+
+    /// Returns the raw signal number.
+    #[must_use]
+    pub const fn as_raw(self) -> c_int {
+        self.0
+    }
+
+    /// `SIGHUP`, sent when the controlling terminal is closed.
+    #[must_use]
+    pub const fn hangup() -> Self {
+        Self(libc::SIGHUP)
+    }
+
+    /// `SIGINT`, sent by Ctrl-C.
+    #[must_use]
+    pub const fn interrupt() -> Self {
+        Self(libc::SIGINT)
+    }
+
+    /// `SIGTERM`, the conventional request to shut down.
+    #[must_use]
+    pub const fn terminate() -> Self {
+        Self(libc::SIGTERM)
+    }
+
+    /// `SIGUSR1`.
+    #[must_use]
+    pub const fn user_defined1() -> Self {
+        Self(libc::SIGUSR1)
+    }
+
+    /// `SIGUSR2`.
+    #[must_use]
+    pub const fn user_defined2() -> Self {
+        Self(libc::SIGUSR2)
+    }
+
+    /// Returns `true` for signals that cannot or must not be handled
+    /// asynchronously.
+    const fn is_forbidden(self) -> bool {
+        matches!(
+            self.0,
+            libc::SIGKILL
+                | libc::SIGSTOP
+                | libc::SIGILL
+                | libc::SIGFPE
+                | libc::SIGSEGV
+                | libc::SIGBUS
+        )
+    }
+}
+
+/// The severity of a memory-pressure event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PressureLevel {
+    /// Memory pressure returned to normal.
+    Normal,
+    /// The system is low on memory; caches should be trimmed.
+    Warning,
+    /// The system is critically low on memory; release everything possible.
+    Critical,
+}
+
+/// A stream of deliveries of one signal, created by [`signal`].
+#[derive(Debug)]
+pub struct Signals {
+    receiver: Receiver<()>,
+    _source: imp::Source,
+}
+
+impl Signals {
+    /// Waits for the next delivery of the signal.
+    ///
+    /// Deliveries arriving in quick succession may be coalesced into one.
+    pub async fn next(&mut self) -> Option<()> {
+        self.receiver.recv().await.ok()
+    }
+}
+
+/// A stream of memory-pressure events, created by [`memory_pressure`].
+#[derive(Debug)]
+pub struct MemoryPressure {
+    receiver: Receiver<PressureLevel>,
+    _source: imp::Source,
+}
+
+impl MemoryPressure {
+    /// Waits for the next memory-pressure event.
+    pub async fn next(&mut self) -> Option<PressureLevel> {
+        self.receiver.recv().await.ok()
+    }
+}
+
+/// Listens for deliveries of `kind`.
+///
+/// Listening replaces the default action of the signal, so for example
+/// `SIGTERM` no longer terminates the process. The default action is not
+/// restored when the stream is dropped.
+///
+/// # Errors
+///
+/// Returns [`io::ErrorKind::InvalidInput`] for signals that cannot be handled
+/// asynchronously, such as `SIGKILL` or `SIGSEGV`, and the system error if
+/// registration fails.
+pub fn signal(kind: SignalKind) -> io::Result<Signals> {
+    signal_with_priority(kind, Priority::default())
+}
+
+/// Listens for deliveries of `kind`, handling them at `priority`.
+///
+/// See [`signal`].
+///
+/// # Errors
+///
+/// See [`signal`].
+///
+/// # Examples
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::{Priority, signals::{SignalKind, signal_with_priority}};
+///
+/// let kind = SignalKind::user_defined1();
+/// let first = signal_with_priority(kind, Priority::Background).unwrap();
+/// drop(first);
+///
+/// // A dropped stream no longer observes the signal; a new one does
+/// let mut second = signal_with_priority(kind, Priority::Background).unwrap();
+/// unsafe { libc::raise(libc::SIGUSR1) };
+/// assert_eq!(block_on(second.next()), Some(()));
+/// ```
+pub fn signal_with_priority(kind: SignalKind, priority: Priority) -> io::Result<Signals> {
+    if kind.is_forbidden() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "signal cannot be handled asynchronously",
+        ));
+    }
+    let (source, receiver) = imp::signal(kind.0, priority)?;
+    Ok(Signals {
+        receiver,
+        _source: source,
+    })
+}
+
+/// Listens for system memory-pressure events.
+///
+/// Only Apple platforms report memory pressure; elsewhere the stream never
+/// yields.
+///
+/// # Errors
+///
+/// Returns the system error if registration fails.
+pub fn memory_pressure() -> io::Result<MemoryPressure> {
+    memory_pressure_with_priority(Priority::default())
+}
+
+/// Listens for system memory-pressure events, handling them at `priority`.
+///
+/// See [`memory_pressure`].
+///
+/// # Errors
+///
+/// See [`memory_pressure`].
+pub fn memory_pressure_with_priority(priority: Priority) -> io::Result<MemoryPressure> {
+    let (source, receiver) = imp::memory_pressure(priority)?;
+    Ok(MemoryPressure {
+        receiver,
+        _source: source,
+    })
+}
+
+#[cfg(target_vendor = "apple")]
+mod imp {
+    use core::ffi::{c_int, c_ulong, c_void};
+    use std::io;
+
+    use async_channel::{Receiver, Sender};
+    use dispatch::ffi::{dispatch_release, dispatch_resume, dispatch_set_context};
+
+    use super::PressureLevel;
+    use crate::{
+        Priority,
+        apple::{
+            _dispatch_source_type_memorypressure, _dispatch_source_type_signal, DispatchSource,
+            SourceType, dispatch_source_cancel, dispatch_source_create, dispatch_source_get_data,
+            dispatch_source_set_cancel_handler_f, dispatch_source_set_event_handler_f, raw_queue,
+        },
+    };
+
+    const DISPATCH_MEMORYPRESSURE_NORMAL: c_ulong = 0x01;
+    const DISPATCH_MEMORYPRESSURE_WARN: c_ulong = 0x02;
+    const DISPATCH_MEMORYPRESSURE_CRITICAL: c_ulong = 0x04;
+
+    /// A resumed dispatch source, cancelled on drop.
+    #[derive(Debug)]
+    pub struct Source(DispatchSource);
+
+    // SAFETY: dispatch sources are thread-safe reference-counted objects.
+    unsafe impl Send for Source {}
+    // SAFETY: see above.
+    unsafe impl Sync for Source {}
+
+    impl Drop for Source {
+        fn drop(&mut self) {
+            // SAFETY: the source is valid; the cancel handler frees its context
+            // once no event handler is running anymore.
+            unsafe {
+                dispatch_source_cancel(self.0);
+                dispatch_release(self.0);
+            }
+        }
+    }
+
+    struct Context<T> {
+        source: DispatchSource,
+        sender: Sender<T>,
+        event: fn(c_ulong) -> T,
+    }
+
+    extern "C" fn on_event<T>(context: *mut c_void) {
+        // SAFETY: the context is valid until the cancel handler runs, which
+        // GCD never runs concurrently with the event handler.
+        let context = unsafe { &*context.cast::<Context<T>>() };
+        // SAFETY: the source outlives its handlers.
+        let data = unsafe { dispatch_source_get_data(context.source) };
+        let _ = context.sender.try_send((context.event)(data));
+    }
+
+    extern "C" fn on_cancel<T>(context: *mut c_void) {
+        // SAFETY: this is the last handler invocation for the source.
+        drop(unsafe { Box::from_raw(context.cast::<Context<T>>()) });
+    }
+
+    fn create<T>(
+        kind: *const SourceType,
+        handle: usize,
+        mask: c_ulong,
+        priority: Priority,
+        event: fn(c_ulong) -> T,
+    ) -> io::Result<(Source, Receiver<T>)> {
+        // SAFETY: `kind` is one of the source types declared above.
+        let source = unsafe { dispatch_source_create(kind, handle, mask, raw_queue(priority)) };
+        if source.is_null() {
+            return Err(io::Error::other("failed to create dispatch source"));
+        }
+        let (sender, receiver) = async_channel::unbounded();
+        let context = Box::into_raw(Box::new(Context {
+            source,
+            sender,
+            event,
+        }));
+        // SAFETY: the source is valid and suspended; `on_cancel` reclaims the
+        // context after the last event.
+        unsafe {
+            dispatch_set_context(source, context.cast());
+            dispatch_source_set_event_handler_f(source, on_event::<T>);
+            dispatch_source_set_cancel_handler_f(source, on_cancel::<T>);
+            dispatch_resume(source);
+        }
+        Ok((Source(source), receiver))
+    }
+
+    pub fn signal(signum: c_int, priority: Priority) -> io::Result<(Source, Receiver<()>)> {
+        // Dispatch sources observe signals only after their default action has
+        // been disabled.
+        // SAFETY: ignoring a signal has no memory-safety implications.
+        if unsafe { libc::signal(signum, libc::SIG_IGN) } == libc::SIG_ERR {
+            return Err(io::Error::last_os_error());
+        }
+        let handle = usize::try_from(signum).map_err(|_| io::ErrorKind::InvalidInput)?;
+        let kind = &raw const _dispatch_source_type_signal;
+        create(kind, handle, 0, priority, |_| ())
+    }
+
+    pub fn memory_pressure(priority: Priority) -> io::Result<(Source, Receiver<PressureLevel>)> {
+        let kind = &raw const _dispatch_source_type_memorypressure;
+        let mask = DISPATCH_MEMORYPRESSURE_NORMAL
+            | DISPATCH_MEMORYPRESSURE_WARN
+            | DISPATCH_MEMORYPRESSURE_CRITICAL;
+        create(kind, 0, mask, priority, |data| {
+            if data & DISPATCH_MEMORYPRESSURE_CRITICAL != 0 {
+                PressureLevel::Critical
+            } else if data & DISPATCH_MEMORYPRESSURE_WARN != 0 {
+                PressureLevel::Warning
+            } else {
+                PressureLevel::Normal
+            }
+        })
+    }
+}
+
+#[cfg(not(target_vendor = "apple"))]
+mod imp {
+    use core::{
+        ffi::c_int,
+        sync::atomic::{AtomicI32, Ordering},
+    };
+    use std::{
+        io,
+        sync::{Mutex, OnceLock, PoisonError},
+        thread,
+    };
+
+    use async_channel::{Receiver, Sender};
+
+    use super::PressureLevel;
+    use crate::Priority;
+
+    /// Keeps a stream's channel open; subscriptions are pruned once their
+    /// receiver is dropped.
+    #[derive(Debug)]
+    pub struct Source {
+        _sender: Option<Sender<PressureLevel>>,
+    }
+
+    #[derive(Default)]
+    struct Registry {
+        installed: Vec<c_int>,
+        subscribers: Vec<(c_int, Sender<()>)>,
+    }
+
+    static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+        installed: Vec::new(),
+        subscribers: Vec::new(),
+    });
+    /// Write end of the pipe the signal handler reports to.
+    static WRITE_FD: AtomicI32 = AtomicI32::new(-1);
+
+    #[cfg(target_os = "android")]
+    use libc::__errno as errno_location;
+    #[cfg(not(target_os = "android"))]
+    use libc::__errno_location as errno_location;
+
+    extern "C" fn on_signal(signum: c_int) {
+        // Only async-signal-safe operations are allowed here.
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let byte = signum as u8;
+        // SAFETY: `write` is async-signal-safe; `errno` is restored so the
+        // interrupted code does not observe a change.
+        unsafe {
+            let errno = *errno_location();
+            libc::write(
+                WRITE_FD.load(Ordering::Relaxed),
+                (&raw const byte).cast(),
+                1,
+            );
+            *errno_location() = errno;
+        }
+    }
+
+    /// Creates the pipe and the thread forwarding signals to subscribers.
+    fn start_forwarding() -> io::Result<()> {
+        static STARTED: OnceLock<Result<(), io::ErrorKind>> = OnceLock::new();
+        let started = STARTED.get_or_init(|| {
+            let mut fds = [0; 2];
+            // SAFETY: `fds` has room for both descriptors.
+            if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+                return Err(io::Error::last_os_error().kind());
+            }
+            let [read_fd, write_fd] = fds;
+            // A full pipe must not block the signal handler.
+            // SAFETY: `write_fd` was just created.
+            unsafe { libc::fcntl(write_fd, libc::F_SETFL, libc::O_NONBLOCK) };
+            WRITE_FD.store(write_fd, Ordering::Relaxed);
+            thread::Builder::new()
+                .name("native-executor-signals".into())
+                .spawn(move || forward(read_fd))
+                .map(drop)
+                .map_err(|error| error.kind())
+        });
+        started.map_err(io::Error::from)
+    }
+
+    fn forward(read_fd: c_int) {
+        loop {
+            let mut byte = 0u8;
+            // SAFETY: reading one byte into a local.
+            let read = unsafe { libc::read(read_fd, (&raw mut byte).cast(), 1) };
+            if read != 1 {
+                continue;
+            }
+            let signum = c_int::from(byte);
+            REGISTRY
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .subscribers
+                .retain(|(subscribed, sender)| {
+                    *subscribed != signum || sender.try_send(()).is_ok()
+                });
+        }
+    }
+
+    pub fn signal(signum: c_int, _priority: Priority) -> io::Result<(Source, Receiver<()>)> {
+        start_forwarding()?;
+        let mut registry = REGISTRY.lock().unwrap_or_else(PoisonError::into_inner);
+        if !registry.installed.contains(&signum) {
+            // SAFETY: `action` is fully initialized before use and `on_signal`
+            // is async-signal-safe.
+            unsafe {
+                let mut action: libc::sigaction = core::mem::zeroed();
+                action.sa_sigaction = on_signal as extern "C" fn(c_int) as libc::sighandler_t;
+                action.sa_flags = libc::SA_RESTART;
+                libc::sigemptyset(&raw mut action.sa_mask);
+                if libc::sigaction(signum, &raw const action, core::ptr::null_mut()) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            registry.installed.push(signum);
+        }
+        let (sender, receiver) = async_channel::unbounded();
+        registry
+            .subscribers
+            .retain(|(_, sender)| !sender.is_closed());
+        registry.subscribers.push((signum, sender));
+        drop(registry);
+        Ok((Source { _sender: None }, receiver))
+    }
+
+    // Mirrors the fallible Apple implementation.
+    #[allow(clippy::unnecessary_wraps)]
+    pub fn memory_pressure(_priority: Priority) -> io::Result<(Source, Receiver<PressureLevel>)> {
+        let (sender, receiver) = async_channel::unbounded();
+        Ok((
+            Source {
+                _sender: Some(sender),
+            },
+            receiver,
+        ))
+    }
+}
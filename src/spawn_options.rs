@@ -0,0 +1,195 @@
+//! Options for spawning a task, for the settings the `spawn` functions do not
+//! take.
+
+use core::future::Future;
+
+use async_task::Task;
+
+#[cfg(feature = "std")]
+use crate::{ClampExemption, SpawnError, try_spawn_pooled};
+use crate::{Priority, hooks::TaskInfo, spawn_main_task, spawn_pooled};
+
+/// How a task is queued on the web, as set by
+/// [`SpawnOptions::web_task_type`].
+///
+/// Other backends ignore it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum WebTaskType {
+    /// Every poll is a microtask, which runs before the browser gets back to
+    /// the event loop. Chains of microtasks delay rendering and input until
+    /// they end.
+    #[default]
+    Micro,
+    /// Every poll is a macrotask, queued with a `MessageChannel` message, so
+    /// the browser can render and handle input between polls.
+    ///
+    /// Messages avoid the clamping of nested `setTimeout(0)` calls. Without
+    /// the `std` feature, `setTimeout(0)` is used instead.
+    Macro,
+}
+
+/// A builder for spawning a task with options.
+///
+/// # Examples
+///
+/// ```rust
+/// use native_executor::{Priority, SpawnOptions};
+///
+/// // Flushes logs in the background without keeping the process alive
+/// SpawnOptions::new()
+///     .priority(Priority::Background)
+///     .daemon(true)
+///     .spawn(async { /* flush */ })
+///     .detach();
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpawnOptions {
+    priority: Option<Priority>,
+    daemon: bool,
+    web_task_type: WebTaskType,
+    clamp_exempt: bool,
+}
+
+impl SpawnOptions {
+    /// Creates options for a task of the default priority that is not a
+    /// daemon.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            priority: None,
+            daemon: false,
+            web_task_type: WebTaskType::Micro,
+            clamp_exempt: false,
+        }
+    }
+
+    /// Sets the priority of the task.
+    ///
+    /// For [`spawn_main`](Self::spawn_main), this is a priority override, as
+    /// taken by [`spawn_main_with_priority`](crate::spawn_main_with_priority).
+    #[must_use]
+    pub const fn priority(mut self, priority: Priority) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Marks the task as a daemon, which does not keep
+    /// `NativeExecutor::keep_alive_until_idle` waiting.
+    ///
+    /// Mark tasks that never complete on their own this way, such as a loop
+    /// over an [`Interval`](crate::timer::Interval), or the process would
+    /// never become idle. Hooks observe the flag in [`TaskInfo::daemon`].
+    #[must_use]
+    pub const fn daemon(mut self, daemon: bool) -> Self {
+        self.daemon = daemon;
+        self
+    }
+
+    /// Sets how the task is queued on the web, which defaults to
+    /// [`WebTaskType::Micro`].
+    ///
+    /// Make long-running main-thread work [`WebTaskType::Macro`] so that it
+    /// does not starve rendering. Closures of
+    /// [`schedule_after`](crate::schedule_after) and
+    /// [`execute_after`](crate::execute_after) always run as macrotasks.
+    /// Hooks observe the choice in [`TaskInfo::web_task_type`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use native_executor::{SpawnOptions, WebTaskType};
+    ///
+    /// // Lays out a long document while frames keep being drawn
+    /// SpawnOptions::new()
+    ///     .web_task_type(WebTaskType::Macro)
+    ///     .spawn_main(async { /* lay out */ })
+    ///     .detach();
+    /// ```
+    #[must_use]
+    pub const fn web_task_type(mut self, web_task_type: WebTaskType) -> Self {
+        self.web_task_type = web_task_type;
+        self
+    }
+
+    /// Runs the task at its priority, ignoring the bounds of
+    /// [`Config::priority_floor`](crate::Config::priority_floor) and
+    /// [`Config::priority_ceiling`](crate::Config::priority_ceiling).
+    ///
+    /// The `exemption` comes from [`Config::clamp_exemption`](crate::Config::clamp_exemption),
+    /// so only code that was handed one can leave the bounds. Hooks observe
+    /// the exemption in [`TaskInfo::clamp_exempt`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{Config, ExecContext, Priority, SpawnOptions, current_context, init};
+    ///
+    /// let config = Config::new().priority_ceiling(Priority::Default);
+    /// let exemption = config.clamp_exemption();
+    /// init(config).unwrap();
+    ///
+    /// let task = SpawnOptions::new()
+    ///     .priority(Priority::UserInteractive)
+    ///     .exempt_from_clamp(exemption)
+    ///     .spawn(async { current_context() });
+    /// assert_eq!(block_on(task), ExecContext::Worker(Priority::UserInteractive));
+    /// ```
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub const fn exempt_from_clamp(mut self, exemption: ClampExemption) -> Self {
+        let _ = exemption;
+        self.clamp_exempt = true;
+        self
+    }
+
+    #[track_caller]
+    fn info(self, priority: Priority) -> TaskInfo {
+        TaskInfo {
+            daemon: self.daemon,
+            web_task_type: self.web_task_type,
+            clamp_exempt: self.clamp_exempt,
+            ..TaskInfo::new(priority)
+        }
+    }
+
+    /// Spawns `future` on the thread pool, as [`spawn`](crate::spawn) does.
+    #[track_caller]
+    pub fn spawn<Fut>(self, future: Fut) -> Task<Fut::Output>
+    where
+        Fut: Future + Send + 'static,
+        Fut::Output: Send,
+    {
+        spawn_pooled(future, self.info(self.priority.unwrap_or_default()))
+    }
+
+    /// Spawns `future` on the thread pool, as [`try_spawn`](crate::try_spawn)
+    /// does.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SpawnError::AtCapacity`] if as many tasks as
+    /// [`Config::max_alive_tasks`](crate::Config::max_alive_tasks) allows are
+    /// alive, unless the task is a daemon.
+    #[cfg(feature = "std")]
+    #[track_caller]
+    pub fn try_spawn<Fut>(self, future: Fut) -> Result<Task<Fut::Output>, SpawnError>
+    where
+        Fut: Future + Send + 'static,
+        Fut::Output: Send,
+    {
+        try_spawn_pooled(future, self.info(self.priority.unwrap_or_default()))
+    }
+
+    /// Spawns `future` on the main thread, as
+    /// [`spawn_main`](crate::spawn_main) does.
+    #[track_caller]
+    pub fn spawn_main<Fut>(self, future: Fut) -> Task<Fut::Output>
+    where
+        Fut: Future + Send + 'static,
+        Fut::Output: Send,
+    {
+        let info = self.info(self.priority.unwrap_or(Priority::UserInteractive));
+        spawn_main_task(future, info, self.priority)
+    }
+}
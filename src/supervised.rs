@@ -0,0 +1,419 @@
+//! Task handles that report panics and cancellation as values.
+
+use core::{
+    any::Any,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering},
+    task::{Context, Poll, Waker, ready},
+};
+use std::{
+    panic::{AssertUnwindSafe, catch_unwind},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use async_task::FallibleTask;
+
+use crate::{Priority, spawn_with_priority};
+
+/// The reason a supervised task did not produce an output.
+#[non_exhaustive]
+pub enum JoinError {
+    /// The task was aborted or cancelled before it completed.
+    Cancelled,
+    /// The task panicked; the payload is the value passed to `panic!`.
+    Panicked(Box<dyn Any + Send>),
+    /// The output was already taken, by awaiting the task or by
+    /// [`SupervisedTask::try_take_output`].
+    AlreadyTaken,
+}
+
+impl JoinError {
+    /// Returns `true` if the task was cancelled.
+    #[must_use]
+    pub const fn is_cancelled(&self) -> bool {
+        matches!(self, Self::Cancelled)
+    }
+
+    /// Returns `true` if the task panicked.
+    #[must_use]
+    pub const fn is_panic(&self) -> bool {
+        matches!(self, Self::Panicked(_))
+    }
+
+    /// Returns the panic payload, if the task panicked.
+    #[must_use]
+    pub fn into_panic(self) -> Option<Box<dyn Any + Send>> {
+        match self {
+            Self::Panicked(payload) => Some(payload),
+            Self::Cancelled | Self::AlreadyTaken => None,
+        }
+    }
+
+    fn panic_message(&self) -> Option<&str> {
+        let Self::Panicked(payload) = self else {
+            return None;
+        };
+        payload
+            .downcast_ref::<&'static str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+    }
+}
+
+impl fmt::Debug for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cancelled => f.write_str("Cancelled"),
+            Self::AlreadyTaken => f.write_str("AlreadyTaken"),
+            Self::Panicked(_) => f
+                .debug_tuple("Panicked")
+                .field(&self.panic_message().unwrap_or("..."))
+                .finish(),
+        }
+    }
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cancelled => f.write_str("task was cancelled"),
+            Self::AlreadyTaken => f.write_str("task output was already taken"),
+            Self::Panicked(_) => match self.panic_message() {
+                Some(message) => write!(f, "task panicked: {message}"),
+                None => f.write_str("task panicked"),
+            },
+        }
+    }
+}
+
+impl std::error::Error for JoinError {}
+
+#[derive(Debug, Default)]
+struct AbortState {
+    aborted: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+    /// Whether the future completed or panicked, rather than being aborted
+    /// or cancelled; set before the task completes.
+    produced: AtomicBool,
+}
+
+/// A cloneable handle that aborts a supervised task.
+///
+/// Obtained from [`SupervisedTask::abort_handle`]; it can be moved to other
+/// tasks or threads while the [`SupervisedTask`] itself is being awaited.
+#[derive(Debug, Clone)]
+pub struct AbortHandle {
+    state: Arc<AbortState>,
+}
+
+impl AbortHandle {
+    /// Aborts the task.
+    ///
+    /// The task's future is dropped the next time it would be polled, and
+    /// awaiting the task yields [`JoinError::Cancelled`]. Aborting a task that
+    /// already completed has no effect.
+    pub fn abort(&self) {
+        self.state.aborted.store(true, Ordering::Release);
+        let waker = self
+            .state
+            .waker
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Returns `true` if [`abort`](Self::abort) has been called.
+    #[must_use]
+    pub fn is_aborted(&self) -> bool {
+        self.state.aborted.load(Ordering::Acquire)
+    }
+}
+
+/// Runs the inner future, converting panics and aborts into [`JoinError`]s.
+struct Supervised<F> {
+    future: Option<F>,
+    abort: Arc<AbortState>,
+}
+
+impl<F: Future> Future for Supervised<F> {
+    type Output = Result<F::Output, JoinError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: `future` is never moved out of its `Option` while pinned; it is
+        // only dropped in place by assigning `None`.
+        let this = unsafe { self.get_unchecked_mut() };
+
+        let mut waker = this
+            .abort
+            .waker
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        match &mut *waker {
+            Some(waker) => waker.clone_from(cx.waker()),
+            None => *waker = Some(cx.waker().clone()),
+        }
+        drop(waker);
+        if this.abort.aborted.load(Ordering::Acquire) {
+            this.future = None;
+            return Poll::Ready(Err(JoinError::Cancelled));
+        }
+
+        let Some(future) = this.future.as_mut() else {
+            return Poll::Ready(Err(JoinError::Cancelled));
+        };
+        // SAFETY: see above.
+        let future = unsafe { Pin::new_unchecked(future) };
+        match catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(output)) => {
+                this.future = None;
+                this.abort.produced.store(true, Ordering::Release);
+                Poll::Ready(Ok(output))
+            }
+            Err(payload) => {
+                this.future = None;
+                this.abort.produced.store(true, Ordering::Release);
+                Poll::Ready(Err(JoinError::Panicked(payload)))
+            }
+        }
+    }
+}
+
+/// A handle to a task spawned with [`spawn_supervised`].
+///
+/// Awaiting it yields `Ok` with the task's output, [`JoinError::Panicked`] if
+/// the task panicked, or [`JoinError::Cancelled`] if it was aborted or
+/// cancelled. Like other task handles, dropping it cancels the task unless it
+/// was [detached](Self::detach).
+///
+/// Code that cannot await, such as a render loop, can check on the task with
+/// [`is_finished`](Self::is_finished) and take its output with
+/// [`try_take_output`](Self::try_take_output).
+#[must_use = "tasks get cancelled when dropped, use `.detach()` to run them in the background"]
+pub struct SupervisedTask<T> {
+    task: FallibleTask<Result<T, JoinError>>,
+    abort: AbortHandle,
+    /// Whether the output has been taken, after which the task yields
+    /// [`JoinError::AlreadyTaken`].
+    taken: bool,
+}
+
+impl<T> fmt::Debug for SupervisedTask<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SupervisedTask")
+            .field("finished", &self.is_finished())
+            .field("aborted", &self.abort.is_aborted())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> SupervisedTask<T> {
+    /// Aborts the task. See [`AbortHandle::abort`].
+    pub fn abort(&self) {
+        self.abort.abort();
+    }
+
+    /// Returns a handle that can abort the task from elsewhere.
+    #[must_use]
+    pub fn abort_handle(&self) -> AbortHandle {
+        self.abort.clone()
+    }
+
+    /// Returns `true` if the task has finished, either by completing,
+    /// panicking, or being aborted.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Returns `true` if the task has finished without completing or
+    /// panicking: it was aborted, or cancelled by its executor.
+    ///
+    /// Aborting a task that had already completed does not make it
+    /// cancelled.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.task.is_finished() && !self.abort.state.produced.load(Ordering::Acquire)
+    }
+
+    /// Takes the result of the task if it has finished, without waiting.
+    ///
+    /// Returns `None` while the task runs, and the result the task would be
+    /// awaited for once it has finished. The output can be taken once: by
+    /// this method or by awaiting the task, whichever comes first. Afterwards
+    /// both yield [`JoinError::AlreadyTaken`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{JoinError, spawn_supervised};
+    /// use std::{future::pending, sync::mpsc, thread, time::Duration};
+    ///
+    /// // A render loop checking on a background job every frame
+    /// let (finish, finished) = mpsc::channel::<()>();
+    /// let mut task = spawn_supervised(async move {
+    ///     finished.recv().unwrap();
+    ///     "mesh"
+    /// });
+    /// assert!(task.try_take_output().is_none());
+    /// finish.send(()).unwrap();
+    /// let output = loop {
+    ///     if let Some(output) = task.try_take_output() {
+    ///         break output;
+    ///     }
+    ///     thread::sleep(Duration::from_millis(1));
+    /// };
+    /// assert_eq!(output.unwrap(), "mesh");
+    /// assert!(task.is_finished() && !task.is_cancelled());
+    ///
+    /// // Taken once, whichever way
+    /// assert!(matches!(task.try_take_output(), Some(Err(JoinError::AlreadyTaken))));
+    /// assert!(matches!(block_on(&mut task), Err(JoinError::AlreadyTaken)));
+    ///
+    /// let mut awaited = spawn_supervised(async { 7 });
+    /// assert_eq!(block_on(&mut awaited).unwrap(), 7);
+    /// assert!(matches!(awaited.try_take_output(), Some(Err(JoinError::AlreadyTaken))));
+    ///
+    /// // Aborted and panicked tasks
+    /// let mut aborted = spawn_supervised(pending::<()>());
+    /// aborted.abort();
+    /// while !aborted.is_finished() {
+    ///     thread::yield_now();
+    /// }
+    /// assert!(aborted.is_cancelled());
+    /// assert!(matches!(aborted.try_take_output(), Some(Err(JoinError::Cancelled))));
+    ///
+    /// let mut panicked = spawn_supervised(async { panic!("no mesh") });
+    /// while !panicked.is_finished() {
+    ///     thread::yield_now();
+    /// }
+    /// assert!(!panicked.is_cancelled());
+    /// assert!(panicked.try_take_output().unwrap().unwrap_err().is_panic());
+    /// ```
+    ///
+    /// Polling the task from a render loop while it completes elsewhere hands
+    /// the output out exactly once:
+    ///
+    /// ```rust
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{JoinError, spawn_supervised};
+    ///
+    /// for n in 0..1_000 {
+    ///     let mut task = spawn_supervised(async move { n });
+    ///     let output = loop {
+    ///         if let Some(output) = task.try_take_output() {
+    ///             break output;
+    ///         }
+    ///     };
+    ///     assert_eq!(output.unwrap(), n);
+    ///     assert!(matches!(block_on(task), Err(JoinError::AlreadyTaken)));
+    /// }
+    /// ```
+    pub fn try_take_output(&mut self) -> Option<Result<T, JoinError>> {
+        if self.taken {
+            return Some(Err(JoinError::AlreadyTaken));
+        }
+        if !self.task.is_finished() {
+            return None;
+        }
+        match Pin::new(self).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => Some(output),
+            // Cancelled while running; the future is being dropped.
+            Poll::Pending => None,
+        }
+    }
+
+    /// Detaches the task so it keeps running in the background.
+    pub fn detach(self) {
+        self.task.detach();
+    }
+
+    /// Converts this handle into the underlying `async_task` handle.
+    ///
+    /// The returned handle resolves to `None` if the task was cancelled by
+    /// its executor.
+    pub fn into_inner(self) -> FallibleTask<Result<T, JoinError>> {
+        self.task
+    }
+}
+
+impl<T> Future for SupervisedTask<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.taken {
+            return Poll::Ready(Err(JoinError::AlreadyTaken));
+        }
+        let output = ready!(Pin::new(&mut self.task).poll(cx));
+        // Without an output, the task was cancelled by its executor.
+        self.taken = output.is_some();
+        Poll::Ready(output.unwrap_or(Err(JoinError::Cancelled)))
+    }
+}
+
+/// Spawns a task whose handle reports panics and cancellation as values.
+///
+/// This is the building block for supervisors: awaiting the returned
+/// [`SupervisedTask`] distinguishes between the future's own output, a panic,
+/// and an abort.
+///
+/// # Examples
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::{JoinError, spawn, spawn_supervised};
+///
+/// // Completion
+/// assert_eq!(block_on(spawn_supervised(async { 7 })).unwrap(), 7);
+///
+/// // Panic
+/// let error = block_on(spawn_supervised(async { panic!("boom") })).unwrap_err();
+/// assert!(error.is_panic());
+///
+/// // Abort from another task while awaiting
+/// let task = spawn_supervised(std::future::pending::<()>());
+/// let abort = task.abort_handle();
+/// spawn(async move { abort.abort() }).detach();
+/// assert!(matches!(block_on(task), Err(JoinError::Cancelled)));
+/// ```
+#[track_caller]
+pub fn spawn_supervised<Fut>(future: Fut) -> SupervisedTask<Fut::Output>
+where
+    Fut: Future + Send + 'static,
+    Fut::Output: Send,
+{
+    spawn_supervised_with_priority(future, Priority::default())
+}
+
+/// Spawns a supervised task at the given priority.
+///
+/// See [`spawn_supervised`].
+#[track_caller]
+pub fn spawn_supervised_with_priority<Fut>(
+    future: Fut,
+    priority: Priority,
+) -> SupervisedTask<Fut::Output>
+where
+    Fut: Future + Send + 'static,
+    Fut::Output: Send,
+{
+    let abort = Arc::new(AbortState::default());
+    let task = spawn_with_priority(
+        Supervised {
+            future: Some(future),
+            abort: abort.clone(),
+        },
+        priority,
+    );
+    SupervisedTask {
+        task: task.fallible(),
+        abort: AbortHandle { state: abort },
+        taken: false,
+    }
+}
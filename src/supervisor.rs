@@ -0,0 +1,435 @@
+//! Automatic restarts for long-running tasks.
+//!
+//! Daemon-style tasks such as a socket pump or a sync loop should keep running
+//! even when an iteration fails. [`supervise`] runs a task produced by a
+//! factory, and whenever the task returns an error or panics, waits for an
+//! exponentially growing backoff and starts a fresh one.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use futures_lite::future::block_on;
+//! use native_executor::supervisor::{RestartPolicy, SupervisorState, supervise};
+//! use std::{
+//!     sync::{Arc, atomic::{AtomicU32, Ordering}},
+//!     time::Duration,
+//! };
+//!
+//! // Fails twice, then succeeds
+//! let attempts = Arc::new(AtomicU32::new(0));
+//! let counter = attempts.clone();
+//! let handle = supervise(
+//!     move || {
+//!         let attempt = counter.fetch_add(1, Ordering::SeqCst);
+//!         async move { if attempt < 2 { Err("not yet") } else { Ok(()) } }
+//!     },
+//!     RestartPolicy::new().backoff(Duration::from_millis(5), Duration::from_millis(20)),
+//! );
+//!
+//! let mut states = handle.watch();
+//! block_on(async {
+//!     while let Some(state) = states.next().await {
+//!         if state == SupervisorState::Stopped {
+//!             break;
+//!         }
+//!     }
+//! });
+//! assert_eq!(attempts.load(Ordering::SeqCst), 3);
+//! assert_eq!(handle.restarts(), 2);
+//! ```
+
+use core::{
+    fmt,
+    future::{Future, poll_fn},
+    pin::pin,
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+    task::Poll,
+    time::Duration,
+};
+use std::{
+    collections::VecDeque,
+    panic::{AssertUnwindSafe, catch_unwind},
+    sync::{Arc, Mutex, PoisonError},
+    time::Instant,
+};
+
+use async_channel::{Receiver, Sender};
+use async_task::Task;
+
+use crate::{
+    AbortHandle, Priority, spawn_supervised_with_priority, spawn_with_priority, timer::Timer,
+};
+
+/// Controls how a supervisor restarts a failed task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestartPolicy {
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    max_restarts: Option<(u32, Duration)>,
+    priority: Priority,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RestartPolicy {
+    /// Creates a policy that restarts indefinitely, backing off from 100 ms up
+    /// to 30 s.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(30),
+            max_restarts: None,
+            priority: Priority::Default,
+        }
+    }
+
+    /// Sets the first backoff delay and the cap it doubles towards.
+    ///
+    /// The backoff starts over from `initial` once an instance has run for
+    /// longer than `max` before failing, so a task that fails rarely is not
+    /// held at the cap forever.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{
+    ///     supervisor::{RestartPolicy, SupervisorState, supervise},
+    ///     timer::Timer,
+    /// };
+    /// use std::time::Duration;
+    ///
+    /// // Runs healthily for longer than the cap before every failure
+    /// let handle = supervise(
+    ///     || async {
+    ///         Timer::after(Duration::from_millis(30)).await;
+    ///         Err::<(), _>("disconnected")
+    ///     },
+    ///     RestartPolicy::new()
+    ///         .backoff(Duration::from_millis(5), Duration::from_millis(20))
+    ///         .max_restarts_within(3, Duration::from_secs(60)),
+    /// );
+    ///
+    /// let mut states = handle.watch();
+    /// let mut delays = Vec::new();
+    /// block_on(async {
+    ///     while let Some(state) = states.next().await {
+    ///         if let SupervisorState::Backoff(delay) = state {
+    ///             delays.push(delay);
+    ///         }
+    ///     }
+    /// });
+    /// assert_eq!(delays, [Duration::from_millis(5); 3]);
+    /// ```
+    #[must_use]
+    pub const fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Stops supervising once `max` restarts happened within `window`.
+    ///
+    /// This bounds restart storms from a task that fails immediately on
+    /// every start.
+    #[must_use]
+    pub const fn max_restarts_within(mut self, max: u32, window: Duration) -> Self {
+        self.max_restarts = Some((max, window));
+        self
+    }
+
+    /// Sets the priority the supervised task runs at.
+    #[must_use]
+    pub const fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    fn backoff_for(&self, consecutive_failures: u32) -> Duration {
+        let factor = 2u32.saturating_pow(consecutive_failures.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// The lifecycle state of a supervised task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SupervisorState {
+    /// An instance of the task is running.
+    Running,
+    /// The task failed and will be restarted after the given delay.
+    Backoff(Duration),
+    /// Supervision ended: the task succeeded, the restart limit was reached,
+    /// or the supervisor was stopped.
+    Stopped,
+}
+
+struct Shared {
+    state: Mutex<SupervisorState>,
+    watchers: Mutex<Vec<Sender<SupervisorState>>>,
+    restarts: AtomicU32,
+    stopping: AtomicBool,
+    current: Mutex<Option<AbortHandle>>,
+    stop_signal: (Sender<()>, Receiver<()>),
+}
+
+impl Shared {
+    fn set_state(&self, state: SupervisorState) {
+        // The state changes under the watcher list, which `watch` holds while
+        // reading it, so a new watcher never sees a state twice.
+        let mut watchers = self.watchers.lock().unwrap_or_else(PoisonError::into_inner);
+        *self.state.lock().unwrap_or_else(PoisonError::into_inner) = state;
+        watchers.retain(|watcher| watcher.try_send(state).is_ok());
+        if state == SupervisorState::Stopped {
+            // Ends the streams once they have received the last transition
+            watchers.clear();
+        }
+    }
+
+    fn current_state(&self) -> SupervisorState {
+        *self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A stream of [`SupervisorState`] transitions, created by
+/// [`SupervisorHandle::watch`].
+#[derive(Debug)]
+pub struct StateWatch {
+    receiver: Receiver<SupervisorState>,
+}
+
+impl StateWatch {
+    /// Waits for the next state transition.
+    ///
+    /// Returns `None` once the supervisor has stopped and all transitions
+    /// were observed.
+    pub async fn next(&mut self) -> Option<SupervisorState> {
+        self.receiver.recv().await.ok()
+    }
+}
+
+/// A handle to a running supervisor.
+///
+/// Dropping the handle stops supervision and cancels the running task.
+pub struct SupervisorHandle {
+    shared: Arc<Shared>,
+    task: Task<()>,
+}
+
+impl fmt::Debug for SupervisorHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SupervisorHandle")
+            .field("state", &self.state())
+            .field("restarts", &self.restarts())
+            .finish_non_exhaustive()
+    }
+}
+
+impl SupervisorHandle {
+    /// Returns the current state.
+    #[must_use]
+    pub fn state(&self) -> SupervisorState {
+        self.shared.current_state()
+    }
+
+    /// Returns how many times the task has been restarted.
+    #[must_use]
+    pub fn restarts(&self) -> u32 {
+        self.shared.restarts.load(Ordering::Acquire)
+    }
+
+    /// Returns a stream of state transitions, starting with the current state.
+    #[must_use]
+    pub fn watch(&self) -> StateWatch {
+        let (sender, receiver) = async_channel::unbounded();
+        // Holding the watcher list while reading the state ensures no transition
+        // is missed or delivered twice.
+        let mut watchers = self
+            .shared
+            .watchers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let state = self.shared.current_state();
+        let _ = sender.try_send(state);
+        if state != SupervisorState::Stopped {
+            watchers.push(sender);
+        }
+        drop(watchers);
+        StateWatch { receiver }
+    }
+
+    /// Stops supervision, aborting the running task, and waits until the
+    /// supervisor has shut down.
+    pub async fn stop(self) {
+        self.shared.stopping.store(true, Ordering::Release);
+        self.shared.stop_signal.0.close();
+        let current = self
+            .shared
+            .current
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        if let Some(current) = current {
+            current.abort();
+        }
+        self.task.await;
+    }
+}
+
+/// Runs the future produced by `factory`, restarting it according to `policy`
+/// whenever it returns `Err` or panics.
+///
+/// A panic in `factory` itself counts as a failed start, and is retried
+/// likewise.
+///
+/// Supervision ends when the future returns `Ok(())`, when the restart limit
+/// of the policy is exceeded, or when [`SupervisorHandle::stop`] is called.
+///
+/// # Examples
+///
+/// A task that panics on every start is given up on once the restart limit
+/// is hit:
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::supervisor::{RestartPolicy, SupervisorState, supervise};
+/// use std::time::Duration;
+///
+/// let handle = supervise(
+///     || async { panic!("always fails") as Result<(), ()> },
+///     RestartPolicy::new()
+///         .backoff(Duration::from_millis(1), Duration::from_millis(1))
+///         .max_restarts_within(3, Duration::from_secs(60)),
+/// );
+///
+/// let mut states = handle.watch();
+/// block_on(async { while states.next().await != Some(SupervisorState::Stopped) {} });
+/// assert_eq!(handle.restarts(), 3);
+/// ```
+///
+/// A factory that panics is retried as well:
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::supervisor::{RestartPolicy, supervise};
+/// use std::{sync::atomic::{AtomicU32, Ordering}, time::Duration};
+///
+/// let calls = AtomicU32::new(0);
+/// let handle = supervise(
+///     move || {
+///         assert!(calls.fetch_add(1, Ordering::SeqCst) > 0, "not connected yet");
+///         async { Ok::<(), ()>(()) }
+///     },
+///     RestartPolicy::new().backoff(Duration::from_millis(1), Duration::from_millis(1)),
+/// );
+///
+/// let mut states = handle.watch();
+/// block_on(async { while states.next().await.is_some() {} });
+/// assert_eq!(handle.restarts(), 1);
+/// ```
+pub fn supervise<F, Fut, E>(mut factory: F, policy: RestartPolicy) -> SupervisorHandle
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(), E>> + Send + 'static,
+    E: Send + 'static,
+{
+    let shared = Arc::new(Shared {
+        state: Mutex::new(SupervisorState::Running),
+        watchers: Mutex::new(Vec::new()),
+        restarts: AtomicU32::new(0),
+        stopping: AtomicBool::new(false),
+        current: Mutex::new(None),
+        stop_signal: async_channel::bounded(1),
+    });
+
+    let loop_shared = shared.clone();
+    let task = spawn_with_priority(
+        async move {
+            let shared = loop_shared;
+            let _stopped = Stopped(shared.clone());
+            let mut recent_restarts = VecDeque::new();
+            let mut consecutive_failures = 0;
+
+            loop {
+                let started = Instant::now();
+                // The panic hook has already reported a panicking factory,
+                // which counts as a failed start.
+                let succeeded = match catch_unwind(AssertUnwindSafe(&mut factory)) {
+                    Ok(future) => {
+                        let task = spawn_supervised_with_priority(future, policy.priority);
+                        *shared
+                            .current
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner) = Some(task.abort_handle());
+                        if shared.stopping.load(Ordering::Acquire) {
+                            task.abort();
+                        }
+                        shared.set_state(SupervisorState::Running);
+                        matches!(task.await, Ok(Ok(())))
+                    }
+                    Err(_) => false,
+                };
+                if succeeded || shared.stopping.load(Ordering::Acquire) {
+                    break;
+                }
+
+                if let Some((max, window)) = policy.max_restarts {
+                    let now = Instant::now();
+                    while recent_restarts
+                        .front()
+                        .is_some_and(|&at: &Instant| now.duration_since(at) > window)
+                    {
+                        recent_restarts.pop_front();
+                    }
+                    if recent_restarts.len() >= max as usize {
+                        break;
+                    }
+                    recent_restarts.push_back(now);
+                }
+
+                if started.elapsed() > policy.max_backoff {
+                    consecutive_failures = 0;
+                }
+                consecutive_failures += 1;
+                let delay = policy.backoff_for(consecutive_failures);
+                shared.set_state(SupervisorState::Backoff(delay));
+                if !sleep_unless_stopped(delay, &shared.stop_signal.1).await {
+                    break;
+                }
+                shared.restarts.fetch_add(1, Ordering::AcqRel);
+            }
+        },
+        policy.priority,
+    );
+
+    SupervisorHandle { shared, task }
+}
+
+/// Publishes [`SupervisorState::Stopped`] once supervision ends, including
+/// when the supervisor task is dropped.
+struct Stopped(Arc<Shared>);
+
+impl Drop for Stopped {
+    fn drop(&mut self) {
+        self.0.set_state(SupervisorState::Stopped);
+    }
+}
+
+/// Sleeps for `delay`, returning `false` early if the stop signal fires.
+async fn sleep_unless_stopped(delay: Duration, stop: &Receiver<()>) -> bool {
+    let mut timer = pin!(Timer::after(delay));
+    let mut stopped = pin!(stop.recv());
+    poll_fn(|cx| {
+        if stopped.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(false);
+        }
+        timer.as_mut().poll(cx).map(|()| true)
+    })
+    .await
+}
@@ -0,0 +1,280 @@
+//! Groups of supervised tasks, organized in lanes by priority.
+
+use alloc::{collections::VecDeque, vec::Vec};
+use core::{fmt, future::Future, time::Duration};
+
+use crate::{
+    JoinError, Priority, SupervisedTask,
+    priority::SchedClass,
+    spawn_supervised_with_priority,
+    timer::{Deadline, Timeout},
+};
+
+/// A set of tasks spawned together, each in the lane of its priority.
+///
+/// Members are [supervised](crate::spawn_supervised), so a panic or an abort
+/// is reported as a [`JoinError`] instead of being lost. Lanes are the
+/// standard priority levels: a [`Priority::Custom`] member belongs to the
+/// lane of its [standard](Priority::standard) level. Lanes can be joined and
+/// aborted on their own, and [`shutdown`](Self::shutdown) sheds the
+/// best-effort lanes before the others.
+///
+/// Dropping the group cancels the members it still holds.
+///
+/// # Examples
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::{Priority, TaskGroup};
+/// use std::future::pending;
+///
+/// let mut group = TaskGroup::new();
+/// group.spawn_with_priority(async { Some("thumbnail") }, Priority::UserInitiated);
+/// group.spawn_with_priority(async { Some("preview") }, Priority::UserInitiated);
+/// group.spawn_with_priority(pending(), Priority::Background);
+/// assert_eq!(group.lane_len(Priority::UserInitiated), 2);
+///
+/// // The critical lane, in spawn order
+/// let critical: Vec<_> = block_on(group.join_lane(Priority::UserInitiated))
+///     .into_iter()
+///     .map(Result::unwrap)
+///     .collect();
+/// assert_eq!(critical, [Some("thumbnail"), Some("preview")]);
+///
+/// // The best-effort lane is given up on
+/// group.abort_lane(Priority::Background);
+/// let shed = block_on(group.join_lane(Priority::Background));
+/// assert!(shed[0].as_ref().unwrap_err().is_cancelled());
+/// assert!(group.is_empty());
+/// ```
+pub struct TaskGroup<T> {
+    /// The members not joined yet, by lane, in spawn order.
+    lanes: [VecDeque<SupervisedTask<T>>; SchedClass::COUNT],
+}
+
+impl<T> fmt::Debug for TaskGroup<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskGroup")
+            .field("members", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Default for TaskGroup<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The lanes shed first by [`TaskGroup::shutdown`].
+const BEST_EFFORT: [SchedClass; 2] = [SchedClass::Background, SchedClass::Utility];
+
+impl<T> TaskGroup<T> {
+    /// Creates an empty group.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            lanes: [const { VecDeque::new() }; SchedClass::COUNT],
+        }
+    }
+
+    /// Returns the number of members not joined yet.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lanes.iter().map(VecDeque::len).sum()
+    }
+
+    /// Returns `true` if every member has been joined.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.lanes.iter().all(VecDeque::is_empty)
+    }
+
+    /// Returns the number of members in the lane of `priority` not joined
+    /// yet.
+    #[must_use]
+    pub fn lane_len(&self, priority: Priority) -> usize {
+        self.lanes[priority.class().index()].len()
+    }
+
+    /// Aborts the members in the lane of `priority`, see
+    /// [`AbortHandle::abort`](crate::AbortHandle::abort).
+    ///
+    /// They stay in the group until joined, which yields
+    /// [`JoinError::Cancelled`] for those that had not completed.
+    pub fn abort_lane(&self, priority: Priority) {
+        self.lanes[priority.class().index()]
+            .iter()
+            .for_each(SupervisedTask::abort);
+    }
+
+    /// Aborts every member, as [`abort_lane`](Self::abort_lane) does.
+    pub fn abort_all(&self) {
+        self.lanes.iter().flatten().for_each(SupervisedTask::abort);
+    }
+
+    /// Waits for the members in the lane of `priority`, and returns their
+    /// results in spawn order.
+    ///
+    /// The members are removed from the group as they are joined; if the
+    /// returned future is dropped, those not joined yet stay.
+    pub async fn join_lane(&mut self, priority: Priority) -> Vec<Result<T, JoinError>> {
+        let lane = &mut self.lanes[priority.class().index()];
+        let mut results = Vec::with_capacity(lane.len());
+        while let Some(member) = lane.front_mut() {
+            results.push(member.await);
+            lane.pop_front();
+        }
+        results
+    }
+
+    /// Waits for every member, and returns their results lane by lane, from
+    /// the most urgent, each in spawn order.
+    pub async fn join_all(&mut self) -> Vec<Result<T, JoinError>> {
+        let mut results = Vec::with_capacity(self.len());
+        for &priority in Priority::ALL.iter().rev() {
+            results.extend(self.join_lane(priority).await);
+        }
+        results
+    }
+
+    /// Shuts the group down in two phases, and reports how each lane ended.
+    ///
+    /// The best-effort members, those of [`Priority::Background`] and
+    /// [`Priority::Utility`], are aborted right away. The others get `grace`
+    /// to complete; those still running then are aborted too.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{LaneReport, Priority, TaskGroup, timer::Timer};
+    /// use std::{
+    ///     sync::{Arc, Mutex},
+    ///     time::{Duration, Instant},
+    /// };
+    ///
+    /// // Records when a member ends, whether it completed or was aborted
+    /// struct Ended(&'static str, Arc<Mutex<Vec<&'static str>>>);
+    /// impl Drop for Ended {
+    ///     fn drop(&mut self) {
+    ///         self.1.lock().unwrap().push(self.0);
+    ///     }
+    /// }
+    ///
+    /// let log = Arc::new(Mutex::new(Vec::new()));
+    /// let mut group = TaskGroup::new();
+    /// let members = [
+    ///     ("prefetch", Priority::Background, 10),
+    ///     ("reindex", Priority::Utility, 10),
+    ///     ("save", Priority::Default, 50),
+    ///     ("render", Priority::UserInitiated, 5_000),
+    /// ];
+    /// for (name, priority, millis) in members {
+    ///     let ended = Ended(name, log.clone());
+    ///     group.spawn_with_priority(
+    ///         async move {
+    ///             let _ended = ended;
+    ///             Timer::after(Duration::from_millis(millis)).await;
+    ///         },
+    ///         priority,
+    ///     );
+    /// }
+    ///
+    /// let start = Instant::now();
+    /// let report = block_on(group.shutdown(Duration::from_millis(300)));
+    /// assert!(start.elapsed() >= Duration::from_millis(300));
+    /// assert!(start.elapsed() < Duration::from_secs(5));
+    ///
+    /// // Best effort first, even if it would have completed in time
+    /// let mut log = log.lock().unwrap().clone();
+    /// log[..2].sort_unstable();
+    /// assert_eq!(log, ["prefetch", "reindex", "save", "render"]);
+    ///
+    /// let aborted = LaneReport { aborted: 1, ..LaneReport::default() };
+    /// assert_eq!(report.lane(Priority::Background), aborted);
+    /// assert_eq!(report.lane(Priority::Utility), aborted);
+    /// assert_eq!(report.lane(Priority::Default).completed, 1);
+    /// assert_eq!(report.lane(Priority::UserInitiated), aborted);
+    /// ```
+    pub async fn shutdown(mut self, grace: Duration) -> ShutdownReport {
+        for class in BEST_EFFORT {
+            self.lanes[class.index()]
+                .iter()
+                .for_each(SupervisedTask::abort);
+        }
+        let deadline = Deadline::after(grace);
+        let mut report = ShutdownReport::default();
+        for (index, lane) in self.lanes.iter_mut().enumerate().rev() {
+            for member in lane {
+                let result = if let Ok(result) = Timeout::at(deadline.clone(), &mut *member).await {
+                    result
+                } else {
+                    member.abort();
+                    member.await
+                };
+                report.lanes[index].record(&result);
+            }
+        }
+        report
+    }
+}
+
+impl<T: Send + 'static> TaskGroup<T> {
+    /// Spawns `future` as a member of the [`Priority::Default`] lane.
+    #[track_caller]
+    pub fn spawn<Fut>(&mut self, future: Fut)
+    where
+        Fut: Future<Output = T> + Send + 'static,
+    {
+        self.spawn_with_priority(future, Priority::default());
+    }
+
+    /// Spawns `future` at `priority`, as a member of the lane of that
+    /// priority.
+    #[track_caller]
+    pub fn spawn_with_priority<Fut>(&mut self, future: Fut, priority: Priority)
+    where
+        Fut: Future<Output = T> + Send + 'static,
+    {
+        let member = spawn_supervised_with_priority(future, priority);
+        self.lanes[priority.class().index()].push_back(member);
+    }
+}
+
+/// How the members of one lane ended, as reported by
+/// [`TaskGroup::shutdown`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LaneReport {
+    /// Members that completed.
+    pub completed: usize,
+    /// Members that panicked.
+    pub panicked: usize,
+    /// Members that were aborted before they completed.
+    pub aborted: usize,
+}
+
+impl LaneReport {
+    const fn record<T>(&mut self, result: &Result<T, JoinError>) {
+        match result {
+            // Members are joined once, so their output is never taken before.
+            Ok(_) | Err(JoinError::AlreadyTaken) => self.completed += 1,
+            Err(JoinError::Panicked(_)) => self.panicked += 1,
+            Err(JoinError::Cancelled) => self.aborted += 1,
+        }
+    }
+}
+
+/// How the lanes of a group ended, as returned by [`TaskGroup::shutdown`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    lanes: [LaneReport; SchedClass::COUNT],
+}
+
+impl ShutdownReport {
+    /// Returns how the members in the lane of `priority` ended.
+    #[must_use]
+    pub const fn lane(&self, priority: Priority) -> LaneReport {
+        self.lanes[priority.class().index()]
+    }
+}
@@ -0,0 +1,180 @@
+//! The task handle of the [`Executor`](crate::Executor) implementations.
+
+use alloc::boxed::Box;
+use core::{
+    any::Any,
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::Task;
+
+/// The task handle returned by the [`Executor`](crate::Executor) and
+/// [`LocalExecutor`](crate::LocalExecutor) implementations of this crate.
+///
+/// It wraps the [`Task`] returned by the spawn functions and behaves the
+/// same, so generic code and direct calls agree:
+///
+/// - Awaiting it yields the output, also after the task has completed.
+/// - Dropping it cancels the task; [`detach`](Self::detach) lets the task
+///   run to completion instead.
+/// - [`cancel`](Self::cancel) cancels the task and returns its output if it
+///   had already completed.
+///
+/// [`ExecutorTask`](crate::ExecutorTask) adds [`result`], which reports a
+/// panic of the task as an error instead of resuming it. The conversions
+/// from and into [`Task`] are free.
+///
+/// [`result`]: crate::ExecutorTask::result
+///
+/// # Examples
+///
+/// Both ways of spawning behave the same:
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::{DefaultExecutor, Executor, Task, TaskHandle, spawn, timer::Timer};
+/// use std::{
+///     sync::{Arc, atomic::{AtomicBool, Ordering}},
+///     thread,
+///     time::Duration,
+/// };
+///
+/// fn check(spawn_task: impl Fn(Arc<AtomicBool>) -> TaskHandle<u32>) {
+///     // Dropping the handle cancels the task
+///     let finished = Arc::new(AtomicBool::new(false));
+///     drop(spawn_task(finished.clone()));
+///     thread::sleep(Duration::from_millis(50));
+///     assert!(!finished.load(Ordering::SeqCst));
+///
+///     // Awaiting after completion yields the output
+///     let finished = Arc::new(AtomicBool::new(false));
+///     let task = spawn_task(finished.clone());
+///     while !task.is_finished() {
+///         thread::sleep(Duration::from_millis(1));
+///     }
+///     assert!(finished.load(Ordering::SeqCst));
+///     assert_eq!(block_on(task), 42);
+///
+///     // Cancelling a running task stops it, and yields no output
+///     let finished = Arc::new(AtomicBool::new(false));
+///     assert_eq!(block_on(spawn_task(finished.clone()).cancel()), None);
+///     thread::sleep(Duration::from_millis(50));
+///     assert!(!finished.load(Ordering::SeqCst));
+///
+///     // Detaching lets the task run to completion
+///     let finished = Arc::new(AtomicBool::new(false));
+///     spawn_task(finished.clone()).detach();
+///     thread::sleep(Duration::from_millis(50));
+///     assert!(finished.load(Ordering::SeqCst));
+/// }
+///
+/// async fn work(finished: Arc<AtomicBool>) -> u32 {
+///     Timer::after(Duration::from_millis(10)).await;
+///     finished.store(true, Ordering::SeqCst);
+///     42
+/// }
+///
+/// check(|finished| DefaultExecutor.spawn(work(finished)));
+/// check(|finished| spawn(work(finished)).into());
+///
+/// // And convert into each other
+/// let task: Task<u32> = DefaultExecutor.spawn(async { 7 }).into();
+/// assert_eq!(block_on(task), 7);
+/// ```
+#[must_use = "dropping the handle cancels the task"]
+pub struct TaskHandle<T> {
+    /// Only `None` once the task has been cancelled through
+    /// [`ExecutorTask::poll_cancel`](crate::ExecutorTask::poll_cancel).
+    task: Option<Task<T>>,
+}
+
+impl<T> fmt::Debug for TaskHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskHandle")
+            .field("finished", &self.is_finished())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> From<Task<T>> for TaskHandle<T> {
+    fn from(task: Task<T>) -> Self {
+        Self { task: Some(task) }
+    }
+}
+
+// Only a handle cancelled through `poll_cancel` has no task left to convert,
+// and a cancelled handle has no further use.
+#[allow(clippy::fallible_impl_from)]
+impl<T> From<TaskHandle<T>> for Task<T> {
+    fn from(handle: TaskHandle<T>) -> Self {
+        handle
+            .task
+            .expect("cannot convert a cancelled `TaskHandle` into a `Task`")
+    }
+}
+
+impl<T> TaskHandle<T> {
+    /// Lets the task run to completion without the handle.
+    pub fn detach(self) {
+        if let Some(task) = self.task {
+            task.detach();
+        }
+    }
+
+    /// Cancels the task, waiting until it has stopped.
+    ///
+    /// Returns the output if the task had already completed.
+    pub async fn cancel(self) -> Option<T> {
+        match self.task {
+            Some(task) => task.cancel().await,
+            None => None,
+        }
+    }
+
+    /// Returns whether the task has completed or been cancelled.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.task.as_ref().is_none_or(Task::is_finished)
+    }
+}
+
+impl<T> Future for TaskHandle<T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let task = self
+            .task
+            .as_mut()
+            .expect("`TaskHandle` polled after cancellation");
+        Pin::new(task).poll(cx)
+    }
+}
+
+impl<T> executor_core::Task<T> for TaskHandle<T> {
+    fn poll_result(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<T, Box<dyn Any + Send>>> {
+        #[cfg(feature = "std")]
+        {
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| self.poll(cx)))
+                .map_or_else(|panic| Poll::Ready(Err(panic)), |poll| poll.map(Ok))
+        }
+        #[cfg(not(feature = "std"))]
+        self.poll(cx).map(Ok)
+    }
+
+    fn poll_cancel(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+        // Dropping the task cancels it; a running task stops at its next
+        // yield point.
+        self.task = None;
+        Poll::Ready(())
+    }
+
+    fn detach(self) {
+        Self::detach(self);
+    }
+}
@@ -10,19 +10,108 @@
 //! # async {
 //! Timer::after(Duration::from_millis(100)).await;  // Precise timing
 //! Timer::after_secs(2).await;                      // Convenience method  
-//! sleep(1).await;                                  // Simple sleep
+//! sleep(Duration::from_secs(1)).await;             // Simple sleep
 //! # };
 //! ```
+//!
+//! [`Interval`] produces ticks at a fixed period, optionally aligned to
+//! wall-clock boundaries. A [`Deadline`] is a single point in time that many
+//! tasks can await or check; a [`Timeout`] bounds a future by one, and
+//! [`with_deadline`] makes one the ambient budget of a whole pipeline.
+//! [`first_of`] waits for a future up to a duration, and then moves on.
+//! [`Race`] and [`Timeout`] are named futures, so they can be stored in
+//! structs without boxing. A [`FramePacer`] drives fixed-timestep loops,
+//! such as the update loop of a game. A [`Debouncer`] collapses bursts of
+//! calls into one, and a [`Throttle`] runs them at most once per window.
+//!
+//! # Foreign executors
+//!
+//! Timers do not need tasks of this crate: they can be awaited from any
+//! executor, before anything has been spawned. On the polyfill backend they
+//! share one timer thread, started on first use, and leave the worker pool
+//! and the main executor alone.
+//!
+//! ```rust
+//! # #[cfg(not(any(target_vendor = "apple", target_os = "android")))] {
+//! use futures_lite::future::{self, block_on};
+//! use native_executor::timer::{Deadline, Interval, Timer};
+//! use std::{
+//!     pin::pin,
+//!     sync::Arc,
+//!     task::{Context, Poll, Wake},
+//!     thread::{self, Thread},
+//!     time::{Duration, Instant},
+//! };
+//!
+//! // A minimal executor that parks the thread between polls
+//! fn park_on<F: Future>(future: F) -> F::Output {
+//!     struct Unpark(Thread);
+//!     impl Wake for Unpark {
+//!         fn wake(self: Arc<Self>) {
+//!             self.0.unpark();
+//!         }
+//!     }
+//!     let waker = Arc::new(Unpark(thread::current())).into();
+//!     let mut cx = Context::from_waker(&waker);
+//!     let mut future = pin!(future);
+//!     loop {
+//!         if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+//!             return output;
+//!         }
+//!         thread::park();
+//!     }
+//! }
+//!
+//! #[cfg(target_os = "linux")]
+//! let threads = || std::fs::read_dir("/proc/self/task").unwrap().count();
+//! #[cfg(target_os = "linux")]
+//! let before = threads();
+//!
+//! let start = Instant::now();
+//! block_on(Timer::after(Duration::from_millis(10)));
+//! park_on(Timer::after(Duration::from_millis(10)));
+//! assert!(start.elapsed() >= Duration::from_millis(20));
+//!
+//! // A timeout: the deadline passes long before the slow work is done
+//! let deadline = Deadline::after(Duration::from_millis(10));
+//! let slow = async {
+//!     Timer::after(Duration::from_secs(60)).await;
+//!     "done"
+//! };
+//! assert_eq!(block_on(future::or(slow, async { deadline.await; "timed out" })), "timed out");
+//!
+//! let mut interval = Interval::every(Duration::from_millis(5));
+//! park_on(async {
+//!     for _ in 0..3 {
+//!         interval.tick().await;
+//!     }
+//! });
+//!
+//! // The timer thread is the only one started
+//! #[cfg(target_os = "linux")]
+//! assert_eq!(threads(), before + 1);
+//! # }
+//! ```
 
+use alloc::sync::Arc;
+#[cfg(feature = "std")]
+use core::sync::atomic::AtomicU64;
 use core::{
+    cell::UnsafeCell,
+    fmt,
     future::Future,
     pin::Pin,
     sync::atomic::{AtomicBool, Ordering},
-    task::{Context, Poll},
+    task::{Context, Poll, Waker},
     time::Duration,
 };
-use std::sync::Arc;
+#[cfg(feature = "std")]
+use std::{
+    sync::{Mutex, PoisonError},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
 
+#[cfg(feature = "std")]
 use crate::{NativeExecutor, PlatformExecutor};
 
 /// A high-precision future that completes after a specified duration.
@@ -33,12 +122,21 @@ use crate::{NativeExecutor, PlatformExecutor};
 ///
 /// # Platform Behavior
 /// - **Apple platforms**: Uses GCD's `dispatch_after` for precise scheduling
+/// - **Polyfill backend**: Uses a single timer thread, started on first use
+/// - **Coalescing**: With [`Config::coalesce_timers`](crate::Config::coalesce_timers),
+///   timers with nearby deadlines share one platform timer
 /// - **Other platforms**: Will use platform-specific high-resolution APIs
 ///
 /// # Performance
 /// Unlike thread-based sleep implementations, `Timer` doesn't block threads and
 /// allows the executor to handle other tasks while waiting.
 ///
+/// # Thread Safety
+/// `Timer` is `Send` and `Sync`, so it can be stored in structures shared
+/// across tasks and threads. It wakes the task of its latest poll, so a
+/// pending timer can be handed to another task; use a [`Deadline`] for a
+/// point in time that several tasks await at once.
+///
 /// # Examples
 /// ```rust
 /// use native_executor::timer::Timer;
@@ -52,13 +150,209 @@ use crate::{NativeExecutor, PlatformExecutor};
 ///     Timer::after_secs(2).await;
 /// }
 /// ```
+///
+/// A timer started by one task completes in the task it is moved to:
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::{spawn, timer::Timer};
+/// use std::{
+///     future::Future,
+///     pin::pin,
+///     task::{Context, Waker},
+///     time::Duration,
+/// };
+///
+/// let mut timer = Timer::after(Duration::from_millis(20));
+/// // Started with a waker that wakes nothing
+/// assert!(pin!(&mut timer).poll(&mut Context::from_waker(Waker::noop())).is_pending());
+/// block_on(spawn(async move {
+///     timer.await;
+/// }));
+/// ```
 #[derive(Debug)]
 pub struct Timer {
     /// The duration to wait. This is taken (set to None) after the timer is started.
     duration: Option<Duration>,
-    /// Atomic flag to track whether the timer has completed.
+    /// When the timer fires: set on creation by [`Timer::at`], otherwise once
+    /// the timer is started.
+    #[cfg(feature = "std")]
+    deadline: Option<Instant>,
+    /// The time left when the timer was paused, until it is resumed.
+    #[cfg(feature = "std")]
+    paused: Option<Duration>,
+    /// The priority the callback is dispatched at, if not the ambient one.
+    priority: Option<crate::Priority>,
+    /// How late the callback may fire, if not the backend's default.
+    tolerance: Option<Duration>,
+    /// Whether the callback runs on the main thread.
+    on_main: bool,
+    /// Whether the timer is coalesced even if the configuration does not
+    /// coalesce timers.
+    #[cfg(feature = "std")]
+    coarse: bool,
+    /// Tracks whether the timer has completed, and the waker of its latest
+    /// poll.
     /// This is shared between the future and the callback that will be executed after the duration.
-    finished: Arc<AtomicBool>,
+    finished: Completion,
+    /// The callback's place in a group of coalesced timers, removed when the
+    /// timer is dropped.
+    #[cfg(feature = "std")]
+    coalesced: Option<crate::coalesce::Entry>,
+    /// The platform timer running the callback, cancelled when the timer is
+    /// dropped before it completes.
+    #[cfg(feature = "std")]
+    scheduled: Option<crate::TimerKey>,
+    /// Counts the timer in [`live_objects`](crate::diagnostics::live_objects)
+    /// once its state is allocated by its first poll.
+    #[cfg(feature = "leak-check")]
+    live: Option<crate::diagnostics::Live>,
+}
+
+/// The granularity of [`Timer::coarse`] when
+/// [`Config::coalesce_timers`](crate::Config::coalesce_timers) is not set.
+#[cfg(feature = "std")]
+pub const COARSE_GRANULARITY: Duration = Duration::from_millis(4);
+
+/// The waker of the latest poll of a [`Timer`], shared with its callback.
+///
+/// A spin lock guards it: it is only held to clone or take the waker, and
+/// timers need it without `std`.
+#[derive(Default)]
+pub(crate) struct WakerSlot {
+    locked: AtomicBool,
+    waker: UnsafeCell<Option<Waker>>,
+}
+
+// SAFETY: `waker` is only accessed with `locked` held.
+unsafe impl Sync for WakerSlot {}
+
+impl fmt::Debug for WakerSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WakerSlot").finish_non_exhaustive()
+    }
+}
+
+impl WakerSlot {
+    fn with<R>(&self, f: impl FnOnce(&mut Option<Waker>) -> R) -> R {
+        /// Releases the lock, also if cloning the waker panics.
+        struct Unlock<'a>(&'a AtomicBool);
+
+        impl Drop for Unlock<'_> {
+            fn drop(&mut self) {
+                self.0.store(false, Ordering::Release);
+            }
+        }
+
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            core::hint::spin_loop();
+        }
+        let _unlock = Unlock(&self.locked);
+        // SAFETY: the lock is held until `_unlock` is dropped.
+        f(unsafe { &mut *self.waker.get() })
+    }
+
+    /// Replaces the waker with `waker`, unless they wake the same task.
+    pub(crate) fn register(&self, waker: &Waker) {
+        self.with(|slot| match slot {
+            Some(registered) => registered.clone_from(waker),
+            None => *slot = Some(waker.clone()),
+        });
+    }
+
+    /// Takes the waker out.
+    pub(crate) fn take(&self) -> Option<Waker> {
+        self.with(Option::take)
+    }
+}
+
+/// The state of a [`Timer`] that is not pooled.
+#[derive(Debug, Default)]
+struct Owned {
+    finished: AtomicBool,
+    waker: WakerSlot,
+}
+
+/// The completion state shared by a [`Timer`] and its platform callback.
+#[derive(Debug, Clone)]
+enum Completion {
+    /// A state allocated for this timer alone.
+    Owned(Arc<Owned>),
+    /// A recycled slot, valid for one generation.
+    #[cfg(feature = "std")]
+    Pooled {
+        slot: &'static crate::timer_pool::Slot,
+        generation: u64,
+    },
+    /// The state of a zero-duration timer, finished from the start.
+    Elapsed,
+    /// The state of a timer that never completes, so never woken.
+    Never,
+    /// The state of a timer that was not polled yet, allocated or pooled by
+    /// its first poll.
+    Unarmed,
+}
+
+impl Completion {
+    /// Returns the state of a timer of `duration`; a zero duration has
+    /// elapsed already and [`Duration::MAX`] never does, so neither is ever
+    /// allocated nor pooled. Other timers are armed by their first poll.
+    const fn after(duration: Duration) -> Self {
+        match duration {
+            Duration::ZERO => Self::Elapsed,
+            Duration::MAX => Self::Never,
+            _ => Self::Unarmed,
+        }
+    }
+
+    fn new() -> Self {
+        #[cfg(feature = "std")]
+        if crate::config::current().timer_pool_capacity_per_thread() > 0 {
+            let (slot, generation) = crate::timer_pool::acquire();
+            return Self::Pooled { slot, generation };
+        }
+        Self::Owned(Arc::default())
+    }
+
+    /// Marks the timer as finished, and wakes the task of its latest poll.
+    fn finish(&self) {
+        let waker = match self {
+            Self::Owned(owned) => {
+                owned.finished.store(true, Ordering::Release);
+                owned.waker.take()
+            }
+            #[cfg(feature = "std")]
+            Self::Pooled { slot, generation } => slot.finish(*generation),
+            Self::Elapsed | Self::Never | Self::Unarmed => None,
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Makes `waker` the one woken once the timer finishes.
+    fn register(&self, waker: &Waker) {
+        match self {
+            Self::Owned(owned) => owned.waker.register(waker),
+            #[cfg(feature = "std")]
+            Self::Pooled { slot, .. } => slot.register(waker),
+            Self::Elapsed | Self::Never | Self::Unarmed => {}
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        match self {
+            Self::Owned(owned) => owned.finished.load(Ordering::Acquire),
+            #[cfg(feature = "std")]
+            Self::Pooled { slot, generation } => slot.is_finished(*generation),
+            Self::Elapsed => true,
+            Self::Never | Self::Unarmed => false,
+        }
+    }
 }
 
 impl Timer {
@@ -72,6 +366,15 @@ impl Timer {
     ///
     /// A new `Timer` instance that can be awaited.
     ///
+    /// A zero `duration` has elapsed already: the timer is ready on its first
+    /// poll, and neither allocates nor schedules a platform timer.
+    ///
+    /// Creating a timer does no work: its shared state is allocated, and
+    /// its platform timer scheduled, by its first poll. A timer raced
+    /// against a future that is ready right away, and dropped without
+    /// being polled, costs nothing. This also makes the constructors
+    /// `const`.
+    ///
     /// # Example
     ///
     /// ```
@@ -84,14 +387,302 @@ impl Timer {
     ///     println!("One second has passed!");
     /// }
     /// ```
+    ///
+    /// A zero delay does not wait for the executor, even when it is
+    /// saturated:
+    ///
+    /// ```
+    /// use futures_lite::future::{block_on, poll_once};
+    /// use native_executor::{spawn, timer::{Timer, sleep}};
+    /// use std::{
+    ///     future::Future,
+    ///     pin::pin,
+    ///     sync::atomic::{AtomicBool, Ordering},
+    ///     task::{Context, Poll, Waker},
+    ///     thread,
+    ///     time::Duration,
+    /// };
+    ///
+    /// static BUSY: AtomicBool = AtomicBool::new(true);
+    /// let busy: Vec<_> = (0..64)
+    ///     .map(|_| spawn(async {
+    ///         while BUSY.load(Ordering::Acquire) {
+    ///             thread::sleep(Duration::from_millis(1));
+    ///         }
+    ///     }))
+    ///     .collect();
+    ///
+    /// let mut cx = Context::from_waker(Waker::noop());
+    /// assert_eq!(pin!(Timer::after(Duration::ZERO)).poll(&mut cx), Poll::Ready(()));
+    /// assert_eq!(block_on(poll_once(sleep(Duration::ZERO))), Some(()));
+    ///
+    /// BUSY.store(false, Ordering::Release);
+    /// busy.into_iter().for_each(block_on);
+    /// ```
+    ///
+    /// A timer can be built in a `const fn`:
+    ///
+    /// ```
+    /// use futures_lite::future::block_on;
+    /// use native_executor::timer::Timer;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// const RETRY_BACKOFF: Duration = Duration::from_millis(20);
+    /// const fn backoff() -> Timer {
+    ///     Timer::after(RETRY_BACKOFF)
+    /// }
+    ///
+    /// let start = Instant::now();
+    /// block_on(backoff());
+    /// assert!(start.elapsed() >= RETRY_BACKOFF);
+    /// ```
     #[must_use]
-    pub fn after(duration: Duration) -> Self {
+    pub const fn after(duration: Duration) -> Self {
+        Self::with_state(duration, Completion::after(duration))
+    }
+
+    const fn with_state(duration: Duration, finished: Completion) -> Self {
         Self {
             duration: Some(duration),
-            finished: Arc::default(),
+            #[cfg(feature = "std")]
+            deadline: None,
+            #[cfg(feature = "std")]
+            paused: None,
+            priority: None,
+            tolerance: None,
+            on_main: false,
+            #[cfg(feature = "std")]
+            coarse: false,
+            finished,
+            #[cfg(feature = "std")]
+            coalesced: None,
+            #[cfg(feature = "std")]
+            scheduled: None,
+            #[cfg(feature = "leak-check")]
+            live: None,
         }
     }
 
+    /// Creates a `Timer` that completes after `duration`, whose platform
+    /// callback is dispatched at `priority`.
+    ///
+    /// Other timers dispatch their callback at the priority of the enclosing
+    /// [`with_priority`](crate::with_priority) section, if any, or at
+    /// [`Priority::Default`](crate::Priority::Default). The callback only
+    /// wakes the task awaiting the timer, so this decides where the wake-up
+    /// runs: on Apple platforms, the queue of the quality-of-service class of
+    /// `priority`, and on Android, the queue of that priority. The polyfill
+    /// backend fires every timer from its timer thread. The timer behaves as
+    /// [`Timer::after`] otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use native_executor::{Priority, timer::Timer};
+    /// use std::{
+    ///     future::Future,
+    ///     pin::pin,
+    ///     sync::{Arc, Mutex},
+    ///     task::{Context, Poll, Wake, Waker},
+    ///     thread,
+    ///     time::Duration,
+    /// };
+    ///
+    /// // The queue label, or thread name, the current code runs on
+    /// fn running_on() -> String {
+    ///     #[cfg(target_vendor = "apple")]
+    ///     {
+    ///         use dispatch::ffi::dispatch_queue_get_label;
+    ///         use std::{ffi::CStr, ptr};
+    ///         // SAFETY: a null queue stands for the current one.
+    ///         let label = unsafe { CStr::from_ptr(dispatch_queue_get_label(ptr::null_mut())) };
+    ///         label.to_str().unwrap().to_owned()
+    ///     }
+    ///     #[cfg(not(target_vendor = "apple"))]
+    ///     thread::current().name().unwrap_or_default().to_owned()
+    /// }
+    ///
+    /// // Records where it is woken
+    /// struct WokenOn(Mutex<Option<String>>);
+    /// impl Wake for WokenOn {
+    ///     fn wake(self: Arc<Self>) {
+    ///         *self.0.lock().unwrap() = Some(running_on());
+    ///     }
+    /// }
+    ///
+    /// let woken_on = Arc::new(WokenOn(Mutex::new(None)));
+    /// let waker = Waker::from(woken_on.clone());
+    /// let mut timer = pin!(Timer::after_with_priority(Duration::from_millis(10), Priority::Background));
+    /// assert!(timer.as_mut().poll(&mut Context::from_waker(&waker)).is_pending());
+    /// let label = loop {
+    ///     if let Some(label) = woken_on.0.lock().unwrap().take() {
+    ///         break label;
+    ///     }
+    ///     thread::sleep(Duration::from_millis(1));
+    /// };
+    /// assert_eq!(timer.poll(&mut Context::from_waker(&waker)), Poll::Ready(()));
+    ///
+    /// # #[cfg(any(target_vendor = "apple", target_os = "android"))]
+    /// assert_eq!(label, "dev.water.native-executor.background");
+    /// # #[cfg(not(any(target_vendor = "apple", target_os = "android")))]
+    /// assert_eq!(label, "dev.water.native-executor.timer");
+    /// ```
+    #[must_use]
+    pub const fn after_with_priority(duration: Duration, priority: crate::Priority) -> Self {
+        let mut timer = Self::after(duration);
+        timer.priority = Some(priority);
+        timer
+    }
+
+    /// Creates a `Timer` that completes after `duration`, allowing it to
+    /// complete up to `tolerance` late so the system can batch wake-ups.
+    ///
+    /// On Apple platforms, the tolerance is the leeway of a dispatch timer
+    /// source; [`Timer::after`] lets GCD pick one instead. A zero tolerance
+    /// asks for the most precise wake-up the platform allows, and a tolerance
+    /// longer than `duration` is clamped to it. Other backends ignore the
+    /// tolerance. The timer is not [coalesced](crate::Config::coalesce_timers),
+    /// as its tolerance replaces the configured granularity, and never
+    /// completes early.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures_lite::future::block_on;
+    /// use native_executor::timer::Timer;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// // A periodic refresh that does not need to be punctual
+    /// let start = Instant::now();
+    /// block_on(Timer::after_with_tolerance(Duration::from_millis(20), Duration::from_millis(10)));
+    /// assert!(start.elapsed() >= Duration::from_millis(20));
+    ///
+    /// // As precise as possible
+    /// let start = Instant::now();
+    /// block_on(Timer::after_with_tolerance(Duration::from_millis(20), Duration::ZERO));
+    /// assert!(start.elapsed() >= Duration::from_millis(20));
+    ///
+    /// // Clamped to the duration
+    /// let start = Instant::now();
+    /// block_on(Timer::after_with_tolerance(Duration::from_millis(20), Duration::MAX));
+    /// let elapsed = start.elapsed();
+    /// assert!(elapsed >= Duration::from_millis(20) && elapsed < Duration::from_secs(5));
+    /// ```
+    #[must_use]
+    pub const fn after_with_tolerance(duration: Duration, tolerance: Duration) -> Self {
+        let mut timer = Self::after(duration);
+        timer.tolerance = Some(tolerance);
+        timer
+    }
+
+    /// Creates a `Timer` that completes after `duration`, rounded up to a
+    /// coarse granularity, sharing its platform timer with the other timers
+    /// due at the same rounded deadline.
+    ///
+    /// The granularity is that of
+    /// [`Config::coalesce_timers`](crate::Config::coalesce_timers) if it is
+    /// set, and [`COARSE_GRANULARITY`], 4 ms, otherwise: the timer fires up
+    /// to that late, never early. Tens of thousands of short timeouts per
+    /// second then cost one platform timer per granularity and priority
+    /// rather than one each. Other timers stay precise unless the
+    /// configuration coalesces them too.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures_lite::future::{block_on, poll_once};
+    /// use native_executor::timer::{COARSE_GRANULARITY, Timer};
+    /// use std::time::{Duration, Instant};
+    ///
+    /// // A thousand request timeouts, started at once
+    /// # #[cfg(feature = "stats")]
+    /// let before = native_executor::diagnostics::platform_timers();
+    /// let start = Instant::now();
+    /// let mut timeouts: Vec<_> = (0..1_000).map(|_| Timer::coarse(Duration::from_millis(10))).collect();
+    /// for timeout in &mut timeouts {
+    ///     assert!(block_on(poll_once(timeout)).is_none());
+    /// }
+    /// timeouts.into_iter().for_each(block_on);
+    /// assert!(start.elapsed() >= Duration::from_millis(10));
+    ///
+    /// // Fired by a few platform timers, one per granularity they span
+    /// # #[cfg(feature = "stats")]
+    /// assert!(native_executor::diagnostics::platform_timers() - before < 100);
+    /// assert_eq!(COARSE_GRANULARITY, Duration::from_millis(4));
+    /// ```
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub const fn coarse(duration: Duration) -> Self {
+        let mut timer = Self::after(duration);
+        timer.coarse = true;
+        timer
+    }
+
+    /// Creates a `Timer` that completes after `duration`, whose callback runs
+    /// on the main thread.
+    ///
+    /// Other timers complete on a queue of the thread pool, or on the timer
+    /// thread of the polyfill backend, from where a task awaiting them on the
+    /// main thread is queued on the main queue again. This one is completed
+    /// by a job of the main queue itself, and the [`spawn_main`] and
+    /// [`spawn_local`] tasks it wakes are polled right after that job, so a
+    /// main-thread task awaiting it never leaves the main thread. It is not
+    /// [coalesced](crate::Config::coalesce_timers).
+    ///
+    /// [`spawn_main`]: crate::spawn_main
+    /// [`spawn_local`]: crate::spawn_local
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(not(any(target_vendor = "apple", target_os = "android")))] {
+    /// use futures_lite::future::block_on;
+    /// use native_executor::{ExecContext, current_context, polyfill, spawn_main, timer::Timer};
+    /// use std::{
+    ///     future::Future,
+    ///     pin::pin,
+    ///     sync::{Arc, Mutex},
+    ///     task::{Context, Wake, Waker},
+    ///     thread,
+    ///     time::{Duration, Instant},
+    /// };
+    ///
+    /// thread::spawn(polyfill::start_main_executor);
+    ///
+    /// // Records where it is woken
+    /// struct WokenOn(Mutex<Option<ExecContext>>);
+    /// impl Wake for WokenOn {
+    ///     fn wake(self: Arc<Self>) {
+    ///         *self.0.lock().unwrap() = Some(current_context());
+    ///     }
+    /// }
+    ///
+    /// let woken_on = Arc::new(WokenOn(Mutex::new(None)));
+    /// let waker = Waker::from(woken_on.clone());
+    /// let mut timer = pin!(Timer::after_on_main(Duration::from_millis(10)));
+    /// assert!(timer.as_mut().poll(&mut Context::from_waker(&waker)).is_pending());
+    /// while woken_on.0.lock().unwrap().is_none() {
+    ///     thread::sleep(Duration::from_millis(1));
+    /// }
+    /// assert_eq!(*woken_on.0.lock().unwrap(), Some(ExecContext::Main));
+    ///
+    /// // A UI update after a delay, without leaving the main thread
+    /// let start = Instant::now();
+    /// let context = block_on(spawn_main(async {
+    ///     Timer::after_on_main(Duration::from_millis(20)).await;
+    ///     current_context()
+    /// }));
+    /// assert_eq!(context, ExecContext::Main);
+    /// assert!(start.elapsed() >= Duration::from_millis(20));
+    /// # }
+    /// ```
+    #[must_use]
+    pub const fn after_on_main(duration: Duration) -> Self {
+        let mut timer = Self::after(duration);
+        timer.on_main = true;
+        timer
+    }
+
     /// Creates a new `Timer` that will complete after the specified number of seconds.
     ///
     /// This is a convenience method that wraps `Timer::after` with `Duration::from_secs`.
@@ -116,64 +707,2714 @@ impl Timer {
     /// }
     /// ```
     #[must_use]
-    pub fn after_secs(secs: u64) -> Self {
+    pub const fn after_secs(secs: u64) -> Self {
         Self::after(Duration::from_secs(secs))
     }
-}
 
-impl Future for Timer {
-    type Output = ();
+    /// Creates a `Timer` that completes after `millis` milliseconds.
+    ///
+    /// This is `Timer::after(Duration::from_millis(millis))`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures_lite::future::block_on;
+    /// use native_executor::timer::Timer;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let start = Instant::now();
+    /// block_on(Timer::after_millis(20));
+    /// assert!(start.elapsed() >= Duration::from_millis(20));
+    /// ```
+    #[must_use]
+    pub const fn after_millis(millis: u64) -> Self {
+        Self::after(Duration::from_millis(millis))
+    }
 
-    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        // If the timer has already finished, return Ready
-        if self.finished.load(Ordering::Acquire) {
-            return Poll::Ready(());
+    /// Creates a `Timer` that completes after `micros` microseconds.
+    ///
+    /// This is `Timer::after(Duration::from_micros(micros))`. The platform
+    /// timers are not that precise: the timer completes once the delay
+    /// has elapsed, often some tens of microseconds later.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures_lite::future::block_on;
+    /// use native_executor::timer::Timer;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let start = Instant::now();
+    /// block_on(Timer::after_micros(1_500));
+    /// assert!(start.elapsed() >= Duration::from_micros(1_500));
+    /// ```
+    #[must_use]
+    pub const fn after_micros(micros: u64) -> Self {
+        Self::after(Duration::from_micros(micros))
+    }
+
+    /// Creates a [`Measured`] timer that completes after `duration`, and
+    /// resolves to the time that actually elapsed.
+    ///
+    /// This is `Timer::after(duration).measured()`; see
+    /// [`measured`](Self::measured).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures_lite::future::block_on;
+    /// use native_executor::timer::Timer;
+    /// use std::time::Duration;
+    ///
+    /// // Scheduling latency, as `requested=20ms actual=21.3ms`
+    /// let requested = Duration::from_millis(20);
+    /// let actual = block_on(Timer::after_measured(requested));
+    /// println!("requested={requested:?} actual={actual:?}");
+    /// assert!(actual >= requested);
+    /// ```
+    #[cfg(feature = "std")]
+    pub const fn after_measured(duration: Duration) -> Measured {
+        Self::after(duration).measured()
+    }
+
+    /// Creates a `Timer` that completes at `deadline`.
+    ///
+    /// The delay is computed when the timer is first polled. A deadline that
+    /// has passed by then, or is right then, completes that poll without
+    /// scheduling a platform timer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures_lite::future::block_on;
+    /// use native_executor::timer::Timer;
+    /// use std::{
+    ///     future::Future,
+    ///     pin::pin,
+    ///     task::{Context, Poll, Waker},
+    ///     time::{Duration, Instant},
+    /// };
+    ///
+    /// // Retry at a fixed point in time, however long the attempt took
+    /// let retry_at = Instant::now() + Duration::from_millis(30);
+    /// let timer = Timer::at(retry_at);
+    /// assert_eq!(timer.deadline(), Some(retry_at));
+    /// block_on(timer);
+    /// assert!(Instant::now() >= retry_at);
+    ///
+    /// // A deadline that is now, or has passed, is ready on the first poll
+    /// let mut cx = Context::from_waker(Waker::noop());
+    /// assert_eq!(pin!(Timer::at(Instant::now())).poll(&mut cx), Poll::Ready(()));
+    /// assert_eq!(pin!(Timer::at(retry_at)).poll(&mut cx), Poll::Ready(()));
+    /// ```
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub const fn at(deadline: Instant) -> Self {
+        // The duration is replaced by the time left on the first poll
+        let mut timer = Self::with_state(Duration::MAX, Completion::Unarmed);
+        timer.deadline = Some(deadline);
+        timer
+    }
+
+    /// Rearms the timer to complete `duration` after its next poll, as a
+    /// new [`Timer::after`] would, whether it was pending or had completed.
+    ///
+    /// The callback scheduled for the previous duration is invalidated: if
+    /// it fires later, it does not complete the rearmed timer. A timer kept
+    /// in a struct can so be reset on every sign of activity, for an idle
+    /// timeout, without being replaced. Poll it again after the reset to arm
+    /// the new duration; a task waiting on it is not woken by the reset. The
+    /// timer keeps the priority, tolerance and granularity it was created
+    /// with, and whether it completes on the main thread.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures_lite::future::{block_on, poll_once};
+    /// use native_executor::timer::Timer;
+    /// use std::{
+    ///     thread,
+    ///     time::{Duration, Instant},
+    /// };
+    ///
+    /// // An idle timeout, reset by activity before it fires
+    /// let mut idle = Timer::after(Duration::from_millis(20));
+    /// assert!(block_on(poll_once(&mut idle)).is_none());
+    /// thread::sleep(Duration::from_millis(10));
+    /// let last_activity = Instant::now();
+    /// idle.reset(Duration::from_millis(50));
+    ///
+    /// // The first callback fires meanwhile, but no longer completes it
+    /// assert!(block_on(poll_once(&mut idle)).is_none());
+    /// thread::sleep(Duration::from_millis(20));
+    /// assert!(block_on(poll_once(&mut idle)).is_none());
+    /// block_on(&mut idle);
+    /// assert!(last_activity.elapsed() >= Duration::from_millis(50));
+    ///
+    /// // A completed timer is pending again
+    /// idle.reset(Duration::from_millis(10));
+    /// assert!(block_on(poll_once(&mut idle)).is_none());
+    /// block_on(idle);
+    /// ```
+    pub fn reset(&mut self, duration: Duration) {
+        #[cfg(feature = "std")]
+        self.disarm();
+        self.duration = Some(duration);
+        self.finished = Completion::after(duration);
+        #[cfg(feature = "std")]
+        {
+            self.deadline = None;
+            self.paused = None;
         }
+    }
 
-        // If this is the first poll, set up the timer
-        if let Some(duration) = self.duration.take() {
-            let waker = cx.waker().clone();
-            let finished = self.finished.clone();
+    /// Returns `true` if the timer has completed, without polling it.
+    ///
+    /// This reads the flag its callback sets, so it turns `true` once the
+    /// callback has run, even if the timer was not polled since. A timer of
+    /// [`at`](Self::at) whose deadline has passed but that was never polled
+    /// is not elapsed yet, as it has no callback until then.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures_lite::future::{block_on, poll_once};
+    /// use native_executor::timer::Timer;
+    /// use std::{thread, time::Duration};
+    ///
+    /// let mut timer = Timer::after(Duration::from_millis(10));
+    /// assert!(!timer.is_elapsed());
+    /// assert!(block_on(poll_once(&mut timer)).is_none());
+    /// while !timer.is_elapsed() {
+    ///     thread::sleep(Duration::from_millis(1));
+    /// }
+    /// assert_eq!(block_on(poll_once(&mut timer)), Some(()));
+    ///
+    /// // With the `futures` feature, a `select!` arm that has completed is
+    /// // not polled again
+    /// # #[cfg(feature = "futures")]
+    /// assert!(futures_core::future::FusedFuture::is_terminated(&timer));
+    /// # #[cfg(feature = "futures")]
+    /// assert!(!futures_core::future::FusedFuture::is_terminated(&Timer::never()));
+    /// ```
+    #[must_use]
+    pub fn is_elapsed(&self) -> bool {
+        self.finished.is_finished()
+    }
 
-            // Schedule the callback to run after the specified duration
-            NativeExecutor::exec_after(
-                duration,
-                move || {
-                    // Mark the timer as finished
-                    finished.store(true, Ordering::Release);
-                    // Wake the task that's waiting on this timer
-                    waker.wake();
-                },
-                crate::Priority::Default,
-            );
+    /// Returns when the timer fires.
+    ///
+    /// This is the deadline of a timer created with [`at`](Self::at). Other
+    /// timers count from their first poll, so their deadline is `None`
+    /// until then, and for a timer that never fires.
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub const fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Wraps the timer in a [`Measured`] future, which resolves to the time
+    /// that elapsed from its first poll until it completed.
+    ///
+    /// The clock starts at the first poll rather than at creation, since
+    /// that is when the timer is scheduled: a timer created ahead of time
+    /// does not count the time before it is awaited as lateness. It stops
+    /// when the awaiting task is polled after the timer completed, so the
+    /// measure includes the time the callback took to fire and the time the
+    /// task took to be polled again. Wrap the timer before polling it, as
+    /// the time of earlier polls is not known.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures_lite::future::block_on;
+    /// use native_executor::timer::Timer;
+    /// use std::{
+    ///     thread,
+    ///     time::{Duration, Instant},
+    /// };
+    ///
+    /// let created = Instant::now();
+    /// let timer = Timer::coarse(Duration::from_millis(20)).measured();
+    /// // Not counted: the timer is not scheduled yet
+    /// thread::sleep(Duration::from_millis(30));
+    /// let actual = block_on(timer);
+    /// assert!(actual >= Duration::from_millis(20));
+    /// assert!(actual <= created.elapsed() - Duration::from_millis(30));
+    /// ```
+    #[cfg(feature = "std")]
+    pub const fn measured(self) -> Measured {
+        Measured {
+            timer: self,
+            started: None,
         }
+    }
 
-        // The timer hasn't completed yet
-        Poll::Pending
+    /// Pauses the timer, keeping the time it has left for
+    /// [`resume`](Self::resume).
+    ///
+    /// The callback in flight is invalidated, as by [`reset`](Self::reset),
+    /// and the paused timer stays pending however long it is polled. Pausing
+    /// a timer that has elapsed, whose deadline has passed, or that is
+    /// paused already does nothing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures_lite::future::{block_on, poll_once};
+    /// use native_executor::timer::Timer;
+    /// use std::{
+    ///     thread,
+    ///     time::{Duration, Instant},
+    /// };
+    ///
+    /// // A countdown, paused while the app is in the background
+    /// let mut countdown = Timer::after(Duration::from_millis(60));
+    /// assert!(block_on(poll_once(&mut countdown)).is_none());
+    /// thread::sleep(Duration::from_millis(20));
+    /// countdown.pause();
+    /// let left = countdown.remaining();
+    /// assert!(countdown.is_paused() && left <= Duration::from_millis(40));
+    ///
+    /// // The original deadline passes while paused
+    /// thread::sleep(Duration::from_millis(80));
+    /// assert!(block_on(poll_once(&mut countdown)).is_none());
+    /// assert_eq!(countdown.remaining(), left);
+    ///
+    /// // Resumed with the time it had left, once however often it is resumed
+    /// let resumed = Instant::now();
+    /// countdown.resume();
+    /// countdown.resume();
+    /// block_on(&mut countdown);
+    /// assert!(resumed.elapsed() >= left);
+    ///
+    /// // An elapsed timer is not paused
+    /// countdown.pause();
+    /// assert!(countdown.is_elapsed() && !countdown.is_paused());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn pause(&mut self) {
+        if self.paused.is_some() || self.is_elapsed() {
+            return;
+        }
+        let remaining = self.remaining();
+        if remaining.is_zero() {
+            return;
+        }
+        self.disarm();
+        self.duration = None;
+        self.deadline = None;
+        self.finished = Completion::after(remaining);
+        self.paused = Some(remaining);
     }
-}
 
-/// Suspends the current async task for the specified number of seconds.
-///
-/// This convenience function provides a simple interface for second-based delays,
-/// using the same high-precision platform-native timing as `Timer::after`.
-///
-/// # Arguments
-/// * `secs` - The number of seconds to sleep
-///
-/// # Platform Integration
-/// Uses the same platform-native scheduling as `Timer` for consistent precision.
-///
-/// # Examples
-/// ```rust
-/// use native_executor::timer::sleep;
-///
-/// async fn delayed_operation() {
-///     println!("Starting operation");
-///     sleep(2).await;  // High-precision 2-second delay
-///     println!("Operation resumed after 2 seconds");
-/// }
-/// ```
-pub async fn sleep(secs: u64) {
-    Timer::after(Duration::from_secs(secs)).await;
+    /// Resumes a [paused](Self::pause) timer with the time it had left,
+    /// counting from its next poll; poll it again to arm it.
+    ///
+    /// Resuming a timer that is not paused does nothing, so it never
+    /// schedules a second callback.
+    #[cfg(feature = "std")]
+    pub const fn resume(&mut self) {
+        if let Some(remaining) = self.paused.take() {
+            self.duration = Some(remaining);
+        }
+    }
+
+    /// Returns `true` if the timer is [paused](Self::pause).
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub const fn is_paused(&self) -> bool {
+        self.paused.is_some()
+    }
+
+    /// Returns the time the timer has left.
+    ///
+    /// This is the time left at the pause for a paused timer, and the full
+    /// duration for one of [`after`](Self::after) that was not polled yet.
+    /// An elapsed timer has none left, and one that never fires
+    /// [`Duration::MAX`].
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn remaining(&self) -> Duration {
+        if let Some(remaining) = self.paused {
+            return remaining;
+        }
+        if self.is_elapsed() {
+            return Duration::ZERO;
+        }
+        match (self.deadline, self.duration) {
+            (Some(deadline), _) => deadline.saturating_duration_since(Instant::now()),
+            (None, Some(duration)) => duration,
+            (None, None) => Duration::MAX,
+        }
+    }
+
+    /// Creates a `Timer` that never completes.
+    ///
+    /// This is `Timer::after(Duration::MAX)`, which schedules nothing. It
+    /// holds no state either: it does not allocate, keeps no waker from its
+    /// polls, and is free to drop. Any other duration, however long, fires
+    /// once it has fully elapsed, never early and without panicking.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(not(target_vendor = "apple"))] {
+    /// use futures_lite::future::{block_on, or};
+    /// use native_executor::{execute_after, timer::Timer};
+    /// use std::{
+    ///     sync::{Arc, atomic::{AtomicUsize, Ordering}},
+    ///     thread,
+    ///     time::Duration,
+    /// };
+    ///
+    /// // A timeout that is disabled by default
+    /// let timeout = Timer::never();
+    /// let winner = block_on(or(async { timeout.await; "timeout" }, async {
+    ///     Timer::after(Duration::from_millis(10)).await;
+    ///     "work"
+    /// }));
+    /// assert_eq!(winner, "work");
+    ///
+    /// // Absurd delays neither panic nor fire early
+    /// let fired = Arc::new(AtomicUsize::new(0));
+    /// for delay in [
+    ///     Duration::MAX,
+    ///     Duration::from_secs(u64::MAX / 2),
+    ///     Duration::from_secs(10 * 365 * 24 * 60 * 60),
+    ///     Duration::from_secs((1 << 32) + 1),
+    ///     Duration::new(u64::MAX, 999_999_999) - Duration::from_nanos(1),
+    /// ] {
+    ///     let fired = fired.clone();
+    ///     execute_after(delay, move || { fired.fetch_add(1, Ordering::SeqCst); });
+    ///     let timer = Timer::after(delay);
+    ///     assert_eq!(block_on(or(async { timer.await; 1 }, async {
+    ///         Timer::after(Duration::from_millis(10)).await;
+    ///         0
+    ///     })), 0);
+    /// }
+    /// thread::sleep(Duration::from_millis(50));
+    /// assert_eq!(fired.load(Ordering::SeqCst), 0);
+    /// # }
+    /// ```
+    ///
+    /// An arm that may be disabled needs no `Option`, and the disabled one
+    /// keeps nothing from its polls:
+    ///
+    /// ```
+    /// use native_executor::timer::Timer;
+    /// use std::{
+    ///     future::Future,
+    ///     pin::pin,
+    ///     sync::Arc,
+    ///     task::{Context, Poll, Wake, Waker},
+    ///     time::Duration,
+    /// };
+    ///
+    /// struct Task;
+    /// impl Wake for Task {
+    ///     fn wake(self: Arc<Self>) {}
+    /// }
+    ///
+    /// let timeout: Option<Duration> = None;
+    /// let deadline = timeout.map_or_else(Timer::never, Timer::after);
+    ///
+    /// let task = Arc::new(Task);
+    /// let waker = Waker::from(task.clone());
+    /// let mut deadline = pin!(deadline);
+    /// for _ in 0..3 {
+    ///     assert_eq!(deadline.as_mut().poll(&mut Context::from_waker(&waker)), Poll::Pending);
+    /// }
+    /// assert_eq!(Arc::strong_count(&task), 2);
+    /// ```
+    #[must_use]
+    pub const fn never() -> Self {
+        Self::after(Duration::MAX)
+    }
+
+    /// Creates an [`Interval`] that ticks every `period`, the first time one
+    /// `period` from now; see [`Interval::every`].
+    ///
+    /// Ticks are due on a fixed schedule, so they do not drift as a loop of
+    /// `Timer::after` does. The interval keeps a single platform timer armed,
+    /// for the tick being waited for, and takes its state from the timer
+    /// pool, so ticking does not allocate. Dropping the interval cancels that
+    /// timer, and nothing is scheduled afterwards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `period` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use futures_lite::future::block_on;
+    /// use native_executor::timer::Timer;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let start = Instant::now();
+    /// let mut interval = Timer::interval(Duration::from_millis(50));
+    /// let ticks: Vec<Instant> = block_on(async {
+    ///     let mut ticks = Vec::new();
+    ///     for _ in 0..3 {
+    ///         ticks.push(interval.tick().await);
+    ///     }
+    ///     ticks
+    /// });
+    /// drop(interval);
+    ///
+    /// // In order, at least a period apart
+    /// assert!(ticks[0] >= start + Duration::from_millis(50));
+    /// assert!(ticks.windows(2).all(|pair| pair[1] - pair[0] >= Duration::from_millis(50)));
+    /// assert!(Instant::now() >= start + Duration::from_millis(150));
+    /// ```
+    #[cfg(feature = "std")]
+    #[must_use]
+    #[track_caller]
+    pub fn interval(period: Duration) -> Interval {
+        Interval::every(period)
+    }
+}
+
+impl Future for Timer {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // If the timer has already finished, return Ready
+        if self.finished.is_finished() {
+            return Poll::Ready(());
+        }
+
+        // The state is only needed once the timer is awaited, so a timer
+        // that is dropped unpolled costs nothing
+        if matches!(self.finished, Completion::Unarmed) {
+            self.finished = Completion::new();
+            #[cfg(feature = "leak-check")]
+            self.live.get_or_insert_with(|| {
+                crate::diagnostics::Live::new(crate::diagnostics::Class::Timer)
+            });
+        }
+
+        // The callback wakes the waker of the latest poll, which is checked
+        // against a callback that finished in the meantime
+        self.finished.register(cx.waker());
+        if self.finished.is_finished() {
+            return Poll::Ready(());
+        }
+
+        // If this is the first poll, set up the timer
+        #[cfg_attr(not(feature = "std"), allow(unused_mut))]
+        let mut duration = self.duration.take();
+        #[cfg(feature = "std")]
+        if let Some(delay) = &mut duration {
+            let now = Instant::now();
+            match self.deadline {
+                Some(deadline) if deadline <= now => {
+                    // Passed already, so there is nothing to wait for
+                    self.finished.finish();
+                    return Poll::Ready(());
+                }
+                Some(deadline) => *delay = deadline - now,
+                None => self.deadline = now.checked_add(*delay),
+            }
+        }
+        // `Duration::MAX` never completes, so it needs no platform timer
+        if let Some(duration) = duration.filter(|d| *d != Duration::MAX) {
+            let finished = self.finished.clone();
+            let priority = self.priority.unwrap_or_else(callback_priority);
+
+            let callback = move || {
+                // Mark the timer as finished and wake the task waiting on it,
+                // unless it was dropped and its slot recycled in the meantime
+                finished.finish();
+            };
+
+            // Schedule the callback to run after the specified duration
+            if self.on_main {
+                crate::dispatch_main_after(duration, callback);
+                return Poll::Pending;
+            }
+            if let Some(tolerance) = self.tolerance {
+                crate::dispatch_after_with_leeway(
+                    duration,
+                    callback,
+                    priority,
+                    Some(tolerance.min(duration)),
+                );
+                return Poll::Pending;
+            }
+            #[cfg(feature = "std")]
+            if let Some(granularity) = crate::config::current()
+                .timer_coalescing_granularity()
+                .or_else(|| self.coarse.then_some(COARSE_GRANULARITY))
+                .filter(|_| duration <= NativeExecutor::MAX_DELAY)
+            {
+                self.coalesced = Some(crate::coalesce::schedule(
+                    duration,
+                    callback,
+                    priority,
+                    granularity,
+                ));
+                return Poll::Pending;
+            }
+            #[cfg(feature = "std")]
+            {
+                self.scheduled = crate::dispatch_after_cancellable(duration, callback, priority);
+            }
+            #[cfg(not(feature = "std"))]
+            crate::dispatch_after(duration, callback, priority);
+        }
+
+        // The timer hasn't completed yet
+        Poll::Pending
+    }
+}
+
+/// A [`Timer`] that resolves to the time that elapsed from its first poll
+/// until it completed.
+///
+/// Created by [`Timer::after_measured`] or [`Timer::measured`]; the timer
+/// behaves as it otherwise would.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Measured {
+    timer: Timer,
+    /// When the timer was first polled.
+    started: Option<Instant>,
+}
+
+#[cfg(feature = "std")]
+impl Measured {
+    /// Returns the timer, dropping the measure.
+    #[must_use]
+    pub fn into_inner(self) -> Timer {
+        self.timer
+    }
+}
+
+#[cfg(feature = "std")]
+impl Future for Measured {
+    type Output = Duration;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Duration> {
+        let started = *self.started.get_or_insert_with(Instant::now);
+        core::task::ready!(Pin::new(&mut self.timer).poll(cx));
+        Poll::Ready(started.elapsed())
+    }
+}
+
+#[cfg(feature = "futures")]
+impl futures_core::future::FusedFuture for Timer {
+    fn is_terminated(&self) -> bool {
+        self.is_elapsed()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Timer {
+    /// Detaches the timer from its callback: cancels its platform timer or
+    /// removes it from its group of coalesced timers, and returns its pooled
+    /// state, which invalidates the state for the callback.
+    fn disarm(&mut self) {
+        if let Some(entry) = self.coalesced.take() {
+            crate::coalesce::cancel(entry);
+        }
+        if let Some(key) = self.scheduled.take()
+            && !self.finished.is_finished()
+        {
+            crate::cancel_after(key);
+        }
+        if let Completion::Pooled { slot, .. } = self.finished {
+            crate::timer_pool::release(slot);
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.disarm();
+    }
+}
+
+/// Suspends the current async task for the specified duration.
+///
+/// This convenience function is `Timer::after(duration).await`, using the
+/// same high-precision platform-native timing. [`sleep_secs`] and
+/// [`sleep_ms`] take whole seconds and milliseconds, and [`sleep_until`] a
+/// point in time.
+///
+/// # Arguments
+/// * `duration` - How long to sleep
+///
+/// # Platform Integration
+/// Uses the same platform-native scheduling as `Timer` for consistent precision.
+/// A zero duration completes on the first poll, without scheduling anything.
+///
+/// # Examples
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::timer::{sleep, sleep_ms, sleep_until};
+/// use std::time::{Duration, Instant};
+///
+/// let start = Instant::now();
+/// block_on(async {
+///     sleep(Duration::from_millis(15)).await;
+///     sleep_ms(15).await;
+///     // Already passed, so it returns right away
+///     sleep_until(start).await;
+/// });
+/// assert!(start.elapsed() >= Duration::from_millis(30));
+/// ```
+pub async fn sleep(duration: Duration) {
+    Timer::after(duration).await;
+}
+
+/// Suspends the current async task for `secs` seconds; see [`sleep`].
+pub async fn sleep_secs(secs: u64) {
+    Timer::after_secs(secs).await;
+}
+
+/// Suspends the current async task for `millis` milliseconds; see [`sleep`].
+pub async fn sleep_ms(millis: u64) {
+    Timer::after(Duration::from_millis(millis)).await;
+}
+
+/// Suspends the current async task for `duration`, dispatching the wake-up
+/// at `priority`; see [`Timer::after_with_priority`].
+pub async fn sleep_with_priority(duration: Duration, priority: crate::Priority) {
+    Timer::after_with_priority(duration, priority).await;
+}
+
+/// A point in time shared by any number of tasks.
+///
+/// Unlike a [`Timer`], a deadline can be cloned cheaply: all clones share one
+/// platform timer, which is started the first time any of them is polled.
+/// Each clone can be awaited, and [`expired`](Self::expired) checks the
+/// deadline synchronously, for example between the steps of a long mailbox
+/// job (see [`Mailbox::transaction`](crate::Mailbox::transaction)).
+///
+/// Each clone is a separate waiter: it keeps the waker of its latest poll
+/// until the deadline passes or the clone is dropped, so any number of tasks
+/// can wait on one deadline. Share a deadline between tasks by cloning it;
+/// a [`Timer`] only ever wakes the task that polls it.
+///
+/// # Examples
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::{spawn, timer::Deadline};
+/// use std::time::{Duration, Instant};
+///
+/// let start = Instant::now();
+/// let deadline = Deadline::after(Duration::from_millis(20));
+/// assert!(!deadline.expired());
+///
+/// // Every awaiter wakes up once the deadline passes
+/// let first = spawn(deadline.clone());
+/// let second = spawn(deadline.clone());
+/// block_on(first);
+/// block_on(second);
+///
+/// assert!(deadline.expired());
+/// assert!(start.elapsed() >= Duration::from_millis(20));
+/// ```
+///
+/// A waiter dropped before the deadline takes its waker along, and the
+/// others still wake:
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::timer::Deadline;
+/// use std::{
+///     future::Future,
+///     pin::pin,
+///     sync::Arc,
+///     task::{Context, Wake, Waker},
+///     time::Duration,
+/// };
+///
+/// struct Noop;
+/// impl Wake for Noop {
+///     fn wake(self: Arc<Self>) {}
+/// }
+///
+/// let deadline = Deadline::after(Duration::from_millis(20));
+/// let abandoned = Arc::new(Noop);
+/// {
+///     let waker = Waker::from(abandoned.clone());
+///     let mut waiter = pin!(deadline.clone());
+///     // Polling again from the same task registers the waker only once
+///     for _ in 0..3 {
+///         assert!(waiter.as_mut().poll(&mut Context::from_waker(&waker)).is_pending());
+///     }
+/// }
+/// assert_eq!(Arc::strong_count(&abandoned), 1);
+///
+/// block_on(deadline);
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct Deadline {
+    shared: Arc<TimerShared>,
+    /// Identifies the waker of this clone among those of the others.
+    waiter: u64,
+}
+
+#[cfg(feature = "std")]
+impl Clone for Deadline {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            waiter: self.shared.next_waiter.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for Deadline {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug)]
+struct TimerShared {
+    /// `None` for a deadline too far away to be represented, which never
+    /// passes.
+    at: Option<Instant>,
+    /// Set by the platform timer, or by a check that found `at` has passed.
+    expired: AtomicBool,
+    /// The pending clones and the platform timer that wakes them. `None`
+    /// until the platform timer is started by a poll.
+    wakers: Mutex<Option<Waiters>>,
+    next_waiter: AtomicU64,
+}
+
+/// The clones of a [`Deadline`] waiting for its platform timer.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+struct Waiters {
+    /// The wakers of the pending clones, by waiter.
+    wakers: Vec<(u64, Waker)>,
+    /// Cancels the platform timer once no clone waits for it.
+    timer: Option<crate::TimerKey>,
+}
+
+#[cfg(feature = "std")]
+impl Deadline {
+    /// Creates a deadline `duration` from now.
+    ///
+    /// A deadline too far away for an [`Instant`], such as one
+    /// [`Duration::MAX`] from now, never passes, as [`Timer::never`] does.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use futures_lite::future::block_on;
+    /// use native_executor::timer::{Deadline, TimerExt, with_deadline};
+    /// use std::time::Duration;
+    ///
+    /// let never = Deadline::after(Duration::MAX);
+    /// assert_eq!(never.instant(), None);
+    /// assert_eq!(never.remaining(), Duration::MAX);
+    /// assert!(!never.expired());
+    ///
+    /// // Absurd timeouts neither panic nor expire
+    /// assert_eq!(block_on(async { 7 }.timeout(Duration::MAX)), Ok(7));
+    /// assert_eq!(block_on(with_deadline(never, async { 7 })), 7);
+    /// ```
+    #[must_use]
+    pub fn after(duration: Duration) -> Self {
+        Self::with_instant(Instant::now().checked_add(duration))
+    }
+
+    /// Creates a deadline at `instant`.
+    #[must_use]
+    pub fn at(instant: Instant) -> Self {
+        Self::with_instant(Some(instant))
+    }
+
+    fn with_instant(at: Option<Instant>) -> Self {
+        Self {
+            shared: Arc::new(TimerShared {
+                at,
+                expired: AtomicBool::new(false),
+                wakers: Mutex::new(None),
+                next_waiter: AtomicU64::new(1),
+            }),
+            waiter: 0,
+        }
+    }
+
+    /// Returns the instant of the deadline, or `None` for one that never
+    /// passes.
+    #[must_use]
+    pub fn instant(&self) -> Option<Instant> {
+        self.shared.at
+    }
+
+    /// Returns the time left until the deadline, which is zero once it has
+    /// passed, and [`Duration::MAX`] for one that never passes.
+    #[must_use]
+    pub fn remaining(&self) -> Duration {
+        self.shared.at.map_or(Duration::MAX, |at| {
+            at.saturating_duration_since(Instant::now())
+        })
+    }
+
+    /// Stops waiting for the deadline: removes the waker of this clone, and
+    /// cancels the platform timer if no other clone waits for it, so that a
+    /// later poll starts one again.
+    fn release(&self) {
+        let mut waiters = self
+            .shared
+            .wakers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let Some(armed) = waiters.as_mut() else {
+            return;
+        };
+        armed.wakers.retain(|(waiter, _)| *waiter != self.waiter);
+        if !armed.wakers.is_empty() || armed.timer.is_none() {
+            return;
+        }
+        let timer = armed.timer.take();
+        *waiters = None;
+        drop(waiters);
+        timer.into_iter().for_each(crate::cancel_after);
+    }
+
+    /// Returns whether the deadline has passed.
+    ///
+    /// This only reads the clock, so it is cheap enough to call between the
+    /// steps of a loop.
+    #[must_use]
+    pub fn expired(&self) -> bool {
+        if self.shared.expired.load(Ordering::Acquire) {
+            return true;
+        }
+        let expired = self.shared.at.is_some_and(|at| Instant::now() >= at);
+        if expired {
+            self.shared.expired.store(true, Ordering::Release);
+        }
+        expired
+    }
+}
+
+#[cfg(feature = "std")]
+impl Future for Deadline {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.expired() {
+            return Poll::Ready(());
+        }
+
+        let mut waiters = self
+            .shared
+            .wakers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        // The timer sets the flag before taking the wakers, so checking again
+        // under the lock cannot miss its wake-up.
+        if self.shared.expired.load(Ordering::Acquire) {
+            return Poll::Ready(());
+        }
+        if let Some(Waiters { wakers, .. }) = waiters.as_mut() {
+            match wakers.iter_mut().find(|(waiter, _)| *waiter == self.waiter) {
+                Some((_, waker)) => waker.clone_from(cx.waker()),
+                None => wakers.push((self.waiter, cx.waker().clone())),
+            }
+        } else {
+            // The clones may all be dropped before the timer fires, which
+            // then finds nothing to wake where it cannot be cancelled.
+            let shared = Arc::downgrade(&self.shared);
+            let timer = crate::dispatch_after_cancellable(
+                self.remaining(),
+                move || {
+                    let Some(shared) = shared.upgrade() else {
+                        return;
+                    };
+                    shared.expired.store(true, Ordering::Release);
+                    let waiters = shared
+                        .wakers
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .take();
+                    waiters
+                        .into_iter()
+                        .flat_map(|waiters| waiters.wakers)
+                        .for_each(|(_, waker)| waker.wake());
+                },
+                callback_priority(),
+            );
+            *waiters = Some(Waiters {
+                wakers: vec![(self.waiter, cx.waker().clone())],
+                timer,
+            });
+        }
+        drop(waiters);
+        Poll::Pending
+    }
+}
+
+/// The error of an operation that gave up because its [`Deadline`] passed.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeadlineExceeded;
+
+#[cfg(feature = "std")]
+impl core::fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("deadline exceeded")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DeadlineExceeded {}
+
+/// The error of a [`timeout`] whose deadline passed before its future
+/// completed; another name for [`DeadlineExceeded`].
+#[cfg(feature = "std")]
+pub type TimeoutError = DeadlineExceeded;
+
+/// Suspends the current task until `instant`; see [`sleep`].
+///
+/// Returns right away if `instant` has passed, as [`Timer::at`] does.
+#[cfg(feature = "std")]
+pub async fn sleep_until(instant: Instant) {
+    Timer::at(instant).await;
+}
+
+/// A future that runs an inner future until it completes or a [`Deadline`]
+/// passes, whichever comes first.
+///
+/// Created by [`Timeout::new`], [`timeout`] or [`timeout_at`]. Being a named
+/// type, it can be stored in a struct, for example next to the connection
+/// whose request it bounds, without boxing.
+///
+/// A future that completes in the same poll as the deadline passes still
+/// counts as completed. Otherwise, once the deadline passes, the inner future
+/// is dropped right away, which cancels it, before the timeout resolves to
+/// [`DeadlineExceeded`]. Dropping the timeout drops the inner future first,
+/// then the deadline.
+///
+/// Once the inner future completes, the timeout stops waiting for its
+/// deadline, and the platform timer behind the deadline is cancelled unless
+/// other clones of it still wait. On the polyfill backend, it leaves the
+/// queue of the timer thread; the backends that cannot cancel their timers
+/// let it fire without keeping the deadline alive, so a 60 s timeout that
+/// completes early holds nothing for the rest of the minute.
+///
+/// # Examples
+///
+/// An idle timeout, re-armed whenever the operation makes progress:
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::timer::{Timeout, Timer};
+/// use std::{
+///     future::poll_fn,
+///     pin::pin,
+///     sync::atomic::{AtomicU32, Ordering},
+///     time::{Duration, Instant},
+/// };
+///
+/// let progress = AtomicU32::new(0);
+/// let transfer = async {
+///     for _ in 0..3 {
+///         Timer::after(Duration::from_millis(20)).await;
+///         progress.fetch_add(1, Ordering::SeqCst);
+///     }
+///     "complete"
+/// };
+///
+/// // Each chunk arrives within 30 ms, the whole transfer takes longer
+/// let start = Instant::now();
+/// let mut request = pin!(Timeout::new(Duration::from_millis(30), transfer));
+/// let mut seen = 0;
+/// let result = block_on(poll_fn(|cx| {
+///     let now = progress.load(Ordering::SeqCst);
+///     if now > seen {
+///         seen = now;
+///         request.as_mut().reset(Duration::from_millis(30));
+///     }
+///     request.as_mut().poll(cx)
+/// }));
+/// assert_eq!(result, Ok("complete"));
+/// assert!(start.elapsed() >= Duration::from_millis(60));
+/// ```
+///
+/// Shortening the deadline of a pending timeout takes effect right away, and
+/// the inner future is dropped exactly once, as soon as the deadline passes:
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::timer::{DeadlineExceeded, Timeout};
+/// use std::{
+///     future::{Future, pending, poll_fn},
+///     pin::pin,
+///     sync::atomic::{AtomicU32, Ordering},
+///     task::Poll,
+///     time::{Duration, Instant},
+/// };
+///
+/// static DROPPED: AtomicU32 = AtomicU32::new(0);
+/// struct Request;
+/// impl Drop for Request {
+///     fn drop(&mut self) {
+///         DROPPED.fetch_add(1, Ordering::SeqCst);
+///     }
+/// }
+/// fn request() -> impl Future<Output = ()> {
+///     let request = Request;
+///     async move {
+///         let _request = request;
+///         pending::<()>().await;
+///     }
+/// }
+///
+/// let start = Instant::now();
+/// {
+///     let mut timeout = pin!(Timeout::new(Duration::from_secs(60), request()));
+///     let mut reset = false;
+///     let result = block_on(poll_fn(|cx| {
+///         let poll = timeout.as_mut().poll(cx);
+///         if !reset {
+///             assert!(poll.is_pending());
+///             timeout.as_mut().reset(Duration::from_millis(10));
+///             reset = true;
+///         }
+///         poll
+///     }));
+///     assert_eq!(result, Err(DeadlineExceeded));
+///     assert!(start.elapsed() < Duration::from_secs(1));
+///     assert_eq!(DROPPED.load(Ordering::SeqCst), 1);
+///     assert_eq!(block_on(timeout.as_mut()), Err(DeadlineExceeded));
+/// }
+/// assert_eq!(DROPPED.load(Ordering::SeqCst), 1);
+///
+/// // `into_inner` hands the future back without dropping it
+/// let inner = Timeout::new(Duration::from_secs(60), request()).into_inner().unwrap();
+/// assert_eq!(DROPPED.load(Ordering::SeqCst), 1);
+/// drop(inner);
+/// assert_eq!(DROPPED.load(Ordering::SeqCst), 2);
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Timeout<F> {
+    /// Structurally pinned; declared first so that it is dropped first.
+    /// `None` once dropped on expiry.
+    future: Option<F>,
+    /// Not pinned: replaced by `reset`.
+    deadline: Deadline,
+    /// The waker of the last poll, woken by `reset` so that the next poll
+    /// arms the new deadline. Not pinned.
+    waker: Option<Waker>,
+}
+
+#[cfg(feature = "std")]
+impl<F> Timeout<F> {
+    /// Bounds `future` by a deadline `duration` from now.
+    pub fn new(duration: Duration, future: F) -> Self {
+        Self::at(Deadline::after(duration), future)
+    }
+
+    /// Bounds `future` by `deadline`.
+    pub const fn at(deadline: Deadline, future: F) -> Self {
+        Self {
+            future: Some(future),
+            deadline,
+            waker: None,
+        }
+    }
+
+    /// Returns the current deadline.
+    #[must_use]
+    pub const fn deadline(&self) -> &Deadline {
+        &self.deadline
+    }
+
+    /// Moves the deadline to `duration` from now, keeping the progress of the
+    /// inner future.
+    ///
+    /// The new deadline applies even if the timeout is pending: the task
+    /// waiting on it is woken to arm it. A timeout whose deadline has passed
+    /// has dropped its inner future, and stays expired.
+    pub fn reset(self: Pin<&mut Self>, duration: Duration) {
+        // SAFETY: only the unpinned fields are touched; `future` is not moved.
+        let this = unsafe { self.get_unchecked_mut() };
+        this.deadline = Deadline::after(duration);
+        if let Some(waker) = this.waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Returns the inner future, giving up on the deadline, or `None` if it
+    /// was dropped when the deadline passed.
+    pub fn into_inner(self) -> Option<F> {
+        self.future
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F: Future> Future for Timeout<F> {
+    type Output = Result<F::Output, DeadlineExceeded>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: `future` is structurally pinned and never moved out while
+        // pinned (`into_inner` takes `self` by value); the other fields are
+        // not pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        let mut future = unsafe { Pin::new_unchecked(&mut this.future) };
+        let Some(inner) = future.as_mut().as_pin_mut() else {
+            return Poll::Ready(Err(DeadlineExceeded));
+        };
+        if let Poll::Ready(output) = inner.poll(cx) {
+            // Cancels the platform timer, unless other clones of the
+            // deadline wait for it
+            this.deadline.release();
+            return Poll::Ready(Ok(output));
+        }
+        if Pin::new(&mut this.deadline).poll(cx).is_ready() {
+            // Dropped in place, so it is never moved while pinned.
+            future.set(None);
+            return Poll::Ready(Err(DeadlineExceeded));
+        }
+        match &mut this.waker {
+            Some(waker) => waker.clone_from(cx.waker()),
+            None => this.waker = Some(cx.waker().clone()),
+        }
+        Poll::Pending
+    }
+}
+
+/// Bounds `future` by a deadline `duration` from now.
+///
+/// This works for any future, `Send` or not, since nothing is spawned. On
+/// expiry the returned [`Timeout`] drops `future`, which cancels it, and
+/// resolves to [`DeadlineExceeded`].
+///
+/// # Examples
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::timer::{DeadlineExceeded, Timer, TimeoutError, timeout};
+/// use std::{rc::Rc, time::Duration};
+///
+/// // A future that is not `Send`
+/// let local = Rc::new("cached");
+/// let lookup = timeout(Duration::from_secs(5), async { *local });
+/// assert_eq!(block_on(lookup), Ok("cached"));
+///
+/// let slow = timeout(Duration::from_millis(10), Timer::after(Duration::from_secs(60)));
+/// assert_eq!(block_on(slow), Err(DeadlineExceeded));
+///
+/// // `TimeoutError` is another name for the same error
+/// let slow = timeout(Duration::from_millis(10), Timer::never());
+/// let result: Result<(), TimeoutError> = block_on(slow);
+/// assert_eq!(result.unwrap_err().to_string(), "deadline exceeded");
+/// ```
+#[cfg(feature = "std")]
+pub fn timeout<F: Future>(duration: Duration, future: F) -> Timeout<F> {
+    Timeout::new(duration, future)
+}
+
+/// Bounds `future` by `deadline`.
+///
+/// On expiry the returned [`Timeout`] drops `future`, which cancels it, and
+/// resolves to [`DeadlineExceeded`].
+#[cfg(feature = "std")]
+pub const fn timeout_at<F: Future>(deadline: Deadline, future: F) -> Timeout<F> {
+    Timeout::at(deadline, future)
+}
+
+/// A future that resolves to the output of whichever of two futures
+/// completes first, dropping the other along with the race.
+///
+/// Created by [`race`]. The first future is polled first, so it wins when
+/// both are ready.
+///
+/// # Examples
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::timer::{Race, Timer, race};
+/// use std::time::Duration;
+///
+/// // A race can be stored under its own name
+/// struct Fetch {
+///     response: Race<Timer, Timer>,
+/// }
+///
+/// let fetch = Fetch {
+///     response: race(Timer::after(Duration::from_millis(5)), Timer::never()),
+/// };
+/// block_on(fetch.response);
+///
+/// let winner = block_on(race(async { "ready" }, async { "also ready" }));
+/// assert_eq!(winner, "ready");
+/// ```
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Race<A, B> {
+    first: A,
+    second: B,
+}
+
+/// Races `first` against `second`, see [`Race`].
+pub const fn race<A, B>(first: A, second: B) -> Race<A, B>
+where
+    A: Future,
+    B: Future<Output = A::Output>,
+{
+    Race { first, second }
+}
+
+impl<A, B> Future for Race<A, B>
+where
+    A: Future,
+    B: Future<Output = A::Output>,
+{
+    type Output = A::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: both fields are structurally pinned and never moved out.
+        let (first, second) = unsafe {
+            let this = self.get_unchecked_mut();
+            (
+                Pin::new_unchecked(&mut this.first),
+                Pin::new_unchecked(&mut this.second),
+            )
+        };
+        if let Poll::Ready(output) = first.poll(cx) {
+            return Poll::Ready(output);
+        }
+        second.poll(cx)
+    }
+}
+
+/// Either of two outcomes, as returned by [`first_of`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Either<L, R> {
+    /// The first outcome, the output of the future for [`first_of`].
+    Left(L),
+    /// The second outcome, [`Elapsed`] for [`first_of`].
+    Right(R),
+}
+
+impl<L, R> Either<L, R> {
+    /// Returns the first outcome, if it is the one.
+    pub fn left(self) -> Option<L> {
+        match self {
+            Self::Left(left) => Some(left),
+            Self::Right(_) => None,
+        }
+    }
+
+    /// Returns the second outcome, if it is the one.
+    pub fn right(self) -> Option<R> {
+        match self {
+            Self::Left(_) => None,
+            Self::Right(right) => Some(right),
+        }
+    }
+}
+
+/// The timer of [`first_of`] fired before its future completed.
+///
+/// Unlike [`DeadlineExceeded`], this is not an error: waiting up to a
+/// duration and then moving on is the expected outcome.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Elapsed;
+
+/// A future that resolves to the output of an inner future, or to
+/// [`Elapsed`] once a timer fires, whichever comes first.
+///
+/// Created by [`first_of`]. The inner future is polled first, so it wins
+/// when both are ready.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct FirstOf<F> {
+    /// `None` once the race is decided. Not pinned.
+    timer: Option<Timer>,
+    /// Structurally pinned.
+    future: F,
+}
+
+/// Waits for `future` for up to `duration`, then gives up on it.
+///
+/// The returned [`FirstOf`] resolves to [`Either::Left`] with the output of
+/// `future` if it completes first, and to [`Either::Right`] once `duration`
+/// has elapsed otherwise, dropping the loser either way. It is
+/// [`timeout`] for when the timer firing is a normal outcome rather than an
+/// error.
+///
+/// Nothing is spawned, so `future` need not be `Send`, and the timer is a
+/// plain [`Timer`], not a shared [`Deadline`]. As the future is polled
+/// first, one that is ready right away never arms the timer. When the future
+/// wins later, the timer is cancelled as the race is decided: its platform
+/// callback is dropped, or it leaves its group of
+/// [coalesced](crate::Config::coalesce_timers) timers, whose platform callback
+/// is dropped once the group is empty, and its pooled state is returned.
+/// Backends that cannot cancel platform callbacks (Apple, Android and the
+/// web) still run them when due, and they then find nothing to wake.
+///
+/// # Examples
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::timer::{Either, Elapsed, Timer, first_of};
+/// use std::{
+///     rc::Rc,
+///     time::{Duration, Instant},
+/// };
+///
+/// // Wait up to 20 ms for the animation, then proceed
+/// let animation = Timer::after(Duration::from_secs(60));
+/// let start = Instant::now();
+/// assert_eq!(block_on(first_of(animation, Duration::from_millis(20))), Either::Right(Elapsed));
+/// assert!(start.elapsed() >= Duration::from_millis(20));
+///
+/// // A future that is not `Send`, and wins
+/// let local = Rc::new("layout");
+/// let outcome = block_on(first_of(async { *local }, Duration::from_millis(500)));
+/// assert_eq!(outcome.left(), Some("layout"));
+/// ```
+pub const fn first_of<F: Future>(future: F, duration: Duration) -> FirstOf<F> {
+    FirstOf {
+        timer: Some(Timer::after(duration)),
+        future,
+    }
+}
+
+impl<F> FirstOf<F> {
+    /// Returns the inner future, giving up on the timer.
+    pub fn into_inner(self) -> F {
+        self.future
+    }
+}
+
+impl<F: Future> Future for FirstOf<F> {
+    type Output = Either<F::Output, Elapsed>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: `future` is structurally pinned and never moved out while
+        // pinned (`into_inner` takes `self` by value); `timer` is not pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        let timer = this
+            .timer
+            .as_mut()
+            .expect("`FirstOf` polled after completion");
+        if let Poll::Ready(output) = unsafe { Pin::new_unchecked(&mut this.future) }.poll(cx) {
+            // Detaches the timer from its callback right away
+            this.timer = None;
+            return Poll::Ready(Either::Left(output));
+        }
+        core::task::ready!(Pin::new(timer).poll(cx));
+        this.timer = None;
+        Poll::Ready(Either::Right(Elapsed))
+    }
+}
+
+/// Timer combinators for any future, as methods.
+///
+/// They build the same futures as the functions of this module, so
+/// `fetch().timeout(duration)` is [`timeout(duration, fetch())`](timeout).
+/// Nothing is spawned, so futures that are not `Send` work too, and the
+/// returned futures are named types that can be stored in structs.
+///
+/// # Examples
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::timer::{Delay, DeadlineExceeded, Timer, TimerExt};
+/// use std::{
+///     rc::Rc,
+///     time::{Duration, Instant},
+/// };
+///
+/// // A request that gives up after 10 ms
+/// let slow = Timer::after(Duration::from_secs(60));
+/// assert_eq!(block_on(slow.timeout(Duration::from_millis(10))), Err(DeadlineExceeded));
+///
+/// // The same, at a point in time, for a future that is not `Send`
+/// let local = Rc::new(42);
+/// let until = Instant::now() + Duration::from_secs(5);
+/// assert_eq!(block_on(async { *local }.deadline(until)), Ok(42));
+///
+/// // A debounced save, stored under its own name
+/// struct Pending {
+///     save: Delay<std::future::Ready<&'static str>>,
+/// }
+/// let start = Instant::now();
+/// let pending = Pending { save: std::future::ready("saved").delay(Duration::from_millis(20)) };
+/// assert_eq!(block_on(pending.save), "saved");
+/// assert!(start.elapsed() >= Duration::from_millis(20));
+/// ```
+pub trait TimerExt: Future + Sized {
+    /// Bounds the future by a deadline `duration` from now; see [`timeout`].
+    #[cfg(feature = "std")]
+    fn timeout(self, duration: Duration) -> Timeout<Self> {
+        Timeout::new(duration, self)
+    }
+
+    /// Bounds the future by a deadline at `instant`; see [`timeout_at`].
+    #[cfg(feature = "std")]
+    fn deadline(self, instant: Instant) -> Timeout<Self> {
+        Timeout::at(Deadline::at(instant), self)
+    }
+
+    /// Waits for `duration`, then polls the future; see [`Delay`].
+    fn delay(self, duration: Duration) -> Delay<Self> {
+        Delay {
+            timer: Some(Timer::after(duration)),
+            future: self,
+        }
+    }
+}
+
+impl<F: Future> TimerExt for F {}
+
+/// A future that waits for a timer before it polls an inner future.
+///
+/// Created by [`TimerExt::delay`]. The inner future is not polled before
+/// the delay has elapsed, so work it does when first polled is delayed too.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Delay<F> {
+    /// `None` once elapsed. Not pinned.
+    timer: Option<Timer>,
+    /// Structurally pinned.
+    future: F,
+}
+
+impl<F> Delay<F> {
+    /// Returns the inner future, giving up on the delay.
+    pub fn into_inner(self) -> F {
+        self.future
+    }
+}
+
+impl<F: Future> Future for Delay<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: `future` is structurally pinned and never moved out while
+        // pinned (`into_inner` takes `self` by value); `timer` is not pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        if let Some(timer) = &mut this.timer {
+            core::task::ready!(Pin::new(timer).poll(cx));
+            // Returns its state to the pool right away
+            this.timer = None;
+        }
+        unsafe { Pin::new_unchecked(&mut this.future) }.poll(cx)
+    }
+}
+
+/// The priority that timer callbacks are dispatched at, which is the ambient
+/// priority of [`with_priority`](crate::with_priority), if any.
+#[cfg(feature = "std")]
+fn callback_priority() -> crate::Priority {
+    crate::current_priority().unwrap_or_default()
+}
+
+#[cfg(not(feature = "std"))]
+const fn callback_priority() -> crate::Priority {
+    crate::Priority::Default
+}
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    /// The ambient deadline of the future being polled on this thread.
+    static AMBIENT: core::cell::RefCell<Option<Deadline>> = const { core::cell::RefCell::new(None) };
+}
+
+/// Runs `future` with `deadline` as its ambient deadline.
+///
+/// While `future` is polled, [`current_deadline`] returns the deadline, and
+/// the `*_until` operations that are given no deadline of their own, such as
+/// [`Mailbox::call_until`](crate::Mailbox::call_until), give up once it
+/// passes. This threads one time budget through every step of a request
+/// without passing it along by hand.
+///
+/// Nested calls can only shorten the budget: the ambient deadline is the
+/// earlier of `deadline` and the one already in effect. Tasks spawned from
+/// `future` do not inherit it.
+///
+/// # Examples
+///
+/// An outer budget of 50 ms bounds every step of the pipeline:
+///
+/// ```rust
+/// # #[cfg(not(target_vendor = "apple"))] {
+/// use futures_lite::future::block_on;
+/// use native_executor::{
+///     Mailbox, execute_main, polyfill, spawn_main,
+///     timer::{Deadline, DeadlineExceeded, Timer, current_deadline, timeout_at, with_deadline},
+/// };
+/// use std::{
+///     sync::mpsc,
+///     thread,
+///     time::{Duration, Instant},
+/// };
+///
+/// thread::spawn(polyfill::start_main_executor);
+/// let mailbox = block_on(spawn_main(async { Mailbox::main(0) }));
+///
+/// // The main thread is busy for longer than the budget
+/// let (started, stalled) = mpsc::channel();
+/// execute_main(move || {
+///     started.send(()).unwrap();
+///     thread::sleep(Duration::from_millis(300));
+/// });
+/// stalled.recv().unwrap();
+///
+/// let start = Instant::now();
+/// let (call, step) = block_on(with_deadline(Deadline::after(Duration::from_millis(50)), async {
+///     // Inherits the outer deadline
+///     let call = mailbox.call_until(None, |count| *count += 1).await;
+///
+///     // A nested budget cannot outlive the outer one
+///     let step = with_deadline(Deadline::after(Duration::from_secs(60)), async {
+///         let deadline = current_deadline().unwrap();
+///         timeout_at(deadline, Timer::after(Duration::from_secs(60))).await
+///     })
+///     .await;
+///     (call, step)
+/// }));
+///
+/// // Both gave up by the same deadline
+/// assert_eq!(call, Err(DeadlineExceeded));
+/// assert_eq!(step, Err(DeadlineExceeded));
+/// assert!(start.elapsed() >= Duration::from_millis(50));
+/// assert!(start.elapsed() < Duration::from_millis(250));
+///
+/// // The abandoned call never ran
+/// assert_eq!(block_on(mailbox.snapshot()), 0);
+/// assert!(current_deadline().is_none());
+/// # }
+/// ```
+#[cfg(feature = "std")]
+pub async fn with_deadline<F: Future>(deadline: Deadline, future: F) -> F::Output {
+    /// Restores the previous ambient deadline, also when `poll` panics.
+    struct Restore(Option<Deadline>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            AMBIENT.with(|ambient| *ambient.borrow_mut() = self.0.take());
+        }
+    }
+
+    let mut future = core::pin::pin!(future);
+    core::future::poll_fn(|cx| {
+        let effective = match current_deadline() {
+            Some(outer)
+                if outer
+                    .instant()
+                    .is_some_and(|outer| deadline.instant().is_none_or(|inner| outer < inner)) =>
+            {
+                outer
+            }
+            _ => deadline.clone(),
+        };
+        let _restore = Restore(AMBIENT.with(|ambient| ambient.replace(Some(effective))));
+        future.as_mut().poll(cx)
+    })
+    .await
+}
+
+/// Returns the ambient deadline set by [`with_deadline`] for the future being
+/// polled, if any.
+#[cfg(feature = "std")]
+#[must_use]
+pub fn current_deadline() -> Option<Deadline> {
+    AMBIENT.with(|ambient| ambient.borrow().clone())
+}
+
+/// A stream of ticks at a fixed period.
+///
+/// Deadlines are computed from the schedule rather than from when the
+/// previous tick was observed, so ticks do not drift. When ticks are missed
+/// because the consumer fell behind, they are skipped rather than delivered
+/// in a burst, unless another [`MissedTickBehavior`] is set; those missed
+/// because the wall clock jumped forward are always skipped.
+///
+/// # Examples
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::timer::Interval;
+/// use std::time::{Duration, Instant};
+///
+/// let start = Instant::now();
+/// let mut interval = Interval::every(Duration::from_millis(10));
+/// block_on(async {
+///     for _ in 0..3 {
+///         interval.tick().await;
+///     }
+/// });
+/// assert!(start.elapsed() >= Duration::from_millis(30));
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct Interval {
+    period: Duration,
+    schedule: Schedule,
+    /// The timer of the tick being waited for, kept across polls.
+    sleep: Option<Timer>,
+    pause_in_background: bool,
+    missed_tick_behavior: MissedTickBehavior,
+}
+
+/// What an [`Interval`] does with the ticks whose deadlines passed while its
+/// consumer was busy, such as a main thread blocked for two seconds under a
+/// period of 100 ms.
+///
+/// A tick counts as missed once the next one is due too. The late tick
+/// itself is always delivered, right away; the behavior decides when the
+/// ones after it are due.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum MissedTickBehavior {
+    /// Delivers the missed ticks right away, one per call to
+    /// [`tick`](Interval::tick), until the interval is back on schedule.
+    /// Each reports its own deadline, so every tick of the schedule is seen.
+    Burst,
+    /// Restarts the schedule from the late tick: the next one is due one
+    /// period after it was delivered, and the missed ones are dropped.
+    Delay,
+    /// Drops the missed ticks and keeps the original schedule: the next tick
+    /// is due on the first deadline of the schedule still ahead. Suits UI
+    /// work, where only the latest state matters.
+    #[default]
+    Skip,
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug)]
+enum Schedule {
+    /// Ticks at `next` and every period after it.
+    /// `None` once the next tick is too far away to be represented, so the
+    /// interval never ticks again.
+    Monotonic { next: Option<Instant> },
+    /// Ticks whenever the wall clock reaches `offset` modulo the period.
+    Aligned {
+        offset: Duration,
+        clock: fn() -> SystemTime,
+        /// The boundary of the tick being waited for, since the UNIX epoch.
+        target: Option<Duration>,
+    },
+}
+
+#[cfg(feature = "std")]
+impl Interval {
+    /// Creates an interval whose first tick is one `period` from now.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `period` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use native_executor::timer::Interval;
+    /// use std::{panic, sync::Mutex, time::Duration};
+    ///
+    /// static LOCATION: Mutex<Option<(String, u32)>> = Mutex::new(None);
+    /// panic::set_hook(Box::new(|info| {
+    ///     let location = info.location().unwrap();
+    ///     *LOCATION.lock().unwrap() = Some((location.file().to_owned(), location.line()));
+    /// }));
+    ///
+    /// assert_eq!(Interval::every(Duration::from_secs(1)).period(), Duration::from_secs(1));
+    ///
+    /// // The panic points at the caller
+    /// let (result, line) = (panic::catch_unwind(|| Interval::every(Duration::ZERO)), line!());
+    /// assert_eq!(*result.unwrap_err().downcast::<&str>().unwrap(), "interval period must be non-zero");
+    /// assert_eq!(LOCATION.lock().unwrap().take(), Some((file!().to_owned(), line)));
+    /// ```
+    #[must_use]
+    #[track_caller]
+    pub fn every(period: Duration) -> Self {
+        assert!(!period.is_zero(), "interval period must be non-zero");
+        Self {
+            period,
+            schedule: Schedule::Monotonic {
+                next: Instant::now().checked_add(period),
+            },
+            sleep: None,
+            pause_in_background: false,
+            missed_tick_behavior: MissedTickBehavior::Skip,
+        }
+    }
+
+    /// Creates an interval that ticks on wall-clock boundaries: whenever the
+    /// time since the UNIX epoch is congruent to `offset` modulo `period`.
+    ///
+    /// For example, a period of one minute with a zero offset ticks at the
+    /// start of every minute, regardless of when the interval was created.
+    ///
+    /// The wall clock is re-read before every tick. If it jumps forward, the
+    /// boundaries in between are skipped; if it jumps backwards, the interval
+    /// ticks once at the next boundary after the new time instead of
+    /// repeating or waiting out the difference.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `period` is zero.
+    #[must_use]
+    #[track_caller]
+    pub fn every_aligned(period: Duration, offset: Duration) -> Self {
+        Self::every_aligned_with_clock(period, offset, SystemTime::now)
+    }
+
+    /// Like [`every_aligned`](Self::every_aligned), but reads the wall clock
+    /// from `clock`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `period` is zero.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use futures_lite::future::block_on;
+    /// use native_executor::timer::Interval;
+    /// use std::{
+    ///     sync::{LazyLock, atomic::{AtomicU64, Ordering}},
+    ///     time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    /// };
+    ///
+    /// // A wall clock that starts 12 ms past a 50 ms boundary and can be
+    /// // moved back by `SKEW_MS`
+    /// static START: LazyLock<Instant> = LazyLock::new(Instant::now);
+    /// static SKEW_MS: AtomicU64 = AtomicU64::new(0);
+    /// fn clock() -> SystemTime {
+    ///     UNIX_EPOCH + Duration::from_millis(1_000_012) + START.elapsed()
+    ///         - Duration::from_millis(SKEW_MS.load(Ordering::SeqCst))
+    /// }
+    /// fn millis() -> u128 {
+    ///     clock().duration_since(UNIX_EPOCH).unwrap().as_millis()
+    /// }
+    ///
+    /// LazyLock::force(&START);
+    /// let period = Duration::from_millis(50);
+    /// let mut interval = Interval::every_aligned_with_clock(period, Duration::from_millis(5), clock);
+    ///
+    /// block_on(async {
+    ///     // The first tick lands on the next boundary, 5 ms past a multiple of 50 ms
+    ///     interval.tick().await;
+    ///     let first = millis();
+    ///     assert!(first % 50 >= 5 && first % 50 < 25, "ticked at {first}");
+    ///     let first_real = Instant::now();
+    ///
+    ///     // Move the clock back by ten periods: the next tick comes after at
+    ///     // most one period instead of a ten period stall or a burst
+    ///     SKEW_MS.store(500, Ordering::SeqCst);
+    ///     interval.tick().await;
+    ///     let waited = first_real.elapsed();
+    ///     assert!(waited >= Duration::from_millis(20) && waited < Duration::from_millis(250));
+    ///     assert!(millis() % 50 >= 5);
+    /// });
+    /// ```
+    #[must_use]
+    #[track_caller]
+    pub fn every_aligned_with_clock(
+        period: Duration,
+        offset: Duration,
+        clock: fn() -> SystemTime,
+    ) -> Self {
+        assert!(!period.is_zero(), "interval period must be non-zero");
+        Self {
+            period,
+            schedule: Schedule::Aligned {
+                offset,
+                clock,
+                target: None,
+            },
+            sleep: None,
+            pause_in_background: false,
+            missed_tick_behavior: MissedTickBehavior::Skip,
+        }
+    }
+
+    /// Returns the period between ticks.
+    #[must_use]
+    pub const fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Returns when the next tick is due, or `None` if it is too far away
+    /// to be represented, as for a period of [`Duration::MAX`], in which
+    /// case the interval never ticks again.
+    ///
+    /// Deadlines follow the schedule set at creation: the next one is a
+    /// whole number of periods after the first, however long the consumer
+    /// took with the previous ticks. For an interval aligned to the wall
+    /// clock, this is the next boundary, as of the current wall-clock time.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use futures_lite::future::block_on;
+    /// use native_executor::timer::Interval;
+    /// use std::{
+    ///     thread,
+    ///     time::{Duration, Instant},
+    /// };
+    ///
+    /// // 20 ticks of 50 ms, each consumed by 10 ms of work
+    /// let period = Duration::from_millis(50);
+    /// let start = Instant::now();
+    /// let mut interval = Interval::every(period);
+    /// let first = interval.next_deadline().unwrap();
+    /// assert!(first >= start + period);
+    /// block_on(async {
+    ///     for tick in 0..20 {
+    ///         assert_eq!(interval.tick().await, first + period * tick);
+    ///         thread::sleep(Duration::from_millis(10));
+    ///     }
+    /// });
+    /// assert_eq!(interval.next_deadline(), Some(first + period * 20));
+    ///
+    /// // The work does not stretch the period
+    /// let elapsed = start.elapsed();
+    /// assert!(elapsed >= Duration::from_secs(1));
+    /// assert!(elapsed < Duration::from_secs(1) + period * 2, "{elapsed:?}");
+    ///
+    /// // A period too long to represent never ticks
+    /// assert_eq!(Interval::every(Duration::MAX).next_deadline(), None);
+    /// ```
+    #[must_use]
+    pub fn next_deadline(&self) -> Option<Instant> {
+        match &self.schedule {
+            Schedule::Monotonic { next } => *next,
+            Schedule::Aligned {
+                offset,
+                clock,
+                target,
+            } => {
+                let now = since_epoch(clock());
+                let boundary = target.unwrap_or_else(|| next_boundary(now, self.period, *offset));
+                Instant::now().checked_add(boundary.saturating_sub(now))
+            }
+        }
+    }
+
+    /// Sets whether the interval pauses while the app is in the
+    /// [background](crate::lifecycle::AppState::Background), which it does
+    /// not by default.
+    ///
+    /// A paused interval arms no platform timer, so it does not wake the CPU
+    /// in the background, except for a timer armed before the app went there.
+    /// Once the app is active again, the ticks missed meanwhile are handled
+    /// as any others, see [`missed_tick_behavior`](Self::missed_tick_behavior):
+    /// by default, the interval ticks once right away, then on schedule.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use futures_lite::future::{block_on, poll_once};
+    /// use native_executor::{
+    ///     lifecycle::{self, AppState},
+    ///     timer::Interval,
+    /// };
+    /// use std::{
+    ///     sync::atomic::{AtomicU64, Ordering},
+    ///     time::{Duration, SystemTime, UNIX_EPOCH},
+    /// };
+    ///
+    /// // A wall clock that only moves by hand
+    /// static NOW_MS: AtomicU64 = AtomicU64::new(1_000_000);
+    /// fn clock() -> SystemTime {
+    ///     UNIX_EPOCH + Duration::from_millis(NOW_MS.load(Ordering::SeqCst))
+    /// }
+    ///
+    /// let period = Duration::from_secs(60);
+    /// let mut refresh = Interval::every_aligned_with_clock(period, Duration::ZERO, clock)
+    ///     .pause_in_background(true);
+    /// block_on(async {
+    ///     let mut tick = Box::pin(refresh.tick());
+    ///     assert!(poll_once(&mut tick).await.is_none());
+    ///
+    ///     // Ten boundaries pass in the background without a tick
+    ///     lifecycle::set_app_state(AppState::Background);
+    ///     NOW_MS.fetch_add(600_000, Ordering::SeqCst);
+    ///     assert!(poll_once(&mut tick).await.is_none());
+    ///
+    ///     // On resume, a single tick catches up
+    ///     lifecycle::set_app_state(AppState::Active);
+    ///     assert!(poll_once(tick).await.is_some());
+    ///     assert!(poll_once(refresh.tick()).await.is_none());
+    /// });
+    /// ```
+    #[must_use]
+    pub const fn pause_in_background(mut self, pause: bool) -> Self {
+        self.pause_in_background = pause;
+        self
+    }
+
+    /// Sets what the interval does with the ticks missed while its consumer
+    /// was busy. Defaults to [`MissedTickBehavior::Skip`].
+    ///
+    /// This applies to intervals of [`every`](Self::every); those aligned to
+    /// the wall clock always skip to the next boundary.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use futures_lite::future::{block_on, poll_once};
+    /// use native_executor::timer::{Interval, MissedTickBehavior};
+    /// use std::{
+    ///     thread,
+    ///     time::{Duration, Instant},
+    /// };
+    ///
+    /// let period = Duration::from_millis(20);
+    /// // A consumer that falls behind by four periods after the first tick,
+    /// // then takes every tick that is ready and the next one
+    /// let slow_consumer = |behavior| {
+    ///     let mut interval = Interval::every(period).missed_tick_behavior(behavior);
+    ///     block_on(async {
+    ///         let first = interval.tick().await;
+    ///         thread::sleep(period * 4 + period / 10);
+    ///         let caught_up = Instant::now();
+    ///         let mut ready = Vec::new();
+    ///         while let Some(tick) = poll_once(interval.tick()).await {
+    ///             ready.push(tick);
+    ///         }
+    ///         (first, ready, caught_up, interval.tick().await)
+    ///     })
+    /// };
+    ///
+    /// // Every missed tick, with its own deadline
+    /// let (first, ready, _, _) = slow_consumer(MissedTickBehavior::Burst);
+    /// assert!(ready.len() >= 4);
+    /// assert_eq!(ready[0], first + period);
+    /// assert!(ready.windows(2).all(|pair| pair[1] - pair[0] == period));
+    ///
+    /// // One late tick, then back on the original schedule
+    /// let (first, ready, caught_up, next) = slow_consumer(MissedTickBehavior::Skip);
+    /// assert_eq!(ready, [first + period]);
+    /// assert!(next > caught_up);
+    /// assert_eq!((next - first).as_nanos() % period.as_nanos(), 0);
+    ///
+    /// // One late tick, then a schedule that restarts from it
+    /// let (first, ready, caught_up, next) = slow_consumer(MissedTickBehavior::Delay);
+    /// assert_eq!(ready, [first + period]);
+    /// assert!(next >= caught_up + period);
+    /// ```
+    #[must_use]
+    pub const fn missed_tick_behavior(mut self, behavior: MissedTickBehavior) -> Self {
+        self.missed_tick_behavior = behavior;
+        self
+    }
+
+    /// Waits until the next tick and returns when it happened.
+    ///
+    /// The tick is due at [`next_deadline`](Self::next_deadline), which does
+    /// not depend on when the previous tick was consumed, so the time spent
+    /// on a tick does not delay the next one. Dropping the returned future
+    /// keeps the wait: the next call resumes it.
+    pub async fn tick(&mut self) -> Instant {
+        core::future::poll_fn(|cx| self.poll_tick(cx)).await
+    }
+
+    /// Polls for the next tick, returning when it happened.
+    ///
+    /// This is [`tick`](Self::tick) for hand-written futures and streams
+    /// that own the interval: on `Pending`, the waker of `cx` is woken once
+    /// the tick is due.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use futures_lite::future::block_on;
+    /// use native_executor::timer::Interval;
+    /// use std::{
+    ///     future::poll_fn,
+    ///     task::Poll,
+    ///     time::{Duration, Instant},
+    /// };
+    ///
+    /// // A heartbeat that owns its interval, without boxing a future
+    /// struct Heartbeat {
+    ///     interval: Interval,
+    ///     beats: u32,
+    /// }
+    ///
+    /// let start = Instant::now();
+    /// let mut heartbeat = Heartbeat { interval: Interval::every(Duration::from_millis(5)), beats: 0 };
+    /// block_on(poll_fn(|cx| {
+    ///     while heartbeat.interval.poll_tick(cx).is_ready() {
+    ///         heartbeat.beats += 1;
+    ///         if heartbeat.beats == 3 {
+    ///             return Poll::Ready(());
+    ///         }
+    ///     }
+    ///     Poll::Pending
+    /// }));
+    /// assert!(start.elapsed() >= Duration::from_millis(15));
+    /// ```
+    pub fn poll_tick(&mut self, cx: &mut Context<'_>) -> Poll<Instant> {
+        loop {
+            if self.pause_in_background && crate::lifecycle::paused(cx.waker()) {
+                // Woken on resume, when the missed ticks are skipped as usual.
+                self.sleep = None;
+                return Poll::Pending;
+            }
+            let slept = match self.sleep.as_mut() {
+                Some(timer) => {
+                    core::task::ready!(Pin::new(timer).poll(cx));
+                    self.sleep = None;
+                    true
+                }
+                None => false,
+            };
+            match &mut self.schedule {
+                Schedule::Monotonic { next } => {
+                    // Too far away to ever tick
+                    let Some(deadline) = *next else {
+                        return Poll::Pending;
+                    };
+                    let now = Instant::now();
+                    if !slept && deadline > now {
+                        self.sleep = Some(Timer::after(deadline - now));
+                        continue;
+                    }
+                    let now = Instant::now();
+                    *next = deadline.checked_add(self.period);
+                    if let Some(missed) = next.filter(|missed| *missed <= now) {
+                        // Ticks were missed while the consumer was busy.
+                        match self.missed_tick_behavior {
+                            MissedTickBehavior::Burst => {}
+                            MissedTickBehavior::Delay => *next = now.checked_add(self.period),
+                            MissedTickBehavior::Skip => {
+                                let behind = now.duration_since(missed).as_nanos();
+                                let skipped = behind / self.period.as_nanos() + 1;
+                                *next = self
+                                    .period
+                                    .checked_mul(u32::try_from(skipped).unwrap_or(u32::MAX))
+                                    .and_then(|skip| missed.checked_add(skip));
+                            }
+                        }
+                    }
+                    return Poll::Ready(deadline);
+                }
+                Schedule::Aligned {
+                    offset,
+                    clock,
+                    target,
+                } => {
+                    let now = since_epoch(clock());
+                    let boundary =
+                        target.get_or_insert_with(|| next_boundary(now, self.period, *offset));
+                    let Some(mut remaining) = boundary.checked_sub(now).filter(|d| !d.is_zero())
+                    else {
+                        *target = None;
+                        return Poll::Ready(Instant::now());
+                    };
+                    if remaining > self.period {
+                        // The clock jumped backwards while waiting.
+                        *boundary = next_boundary(now, self.period, *offset);
+                        remaining = boundary.saturating_sub(now);
+                    }
+                    self.sleep = Some(Timer::after(remaining));
+                }
+            }
+        }
+    }
+}
+
+/// Paces a loop to a target frame duration, such as the update loop of a
+/// game running at a fixed timestep.
+///
+/// Frames are due at fixed deadlines, one target duration apart, starting
+/// one target duration after the pacer is created. [`next`](Self::next)
+/// sleeps on the platform timer until the next deadline; within the
+/// [spin threshold](Self::spin_threshold) of it, it yields to the executor
+/// instead, trading CPU time for sub-millisecond precision where the
+/// platform timer is coarser than that.
+///
+/// When the loop falls behind, the [`CatchUp`] policy decides which of the
+/// passed deadlines still produce a frame; the others are reported in
+/// [`FrameInfo::missed`].
+///
+/// # Examples
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::timer::{CatchUp, FramePacer};
+/// use std::time::{Duration, Instant};
+///
+/// let start = Instant::now();
+/// let mut pacer = FramePacer::new(Duration::from_millis(8))
+///     .catch_up(CatchUp::RunMissedUpdates { max: 4 })
+///     .spin_threshold(Duration::from_millis(1));
+/// block_on(async {
+///     for _ in 0..3 {
+///         let frame = pacer.next().await;
+///         assert_eq!(frame.dt, Duration::from_millis(8));
+///     }
+/// });
+/// assert!(start.elapsed() >= Duration::from_millis(24));
+/// ```
+///
+/// With a virtual clock, lateness can be injected between frames:
+///
+/// ```rust
+/// use futures_lite::future::block_on;
+/// use native_executor::timer::{CatchUp, FramePacer};
+/// use std::{
+///     sync::{LazyLock, atomic::{AtomicU64, Ordering}},
+///     time::{Duration, Instant},
+/// };
+///
+/// static START: LazyLock<Instant> = LazyLock::new(Instant::now);
+/// static NOW_MS: AtomicU64 = AtomicU64::new(0);
+/// fn clock() -> Instant {
+///     *START + Duration::from_millis(NOW_MS.load(Ordering::SeqCst))
+/// }
+/// let ms = Duration::from_millis;
+///
+/// // Dropping missed frames: one long frame covering the gap
+/// let mut pacer = FramePacer::with_clock(ms(10), clock);
+/// NOW_MS.store(10, Ordering::SeqCst);
+/// let frame = block_on(pacer.next());
+/// assert_eq!((frame.dt, frame.missed, frame.now), (ms(10), 0, clock()));
+///
+/// NOW_MS.store(45, Ordering::SeqCst);
+/// let frame = block_on(pacer.next());
+/// assert_eq!((frame.dt, frame.missed), (ms(30), 2));
+///
+/// // Running missed updates: up to `max` overdue frames are delivered
+/// // right away, older ones are dropped
+/// NOW_MS.store(0, Ordering::SeqCst);
+/// let mut pacer = FramePacer::with_clock(ms(10), clock)
+///     .catch_up(CatchUp::RunMissedUpdates { max: 1 });
+/// NOW_MS.store(35, Ordering::SeqCst);
+/// let frames: Vec<_> = (0..2)
+///     .map(|_| block_on(pacer.next()))
+///     .map(|frame| (frame.dt, frame.missed))
+///     .collect();
+/// assert_eq!(frames, [(ms(20), 1), (ms(10), 0)]);
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct FramePacer {
+    target: Duration,
+    policy: CatchUp,
+    spin: Duration,
+    clock: fn() -> Instant,
+    /// The deadline of the previous frame.
+    last: Instant,
+    /// The deadline of the next frame.
+    next: Instant,
+}
+
+/// What a [`FramePacer`] does with the frames whose deadlines passed while
+/// the loop was busy.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CatchUp {
+    /// Delivers overdue frames immediately, one per call to
+    /// [`FramePacer::next`], so a fixed-timestep simulation runs every
+    /// update. At most `max` frames beyond the current one are caught up;
+    /// older ones are dropped.
+    RunMissedUpdates {
+        /// The maximum number of overdue frames to catch up.
+        max: u32,
+    },
+    /// Drops every overdue frame except the latest, whose `dt` then covers
+    /// the dropped ones.
+    #[default]
+    DropMissed,
+}
+
+/// A frame produced by [`FramePacer::next`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameInfo {
+    /// The time between the deadlines of the previous frame and this one: the
+    /// target duration, plus one target duration per missed frame.
+    pub dt: Duration,
+    /// The number of frames dropped right before this one.
+    pub missed: u32,
+    /// When the frame was produced, which is at or after its deadline.
+    pub now: Instant,
+}
+
+#[cfg(feature = "std")]
+impl FramePacer {
+    /// Creates a pacer whose first frame is due one `target` from now.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `target` is zero.
+    #[must_use]
+    #[track_caller]
+    pub fn new(target: Duration) -> Self {
+        Self::with_clock(target, Instant::now)
+    }
+
+    /// Like [`new`](Self::new), but reads the time from `clock`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `target` is zero.
+    #[must_use]
+    #[track_caller]
+    pub fn with_clock(target: Duration, clock: fn() -> Instant) -> Self {
+        assert!(!target.is_zero(), "frame duration must be non-zero");
+        let last = clock();
+        Self {
+            target,
+            policy: CatchUp::DropMissed,
+            spin: Duration::ZERO,
+            clock,
+            last,
+            next: last + target,
+        }
+    }
+
+    /// Sets the policy for frames whose deadlines passed while the loop was
+    /// busy. Defaults to [`CatchUp::DropMissed`].
+    #[must_use]
+    pub const fn catch_up(mut self, policy: CatchUp) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Sets how close to a deadline [`next`](Self::next) stops sleeping and
+    /// yields to the executor until the deadline instead.
+    ///
+    /// Defaults to zero, which relies on the platform timer alone. A
+    /// threshold of a millisecond or two gives sub-millisecond precision on
+    /// desktop platforms, at the cost of keeping a worker busy; avoid it on
+    /// battery-powered devices.
+    #[must_use]
+    pub const fn spin_threshold(mut self, threshold: Duration) -> Self {
+        self.spin = threshold;
+        self
+    }
+
+    /// Returns the target frame duration.
+    #[must_use]
+    pub const fn target(&self) -> Duration {
+        self.target
+    }
+
+    /// Waits until the next frame is due and returns its timing.
+    pub async fn next(&mut self) -> FrameInfo {
+        let mut now = (self.clock)();
+        while now < self.next {
+            let remaining = self.next.duration_since(now);
+            match remaining.checked_sub(self.spin).filter(|d| !d.is_zero()) {
+                Some(sleep) => Timer::after(sleep).await,
+                None => yield_now().await,
+            }
+            now = (self.clock)();
+        }
+
+        let overdue = now.duration_since(self.next).as_nanos() / self.target.as_nanos();
+        let overdue = u32::try_from(overdue).unwrap_or(u32::MAX);
+        let missed = match self.policy {
+            CatchUp::RunMissedUpdates { max } => overdue.saturating_sub(max),
+            CatchUp::DropMissed => overdue,
+        };
+        let deadline = self.next + self.target * missed;
+        let dt = deadline - self.last;
+        self.last = deadline;
+        self.next = deadline + self.target;
+        FrameInfo { dt, missed, now }
+    }
+}
+
+/// Yields to the executor once.
+#[cfg(feature = "std")]
+fn yield_now() -> impl Future<Output = ()> {
+    let mut yielded = false;
+    core::future::poll_fn(move |cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+}
+
+#[cfg(feature = "std")]
+fn since_epoch(time: SystemTime) -> Duration {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default()
+}
+
+/// Returns the first instant after `now` that is congruent to `offset`
+/// modulo `period`.
+#[cfg(feature = "std")]
+fn next_boundary(now: Duration, period: Duration, offset: Duration) -> Duration {
+    const NANOS_PER_SEC: u128 = 1_000_000_000;
+    let (now, period) = (now.as_nanos(), period.as_nanos());
+    let offset = offset.as_nanos() % period;
+    let boundary = if now < offset {
+        offset
+    } else {
+        ((now - offset) / period + 1) * period + offset
+    };
+    #[allow(clippy::cast_possible_truncation)]
+    Duration::new(
+        u64::try_from(boundary / NANOS_PER_SEC).unwrap_or(u64::MAX),
+        (boundary % NANOS_PER_SEC) as u32,
+    )
+}
+
+/// Collapses bursts of calls into one: runs the closure of the latest call
+/// once no other call came for a quiet period.
+///
+/// Each [`call`](Self::call) replaces the pending closure and pushes the
+/// firing back to one quiet period after it, so keystrokes or resize
+/// notifications trigger a single search or layout once they stop. The
+/// closure runs on the thread pool, at the priority of the debouncer.
+///
+/// Calls are cheap from any thread: they take a lock for a moment, and at
+/// most one platform timer is in flight, which is re-armed for the rest of
+/// the quiet period if it fires early. Dropping the debouncer, or
+/// [`cancel`](Self::cancel), drops the pending closure without running it.
+///
+/// # Examples
+///
+/// ```rust
+/// use native_executor::timer::Debouncer;
+/// use std::{sync::mpsc, thread, time::Duration};
+///
+/// let (sender, searches) = mpsc::channel();
+/// let search = Debouncer::new(Duration::from_millis(30));
+///
+/// // A burst of keystrokes, less than the quiet period apart
+/// for query in ["n", "na", "nat", "nati", "nativ", "native"] {
+///     let sender = sender.clone();
+///     search.call(move || sender.send(query).unwrap());
+///     thread::sleep(Duration::from_millis(5));
+/// }
+/// assert!(search.is_pending());
+/// assert_eq!(searches.recv().unwrap(), "native");
+/// assert!(searches.recv_timeout(Duration::from_millis(60)).is_err());
+///
+/// // Dropped before the quiet period ends: nothing runs
+/// search.call(move || sender.send("dropped").unwrap());
+/// drop(search);
+/// assert!(searches.recv_timeout(Duration::from_millis(60)).is_err());
+/// ```
+#[cfg(feature = "std")]
+pub struct Debouncer {
+    quiet: Duration,
+    priority: crate::Priority,
+    /// Held weakly by the platform timer, so dropping the debouncer drops
+    /// the pending closure.
+    state: Arc<Mutex<Debounced>>,
+}
+
+#[cfg(feature = "std")]
+struct Debounced {
+    /// The closure of the latest call, until it runs or is cancelled.
+    pending: Option<alloc::boxed::Box<dyn FnOnce() + Send>>,
+    /// When the closure is due: one quiet period after the latest call.
+    /// `None` if that is too far away to be represented, so it never is.
+    due: Option<Instant>,
+    /// Whether a platform timer is in flight.
+    armed: bool,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Debug for Debouncer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Debouncer")
+            .field("quiet", &self.quiet)
+            .field("priority", &self.priority)
+            .field("pending", &self.is_pending())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Debouncer {
+    /// Creates a debouncer that runs closures once calls have stopped for
+    /// `quiet`, at [`Priority::Default`](crate::Priority::Default).
+    #[must_use]
+    pub fn new(quiet: Duration) -> Self {
+        Self::with_priority(quiet, crate::Priority::default())
+    }
+
+    /// Creates a debouncer that runs closures once calls have stopped for
+    /// `quiet`, at `priority`.
+    #[must_use]
+    pub fn with_priority(quiet: Duration, priority: crate::Priority) -> Self {
+        Self {
+            quiet,
+            priority,
+            state: Arc::new(Mutex::new(Debounced {
+                pending: None,
+                due: None,
+                armed: false,
+            })),
+        }
+    }
+
+    /// Makes `f` the closure to run once no call has come for the quiet
+    /// period, replacing the pending one.
+    pub fn call(&self, f: impl FnOnce() + Send + 'static) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let replaced = state.pending.replace(alloc::boxed::Box::new(f));
+        state.due = Instant::now().checked_add(self.quiet);
+        let arm = !core::mem::replace(&mut state.armed, true);
+        drop(state);
+        // The replaced closure may own values whose destructors call back.
+        drop(replaced);
+        if arm {
+            arm_debounced(Arc::downgrade(&self.state), self.quiet, self.priority);
+        }
+    }
+
+    /// Drops the pending closure without running it.
+    pub fn cancel(&self) {
+        let pending = self
+            .state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pending
+            .take();
+        drop(pending);
+    }
+
+    /// Returns `true` if a closure waits for the quiet period to end.
+    #[must_use]
+    pub fn is_pending(&self) -> bool {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pending
+            .is_some()
+    }
+}
+
+/// Checks the debouncer of `state` once `delay` has elapsed.
+#[cfg(feature = "std")]
+fn arm_debounced(
+    state: alloc::sync::Weak<Mutex<Debounced>>,
+    delay: Duration,
+    priority: crate::Priority,
+) {
+    crate::dispatch_after(
+        delay,
+        move || {
+            let Some(shared) = state.upgrade() else {
+                return;
+            };
+            let mut debounced = shared.lock().unwrap_or_else(PoisonError::into_inner);
+            let now = Instant::now();
+            if debounced.pending.is_some() {
+                match debounced.due {
+                    Some(due) if due > now => {
+                        // Called again since the timer was armed
+                        drop(debounced);
+                        arm_debounced(state, due - now, priority);
+                        return;
+                    }
+                    // Never due, until a later call re-arms the timer
+                    None => {
+                        debounced.armed = false;
+                        return;
+                    }
+                    Some(_) => {}
+                }
+            }
+            debounced.armed = false;
+            let pending = debounced.pending.take();
+            drop(debounced);
+            // Runs on the pool rather than on the timer
+            if let Some(f) = pending {
+                crate::execute_with_priority(f, priority);
+            }
+        },
+        priority,
+    );
+}
+
+/// Runs closures at most once per window, as a rate limit.
+///
+/// A [`call`](Self::call) outside a window opens one. On the leading edge,
+/// its closure runs right away; later calls in the window are suppressed.
+/// On the trailing edge, the closure of the latest suppressed call runs as
+/// the window ends, and opens the next window, so runs stay a window apart.
+/// Both edges are on by default, see [`leading`](Self::leading) and
+/// [`trailing`](Self::trailing). Closures run on the thread pool, at the
+/// priority of the throttle.
+///
+/// Calls and the end of a window are serialized, so a call that arrives as
+/// the window closes either runs on the trailing edge or opens the next
+/// window, never both and never neither. Dropping the throttle drops the
+/// suppressed closure without running it.
+///
+/// # Examples
+///
+/// ```rust
+/// use native_executor::timer::Throttle;
+/// use std::{sync::mpsc, thread, time::Duration};
+///
+/// // 20 scroll events, 5 ms apart, over two 50 ms windows
+/// let scroll = |throttle: Throttle| {
+///     let (sender, runs) = mpsc::channel();
+///     for event in 0..20 {
+///         let sender = sender.clone();
+///         throttle.call(move || sender.send(event).unwrap());
+///         thread::sleep(Duration::from_millis(5));
+///     }
+///     drop(sender);
+///     let runs: Vec<u32> = runs.iter().collect();
+///     assert!((2..=5).contains(&runs.len()), "{runs:?}");
+///     runs
+/// };
+/// let window = Duration::from_millis(50);
+///
+/// // The first event right away, and the latest one at the end of each window
+/// let runs = scroll(Throttle::new(window));
+/// assert_eq!((runs[0], runs.last()), (0, Some(&19)));
+///
+/// // Only the first event of each window
+/// let runs = scroll(Throttle::new(window).trailing(false));
+/// assert_eq!(runs[0], 0);
+///
+/// // Only the latest event of each window
+/// let runs = scroll(Throttle::new(window).leading(false));
+/// assert_ne!(runs[0], 0);
+/// assert_eq!(runs.last(), Some(&19));
+/// ```
+#[cfg(feature = "std")]
+pub struct Throttle {
+    window: Duration,
+    priority: crate::Priority,
+    leading: bool,
+    trailing: bool,
+    /// Held weakly by the platform timer, so dropping the throttle drops the
+    /// suppressed closure.
+    state: Arc<Mutex<Throttled>>,
+}
+
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct Throttled {
+    /// Whether a window is open, with its platform timer in flight.
+    open: bool,
+    /// The closure of the latest suppressed call, run on the trailing edge.
+    pending: Option<alloc::boxed::Box<dyn FnOnce() + Send>>,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Debug for Throttle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Throttle")
+            .field("window", &self.window)
+            .field("priority", &self.priority)
+            .field("leading", &self.leading)
+            .field("trailing", &self.trailing)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Throttle {
+    /// Creates a throttle that runs closures at most once per `window`, on
+    /// both edges, at [`Priority::Default`](crate::Priority::Default).
+    #[must_use]
+    pub fn new(window: Duration) -> Self {
+        Self::with_priority(window, crate::Priority::default())
+    }
+
+    /// Creates a throttle that runs closures at most once per `window`, on
+    /// both edges, at `priority`.
+    #[must_use]
+    pub fn with_priority(window: Duration, priority: crate::Priority) -> Self {
+        Self {
+            window,
+            priority,
+            leading: true,
+            trailing: true,
+            state: Arc::default(),
+        }
+    }
+
+    /// Sets whether the call that opens a window runs right away. On by
+    /// default; when off, it is suppressed like the calls after it.
+    #[must_use]
+    pub const fn leading(mut self, leading: bool) -> Self {
+        self.leading = leading;
+        self
+    }
+
+    /// Sets whether the latest suppressed call runs as its window ends. On
+    /// by default; when off, suppressed calls are dropped.
+    #[must_use]
+    pub const fn trailing(mut self, trailing: bool) -> Self {
+        self.trailing = trailing;
+        self
+    }
+
+    /// Runs `f` unless a window is open, then opens one; otherwise keeps it
+    /// for the trailing edge, in place of the previous suppressed call.
+    pub fn call(&self, f: impl FnOnce() + Send + 'static) {
+        let f: alloc::boxed::Box<dyn FnOnce() + Send> = alloc::boxed::Box::new(f);
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if state.open {
+            let suppressed = if self.trailing {
+                state.pending.replace(f)
+            } else {
+                Some(f)
+            };
+            drop(state);
+            // It may own values whose destructors call back.
+            drop(suppressed);
+            return;
+        }
+        state.open = true;
+        let run = if self.leading {
+            Some(f)
+        } else {
+            state.pending = self.trailing.then_some(f);
+            None
+        };
+        drop(state);
+        if let Some(f) = run {
+            crate::execute_with_priority(f, self.priority);
+        }
+        close_window(Arc::downgrade(&self.state), self.window, self.priority);
+    }
+}
+
+/// Ends the window of the throttle of `state` once `window` has elapsed,
+/// running the trailing call, which opens the next window.
+#[cfg(feature = "std")]
+fn close_window(
+    state: alloc::sync::Weak<Mutex<Throttled>>,
+    window: Duration,
+    priority: crate::Priority,
+) {
+    crate::dispatch_after(
+        window,
+        move || {
+            let Some(shared) = state.upgrade() else {
+                return;
+            };
+            let mut throttled = shared.lock().unwrap_or_else(PoisonError::into_inner);
+            let pending = throttled.pending.take();
+            throttled.open = pending.is_some();
+            drop(throttled);
+            if let Some(f) = pending {
+                crate::execute_with_priority(f, priority);
+                close_window(state, window, priority);
+            }
+        },
+        priority,
+    );
 }
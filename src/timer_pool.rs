@@ -0,0 +1,199 @@
+//! Recycled completion state of [`Timer`](crate::timer::Timer)s.
+//!
+//! Workloads that create and cancel many short timeouts would otherwise
+//! allocate the state shared with the platform callback once per timer.
+//! Slots are allocated once and never freed: a released slot goes to a
+//! per-thread cache, and past the configured capacity to a shared lock-free
+//! free list, from which an empty cache takes every slot at once.
+//!
+//! A released slot may still be reachable from the callback of a cancelled
+//! timer. Each slot therefore carries a generation, bumped on release, and a
+//! callback only completes the slot if the generation it captured is still
+//! current.
+
+use core::{
+    cell::Cell,
+    ptr,
+    sync::atomic::{AtomicPtr, AtomicU64, Ordering},
+    task::Waker,
+};
+
+use alloc::boxed::Box;
+
+use crate::timer::WakerSlot;
+
+/// The completion state of one timer at a time.
+#[derive(Debug)]
+pub struct Slot {
+    /// The generation, shifted left by one, with the low bit set once the
+    /// timer of that generation has finished.
+    state: AtomicU64,
+    /// The waker of the latest poll of the timer of the current generation.
+    waker: WakerSlot,
+    /// The next free slot while this one is free.
+    next: AtomicPtr<Self>,
+}
+
+impl Slot {
+    /// Marks the timer of `generation` as finished and returns the waker of
+    /// its latest poll; a no-op if the slot has been released since.
+    pub fn finish(&self, generation: u64) -> Option<Waker> {
+        self.state
+            .compare_exchange(
+                generation << 1,
+                generation << 1 | 1,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .ok()
+            .and_then(|_| self.waker.take())
+    }
+
+    /// Makes `waker` the one returned once the timer finishes.
+    pub fn register(&self, waker: &Waker) {
+        self.waker.register(waker);
+    }
+
+    /// Returns whether the timer of `generation` has finished.
+    pub fn is_finished(&self, generation: u64) -> bool {
+        self.state.load(Ordering::Acquire) == generation << 1 | 1
+    }
+
+    fn next(&self) -> Option<&'static Self> {
+        // SAFETY: free lists only link slots, which are never freed.
+        unsafe { self.next.load(Ordering::Relaxed).as_ref() }
+    }
+
+    fn set_next(&self, next: Option<&'static Self>) {
+        self.next
+            .store(next.map_or(ptr::null_mut(), as_ptr), Ordering::Relaxed);
+    }
+}
+
+const fn as_ptr(slot: &'static Slot) -> *mut Slot {
+    ptr::from_ref(slot).cast_mut()
+}
+
+/// Slots released beyond the capacity of a thread's cache.
+static SHARED: AtomicPtr<Slot> = AtomicPtr::new(ptr::null_mut());
+
+/// Pushes the linked slots from `first` to `last` onto the shared list.
+fn push_shared(first: &'static Slot, last: &'static Slot) {
+    let mut head = SHARED.load(Ordering::Relaxed);
+    loop {
+        // SAFETY: see `Slot::next`.
+        last.set_next(unsafe { head.as_ref() });
+        match SHARED.compare_exchange_weak(
+            head,
+            as_ptr(first),
+            Ordering::Release,
+            Ordering::Relaxed,
+        ) {
+            Ok(_) => return,
+            Err(current) => head = current,
+        }
+    }
+}
+
+/// Takes the whole shared list; taking every slot at once rules out ABA.
+fn take_shared() -> Option<&'static Slot> {
+    // SAFETY: see `Slot::next`.
+    unsafe { SHARED.swap(ptr::null_mut(), Ordering::Acquire).as_ref() }
+}
+
+/// The free slots of one thread.
+struct LocalCache {
+    head: Cell<Option<&'static Slot>>,
+    len: Cell<usize>,
+}
+
+impl LocalCache {
+    fn pop(&self) -> Option<&'static Slot> {
+        let slot = self.head.get().or_else(|| {
+            let mut len = 0;
+            let mut slot = take_shared();
+            self.head.set(slot);
+            while let Some(current) = slot {
+                len += 1;
+                slot = current.next();
+            }
+            self.len.set(len);
+            self.head.get()
+        })?;
+        self.head.set(slot.next());
+        self.len.set(self.len.get() - 1);
+        Some(slot)
+    }
+
+    /// Caches `slot`, or hands it to the shared list if the cache is full.
+    fn push(&self, slot: &'static Slot, capacity: usize) {
+        if self.len.get() >= capacity {
+            push_shared(slot, slot);
+            return;
+        }
+        slot.set_next(self.head.get());
+        self.head.set(Some(slot));
+        self.len.set(self.len.get() + 1);
+    }
+}
+
+impl Drop for LocalCache {
+    fn drop(&mut self) {
+        let Some(first) = self.head.take() else {
+            return;
+        };
+        let mut last = first;
+        while let Some(next) = last.next() {
+            last = next;
+        }
+        push_shared(first, last);
+    }
+}
+
+std::thread_local! {
+    static CACHE: LocalCache = const {
+        LocalCache {
+            head: Cell::new(None),
+            len: Cell::new(0),
+        }
+    };
+}
+
+/// Takes a free slot and its current generation, allocating a slot if none
+/// is free.
+pub fn acquire() -> (&'static Slot, u64) {
+    let slot = CACHE
+        .try_with(LocalCache::pop)
+        .unwrap_or_else(|_| {
+            // The thread is exiting: take one shared slot, return the rest.
+            let first = take_shared()?;
+            if let Some(rest) = first.next() {
+                let mut last = rest;
+                while let Some(next) = last.next() {
+                    last = next;
+                }
+                push_shared(rest, last);
+            }
+            Some(first)
+        })
+        .unwrap_or_else(|| {
+            Box::leak(Box::new(Slot {
+                state: AtomicU64::new(0),
+                waker: WakerSlot::default(),
+                next: AtomicPtr::new(ptr::null_mut()),
+            }))
+        });
+    (slot, slot.state.load(Ordering::Acquire) >> 1)
+}
+
+/// Returns `slot` to the pool, invalidating its current generation.
+pub fn release(slot: &'static Slot) {
+    let generation = slot.state.load(Ordering::Acquire) >> 1;
+    slot.state
+        .store(generation.wrapping_add(1) << 1, Ordering::Release);
+    drop(slot.waker.take());
+    let capacity = crate::config::current().timer_pool_capacity_per_thread();
+    if CACHE.try_with(|cache| cache.push(slot, capacity)).is_err() {
+        push_shared(slot, slot);
+    }
+}
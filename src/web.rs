@@ -5,6 +5,10 @@
 
 use crate::{PlatformExecutor, Priority};
 use alloc::boxed::Box;
+#[cfg(feature = "std")]
+use alloc::{collections::VecDeque, rc::Rc};
+#[cfg(feature = "std")]
+use core::cell::RefCell;
 use core::time::Duration;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::spawn_local;
@@ -15,6 +19,92 @@ extern "C" {
     fn set_timeout(closure: &Closure<dyn FnMut()>, time: u32);
 }
 
+#[cfg(feature = "std")]
+#[wasm_bindgen]
+extern "C" {
+    type MessageChannel;
+
+    #[wasm_bindgen(constructor)]
+    fn new() -> MessageChannel;
+
+    #[wasm_bindgen(method, getter)]
+    fn port1(this: &MessageChannel) -> MessagePort;
+
+    #[wasm_bindgen(method, getter)]
+    fn port2(this: &MessageChannel) -> MessagePort;
+
+    type MessagePort;
+
+    #[wasm_bindgen(method, setter)]
+    fn set_onmessage(this: &MessagePort, handler: &Closure<dyn FnMut()>);
+
+    #[wasm_bindgen(method, js_name = postMessage)]
+    fn post_message(this: &MessagePort, message: &JsValue);
+}
+
+/// Jobs waiting for their message, oldest first.
+#[cfg(feature = "std")]
+type Jobs = Rc<RefCell<VecDeque<Box<dyn FnOnce()>>>>;
+
+/// The queue of macrotasks, each announced by one message to `sender`, whose
+/// receiving port runs the oldest job per message.
+#[cfg(feature = "std")]
+struct Macrotasks {
+    sender: MessagePort,
+    jobs: Jobs,
+    /// Keeps the receiving port and its handler alive.
+    _receiver: (MessagePort, Closure<dyn FnMut()>),
+}
+
+#[cfg(feature = "std")]
+impl Macrotasks {
+    fn new() -> Self {
+        let channel = MessageChannel::new();
+        let jobs = Jobs::default();
+        let handler = Closure::new({
+            let jobs = jobs.clone();
+            move || {
+                // The borrow ends before the job runs, which may queue more.
+                let job = jobs.borrow_mut().pop_front();
+                if let Some(job) = job {
+                    job();
+                }
+            }
+        });
+        let receiver = channel.port1();
+        receiver.set_onmessage(&handler);
+        Self {
+            sender: channel.port2(),
+            jobs,
+            _receiver: (receiver, handler),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    static MACROTASKS: Macrotasks = Macrotasks::new();
+}
+
+/// Runs `f` in a macrotask of its own, after the browser has had a chance to
+/// render and handle input.
+///
+/// Jobs run in submission order. Without `std`, there is no place for the
+/// channel, and each job gets its own `setTimeout(0)` instead.
+pub fn exec_macro(f: impl FnOnce() + 'static) {
+    #[cfg(feature = "std")]
+    MACROTASKS.with(|macrotasks| {
+        macrotasks.jobs.borrow_mut().push_back(Box::new(f));
+        macrotasks.sender.post_message(&JsValue::UNDEFINED);
+    });
+    #[cfg(not(feature = "std"))]
+    {
+        let closure = Closure::once(Box::new(f) as Box<dyn FnOnce()>);
+        set_timeout(&closure, 0);
+        closure.forget();
+    }
+}
+
 /// Web-based executor implementation for WASM targets.
 ///
 /// This executor uses `wasm-bindgen-futures::spawn_local` to execute futures
@@ -23,7 +113,8 @@ extern "C" {
 pub struct WebExecutor;
 
 impl PlatformExecutor for WebExecutor {
-    fn exec_main(f: impl FnOnce() + Send + 'static) {
+    fn exec_main(f: impl FnOnce() + Send + 'static, _priority: Option<Priority>) {
+        // Main-thread work runs in submission order
         spawn_local(async move { f() });
     }
 
@@ -32,9 +123,28 @@ impl PlatformExecutor for WebExecutor {
         spawn_local(async move { f() });
     }
 
+    /// The longest `setTimeout` delay; browsers fire longer ones right away.
+    const MAX_DELAY: Duration = Duration::from_millis(i32::MAX as u64);
+
     fn exec_after(delay: Duration, f: impl FnOnce() + Send + 'static, _priority: Priority) {
+        debug_assert!(
+            delay <= Self::MAX_DELAY,
+            "delays are clamped to `MAX_DELAY` by `dispatch_after`"
+        );
+        // Messages are not clamped the way nested `setTimeout(0)` calls are.
+        if delay.is_zero() {
+            exec_macro(f);
+            return;
+        }
+        let millis = u32::try_from(delay.as_millis()).unwrap_or(u32::MAX);
         let closure = Closure::once(Box::new(f) as Box<dyn FnOnce()>);
-        set_timeout(&closure, delay.as_millis() as u32);
+        set_timeout(&closure, millis);
         closure.forget();
     }
+
+    #[cfg(feature = "std")]
+    fn is_main_thread() -> bool {
+        // Work only ever runs on the thread of the event loop.
+        true
+    }
 }
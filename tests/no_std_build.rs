@@ -0,0 +1,20 @@
+//! Checks that the crate builds without the `std` feature, with only `alloc`.
+
+use std::{env, path::Path, process::Command};
+
+#[test]
+fn builds_without_std() {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let status = Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned()))
+        .args(["check", "--lib", "--quiet", "--no-default-features"])
+        .current_dir(manifest_dir)
+        // The build running this test holds the lock on the default target
+        // directory.
+        .env(
+            "CARGO_TARGET_DIR",
+            Path::new(manifest_dir).join("target/no_std_build"),
+        )
+        .status()
+        .expect("cargo could not be run");
+    assert!(status.success(), "the crate does not build without `std`");
+}
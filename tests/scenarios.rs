@@ -0,0 +1,77 @@
+//! Runs each scenario of the demo binary on the main thread.
+//!
+//! The polyfill backend serves as the main thread a thread started for the
+//! tests, as the test harness runs tests on threads of its own.
+#![cfg(not(any(target_vendor = "apple", target_os = "android")))]
+
+#[path = "../src/scenarios.rs"]
+mod scenarios;
+
+use futures_lite::future::block_on;
+use native_executor::{LaneReport, Priority, polyfill, spawn_main, timer::DeadlineExceeded};
+use std::{future::Future, sync::Once, thread, time::Duration};
+
+/// Runs `future` on the main thread until it completes.
+fn run<F>(future: F) -> F::Output
+where
+    F: Future + Send + 'static,
+    F::Output: Send,
+{
+    static MAIN: Once = Once::new();
+    MAIN.call_once(|| {
+        thread::spawn(polyfill::start_main_executor);
+    });
+    block_on(spawn_main(future))
+}
+
+#[test]
+fn hello() {
+    assert_eq!(
+        run(scenarios::hello()),
+        "Hello, world from native-executor!"
+    );
+}
+
+#[test]
+fn report_progress() {
+    let log = run(scenarios::report_progress());
+    let expected: Vec<_> = ["background", "default", "user-initiated"]
+        .into_iter()
+        .flat_map(|name| (1..=3).map(move |step| (name, step)))
+        .collect();
+    assert_eq!(log, expected);
+}
+
+#[test]
+fn ticks() {
+    let elapsed = run(scenarios::ticks(3));
+    assert_eq!(elapsed.len(), 3);
+    for (tick, elapsed) in elapsed.into_iter().enumerate() {
+        let tick = u32::try_from(tick).unwrap();
+        assert!(elapsed >= scenarios::TICK_PERIOD * tick);
+        assert!(elapsed < scenarios::TICK_PERIOD * tick + Duration::from_secs(1));
+    }
+}
+
+#[test]
+fn timeout_race() {
+    let (quick, stalled) = run(scenarios::timeout_race());
+    assert_eq!(quick, Ok("quick work"));
+    assert_eq!(stalled, Err(DeadlineExceeded));
+}
+
+#[test]
+fn shutdown() {
+    let report = run(scenarios::shutdown());
+    let aborted = LaneReport {
+        aborted: 1,
+        ..LaneReport::default()
+    };
+    let completed = LaneReport {
+        completed: 1,
+        ..LaneReport::default()
+    };
+    assert_eq!(report.lane(Priority::Background), aborted);
+    assert_eq!(report.lane(Priority::Default), completed);
+    assert_eq!(report.lane(Priority::UserInitiated), aborted);
+}